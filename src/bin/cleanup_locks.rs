@@ -2,7 +2,6 @@
 
 use anyhow::Result;
 use log::info;
-use std::path::PathBuf;
 
 /// Main entry point for the cleanup utility.
 fn main() -> Result<()> {
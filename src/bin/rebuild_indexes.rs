@@ -0,0 +1,54 @@
+//! Utility to rebuild the storage indexes of the Linksy email client.
+
+use anyhow::Result;
+use log::info;
+use std::io::Write;
+
+/// Main entry point for the rebuild utility.
+fn main() -> Result<()> {
+    // Initialize logging
+    env_logger::init();
+    info!("Linksy storage index rebuild utility");
+
+    // Determine base directory
+    let base_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?
+        .join("linksy");
+
+    // Create storage path
+    let storage_path = base_dir.join("storage");
+    if !storage_path.exists() {
+        println!("Storage directory not found at {:?}", storage_path);
+        return Ok(());
+    }
+
+    // Open the storage; this fails while Linksy is running
+    let storage = match linksy::storage::EmailStorage::new(&storage_path) {
+        Ok(storage) => storage,
+        Err(e) => {
+            println!("Could not open the storage at {:?}: {}", storage_path, e);
+            println!("Quit Linksy before rebuilding the indexes");
+            return Ok(());
+        }
+    };
+
+    println!("Rebuilding the indexes in {:?}", storage_path);
+
+    // Show the progress on one line
+    let report = storage.rebuild_indexes(|processed, total| {
+        if processed % 100 == 0 || processed == total {
+            print!("\rProcessed {} of {} records", processed, total);
+            let _ = std::io::stdout().flush();
+        }
+    })?;
+
+    if report.processed > 0 {
+        println!();
+    }
+    println!(
+        "Rebuild completed: {} records processed, {} moved to their folder, {} unreadable records removed",
+        report.processed, report.rekeyed, report.dropped
+    );
+
+    Ok(())
+}
@@ -3,10 +3,13 @@
 //! This module handles loading, saving, and accessing user configuration
 //! including email accounts, server settings, and application preferences.
 
+use crate::models::Email;
 use anyhow::Result;
+use chrono::format::{Item, StrftimeItems};
 use config::{Config, File};
+use log::warn;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Represents the application configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,7 +17,241 @@ pub struct AppConfig {
     /// Email accounts configured in the application
     pub accounts: Vec<EmailAccount>,
     /// General application settings
+    #[serde(default)]
     pub settings: AppSettings,
+    /// Canned responses that can be inserted while composing
+    #[serde(default)]
+    pub templates: Vec<EmailTemplate>,
+    /// Named searches listed as virtual folders
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+    /// Sort order and filter chosen for each folder
+    #[serde(default)]
+    pub folder_views: Vec<FolderView>,
+}
+
+impl AppConfig {
+    /// Clears a default account that isn't configured any more.
+    ///
+    /// The account may have been deleted by editing the configuration file.
+    ///
+    /// # Returns
+    /// true if the default account was cleared, false otherwise
+    pub fn validate_default_account(&mut self) -> bool {
+        let Some(default_id) = &self.settings.default_account else {
+            return false;
+        };
+        if self.accounts.iter().any(|account| &account.id == default_id) {
+            return false;
+        }
+        
+        warn!("default_account {:?} isn't configured, using the first account", default_id);
+        self.settings.default_account = None;
+        true
+    }
+    
+    /// Gets the index of the default account.
+    ///
+    /// # Returns
+    /// The index of the `default_account`, the first account if it isn't
+    /// set or configured, or None if there are no accounts
+    pub fn default_account_index(&self) -> Option<usize> {
+        if self.accounts.is_empty() {
+            return None;
+        }
+        
+        let default = self.settings.default_account.as_ref()
+            .and_then(|id| self.accounts.iter().position(|account| &account.id == id));
+        Some(default.unwrap_or(0))
+    }
+}
+
+/// Represents a named canned response.
+///
+/// The subject and body may contain placeholders that are filled in when the
+/// template is inserted: `{name}` and `{email}` for the first recipient and
+/// `{my_name}` for the sending account.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmailTemplate {
+    /// Name shown in the template picker
+    pub name: String,
+    /// Subject line
+    pub subject: String,
+    /// Message body
+    pub body: String,
+}
+
+/// Represents a named search that is listed as a virtual folder.
+///
+/// The query is run again each time the folder is opened.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SavedSearch {
+    /// Name shown in the folders list
+    pub name: String,
+    /// The query, e.g. `from:boss OR flagged`
+    pub query: String,
+    /// The folder to search, or None to search every folder
+    #[serde(default)]
+    pub folder: Option<String>,
+}
+
+/// Represents how the email list of a folder is sorted and filtered.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderView {
+    /// ID of the account the folder belongs to
+    pub account: String,
+    /// Name of the folder
+    pub folder: String,
+    /// Order of the emails
+    #[serde(default)]
+    pub sort: SortMode,
+    /// Which emails are listed
+    #[serde(default)]
+    pub filter: ViewFilter,
+}
+
+impl FolderView {
+    /// Sorts and filters the emails of the folder.
+    ///
+    /// Emails from VIP senders come first, each group in the folder's order.
+    ///
+    /// # Parameters
+    /// - `emails`: The emails in the folder
+    /// - `settings`: The settings with the VIP senders
+    ///
+    /// # Returns
+    /// The emails to list, in order
+    pub fn arrange(&self, mut emails: Vec<Email>, settings: &AppSettings) -> Vec<Email> {
+        emails.retain(|email| self.filter.matches(email));
+        self.sort.sort(&mut emails);
+        emails.sort_by_key(|email| !settings.is_vip(&email.from));
+        emails
+    }
+    
+    /// Describes the sort order and filter for the list title.
+    ///
+    /// # Returns
+    /// e.g. "sorted by sender, unread only"
+    pub fn describe(&self) -> String {
+        match self.filter {
+            ViewFilter::All => format!("sorted by {}", self.sort.label()),
+            filter => format!("sorted by {}, {} only", self.sort.label(), filter.label()),
+        }
+    }
+}
+
+/// Order of the emails in a folder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    /// Newest first
+    #[default]
+    Newest,
+    /// Oldest first
+    Oldest,
+    /// By sender name or address, newest first for each sender
+    Sender,
+    /// By subject, newest first for each subject
+    Subject,
+}
+
+impl SortMode {
+    /// Gets the sort order that follows this one.
+    ///
+    /// # Returns
+    /// The next sort order, wrapping around to Newest
+    pub fn next(&self) -> Self {
+        match self {
+            SortMode::Newest => SortMode::Oldest,
+            SortMode::Oldest => SortMode::Sender,
+            SortMode::Sender => SortMode::Subject,
+            SortMode::Subject => SortMode::Newest,
+        }
+    }
+    
+    /// Gets the name of the sort order.
+    ///
+    /// # Returns
+    /// The name shown in the list title
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortMode::Newest => "newest",
+            SortMode::Oldest => "oldest",
+            SortMode::Sender => "sender",
+            SortMode::Subject => "subject",
+        }
+    }
+    
+    /// Sorts emails in this order.
+    ///
+    /// # Parameters
+    /// - `emails`: The emails to sort
+    pub fn sort(&self, emails: &mut [Email]) {
+        let sender = |email: &Email| email.from_name.as_deref().unwrap_or(&email.from).to_lowercase();
+        
+        // Newest first breaks ties between emails from the same sender or with the same subject
+        emails.sort_by_key(|email| std::cmp::Reverse(email.date));
+        match self {
+            SortMode::Newest => {},
+            SortMode::Oldest => emails.reverse(),
+            SortMode::Sender => emails.sort_by_cached_key(sender),
+            SortMode::Subject => emails.sort_by_cached_key(|email| email.subject.to_lowercase()),
+        }
+    }
+}
+
+/// Which emails of a folder are listed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewFilter {
+    /// Every email
+    #[default]
+    All,
+    /// Emails that haven't been read
+    Unread,
+    /// Flagged emails
+    Flagged,
+}
+
+impl ViewFilter {
+    /// Gets the filter that follows this one.
+    ///
+    /// # Returns
+    /// The next filter, wrapping around to All
+    pub fn next(&self) -> Self {
+        match self {
+            ViewFilter::All => ViewFilter::Unread,
+            ViewFilter::Unread => ViewFilter::Flagged,
+            ViewFilter::Flagged => ViewFilter::All,
+        }
+    }
+    
+    /// Gets the name of the filter.
+    ///
+    /// # Returns
+    /// The name shown in the list title
+    pub fn label(&self) -> &'static str {
+        match self {
+            ViewFilter::All => "all",
+            ViewFilter::Unread => "unread",
+            ViewFilter::Flagged => "flagged",
+        }
+    }
+    
+    /// Checks if an email is listed with this filter.
+    ///
+    /// # Parameters
+    /// - `email`: The email
+    ///
+    /// # Returns
+    /// true if the email is listed, false otherwise
+    pub fn matches(&self, email: &Email) -> bool {
+        match self {
+            ViewFilter::All => true,
+            ViewFilter::Unread => !email.is_read,
+            ViewFilter::Flagged => email.is_flagged,
+        }
+    }
 }
 
 /// Represents an email account configuration.
@@ -32,6 +269,43 @@ pub struct EmailAccount {
     pub pop3: Option<ServerConfig>,
     /// SMTP server configuration
     pub smtp: ServerConfig,
+    /// Seconds a sent message waits in the outbox, where it can be recalled, before it goes to SMTP
+    #[serde(default)]
+    pub send_delay_secs: u64,
+    /// SOCKS5 proxy that all of the account's connections go through
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Whether to add the account's own address as Bcc on every message
+    #[serde(default)]
+    pub bcc_self: bool,
+    /// Address to add as Cc on every message
+    #[serde(default)]
+    pub auto_cc: Option<String>,
+    /// Address that replies to the account's messages go to, if not the account's own
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// Whether to connect when the application starts, rather than when the account is opened
+    #[serde(default = "default_auto_connect")]
+    pub auto_connect: bool,
+    /// Whether the account is in use; a disabled account stays configured but never connects or sends
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// Gets the default of the auto_connect account option.
+///
+/// # Returns
+/// true, so accounts connect on startup unless configured otherwise
+fn default_auto_connect() -> bool {
+    true
+}
+
+/// Gets the default of the enabled account option.
+///
+/// # Returns
+/// true, so accounts are in use unless disabled
+fn default_enabled() -> bool {
+    true
 }
 
 /// Represents a mail server configuration.
@@ -49,15 +323,423 @@ pub struct ServerConfig {
     pub use_ssl: bool,
 }
 
+/// Represents a SOCKS5 proxy configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    /// Proxy hostname
+    pub host: String,
+    /// Proxy port
+    pub port: u16,
+    /// Username for proxies that require authentication
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Password for proxies that require authentication
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
 /// Represents general application settings.
+///
+/// Missing fields fall back to their defaults so older configuration files
+/// keep loading as new settings are added.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
-    /// Default account ID to use
+    /// Default account ID to use, e.g. for the inbox opened on startup
     pub default_account: Option<String>,
+    /// View shown when the application starts
+    pub startup_view: StartupView,
     /// Whether to check for new emails automatically
     pub auto_check: bool,
     /// Interval in minutes for auto-checking emails
     pub check_interval: u32,
+    /// Whether the terminal bell rings when new mail arrives
+    pub notify_bell: bool,
+    /// Whether a desktop notification is shown when new mail arrives
+    pub notify_desktop: bool,
+    /// Shell command run for each account with new mail
+    pub new_mail_command: Option<String>,
+    /// Whether to show the HTML part of an email instead of the plain-text part
+    pub prefer_html: bool,
+    /// When an opened email is marked as read
+    pub auto_mark_read: MarkReadPolicy,
+    /// When quitting asks first
+    pub confirm_quit: QuitConfirmation,
+    /// Command used to compose in an external editor, overriding $VISUAL and $EDITOR
+    pub editor: Option<String>,
+    /// Whether replies are written above or below the quoted message
+    pub reply_position: ReplyPosition,
+    /// How the quoted message is marked in replies
+    pub quote_style: QuoteStyle,
+    /// Line introducing the quoted message in replies
+    pub reply_attribution: String,
+    /// Header introducing the original message in forwards
+    pub forward_attribution: String,
+    /// strftime-style format for dates
+    pub date_format: String,
+    /// strftime-style format for times of day
+    pub time_format: String,
+    /// Whether to skip all connections and show only cached mail
+    pub offline: bool,
+    /// Whether line endings in outgoing text are converted to CRLF
+    pub crlf_line_endings: bool,
+    /// Content-Transfer-Encoding of outgoing text parts
+    pub transfer_encoding: TransferEncoding,
+    /// Charset outgoing text parts are written in
+    pub charset: String,
+    /// Archive address every sent message is blind copied to, from any account
+    pub archive_bcc: Option<String>,
+    /// Size in KB beyond which only the start of a message is downloaded, or 0 for no limit
+    pub max_body_size_kb: usize,
+    /// Most emails kept in the cache of each folder, or 0 for no limit
+    pub max_cached_per_folder: usize,
+    /// Whether opening an email downloads only its text, and attachments when they're previewed
+    pub attachments_on_demand: bool,
+    /// Whether remote images in HTML emails are left out until the user loads them
+    pub block_remote_content: bool,
+    /// Senders whose remote images are always shown
+    pub remote_content_senders: Vec<String>,
+    /// Width in columns the email body is wrapped at and centered in, or 0 to use the whole width
+    pub max_body_width: u16,
+    /// Lines of the previous page kept in view when paging through an email
+    pub scroll_overlap: u16,
+    /// Senders whose emails are highlighted and listed first
+    pub vip_senders: Vec<String>,
+    /// Tags offered in the tag picker, stored as IMAP keywords
+    pub tags: Vec<String>,
+    /// Whether the Trash folder of every IMAP account is emptied on exit
+    pub empty_trash_on_exit: bool,
+    /// Whether the user has confirmed emptying Trash on exit
+    pub empty_trash_confirmed: bool,
+    /// Where cached emails are kept
+    pub storage_backend: StorageBackend,
+    /// Directory of the Maildir backend, or None for "maildir" in the configuration directory
+    pub maildir_path: Option<String>,
+    /// Least severe messages kept for the log view
+    pub log_level: LogLevel,
+}
+
+/// Default strftime-style format for dates.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Default strftime-style format for times of day.
+pub const DEFAULT_TIME_FORMAT: &str = "%H:%M";
+
+/// Default charset of outgoing text parts.
+pub const DEFAULT_CHARSET: &str = "utf-8";
+
+impl AppSettings {
+    /// Gets how much of a message to download when it's opened.
+    ///
+    /// # Returns
+    /// The limit in bytes, or None if messages are always downloaded in full
+    pub fn max_body_bytes(&self) -> Option<usize> {
+        (self.max_body_size_kb > 0).then(|| self.max_body_size_kb.saturating_mul(1024))
+    }
+    
+    /// Gets how many emails the cache keeps of each folder.
+    ///
+    /// # Returns
+    /// The number of emails, or None if the cache keeps them all
+    pub fn cache_limit(&self) -> Option<usize> {
+        (self.max_cached_per_folder > 0).then_some(self.max_cached_per_folder)
+    }
+    
+    /// Checks if remote images from a sender are shown without asking.
+    ///
+    /// # Parameters
+    /// - `sender`: The sender address
+    ///
+    /// # Returns
+    /// true if remote content isn't blocked or the sender is always allowed, false otherwise
+    pub fn allows_remote_content(&self, sender: &str) -> bool {
+        !self.block_remote_content
+            || self.remote_content_senders.iter().any(|allowed| allowed.eq_ignore_ascii_case(sender))
+    }
+    
+    /// Gets the archive address every sent message is blind copied to.
+    ///
+    /// # Returns
+    /// The address, or None if it isn't set
+    pub fn archive_address(&self) -> Option<&str> {
+        self.archive_bcc.as_deref()
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+    }
+    
+    /// Checks if a sender is a VIP.
+    ///
+    /// # Parameters
+    /// - `sender`: The sender address
+    ///
+    /// # Returns
+    /// true if the sender is in the VIP list, false otherwise
+    pub fn is_vip(&self, sender: &str) -> bool {
+        self.vip_senders.iter().any(|vip| vip.eq_ignore_ascii_case(sender))
+    }
+    
+    /// Checks if the Trash folders are emptied when the application exits.
+    ///
+    /// # Returns
+    /// true if the setting is on and the user has confirmed it, false otherwise
+    pub fn empties_trash_on_exit(&self) -> bool {
+        self.empty_trash_on_exit && self.empty_trash_confirmed
+    }
+    
+    /// Gets the directory of the Maildir backend.
+    ///
+    /// # Parameters
+    /// - `base_dir`: The configuration directory
+    ///
+    /// # Returns
+    /// The maildir_path, with a leading "~/" meaning the home directory, or the default
+    pub fn maildir_dir(&self, base_dir: &Path) -> PathBuf {
+        let Some(path) = &self.maildir_path else {
+            return base_dir.join("maildir");
+        };
+        
+        match (path.strip_prefix("~/"), dirs::home_dir()) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(path),
+        }
+    }
+    
+    /// Replaces invalid date and time formats with the defaults.
+    ///
+    /// # Returns
+    /// true if a format was replaced, false otherwise
+    pub fn validate_formats(&mut self) -> bool {
+        let mut replaced = false;
+        
+        if !is_valid_format(&self.date_format) {
+            warn!("Invalid date_format {:?}, using {:?}", self.date_format, DEFAULT_DATE_FORMAT);
+            self.date_format = DEFAULT_DATE_FORMAT.to_string();
+            replaced = true;
+        }
+        
+        if !is_valid_format(&self.time_format) {
+            warn!("Invalid time_format {:?}, using {:?}", self.time_format, DEFAULT_TIME_FORMAT);
+            self.time_format = DEFAULT_TIME_FORMAT.to_string();
+            replaced = true;
+        }
+        
+        replaced
+    }
+    
+    /// Replaces an unknown charset with the default.
+    ///
+    /// # Returns
+    /// true if the charset was replaced, false otherwise
+    pub fn validate_charset(&mut self) -> bool {
+        if encoding_rs::Encoding::for_label(self.charset.trim().as_bytes()).is_some() {
+            return false;
+        }
+        
+        warn!("Unknown charset {:?}, using {:?}", self.charset, DEFAULT_CHARSET);
+        self.charset = DEFAULT_CHARSET.to_string();
+        true
+    }
+}
+
+/// Checks if a strftime-style format string can be used for formatting.
+///
+/// # Parameters
+/// - `format`: The format string
+///
+/// # Returns
+/// true if every specifier in the format is recognized, false otherwise
+pub fn is_valid_format(format: &str) -> bool {
+    StrftimeItems::new(format).all(|item| !matches!(item, Item::Error))
+}
+
+/// Where the reply text goes relative to the quoted message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyPosition {
+    /// Reply below the quoted message (bottom-posting)
+    #[default]
+    Bottom,
+    /// Reply above the quoted message (top-posting)
+    Top,
+}
+
+/// How the quoted message is marked in replies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuoteStyle {
+    /// Prefix each quoted line with "> "
+    #[default]
+    Prefix,
+    /// Indent each quoted line with four spaces
+    Indent,
+}
+
+/// Content-Transfer-Encoding of outgoing text parts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransferEncoding {
+    /// 7bit for plain ASCII, quoted-printable otherwise
+    #[default]
+    Auto,
+    /// Raw 8-bit text, for servers that support 8BITMIME
+    #[serde(rename = "8bit")]
+    EightBit,
+    /// Quoted-printable
+    QuotedPrintable,
+    /// Base64
+    Base64,
+}
+
+/// When an opened email is marked as read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkReadPolicy {
+    /// As soon as it's opened
+    #[default]
+    Immediate,
+    /// Once it has been open for this many seconds
+    AfterDelay(u64),
+    /// Only when the user marks it
+    Manual,
+}
+
+/// When quitting asks for confirmation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuitConfirmation {
+    /// Never, the message being written is lost
+    Never,
+    /// Only while a message is being written, offering to save it as a draft
+    #[default]
+    Unsaved,
+    /// Every time
+    Always,
+}
+
+/// Where cached emails are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// The sled database, with the rest of the cache
+    #[default]
+    Sled,
+    /// Maildir directories, one per account and folder, readable by other mail tools
+    Maildir,
+}
+
+/// The view shown when the application starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupView {
+    /// The account list
+    #[default]
+    Accounts,
+    /// The inbox of the default account, or of the first account
+    Inbox,
+}
+
+/// Least severe log messages that are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    /// Only errors
+    Error,
+    /// Errors and warnings
+    Warn,
+    /// Errors, warnings and progress messages
+    #[default]
+    Info,
+    /// Everything but the most detailed tracing
+    Debug,
+    /// Everything
+    Trace,
+}
+
+impl LogLevel {
+    /// Gets the level that follows this one, from least to most detailed.
+    ///
+    /// # Returns
+    /// The next level, wrapping around to Error
+    pub fn next(&self) -> Self {
+        match self {
+            LogLevel::Error => LogLevel::Warn,
+            LogLevel::Warn => LogLevel::Info,
+            LogLevel::Info => LogLevel::Debug,
+            LogLevel::Debug => LogLevel::Trace,
+            LogLevel::Trace => LogLevel::Error,
+        }
+    }
+    
+    /// Gets the name of the level.
+    ///
+    /// # Returns
+    /// The name shown in the log view title
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+    
+    /// Gets the matching filter of the log crate.
+    ///
+    /// # Returns
+    /// The filter that lets messages of this level and more severe ones through
+    pub fn filter(&self) -> log::LevelFilter {
+        match self {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            default_account: None,
+            startup_view: StartupView::default(),
+            auto_check: true,
+            check_interval: 15,
+            notify_bell: false,
+            notify_desktop: false,
+            new_mail_command: None,
+            prefer_html: false,
+            auto_mark_read: MarkReadPolicy::default(),
+            confirm_quit: QuitConfirmation::default(),
+            editor: None,
+            reply_position: ReplyPosition::default(),
+            quote_style: QuoteStyle::default(),
+            reply_attribution: "On {date}, {name} wrote:".to_string(),
+            forward_attribution: "---------- Forwarded message ----------\nFrom: {from}\nDate: {date}\nSubject: {subject}\nTo: {to}".to_string(),
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
+            offline: false,
+            crlf_line_endings: true,
+            transfer_encoding: TransferEncoding::default(),
+            charset: DEFAULT_CHARSET.to_string(),
+            archive_bcc: None,
+            max_body_size_kb: 2048,
+            max_cached_per_folder: 0,
+            attachments_on_demand: false,
+            block_remote_content: true,
+            remote_content_senders: Vec::new(),
+            max_body_width: 100,
+            scroll_overlap: 2,
+            vip_senders: Vec::new(),
+            tags: vec!["$Todo".to_string(), "$Important".to_string()],
+            empty_trash_on_exit: false,
+            empty_trash_confirmed: false,
+            storage_backend: StorageBackend::default(),
+            maildir_path: None,
+            log_level: LogLevel::default(),
+        }
+    }
 }
 
 /// Configuration manager for the application.
@@ -104,7 +786,15 @@ impl ConfigManager {
             .add_source(File::with_name(config_path))
             .build()?;
             
-        let app_config = config.try_deserialize::<AppConfig>()?;
+        let mut app_config = config.try_deserialize::<AppConfig>()?;
+        
+        // Fall back to the default formats rather than failing to render dates
+        app_config.settings.validate_formats();
+        app_config.settings.validate_charset();
+        
+        // Don't point at an account that was removed from the file
+        app_config.validate_default_account();
+        
         Ok(app_config)
     }
     
@@ -115,11 +805,10 @@ impl ConfigManager {
     fn default_config() -> AppConfig {
         AppConfig {
             accounts: Vec::new(),
-            settings: AppSettings {
-                default_account: None,
-                auto_check: true,
-                check_interval: 15,
-            },
+            settings: AppSettings::default(),
+            templates: Vec::new(),
+            saved_searches: Vec::new(),
+            folder_views: Vec::new(),
         }
     }
     
@@ -127,7 +816,11 @@ impl ConfigManager {
     ///
     /// # Returns
     /// A Result indicating success or failure
-    pub fn save_config(&self) -> Result<()> {
+    pub fn save_config(&mut self) -> Result<()> {
+        // Never write out a format that can't be rendered
+        self.config.settings.validate_formats();
+        self.config.settings.validate_charset();
+        
         let serialized = serde_json::to_string_pretty(&self.config)?;
         std::fs::write(&self.config_path, serialized)?;
         Ok(())
@@ -170,7 +863,6 @@ impl ConfigManager {
     ///
     /// # Returns
     /// A Result indicating success or failure
-    #[allow(dead_code)]
     pub fn add_account(&mut self, account: EmailAccount) -> Result<()> {
         // Check if account with same ID already exists
         if self.config.accounts.iter().any(|a| a.id == account.id) {
@@ -182,6 +874,87 @@ impl ConfigManager {
         Ok(())
     }
     
+    /// Finds an existing account with the same email address and IMAP server as another.
+    ///
+    /// Accounts with different IDs can still point at the same mailbox, which
+    /// shows the same mail twice.
+    ///
+    /// # Parameters
+    /// - `account`: The account being added or edited
+    /// - `is_new_account`: Whether the account is new; an edited account is
+    ///   skipped by its ID
+    ///
+    /// # Returns
+    /// An Option containing the matching account
+    pub fn find_duplicate_account(&self, account: &EmailAccount, is_new_account: bool) -> Option<&EmailAccount> {
+        let imap_host = |a: &EmailAccount| a.imap.as_ref().map(|imap| imap.host.to_lowercase());
+
+        self.config.accounts
+            .iter()
+            .filter(|existing| is_new_account || existing.id != account.id)
+            .find(|existing| {
+                existing.email.eq_ignore_ascii_case(account.email.trim())
+                    && imap_host(existing) == imap_host(account)
+            })
+    }
+    
+    /// Replaces an email account in the configuration with an edited copy.
+    ///
+    /// # Parameters
+    /// - `account`: The edited account, with the ID of the one it replaces
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn update_account(&mut self, account: EmailAccount) -> Result<()> {
+        let Some(existing) = self.config.accounts.iter_mut().find(|a| a.id == account.id) else {
+            return Err(anyhow::anyhow!("Account with ID {} not found", account.id));
+        };
+        
+        *existing = account;
+        self.save_config()?;
+        Ok(())
+    }
+    
+    /// Moves an email account to a new position in the configuration.
+    ///
+    /// # Parameters
+    /// - `account_id`: The ID of the account to move
+    /// - `new_index`: The index to move the account to
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn move_account(&mut self, account_id: &str, new_index: usize) -> Result<()> {
+        let Some(index) = self.config.accounts.iter().position(|a| a.id == account_id) else {
+            return Err(anyhow::anyhow!("Account with ID {} not found", account_id));
+        };
+        if new_index >= self.config.accounts.len() {
+            return Err(anyhow::anyhow!("Can't move account {} to position {}", account_id, new_index));
+        }
+        
+        let account = self.config.accounts.remove(index);
+        self.config.accounts.insert(new_index, account);
+        self.save_config()?;
+        Ok(())
+    }
+    
+    /// Enables or disables an email account in the configuration.
+    ///
+    /// # Parameters
+    /// - `account_id`: The ID of the account
+    /// - `enabled`: Whether the account is in use
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn set_account_enabled(&mut self, account_id: &str, enabled: bool) -> Result<()> {
+        let Some(account) = self.config.accounts.iter_mut().find(|a| a.id == account_id) else {
+            return Err(anyhow::anyhow!("Account with ID {} not found", account_id));
+        };
+        
+        account.enabled = enabled;
+        self.save_config()?;
+        Ok(())
+    }
+    
     /// Removes an email account from the configuration.
     ///
     /// # Parameters
@@ -189,7 +962,7 @@ impl ConfigManager {
     ///
     /// # Returns
     /// A Result indicating success or failure
-    #[allow(dead_code)]
+    #[allow(clippy::collapsible_if)]
     pub fn remove_account(&mut self, account_id: &str) -> Result<()> {
         let initial_len = self.config.accounts.len();
         self.config.accounts.retain(|a| a.id != account_id);
@@ -1,16 +1,37 @@
 //! Main application controller for the Linksy email client.
 
-use crate::state::{AppState, AccountManager, EmailManager, View};
+use crate::config::{AppSettings, MarkReadPolicy, StartupView};
+use crate::models::{Account, Address, Attachment, ConnectionStatus, Email, EmailKey, ScheduledEmail, SearchQuery, SCHEDULED_FOLDER};
+use crate::state::{AppState, AccountBundle, AccountManager, EmailManager, OutboxEntry, RetryOutcome, SharedState, View};
+use crate::ui::{notify, viewer};
 use crate::ui::views::account_config::AccountFormState;
-use anyhow::Result;
-use log::info;
+use crate::ui::views::attachments::preview_attachment;
+use crate::ui::views::compose::ComposeState;
+use crate::protocols::{ImapClient, SearchResults};
+use crate::ui::views::search::SearchState;
+use crate::ui::views::tags::tag_label;
+use anyhow::{anyhow, Result};
+use log::{error, info, warn};
+use std::borrow::Cow;
+use std::future::Future;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{Mutex, RwLockWriteGuard, Semaphore};
+use tokio::task::JoinSet;
+
+/// How often the background task checks for scheduled messages that are due.
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How many accounts are connected or fetched at the same time.
+const MAX_CONCURRENT_ACCOUNTS: usize = 4;
+
+/// How long quitting waits for messages already being sent.
+const SHUTDOWN_SEND_WAIT: Duration = Duration::from_secs(10);
 
 /// Main controller for the application.
 pub struct AppController {
     /// Application state
-    state: Arc<Mutex<AppState>>,
+    state: Arc<SharedState>,
     /// Account manager
     account_manager: Arc<Mutex<AccountManager>>,
     /// Email manager
@@ -28,7 +49,7 @@ impl AppController {
     /// # Returns
     /// A new AppController instance
     pub fn new(
-        state: Arc<Mutex<AppState>>,
+        state: Arc<SharedState>,
         account_manager: Arc<Mutex<AccountManager>>,
         email_manager: Arc<Mutex<EmailManager>>,
     ) -> Self {
@@ -47,9 +68,56 @@ impl AppController {
         // Load accounts
         self.load_accounts().await?;
         
+        // Open the configured startup view
+        self.open_startup_view().await;
+        
         Ok(())
     }
     
+    /// Opens the inbox of the default account if the `startup_view` setting asks for it.
+    ///
+    /// The default account is the `default_account` setting, or the first
+    /// account. start_startup_connect connects it.
+    async fn open_startup_view(&self) {
+        let mut state = self.state.write().await;
+        let config = state.config_manager.get_config();
+        if config.settings.startup_view != StartupView::Inbox {
+            return;
+        }
+        
+        // Find the default account
+        let Some(index) = config.default_account_index().filter(|&index| index < state.account_summaries.len()) else {
+            return;
+        };
+        
+        state.set_selected_account(Some(index));
+        state.set_selected_folder("INBOX".to_string());
+        let (folders, _) = state.get_folder_list();
+        state.selected_folder_index = folders.iter().position(|folder| folder == "INBOX").unwrap_or(0);
+        state.set_current_view(View::Emails);
+    }
+    
+    /// Connects the account opened on startup in the background and loads its inbox.
+    ///
+    /// Does nothing unless the `startup_view` setting opened an inbox.
+    pub fn start_startup_connect(self: &Arc<Self>) {
+        let controller = self.clone();
+        tokio::spawn(async move {
+            let opened = {
+                let state = controller.state.read().await;
+                state.current_view == View::Emails && state.selected_account.is_some()
+            };
+            if !opened {
+                return;
+            }
+            
+            if let Err(e) = controller.connect_selected_account().await {
+                error!("Connecting failed: {}", e);
+                controller.set_status_message(format!("Connecting failed: {}", e)).await;
+            }
+        });
+    }
+    
     /// Loads accounts from configuration.
     ///
     /// # Returns
@@ -57,7 +125,7 @@ impl AppController {
     pub async fn load_accounts(&self) -> Result<()> {
         // Get configuration manager from state
         let config_manager = {
-            let state = self.state.lock().await;
+            let state = self.state.read().await;
             state.config_manager.clone()
         };
         
@@ -66,12 +134,20 @@ impl AppController {
         account_manager.load_accounts(&config_manager).await?;
         
         // Update state with accounts
-        let accounts = account_manager.get_accounts().clone();
-        let mut state = self.state.lock().await;
-        state.accounts = accounts;
+        let accounts = account_manager.get_bundles();
+        let mut state = self.state.write().await;
+        
+        // Show the folders from the last session until the accounts connect
+        for bundle in &accounts {
+            let mut account = bundle.account.lock().await;
+            match state.storage.get_folders(&account.config.id) {
+                Ok(folders) if !folders.is_empty() => account.folders = folders,
+                Ok(_) => {},
+                Err(e) => error!("Failed to load cached folders: {}", e),
+            }
+        }
         
-        // Update account summaries
-        state.update_account_summaries();
+        state.set_accounts(accounts);
         
         // Select first account if available
         if !state.accounts.is_empty() {
@@ -86,19 +162,39 @@ impl AppController {
     /// # Returns
     /// A Result indicating success or failure
     pub async fn connect_selected_account(&self) -> Result<()> {
-        // Get selected account index
-        let selected_account = {
-            let state = self.state.lock().await;
-            state.selected_account
+        // Get selected account
+        let (selected_account, send_only, offline, disabled) = {
+            let state = self.state.read().await;
+            (state.get_selected_account_id(), state.is_selected_account_send_only(), state.offline, state.is_selected_account_disabled())
         };
         
-        if let Some(index) = selected_account {
+        if offline || disabled {
+            // Serve the folder from the cache instead of connecting
+            self.load_emails().await?;
+            let mut state = self.state.write().await;
+            let message = if offline {
+                "Offline - showing cached mail"
+            } else {
+                "Account disabled - showing cached mail; press 'x' in the accounts view to enable it"
+            };
+            state.set_status_message(message.to_string());
+            return Ok(());
+        }
+        
+        if let Some(account_id) = selected_account {
             // Connect to account
-            let account_manager = self.account_manager.lock().await;
-            let connection_successful = account_manager.connect_account(index).await?;
+            let connection_successful = self.connect_account(&account_id).await?;
             
-            // If connection was successful, load emails
-            if connection_successful {
+            if send_only {
+                // There is no mailbox to load, so point the user at sending instead
+                {
+                    let mut state = self.state.write().await;
+                    state.set_selected_folder(SCHEDULED_FOLDER.to_string());
+                    state.set_status_message("Send-only account (no IMAP or POP3) - press 'c' to compose".to_string());
+                }
+                self.load_emails().await?;
+            } else if connection_successful {
+                // If connection was successful, load emails
                 self.load_emails().await?;
             }
         }
@@ -106,41 +202,146 @@ impl AppController {
         Ok(())
     }
     
+    /// Connects to an account unless it's already connected.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    ///
+    /// # Returns
+    /// A Result containing whether the account is connected
+    async fn connect_account(&self, account_id: &str) -> Result<bool> {
+        let Some(bundle) = self.account_manager.lock().await.get_bundle(account_id).cloned() else {
+            return Ok(false);
+        };
+        
+        // Accounts connected on startup are ready to use
+        if bundle.account.lock().await.is_connected() {
+            return Ok(true);
+        }
+        
+        let connection_successful = bundle.connect().await?;
+        if connection_successful {
+            self.store_folders(&bundle.account).await;
+        }
+        
+        Ok(connection_successful)
+    }
+    
+    /// Connects the accounts that connect on startup in the background.
+    ///
+    /// Accounts with `auto_connect` turned off stay disconnected until they
+    /// are opened, and disabled accounts stay disconnected. Nothing is
+    /// connected in offline mode.
+    pub fn start_auto_connect(self: &Arc<Self>) {
+        let controller = self.clone();
+        tokio::spawn(async move {
+            if controller.state.read().await.offline {
+                return;
+            }
+            
+            // Connect several accounts at once, so a slow server doesn't hold up the others
+            let bundles = controller.account_manager.lock().await.get_bundles();
+            let connected = for_each_account(bundles, |bundle| async move {
+                let (auto_connect, connected) = {
+                    let account = bundle.account.lock().await;
+                    (account.config.auto_connect && account.config.enabled, account.is_connected())
+                };
+                if !auto_connect || connected {
+                    return None;
+                }
+                
+                match bundle.connect().await {
+                    Ok(true) => Some(bundle.account),
+                    Ok(false) => None,
+                    Err(e) => {
+                        error!("Failed to connect on startup: {}", e);
+                        None
+                    },
+                }
+            }).await;
+            
+            // Save the folder lists
+            for account in connected.into_iter().flatten() {
+                controller.store_folders(&account).await;
+            }
+        });
+    }
+    
+    /// Saves an account's folder list so the next session can show it before connecting.
+    ///
+    /// Only folders listed by a connected IMAP session are saved.
+    ///
+    /// # Parameters
+    /// - `account`: The account
+    async fn store_folders(&self, account: &Arc<Mutex<Account>>) {
+        let (account_id, folders) = {
+            let account = account.lock().await;
+            if account.imap_status != ConnectionStatus::Connected {
+                return;
+            }
+            (account.config.id.clone(), account.folders.clone())
+        };
+        
+        let state = self.state.read().await;
+        if let Err(e) = state.storage.store_folders(&account_id, &folders) {
+            error!("Failed to cache folders: {}", e);
+        }
+    }
+    
     /// Loads emails for the selected account and folder.
     ///
     /// # Returns
     /// A Result indicating success or failure
     pub async fn load_emails(&self) -> Result<()> {
-        // Get selected account index and folder
-        let (selected_account, folder) = {
-            let state = self.state.lock().await;
-            (state.selected_account, state.selected_folder.clone())
+        // Get selected account and folder
+        let (selected_account, folder, offline) = {
+            let state = self.state.read().await;
+            (state.get_selected_account_id(), state.selected_folder.clone(), state.offline)
         };
         
-        if let Some(index) = selected_account {
-            // Get account and clients
-            let account_manager = self.account_manager.lock().await;
-            let account = account_manager.get_account(index).cloned();
-            let imap_client = account_manager.get_imap_client(index).cloned();
-            let pop3_client = account_manager.get_pop3_client(index).cloned();
+        if folder == SCHEDULED_FOLDER {
+            return self.load_scheduled_emails().await;
+        }
+        
+        if let Some(account_id) = selected_account {
+            // Get account and clients; without clients only the cache is read
+            let (account, imap_client, pop3_client) = {
+                let account_manager = self.account_manager.lock().await;
+                (
+                    account_manager.get_account(&account_id).cloned(),
+                    account_manager.get_imap_client(&account_id).filter(|_| !offline).cloned(),
+                    account_manager.get_pop3_client(&account_id).filter(|_| !offline).cloned(),
+                )
+            };
             
             if let Some(account) = account {
-                // Load emails
-                let email_manager = self.email_manager.lock().await;
-                let emails = email_manager.load_emails(
+                // Load emails without holding the managers, so other accounts aren't held up
+                let email_manager = self.email_manager.lock().await.clone();
+                let cache_limit = self.state.read().await.config_manager.get_config().settings.cache_limit();
+                let fetched = email_manager.load_emails(
                     &account,
                     imap_client.as_ref(),
                     pop3_client.as_ref(),
                     &folder,
                     50,
+                    cache_limit,
                 ).await?;
                 
-                // Update state with emails
-                let mut state = self.state.lock().await;
-                state.emails = emails;
+                // Update state with emails, in the folder's saved order
+                let Some(mut state) = self.state_for_account(&account).await else {
+                    return Ok(());
+                };
+                state.emails = Arc::new(state.folder_view().arrange(fetched.emails, &state.config_manager.get_config().settings));
+                
+                // Say that the list is incomplete; refreshing picks up where the fetch stopped
+                if let Some(reason) = fetched.interrupted {
+                    let message = format!("Fetching {} was interrupted ({}) - press F5 to fetch the rest", folder, reason);
+                    state.set_status_message(message);
+                }
                 
                 // Reset selected email
-                state.selected_email = if state.emails.is_empty() { None } else { Some(0) };
+                state.selected_email = None;
+                state.clamp_selection();
                 state.viewed_email = None;
             }
         }
@@ -148,244 +349,1672 @@ impl AppController {
         Ok(())
     }
     
-    /// Retries failed connections for the selected account.
+    /// Gets the state to list what was fetched for an account.
+    ///
+    /// The account may have been edited, deleted or unselected while the
+    /// fetch was running; its emails then belong to no account that's
+    /// shown, so they're dropped rather than listed under another one.
+    ///
+    /// # Parameters
+    /// - `account`: The account the fetch started with
     ///
     /// # Returns
-    /// A Result indicating success or failure
-    pub async fn retry_connections(&self) -> Result<()> {
-        // Get selected account index
-        let selected_account = {
-            let state = self.state.lock().await;
-            state.selected_account
-        };
-        
-        if let Some(index) = selected_account {
-            // Retry connections
-            let account_manager = self.account_manager.lock().await;
-            account_manager.retry_connections(index).await?;
-            
-            // Load emails
-            self.load_emails().await?;
+    /// The locked state if the account is still the selected one, None otherwise
+    async fn state_for_account(&self, account: &Arc<Mutex<Account>>) -> Option<RwLockWriteGuard<'_, AppState>> {
+        // Lock the managers in the order saving an account does, so it can't change in between
+        let account_manager = self.account_manager.lock().await;
+        let state = self.state.write().await;
+        if account_manager.is_current(account) && state.is_selected_account(account) {
+            return Some(state);
         }
         
-        Ok(())
+        info!("Dropped the emails fetched for an account that changed during the fetch");
+        None
     }
     
-    /// Disconnects all clients.
+    /// Lists the selected account's scheduled messages as the email list.
     ///
     /// # Returns
     /// A Result indicating success or failure
-    pub async fn disconnect_all_clients(&self) -> Result<()> {
-        let account_manager = self.account_manager.lock().await;
-        account_manager.disconnect_all_clients().await?;
+    async fn load_scheduled_emails(&self) -> Result<()> {
+        let mut state = self.state.write().await;
+        let account_id = state.selected_account
+            .and_then(|index| state.account_summaries.get(index))
+            .map(|summary| summary.id.clone())
+            .ok_or_else(|| anyhow!("No account selected"))?;
+        
+        let scheduled = state.storage.get_scheduled_emails(&account_id)?;
+        state.emails = Arc::new(scheduled.iter().map(|entry| entry.to_list_entry()).collect());
+        state.selected_email = None;
+        state.clamp_selection();
+        state.viewed_email = None;
         
         Ok(())
     }
     
-    /// Marks an email as read.
+    /// Refetches emails for the selected folder without reconnecting.
     ///
-    /// # Parameters
-    /// - `email_index`: The index of the email to mark
+    /// Shows the loading indicator while the fetch is running and keeps the
+    /// current email selected if it is still in the folder.
     ///
     /// # Returns
     /// A Result indicating success or failure
-    pub async fn mark_email_as_read(&self, email_index: usize) -> Result<()> {
-        // Get selected account index, folder, and email
-        let (selected_account, folder, email) = {
-            let state = self.state.lock().await;
-            if email_index >= state.emails.len() {
-                return Ok(());
-            }
-            (
-                state.selected_account,
-                state.selected_folder.clone(),
-                state.emails[email_index].clone(),
-            )
+    pub async fn refresh_emails(&self) -> Result<()> {
+        // Show the loading indicator and remember the selection
+        let selected_id = {
+            let mut state = self.state.write().await;
+            let message = format!("Refreshing {}...", state.selected_folder);
+            state.set_loading_message(Some(message));
+            state.selected_email
+                .and_then(|index| state.emails.get(index))
+                .map(|email| email.id.clone())
         };
         
-        if let Some(index) = selected_account {
-            // Get IMAP client
-            let account_manager = self.account_manager.lock().await;
-            let imap_client = account_manager.get_imap_client(index).cloned();
-            
-            if let Some(imap_client) = imap_client {
-                // Mark as read
-                let email_manager = self.email_manager.lock().await;
-                email_manager.mark_as_read(&imap_client, &email, &folder).await?;
-                
-                // Update email in state
-                let mut state = self.state.lock().await;
-                if email_index < state.emails.len() {
-                    state.emails[email_index].is_read = true;
-                }
-                
-                // Update viewed email if it's the same
-                if let Some(viewed_email) = &mut state.viewed_email {
-                    if viewed_email.id == email.id {
-                        viewed_email.is_read = true;
-                    }
-                }
-            }
+        let result = self.load_emails().await;
+        
+        let mut state = self.state.write().await;
+        state.set_loading_message(None);
+        result?;
+        
+        // Restore the previous selection
+        if let Some(id) = selected_id
+            && let Some(index) = state.emails.iter().position(|email| email.id == id) {
+            state.selected_email = Some(index);
         }
         
         Ok(())
     }
     
-    /// Creates a new account form.
+    /// Searches the selected account for emails matching a query and lists the results.
+    ///
+    /// # Parameters
+    /// - `query`: The query, as typed by the user
+    /// - `all_folders`: Whether to search every folder instead of the one the search starts from
+    /// - `name`: The name of the saved search being opened, if any
     ///
     /// # Returns
     /// A Result indicating success or failure
-    pub async fn create_account_form(&self) -> Result<()> {
-        let mut state = self.state.lock().await;
-        state.account_form_state = Some(AccountFormState::new());
-        state.current_view = View::AccountConfig;
+    pub async fn search_emails(&self, query: String, all_folders: bool, name: Option<String>) -> Result<()> {
+        let (selected_account, account, folder, offline) = {
+            let mut state = self.state.write().await;
+            state.set_loading_message(Some(format!("Searching for \"{}\"...", query)));
+            let account = state.selected_account
+                .and_then(|index| state.accounts.get(index))
+                .map(|bundle| bundle.account.clone());
+            (state.get_selected_account_id(), account, state.search_folder().to_string(), state.offline)
+        };
+        
+        let result = self.run_search(selected_account, &folder, &SearchQuery::parse(&query), all_folders, offline).await;
+        
+        let state = match &account {
+            Some(account) => self.state_for_account(account).await,
+            None => None,
+        };
+        let Some(mut state) = state else {
+            self.state.write().await.set_loading_message(None);
+            return Ok(());
+        };
+        state.set_loading_message(None);
+        let results = result?;
+        
+        // List the results in place of the folder, saying if the cache was searched instead
+        let search = SearchState::new(query, all_folders, folder, name);
+        let message = match &results.rejected {
+            Some(reason) => format!("{} emails found in the cache of {} - {}", results.emails.len(), search.scope_label(), reason),
+            None => format!("{} emails found in {}", results.emails.len(), search.scope_label()),
+        };
+        state.set_status_message(message);
+        state.emails = Arc::new(results.emails);
+        state.selected_email = None;
+        state.clamp_selection();
+        state.viewed_email = None;
+        state.search = Some(search);
         
         Ok(())
     }
     
-    /// Saves an account from the form.
+    /// Runs a search on the server, or on the cache when IMAP isn't available.
+    ///
+    /// # Parameters
+    /// - `selected_account`: The account ID
+    /// - `folder`: The folder the search starts from
+    /// - `query`: The query to match
+    /// - `all_folders`: Whether to search every folder of the account
+    /// - `offline`: Whether only the cache may be searched
     ///
     /// # Returns
-    /// A Result indicating success or failure
-    pub async fn save_account_form(&self) -> Result<()> {
-        // Get form state and check if it's valid
-        let state = self.state.lock().await;
-        let form_state_opt = state.account_form_state.clone();
-        drop(state);
+    /// A Result containing the search results or an error
+    async fn run_search(
+        &self,
+        selected_account: Option<String>,
+        folder: &str,
+        query: &SearchQuery,
+        all_folders: bool,
+        offline: bool,
+    ) -> Result<SearchResults> {
+        let account_id = selected_account.ok_or_else(|| anyhow!("No account selected"))?;
         
-        let (form_state, is_new_account) = if let Some(mut form_state) = form_state_opt {
-            if !form_state.validate() {
-                return Ok(());
-            }
-            (form_state.clone(), form_state.is_new_account)
+        let account_manager = self.account_manager.lock().await;
+        let account = account_manager.get_account(&account_id).cloned()
+            .ok_or_else(|| anyhow!("No account selected"))?;
+        let imap_client = account_manager.get_imap_client(&account_id).filter(|_| !offline).cloned();
+        drop(account_manager);
+        
+        let folders = if all_folders {
+            account.lock().await.folders.clone()
         } else {
-            return Ok(());
+            vec![folder.to_string()]
         };
         
-        // Finalize account
-        let account_config = form_state.finalize_account();
+        // Search on a copy so refreshes and body loads aren't held up while
+        // every folder is searched
+        let email_manager = self.email_manager.lock().await.clone();
+        email_manager.search_emails(&account, imap_client.as_ref(), &folders, query, 50).await
+    }
+    
+    /// Retries failed connections for the selected account.
+    ///
+    /// The account manager isn't held while connecting, so other accounts
+    /// and the UI aren't held up by a slow server. The status bar says
+    /// how each retried protocol did.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn retry_connections(&self) -> Result<()> {
+        if self.report_offline().await || self.report_disabled().await {
+            return Ok(());
+        }
         
-        // Get configuration manager from state
-        let mut config_manager = {
-            let state = self.state.lock().await;
-            state.config_manager.clone()
+        // Get selected account
+        let selected_account = {
+            let state = self.state.read().await;
+            state.get_selected_account_id()
+        };
+        let Some(account_id) = selected_account else {
+            return Ok(());
+        };
+        let Some(bundle) = self.account_manager.lock().await.get_bundle(&account_id).cloned() else {
+            return Ok(());
         };
         
-        // Add or update account
-        let mut account_manager = self.account_manager.lock().await;
-        if is_new_account {
-            // Add new account
-            account_manager.add_account(account_config, &mut config_manager).await?;
-        } else {
-            // Get selected account index
-            let selected_account = {
-                let state = self.state.lock().await;
-                state.selected_account
-            };
-            
-            if let Some(index) = selected_account {
-                // Update existing account
-                account_manager.update_account(index, account_config, &mut config_manager).await?;
-            }
+        // Retry connections
+        let outcomes = bundle.retry_failed().await;
+        if outcomes.is_empty() {
+            let status = bundle.account.lock().await.get_status_summary();
+            self.set_status_message(format!("No failed connections to retry - {}", status)).await;
+            return Ok(());
         }
         
-        // Update state with accounts
-        let accounts = account_manager.get_accounts().clone();
-        let mut state = self.state.lock().await;
-        state.accounts = accounts;
-        
-        // Update account summaries
-        state.update_account_summaries();
-        
-        // Clear form state and go back to accounts view
-        state.account_form_state = None;
-        state.current_view = View::Accounts;
+        // Save the folders and load emails
+        let reconnected = outcomes.iter().any(|outcome| outcome.error.is_none());
+        if reconnected {
+            self.store_folders(&bundle.account).await;
+            self.load_emails().await?;
+        }
         
-        // Set status message
-        state.set_status_message("Account saved successfully".to_string());
+        self.set_status_message(describe_retry(&outcomes)).await;
         
         Ok(())
     }
     
-    /// Deletes the selected account.
+    /// Disconnects and reconnects every protocol of the selected account.
     ///
     /// # Returns
     /// A Result indicating success or failure
-    pub async fn delete_selected_account(&self) -> Result<()> {
-        // Get selected account index
-        let selected_account = {
-            let state = self.state.lock().await;
-            state.selected_account
+    pub async fn reconnect_selected_account(&self) -> Result<()> {
+        if self.report_offline().await || self.report_disabled().await {
+            return Ok(());
+        }
+        
+        // Get selected account
+        let (selected_account, send_only) = {
+            let state = self.state.read().await;
+            (state.get_selected_account_id(), state.is_selected_account_send_only())
         };
         
-        if let Some(index) = selected_account {
-            // Get configuration manager from state
-            let mut config_manager = {
-                let state = self.state.lock().await;
-                state.config_manager.clone()
-            };
-            
-            // Delete account
-            let mut account_manager = self.account_manager.lock().await;
-            account_manager.delete_account(index, &mut config_manager).await?;
-            
-            // Update state with accounts
-            let accounts = account_manager.get_accounts().clone();
-            let mut state = self.state.lock().await;
-            state.accounts = accounts;
-            
-            // Update account summaries
-            state.update_account_summaries();
-            
-            // Update selected account
-            if state.accounts.is_empty() {
-                state.selected_account = None;
-            } else if index >= state.accounts.len() {
-                state.selected_account = Some(state.accounts.len() - 1);
-            }
-            
-            // Set status message
-            state.set_status_message("Account deleted successfully".to_string());
+        let Some(account_id) = selected_account else {
+            return Ok(());
+        };
+        
+        // Reconnect all protocols, without holding up other accounts meanwhile
+        let Some(bundle) = self.account_manager.lock().await.get_bundle(&account_id).cloned() else {
+            return Ok(());
+        };
+        let connection_successful = bundle.reconnect().await?;
+        if connection_successful {
+            self.store_folders(&bundle.account).await;
+        }
+        let status = bundle.account.lock().await.get_status_summary();
+        
+        // Reload the mailbox over the new sessions
+        if connection_successful && !send_only {
+            self.load_emails().await?;
         }
         
+        let mut state = self.state.write().await;
+        state.set_status_message(format!("Reconnected - {}", status));
+        
         Ok(())
     }
     
-    /// Closes the database storage.
+    /// Tells the user that connections are disabled when offline.
+    ///
+    /// # Returns
+    /// true if the app is offline, false otherwise
+    async fn report_offline(&self) -> bool {
+        let mut state = self.state.write().await;
+        if state.offline {
+            state.set_status_message("Offline - connections are disabled".to_string());
+        }
+        state.offline
+    }
+    
+    /// Tells the user that the selected account is disabled.
+    ///
+    /// # Returns
+    /// true if the selected account is disabled, false otherwise
+    async fn report_disabled(&self) -> bool {
+        let mut state = self.state.write().await;
+        let disabled = state.is_selected_account_disabled();
+        if disabled {
+            state.set_status_message("Account disabled - press 'x' in the accounts view to enable it".to_string());
+        }
+        disabled
+    }
+    
+    /// Gets the IMAP client of an account.
+    ///
+    /// # Parameters
+    /// - `account_id`: The ID of the account
+    ///
+    /// # Returns
+    /// An Option containing the IMAP client, or None if the account isn't managed
+    async fn imap_client_for(&self, account_id: &str) -> Option<Arc<Mutex<ImapClient>>> {
+        self.account_manager.lock().await.get_imap_client(account_id).cloned()
+    }
+    
+    /// Disconnects all clients.
     ///
     /// # Returns
     /// A Result indicating success or failure
-    pub async fn close_storage(&self) -> Result<()> {
-        info!("Closing database storage");
-        
-        // Close storage in app state
-        let state = self.state.lock().await;
-        state.storage.close()?;
-        
-        // Close storage in email manager
-        let email_manager = self.email_manager.lock().await;
-        email_manager.close_storage()?;
+    pub async fn disconnect_all_clients(&self) -> Result<()> {
+        let account_manager = self.account_manager.lock().await;
+        account_manager.disconnect_all_clients().await?;
         
         Ok(())
     }
     
-    /// Shuts down the application.
+    /// Marks an email as read.
+    ///
+    /// # Parameters
+    /// - `key`: The email's account, folder and ID
     ///
     /// # Returns
     /// A Result indicating success or failure
-    pub async fn shutdown(&self) -> Result<()> {
-        info!("Shutting down Linksy email client");
-        
-        // Disconnect all clients
-        self.disconnect_all_clients().await?;
-        
-        // Close database storage
-        self.close_storage().await?;
+    pub async fn mark_email_as_read(&self, key: EmailKey) -> Result<()> {
+        self.change_email_flag(key, FlagChange::Read(true)).await
+    }
+    
+    /// Marks an email that was just opened as read, as the settings say.
+    ///
+    /// With a delay, the email is only marked if it is still the one being
+    /// viewed once the delay has passed.
+    ///
+    /// # Parameters
+    /// - `key`: The email's account, folder and ID
+    /// - `policy`: When to mark the email as read
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn mark_opened_email_as_read(&self, key: EmailKey, policy: MarkReadPolicy) -> Result<()> {
+        let delay = match policy {
+            MarkReadPolicy::Immediate => return self.mark_email_as_read(key).await,
+            MarkReadPolicy::AfterDelay(secs) => Duration::from_secs(secs),
+            MarkReadPolicy::Manual => return Ok(()),
+        };
+        tokio::time::sleep(delay).await;
         
-        // Set running state to false
-        let mut state = self.state.lock().await;
-        state.running = false;
+        let still_viewed = {
+            let state = self.state.read().await;
+            state.current_view == View::EmailDetail
+                && state.viewed_email.as_ref().is_some_and(|email| email.has_key(&key))
+        };
+        if !still_viewed {
+            return Ok(());
+        }
         
-        Ok(())
+        self.mark_email_as_read(key).await
     }
+    
+    /// Changes the read or flagged state of an email on the server.
+    ///
+    /// The change is shown right away and the email is marked as pending
+    /// until the server confirms it. If the server call fails, the change is
+    /// reverted and the user is told. The email is looked up when the change
+    /// starts, since the list may have been reloaded since it was asked for.
+    ///
+    /// # Parameters
+    /// - `key`: The email's account, folder and ID
+    /// - `change`: The change to make
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn change_email_flag(&self, key: EmailKey, change: FlagChange) -> Result<()> {
+        let offline = self.state.read().await.offline;
+        
+        // Flags can only be kept on the email's IMAP server
+        let imap_client = self.imap_client_for(&key.account_id).await.filter(|_| !offline);
+        let Some(imap_client) = imap_client else {
+            // Opening an email marks it as read, which isn't worth a message
+            if change != FlagChange::Read(true) {
+                self.set_status_message("Can't change flags without an IMAP connection".to_string()).await;
+            }
+            return Ok(());
+        };
+        
+        // Apply the change locally and remember the email as it was
+        let original = {
+            let mut state = self.state.write().await;
+            let Some(email) = find_email(&state, &key) else {
+                return Ok(());
+            };
+            if change.is_applied_to(email) {
+                return Ok(());
+            }
+            let original = email.clone();
+            
+            update_listed_email(&mut state, &original, |email| {
+                change.apply(email);
+                email.sync_pending = true;
+            });
+            original
+        };
+        
+        let result = {
+            let email_manager = self.email_manager.lock().await.clone();
+            let folder = &original.folder;
+            match change {
+                FlagChange::Read(true) => email_manager.mark_as_read(&imap_client, &original, folder).await,
+                FlagChange::Read(false) => email_manager.mark_as_unread(&imap_client, &original, folder).await,
+                FlagChange::Flagged(true) => email_manager.flag_email(&imap_client, &original, folder).await,
+                FlagChange::Flagged(false) => email_manager.unflag_email(&imap_client, &original, folder).await,
+            }
+        };
+        
+        // Confirm the change, or put the email back the way it was
+        let mut state = self.state.write().await;
+        match result {
+            Ok(()) => update_listed_email(&mut state, &original, |email| email.sync_pending = false),
+            Err(e) => {
+                update_listed_email(&mut state, &original, |email| {
+                    email.is_read = original.is_read;
+                    email.is_flagged = original.is_flagged;
+                    email.sync_pending = false;
+                });
+                state.set_status_message(format!("Couldn't mark \"{}\" as {} on the server: {}", original.subject, change.label(), e));
+            },
+        }
+        
+        Ok(())
+    }
+    
+    /// Changes the read or flagged state of several emails on the server.
+    ///
+    /// Each email is changed like with change_email_flag, one after the
+    /// other. Emails that are no longer listed are skipped.
+    ///
+    /// # Parameters
+    /// - `keys`: The emails' accounts, folders and IDs
+    /// - `change`: The change to make
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn change_email_flags(&self, keys: Vec<EmailKey>, change: FlagChange) -> Result<()> {
+        for key in keys {
+            self.change_email_flag(key, change).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Adds a tag to an email or removes it.
+    ///
+    /// The tag is stored as an IMAP keyword if the server keeps keywords,
+    /// and otherwise only in the local cache.
+    ///
+    /// # Parameters
+    /// - `key`: The email's account, folder and ID
+    /// - `tag`: The tag
+    /// - `add`: true to add the tag, false to remove it
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn change_email_tag(&self, key: EmailKey, tag: String, add: bool) -> Result<()> {
+        let (offline, original) = {
+            let state = self.state.read().await;
+            let Some(email) = find_email(&state, &key) else {
+                return Ok(());
+            };
+            (state.offline, email.clone())
+        };
+        
+        // Keywords can only be kept on the email's IMAP server
+        let imap_client = self.imap_client_for(&key.account_id).await.filter(|_| !offline);
+        let email_manager = self.email_manager.lock().await.clone();
+        let updated = email_manager.change_tag(imap_client.as_ref(), &original, &tag, add).await?;
+        
+        let mut state = self.state.write().await;
+        update_listed_email(&mut state, &original, |email| {
+            email.keywords = updated.keywords.clone();
+            email.local_tags = updated.local_tags.clone();
+        });
+        let message = match (add, updated.is_local_tag(&tag)) {
+            (true, true) => format!("Tagged \"{}\" {} locally - the server doesn't keep keywords", original.subject, tag_label(&tag)),
+            (true, false) => format!("Tagged \"{}\" {}", original.subject, tag_label(&tag)),
+            (false, _) => format!("Removed {} from \"{}\"", tag_label(&tag), original.subject),
+        };
+        state.set_status_message(message);
+        
+        Ok(())
+    }
+    
+    /// Downloads the body of a headers-only email so it can be read.
+    ///
+    /// Unless `full` is set, large messages are only downloaded up to the
+    /// `max_body_size_kb` setting, and with `attachments_on_demand` only the
+    /// text parts of a multipart message are downloaded.
+    ///
+    /// # Parameters
+    /// - `key`: The email's account, folder and ID
+    /// - `full`: Whether to download all of the message, also to complete a truncated one
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn load_email_body(&self, key: EmailKey, full: bool) -> Result<()> {
+        let (email, offline, max_bytes, on_demand) = {
+            let state = self.state.read().await;
+            let settings = &state.config_manager.get_config().settings;
+            let max_bytes = if full {
+                None
+            } else {
+                settings.max_body_bytes()
+            };
+            match find_email(&state, &key) {
+                Some(email) if email.headers_only || (full && email.truncated) => (
+                    email.clone(),
+                    state.offline,
+                    max_bytes,
+                    settings.attachments_on_demand && !full,
+                ),
+                _ => return Ok(()),
+            }
+        };
+        
+        let imap_client = self.imap_client_for(&key.account_id).await.filter(|_| !offline);
+        let Some(imap_client) = imap_client else {
+            self.set_status_message("This email hasn't been downloaded yet; connect to read it".to_string()).await;
+            return Ok(());
+        };
+        
+        // Only the text parts are needed to read a multipart message
+        let body_sections = email.structure.as_ref()
+            .filter(|structure| on_demand && email.headers_only && structure.is_multipart())
+            .map(|structure| structure.body_sections())
+            .filter(|sections| !sections.is_empty());
+        
+        let full_email = {
+            let email_manager = self.email_manager.lock().await.clone();
+            match body_sections {
+                Some(sections) => email_manager.fetch_body_parts(&imap_client, &email, &sections).await?,
+                None => email_manager.fetch_body(&imap_client, &email, &key.folder, max_bytes).await?,
+            }
+        };
+        
+        // Replace the email in the list and the detail view, keeping the flags shown
+        let mut state = self.state.write().await;
+        update_listed_email(&mut state, &email, |listed| {
+            *listed = Email { is_read: listed.is_read, is_flagged: listed.is_flagged, sync_pending: listed.sync_pending, ..full_email.clone() };
+        });
+        
+        Ok(())
+    }
+    
+    /// Downloads an attachment of an email that was only listed and previews it.
+    ///
+    /// # Parameters
+    /// - `key`: The email's account, folder and ID
+    /// - `index`: Index of the attachment
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn download_attachment(&self, key: EmailKey, index: usize) -> Result<()> {
+        let (offline, email) = {
+            let mut state = self.state.write().await;
+            let Some(email) = find_email(&state, &key).cloned() else {
+                return Ok(());
+            };
+            let Some(attachment) = email.attachments.get(index) else {
+                return Ok(());
+            };
+            state.set_loading_message(Some(format!("Downloading {}...", attachment.filename)));
+            (state.offline, email)
+        };
+        
+        let imap_client = self.imap_client_for(&key.account_id).await.filter(|_| !offline);
+        let email_manager = self.email_manager.lock().await.clone();
+        let result = match imap_client {
+            Some(imap_client) => email_manager.fetch_attachment(&imap_client, &email, index).await,
+            None => Err(anyhow!("connect to download the attachment")),
+        };
+        
+        let mut state = self.state.write().await;
+        state.set_loading_message(None);
+        let updated = result?;
+        update_listed_email(&mut state, &email, |listed| listed.attachments = updated.attachments.clone());
+        
+        // Show the preview if the attachment list is still open
+        match updated.attachments.get(index).map(preview_attachment) {
+            Some(Ok(preview)) => {
+                if let Some(picker) = state.attachment_picker.as_mut() {
+                    picker.selected = index;
+                    picker.preview = Some(preview);
+                }
+            },
+            Some(Err(e)) => state.set_status_message(e.to_string()),
+            None => {},
+        }
+        
+        Ok(())
+    }
+    
+    /// Opens an email in the system's default viewer for .eml files.
+    ///
+    /// A message that couldn't be parsed still has its raw source; any
+    /// other is downloaded again, since the cache doesn't keep the original.
+    ///
+    /// # Parameters
+    /// - `key`: The email's account, folder and ID
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn open_in_viewer(&self, key: EmailKey) -> Result<()> {
+        let (offline, email) = {
+            let mut state = self.state.write().await;
+            let Some(email) = find_email(&state, &key).cloned() else {
+                return Ok(());
+            };
+            if email.raw_source.is_none() {
+                state.set_loading_message(Some("Downloading the original message...".to_string()));
+            }
+            (state.offline, email)
+        };
+        
+        let raw = match &email.raw_source {
+            Some(source) => Ok(source.clone().into_bytes()),
+            None => {
+                let imap_client = self.imap_client_for(&key.account_id).await.filter(|_| !offline);
+                let email_manager = self.email_manager.lock().await.clone();
+                match imap_client {
+                    Some(imap_client) => email_manager.fetch_raw(&imap_client, &email).await,
+                    None => Err(anyhow!("connect to download the original message")),
+                }
+            },
+        };
+        
+        let mut state = self.state.write().await;
+        state.set_loading_message(None);
+        let path = viewer::open_message(&raw?)?;
+        state.set_status_message(format!("Opened the message in your mail viewer from {}", path.display()));
+        state.viewer_files.push(path);
+        
+        Ok(())
+    }
+    
+    /// Sets a status message.
+    ///
+    /// # Parameters
+    /// - `message`: The message to set
+    pub async fn set_status_message(&self, message: String) {
+        let mut state = self.state.write().await;
+        state.set_status_message(message);
+    }
+    
+    /// Creates a new account form.
+    ///
+    /// Takes the already locked state since this is called from key handling.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    pub fn create_account_form(&self, state: &mut AppState) {
+        state.account_form_state = Some(AccountFormState::new());
+        state.current_view = View::AccountConfig;
+    }
+    
+    /// Creates an account form for a new account with the settings of the selected one.
+    ///
+    /// Takes the already locked state since this is called from key handling.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    pub fn copy_account_form(&self, state: &mut AppState) {
+        let Some(account) = state.get_selected_account_config() else {
+            return;
+        };
+        
+        let name = account.name.clone();
+        state.account_form_state = Some(AccountFormState::copy_of(account));
+        state.current_view = View::AccountConfig;
+        state.set_status_message(format!("Copied the server settings of {} - fill in the new account's details", name));
+    }
+    
+    /// Saves an account from the form.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn save_account_form(&self) -> Result<()> {
+        // Get form state and check if it's valid
+        let state = self.state.read().await;
+        let form_state_opt = state.account_form_state.clone();
+        drop(state);
+        
+        let (form_state, is_new_account) = if let Some(mut form_state) = form_state_opt {
+            if !form_state.validate() {
+                return Ok(());
+            }
+            (form_state.clone(), form_state.is_new_account)
+        } else {
+            return Ok(());
+        };
+        
+        // Finalize account
+        let account_config = form_state.finalize_account();
+        
+        let mut account_manager = self.account_manager.lock().await;
+        let mut state = self.state.write().await;
+        
+        // Add or update account, saving only this account in the configuration
+        let replaced = if is_new_account {
+            state.config_manager.add_account(account_config.clone())?;
+            account_manager.add_account(account_config);
+            None
+        } else if account_manager.get_bundle(&account_config.id).is_some() {
+            // Update the account the form was opened for, wherever it is now
+            state.config_manager.update_account(account_config.clone())?;
+            account_manager.update_account(account_config)
+        } else {
+            // Keep the form open so the edits aren't lost
+            state.set_status_message(format!("No account with ID \"{}\" to save the changes to", account_config.id));
+            return Ok(());
+        };
+        
+        // Update state with accounts
+        state.set_accounts(account_manager.get_bundles());
+        drop(account_manager);
+        
+        // Clear form state and go back to accounts view
+        state.account_form_state = None;
+        state.current_view = View::Accounts;
+        
+        // Set status message
+        state.set_status_message("Account saved successfully".to_string());
+        drop(state);
+        
+        // Close the edited account's old connections
+        if let Some(bundle) = replaced {
+            bundle.disconnect().await;
+        }
+        
+        Ok(())
+    }
+    
+    /// Deletes the selected account.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn delete_selected_account(&self) -> Result<()> {
+        let mut account_manager = self.account_manager.lock().await;
+        let mut state = self.state.write().await;
+        
+        // Get selected account
+        if let (Some(index), Some(account_id)) = (state.selected_account, state.get_selected_account_id()) {
+            // Delete account
+            state.config_manager.remove_account(&account_id)?;
+            let removed = account_manager.delete_account(&account_id);
+            
+            // Update state with accounts
+            state.set_accounts(account_manager.get_bundles());
+            drop(account_manager);
+            
+            // Update selected account; the deleted account's emails leave the selection
+            if state.accounts.is_empty() {
+                state.selected_account = None;
+            } else if index >= state.accounts.len() {
+                state.selected_account = Some(state.accounts.len() - 1);
+            }
+            state.selected_emails.clear();
+            
+            // Set status message
+            state.set_status_message("Account deleted successfully".to_string());
+            drop(state);
+            
+            // Close the deleted account's connections
+            if let Some(bundle) = removed {
+                bundle.disconnect().await;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Moves the selected account one position up or down in the list.
+    ///
+    /// # Parameters
+    /// - `up`: true to move the account up, false to move it down
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn move_selected_account(&self, up: bool) -> Result<()> {
+        let mut account_manager = self.account_manager.lock().await;
+        let mut state = self.state.write().await;
+        
+        // Get the selected account
+        let (Some(index), Some(account_id)) = (state.selected_account, state.get_selected_account_id()) else {
+            return Ok(());
+        };
+        let new_index = if up {
+            match index.checked_sub(1) {
+                Some(new_index) => new_index,
+                None => return Ok(()),
+            }
+        } else {
+            index + 1
+        };
+        if new_index >= account_manager.account_count() {
+            return Ok(());
+        }
+        
+        // Move the account in the configuration, then with its clients
+        state.config_manager.move_account(&account_id, new_index)?;
+        account_manager.move_account(&account_id, new_index);
+        
+        // Update state with the new order, keeping the moved account selected
+        state.set_accounts(account_manager.get_bundles());
+        state.selected_account = account_manager.find_account_index(&account_id);
+        
+        Ok(())
+    }
+    
+    /// Enables the selected account, or disables it.
+    ///
+    /// A disabled account is disconnected and left out of every background
+    /// task until it's enabled again. Enabling it connects it again if it
+    /// connects on startup.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn toggle_selected_account_enabled(&self) -> Result<()> {
+        // Get selected account
+        let (selected, offline) = {
+            let state = self.state.read().await;
+            let selected = state.selected_account
+                .and_then(|index| state.account_summaries.get(index).map(|summary| (summary.id.clone(), summary.enabled, summary.name.clone())));
+            (selected, state.offline)
+        };
+        
+        let Some((account_id, was_enabled, name)) = selected else {
+            return Ok(());
+        };
+        let Some(bundle) = self.account_manager.lock().await.get_bundle(&account_id).cloned() else {
+            return Ok(());
+        };
+        
+        // Save the change first, so the account is left as it was if that fails
+        self.state.write().await.config_manager.set_account_enabled(&bundle.id, !was_enabled)?;
+        
+        // Disconnect a disabled account
+        bundle.set_enabled(!was_enabled).await;
+        {
+            let mut state = self.state.write().await;
+            state.update_account_summaries();
+            let message = if was_enabled { "disabled" } else { "enabled" };
+            state.set_status_message(format!("{} {}", name, message));
+        }
+        
+        // Connect an enabled account like on startup
+        let auto_connect = bundle.account.lock().await.config.auto_connect;
+        if !was_enabled && auto_connect && !offline && bundle.connect().await? {
+            self.store_folders(&bundle.account).await;
+        }
+        
+        Ok(())
+    }
+    
+    /// Queues the message in the compose form for sending from the selected account.
+    ///
+    /// The message waits in the outbox for the account's send delay, during
+    /// which it can be recalled. Takes the already locked state since this is
+    /// called from key handling.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn queue_composed_email(&self, state: &mut AppState) -> Result<()> {
+        let (email, delay) = Self::build_composed_email(state)?;
+        
+        // Queue the message and leave the compose view
+        state.outbox.queue(email, delay);
+        state.compose_state = None;
+        state.current_view = View::Emails;
+        if delay.is_zero() {
+            let message = match state.config_manager.get_config().settings.archive_address() {
+                Some(archive) => format!("Sending message, with a Bcc to the archive at {}...", archive),
+                None => "Sending message...".to_string(),
+            };
+            state.set_status_message(message);
+        }
+        
+        Ok(())
+    }
+    
+    /// Stores the message in the compose form to be sent later from the selected account.
+    ///
+    /// Takes the already locked state since this is called from key handling.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    /// - `delay`: How long from now to send the message
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn schedule_composed_email(&self, state: &mut AppState, delay: Duration) -> Result<()> {
+        let (mut email, _) = Self::build_composed_email(state)?;
+        
+        // Scheduled messages need an id to be stored under
+        email.id = uuid::Uuid::new_v4().to_string();
+        email.folder = SCHEDULED_FOLDER.to_string();
+        
+        let scheduled = ScheduledEmail::new(email, SystemTime::now() + delay);
+        state.storage.store_scheduled_email(&scheduled)?;
+        
+        // Leave the compose view
+        state.compose_state = None;
+        state.current_view = View::Emails;
+        state.set_status_message(format!(
+            "Scheduled \"{}\" to send {}",
+            scheduled.email.subject,
+            crate::ui::views::format_time_until(scheduled.send_at),
+        ));
+        
+        Ok(())
+    }
+    
+    /// Keeps the message in the compose form as the draft, to be written further later.
+    ///
+    /// Unlike sending, the message doesn't need recipients or an enabled
+    /// account. It replaces any draft saved before.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn save_draft(&self, state: &mut AppState) -> Result<()> {
+        let compose = state.compose_state.as_ref()
+            .ok_or_else(|| anyhow!("Nothing to save"))?;
+        
+        let mut draft = compose.to_email();
+        draft.id = uuid::Uuid::new_v4().to_string();
+        if let Some(summary) = state.selected_account.and_then(|index| state.account_summaries.get(index)) {
+            draft.account_id = summary.id.clone();
+        }
+        state.storage.store_draft(&draft)?;
+        
+        Ok(())
+    }
+    
+    /// Builds a message from the compose form for the selected account.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result containing the message and the account's send delay
+    fn build_composed_email(state: &AppState) -> Result<(Email, Duration)> {
+        let compose = state.compose_state.as_ref()
+            .ok_or_else(|| anyhow!("Nothing to send"))?;
+        
+        // Find the sending account
+        let account_id = state.selected_account
+            .and_then(|index| state.account_summaries.get(index))
+            .map(|summary| summary.id.clone())
+            .ok_or_else(|| anyhow!("No account selected"))?;
+        let account_config = state.config_manager.get_config().accounts
+            .iter()
+            .find(|account| account.id == account_id)
+            .ok_or_else(|| anyhow!("Account {} not found", account_id))?;
+        if !account_config.enabled {
+            return Err(anyhow!("{} is disabled", account_config.name));
+        }
+        
+        // Build the message
+        let mut email = compose.to_email();
+        email.account_id = account_config.id.clone();
+        email.from = account_config.email.clone();
+        email.from_name = Some(account_config.name.clone());
+        
+        if email.to.is_empty() && email.cc.is_empty() {
+            return Err(anyhow!("Add at least one recipient"));
+        }
+        
+        Ok((email, Duration::from_secs(account_config.send_delay_secs)))
+    }
+    
+    /// Queues the reply to the calendar invitation in the viewed email.
+    ///
+    /// The reply goes to the organizer from the email's account, with the
+    /// answer in an iCalendar part with METHOD=REPLY. Takes the already
+    /// locked state since this is called from key handling.
+    ///
+    /// # Parameters
+    /// - `accept`: true to accept the invitation, false to decline it
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn answer_invite(&self, accept: bool, state: &mut AppState) -> Result<()> {
+        let email = state.viewed_email.as_ref()
+            .ok_or_else(|| anyhow!("No email is open"))?;
+        let event = email.invite.as_ref()
+            .filter(|event| event.can_reply())
+            .ok_or_else(|| anyhow!("This email has no invitation to answer"))?;
+        let organizer = event.organizer.clone()
+            .ok_or_else(|| anyhow!("The invitation has no organizer"))?;
+        let account_config = state.config_manager.get_config().accounts
+            .iter()
+            .find(|account| account.id == email.account_id)
+            .ok_or_else(|| anyhow!("Account {} not found", email.account_id))?;
+        if !account_config.enabled {
+            return Err(anyhow!("{} is disabled", account_config.name));
+        }
+        
+        // Build the reply
+        let (answer, answered) = if accept { ("Accepted", "accepted") } else { ("Declined", "declined") };
+        let title = event.summary.clone().unwrap_or_else(|| email.subject.clone());
+        let data = event.reply(&account_config.email, accept).into_bytes();
+        let mut reply = Email::new();
+        reply.account_id = account_config.id.clone();
+        reply.from = account_config.email.clone();
+        reply.from_name = Some(account_config.name.clone());
+        reply.to = vec![organizer.clone()];
+        reply.subject = format!("{}: {}", answer, title);
+        reply.body_text = Some(format!("{} has {} the invitation to {}.", account_config.name, answered, title));
+        reply.attachments.push(Attachment {
+            id: uuid::Uuid::new_v4().to_string(),
+            filename: "reply.ics".to_string(),
+            content_type: "text/calendar; method=REPLY; charset=utf-8".to_string(),
+            size: data.len(),
+            data,
+            content_id: None,
+            inline: false,
+            section: None,
+        });
+        
+        // Send it right away; there is nothing to edit
+        state.outbox.queue(reply, Duration::ZERO);
+        state.set_status_message(format!("Sending \"{}\" to {}...", answer, organizer));
+        
+        Ok(())
+    }
+    
+    /// Takes the selected scheduled message out of the schedule and opens it in compose.
+    ///
+    /// Takes the already locked state since this is called from key handling.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn edit_scheduled_email(&self, state: &mut AppState) -> Result<()> {
+        let email = self.remove_selected_scheduled_email(state)?;
+        
+        state.compose_state = Some(ComposeState::from_email(&email));
+        state.current_view = View::ComposeEmail;
+        state.set_status_message("Unscheduled for editing - press Ctrl+l to schedule it again".to_string());
+        
+        Ok(())
+    }
+    
+    /// Opens a copy of a sent message in compose, to edit and send again.
+    ///
+    /// The text is downloaded first if it hasn't been, or only part of it
+    /// was. Only the text parts are fetched when the body structure is
+    /// known, since compose doesn't keep the attachments.
+    ///
+    /// # Parameters
+    /// - `key`: The email's account, folder and ID
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn edit_as_new(&self, key: EmailKey) -> Result<()> {
+        let (mut email, offline) = {
+            let state = self.state.read().await;
+            let Some(email) = find_email(&state, &key).cloned() else {
+                return Ok(());
+            };
+            (email, state.offline)
+        };
+        
+        if email.headers_only || email.truncated {
+            let imap_client = self.imap_client_for(&key.account_id).await.filter(|_| !offline);
+            let Some(imap_client) = imap_client else {
+                self.set_status_message("This email hasn't been downloaded yet; connect to edit it".to_string()).await;
+                return Ok(());
+            };
+            
+            let body_sections = email.structure.as_ref()
+                .filter(|structure| structure.is_multipart())
+                .map(|structure| structure.body_sections())
+                .filter(|sections| !sections.is_empty());
+            
+            let fetched = {
+                let email_manager = self.email_manager.lock().await.clone();
+                match body_sections {
+                    Some(sections) => email_manager.fetch_body_parts(&imap_client, &email, &sections).await?,
+                    None => email_manager.fetch_body(&imap_client, &email, &key.folder, None).await?,
+                }
+            };
+            
+            // Keep the list in step with what was downloaded
+            let mut state = self.state.write().await;
+            update_listed_email(&mut state, &email, |listed| {
+                *listed = Email { is_read: listed.is_read, is_flagged: listed.is_flagged, sync_pending: listed.sync_pending, ..fetched.clone() };
+            });
+            email = fetched;
+        }
+        
+        let mut state = self.state.write().await;
+        state.compose_state = Some(ComposeState::from_email(&email));
+        state.current_view = View::ComposeEmail;
+        state.set_status_message(format!("Editing a copy of \"{}\" - press Ctrl+s to send it", email.subject));
+        
+        Ok(())
+    }
+    
+    /// Cancels the selected scheduled message.
+    ///
+    /// Takes the already locked state since this is called from key handling.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn cancel_scheduled_email(&self, state: &mut AppState) -> Result<()> {
+        let email = self.remove_selected_scheduled_email(state)?;
+        state.set_status_message(format!("Cancelled \"{}\"", email.subject));
+        
+        Ok(())
+    }
+    
+    /// Removes the selected message of the Scheduled folder from storage and the list.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result containing the removed message
+    fn remove_selected_scheduled_email(&self, state: &mut AppState) -> Result<Email> {
+        let index = state.selected_email
+            .filter(|index| *index < state.emails.len())
+            .ok_or_else(|| anyhow!("No scheduled message selected"))?;
+        
+        // The background task may have picked it up already
+        let email = state.emails[index].clone();
+        if state.outbox.contains(&email.id) {
+            return Err(anyhow!("\"{}\" is already being sent", email.subject));
+        }
+        
+        state.storage.delete_scheduled_email(&email.account_id, &email.id)?;
+        Arc::make_mut(&mut state.emails).remove(index);
+        state.clamp_selection();
+        
+        Ok(email)
+    }
+    
+    /// Starts the background task that hands due scheduled messages to the outbox.
+    pub fn start_scheduler(self: &Arc<Self>) {
+        let controller = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SCHEDULE_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = controller.dispatch_scheduled_emails().await {
+                    error!("Failed to check scheduled messages: {}", e);
+                }
+            }
+        });
+    }
+    
+    /// Starts the background task that checks the inboxes for new mail.
+    ///
+    /// The inboxes of the connected accounts are fetched every
+    /// check_interval minutes while auto_check is on, and new mail is
+    /// announced as the notification settings say.
+    pub fn start_mail_checker(self: &Arc<Self>) {
+        let controller = self.clone();
+        tokio::spawn(async move {
+            loop {
+                // Read the settings each time so changes apply without a restart;
+                // auto_check is read after the sleep so it applies on the next wake
+                let minutes = {
+                    let state = controller.state.read().await;
+                    state.config_manager.get_config().settings.check_interval.max(1)
+                };
+                tokio::time::sleep(Duration::from_secs(u64::from(minutes) * 60)).await;
+                
+                let auto_check = controller.state.read().await.config_manager.get_config().settings.auto_check;
+                if auto_check {
+                    controller.check_new_mail().await;
+                }
+            }
+        });
+    }
+    
+    /// Fetches the inbox of every connected account and announces new mail.
+    async fn check_new_mail(&self) {
+        let settings = {
+            let state = self.state.read().await;
+            if state.offline {
+                return;
+            }
+            state.config_manager.get_config().settings.clone()
+        };
+        
+        // Take the clients out so the account manager isn't locked while fetching
+        let bundles = self.account_manager.lock().await.get_bundles();
+        let email_manager = self.email_manager.lock().await.clone();
+        
+        // Fetch several inboxes at once, so a slow server doesn't hold up the others
+        let cache_limit = settings.cache_limit();
+        let results = for_each_account(bundles, move |bundle| {
+            let email_manager = email_manager.clone();
+            async move {
+                let (id, name, connected) = {
+                    let account = bundle.account.lock().await;
+                    let imap = account.has_imap() && account.imap_status == ConnectionStatus::Connected;
+                    let pop3 = account.has_pop3() && account.pop3_status == ConnectionStatus::Connected;
+                    (account.config.id.clone(), account.config.name.clone(), account.config.enabled && (imap || pop3))
+                };
+                if !connected {
+                    return None;
+                }
+                
+                let fetched = email_manager.load_emails(
+                    &bundle.account,
+                    Some(&bundle.imap_client),
+                    Some(&bundle.pop3_client),
+                    "INBOX",
+                    50,
+                    cache_limit,
+                ).await;
+                match fetched {
+                    Ok(fetched) if fetched.new_unread > 0 => Some((id, name, fetched.new_unread)),
+                    Ok(_) => None,
+                    Err(e) => {
+                        error!("Failed to check {} for new mail: {}", name, e);
+                        None
+                    },
+                }
+            }
+        }).await;
+        
+        // List the counts in account order
+        let found: Vec<_> = results.into_iter().flatten().collect();
+        if let Some(command) = settings.new_mail_command.as_deref().filter(|command| !command.trim().is_empty()) {
+            for (id, name, count) in &found {
+                notify::run_new_mail_command(command, id, name, *count);
+            }
+        }
+        
+        let counts: Vec<_> = found.into_iter().map(|(_, name, count)| (name, count)).collect();
+        if !counts.is_empty() {
+            info!("{}", notify::describe_new_mail(&counts));
+            self.set_status_message(notify::describe_new_mail(&counts)).await;
+            notify::notify_new_mail(&settings, &counts);
+        }
+    }
+    
+    /// Queues the scheduled messages whose send time has passed.
+    ///
+    /// They stay in storage until they are sent, so they aren't lost if the
+    /// application exits first. Messages of disabled accounts wait until
+    /// the account is enabled again.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn dispatch_scheduled_emails(&self) -> Result<()> {
+        let mut state = self.state.write().await;
+        let disabled: Vec<&str> = state.config_manager.get_config().accounts.iter()
+            .filter(|account| !account.enabled)
+            .map(|account| account.id.as_str())
+            .collect();
+        let due: Vec<ScheduledEmail> = state.storage.get_all_scheduled_emails()?
+            .into_iter()
+            .filter(|entry| entry.is_due() && !state.outbox.contains(&entry.email.id))
+            .filter(|entry| !disabled.contains(&entry.email.account_id.as_str()))
+            .collect();
+        
+        for entry in due {
+            info!("Scheduled message \"{}\" is due", entry.email.subject);
+            state.outbox.queue(entry.email, Duration::ZERO);
+        }
+        
+        Ok(())
+    }
+    
+    /// Takes the most recently queued message out of the outbox and reopens it in compose.
+    ///
+    /// Takes the already locked state since this is called from key handling.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    pub fn recall_last_email(&self, state: &mut AppState) {
+        if state.current_view == View::ComposeEmail && state.compose_state.as_ref().is_some_and(|c| !c.is_empty()) {
+            state.set_status_message("Finish or cancel the current message before recalling one".to_string());
+            return;
+        }
+        
+        match state.outbox.recall_latest() {
+            Some(email) => {
+                // A recalled scheduled message leaves the schedule too
+                if email.folder == SCHEDULED_FOLDER
+                    && let Err(e) = state.storage.delete_scheduled_email(&email.account_id, &email.id) {
+                    error!("Failed to remove recalled scheduled message: {}", e);
+                }
+                state.compose_state = Some(ComposeState::from_email(&email));
+                state.current_view = View::ComposeEmail;
+                state.set_status_message("Message recalled".to_string());
+            },
+            None => state.set_status_message("No message to recall".to_string()),
+        }
+    }
+    
+    /// Sends the outbox messages whose delay has passed.
+    ///
+    /// Each message is sent in its own task so a slow server doesn't hold up
+    /// the UI. Called from the main loop.
+    pub async fn flush_outbox(self: &Arc<Self>) {
+        // Look first, since taking the state for writing counts as a change
+        {
+            let state = self.state.read().await;
+            // Messages wait in the outbox until the app is back online
+            if !state.outbox.has_due() || state.offline {
+                return;
+            }
+        }
+        let due = self.state.write().await.outbox.take_due();
+        
+        for entry in due {
+            let controller = self.clone();
+            tokio::spawn(async move {
+                controller.send_outbox_entry(entry).await;
+            });
+        }
+    }
+    
+    /// Sends one outbox message, putting it back in the outbox if sending fails.
+    ///
+    /// # Parameters
+    /// - `entry`: The outbox entry to send
+    async fn send_outbox_entry(&self, entry: OutboxEntry) {
+        let result = self.send_email(&entry.email).await;
+        
+        let mut state = self.state.write().await;
+        match result {
+            Ok(()) => {
+                info!("Sent message \"{}\"", entry.email.subject);
+                state.outbox.finish_sending(entry.id);
+                let message = match state.config_manager.get_config().settings.archive_address() {
+                    Some(archive) => format!("Sent \"{}\", with a Bcc to the archive at {}", entry.email.subject, archive),
+                    None => format!("Sent \"{}\"", entry.email.subject),
+                };
+                state.set_status_message(message);
+                if let Err(e) = state.record_recipients(&entry.email) {
+                    error!("Failed to remember the recipients: {}", e);
+                }
+                
+                // A sent scheduled message leaves the schedule
+                if entry.email.folder == SCHEDULED_FOLDER
+                    && let Err(e) = state.storage.delete_scheduled_email(&entry.email.account_id, &entry.email.id) {
+                    error!("Failed to remove sent scheduled message: {}", e);
+                }
+            },
+            Err(e) => {
+                error!("Failed to send message \"{}\": {}", entry.email.subject, e);
+                state.set_status_message(format!("Failed to send \"{}\", will retry: {}", entry.email.subject, e));
+                state.outbox.requeue_failed(entry, e.to_string());
+            },
+        }
+    }
+    
+    /// Sends a message through the SMTP client of its account, connecting if needed.
+    ///
+    /// # Parameters
+    /// - `email`: The message to send
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn send_email(&self, email: &Email) -> Result<()> {
+        let smtp_client = {
+            let account_manager = self.account_manager.lock().await;
+            let bundle = account_manager.get_bundle(&email.account_id)
+                .ok_or_else(|| anyhow!("Account {} not found", email.account_id))?;
+            let account = bundle.account.lock().await;
+            if !account.config.enabled {
+                return Err(anyhow!("{} is disabled", account.config.name));
+            }
+            bundle.smtp_client.clone()
+        };
+        
+        let settings = self.state.read().await.config_manager.get_config().settings.clone();
+        let email = with_archive_bcc(email, &settings);
+        
+        let mut client = smtp_client.lock().await;
+        if !client.is_connected().await {
+            client.connect().await?;
+        }
+        client.send_email(&email, &settings).await
+    }
+    
+    /// Closes the database storage.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn close_storage(&self) -> Result<()> {
+        info!("Closing database storage");
+        
+        // Close storage in app state
+        let state = self.state.read().await;
+        state.storage.close()?;
+        
+        // Close storage in email manager
+        let email_manager = self.email_manager.lock().await;
+        email_manager.close_storage()?;
+        
+        Ok(())
+    }
+    
+    /// Keeps the messages still in the outbox as scheduled messages, due now.
+    ///
+    /// They are sent when the application starts again, like any other
+    /// scheduled message that came due. Messages already being sent get a
+    /// short while to finish first.
+    async fn keep_unsent_messages(&self) {
+        let deadline = tokio::time::Instant::now() + SHUTDOWN_SEND_WAIT;
+        while self.state.read().await.outbox.is_sending() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        
+        let mut state = self.state.write().await;
+        if state.outbox.is_sending() {
+            warn!("Quitting while a message is still being sent; it may not have been sent");
+        }
+        for entry in state.outbox.take_waiting() {
+            // Scheduled messages stay in storage until they are sent
+            if entry.email.folder == SCHEDULED_FOLDER {
+                continue;
+            }
+            
+            let mut email = entry.email;
+            email.id = uuid::Uuid::new_v4().to_string();
+            email.folder = SCHEDULED_FOLDER.to_string();
+            let scheduled = ScheduledEmail::new(email, SystemTime::now());
+            match state.storage.store_scheduled_email(&scheduled) {
+                Ok(()) => info!("Kept unsent message \"{}\" for the next start", scheduled.email.subject),
+                Err(e) => error!("Failed to keep unsent message \"{}\": {}", scheduled.email.subject, e),
+            }
+        }
+    }
+    
+    /// Empties the Trash folder of every connected IMAP account, if enabled.
+    ///
+    /// Failures are logged and don't stop the shutdown.
+    async fn empty_trash_folders(&self) {
+        {
+            let state = self.state.read().await;
+            if state.offline || !state.config_manager.get_config().settings.empties_trash_on_exit() {
+                return;
+            }
+        }
+        
+        let bundles = self.account_manager.lock().await.get_bundles();
+        let email_manager = self.email_manager.lock().await.clone();
+        
+        for bundle in &bundles {
+            // Only connected accounts with a known Trash folder can be emptied
+            let (account_id, trash_folder) = {
+                let account = bundle.account.lock().await;
+                match &account.trash_folder {
+                    Some(trash_folder) if account.imap_status == ConnectionStatus::Connected => {
+                        (account.config.id.clone(), trash_folder.clone())
+                    },
+                    _ => continue,
+                }
+            };
+            match email_manager.empty_folder(&bundle.imap_client, &account_id, &trash_folder).await {
+                Ok(deleted) => info!("Emptied {} of account {}: {} messages deleted", trash_folder, account_id, deleted),
+                Err(e) => error!("Failed to empty {} of account {}: {}", trash_folder, account_id, e),
+            }
+        }
+    }
+    
+    /// Shuts down the application.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn shutdown(&self) -> Result<()> {
+        info!("Shutting down Linksy email client");
+        
+        // Keep unsent messages before the storage is closed
+        self.keep_unsent_messages().await;
+        
+        // Empty Trash while the clients are still connected
+        self.empty_trash_folders().await;
+        
+        // Disconnect all clients
+        self.disconnect_all_clients().await?;
+        
+        // Close database storage
+        self.close_storage().await?;
+        
+        // Set running state to false
+        let mut state = self.state.write().await;
+        state.running = false;
+        
+        // Remove the messages written for the mail viewer
+        viewer::remove_files(&state.viewer_files);
+        
+        Ok(())
+    }
+}
+
+/// A change to the read or flagged state of an email.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagChange {
+    /// Mark the email as read, or unread with false
+    Read(bool),
+    /// Flag the email, or unflag it with false
+    Flagged(bool),
+}
+
+impl FlagChange {
+    /// Checks if an email already has the state this change sets.
+    ///
+    /// # Parameters
+    /// - `email`: The email
+    ///
+    /// # Returns
+    /// true if there is nothing to change, false otherwise
+    fn is_applied_to(&self, email: &Email) -> bool {
+        match self {
+            FlagChange::Read(read) => email.is_read == *read,
+            FlagChange::Flagged(flagged) => email.is_flagged == *flagged,
+        }
+    }
+    
+    /// Sets the state of an email.
+    ///
+    /// # Parameters
+    /// - `email`: The email to change
+    fn apply(&self, email: &mut Email) {
+        match self {
+            FlagChange::Read(read) => email.is_read = *read,
+            FlagChange::Flagged(flagged) => email.is_flagged = *flagged,
+        }
+    }
+    
+    /// Describes the state this change sets.
+    ///
+    /// # Returns
+    /// "read", "unread", "flagged" or "unflagged"
+    fn label(&self) -> &'static str {
+        match self {
+            FlagChange::Read(true) => "read",
+            FlagChange::Read(false) => "unread",
+            FlagChange::Flagged(true) => "flagged",
+            FlagChange::Flagged(false) => "unflagged",
+        }
+    }
+}
+
+/// Runs a task for each account, at most MAX_CONCURRENT_ACCOUNTS at a time.
+///
+/// The tasks run on their own and lock only the account and clients they
+/// use, so one slow account doesn't hold up the others. The IMAP client
+/// runs its blocking commands on the blocking pool itself, so a server
+/// that doesn't answer doesn't stall the runtime. A task that panics is
+/// logged and left out of the results.
+///
+/// # Parameters
+/// - `bundles`: The accounts with their clients
+/// - `task`: Creates the task for an account
+///
+/// # Returns
+/// The results of the tasks, in account order
+async fn for_each_account<T, F, Fut>(bundles: Vec<AccountBundle>, task: F) -> Vec<T>
+where
+    F: Fn(AccountBundle) -> Fut,
+    Fut: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let limit = Arc::new(Semaphore::new(MAX_CONCURRENT_ACCOUNTS));
+    let mut tasks = JoinSet::new();
+    
+    for (index, bundle) in bundles.into_iter().enumerate() {
+        let limit = limit.clone();
+        let future = task(bundle);
+        tasks.spawn(async move {
+            // Wait until fewer than the limit are running
+            let _permit = limit.acquire_owned().await;
+            (index, future.await)
+        });
+    }
+    
+    let mut results = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(result) => results.push(result),
+            Err(e) => error!("Account task failed: {}", e),
+        }
+    }
+    
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Finds an email in the list, or the viewed one if it's no longer listed.
+///
+/// # Parameters
+/// - `state`: The application state
+/// - `key`: The email's account, folder and ID
+///
+/// # Returns
+/// An Option containing the email
+fn find_email<'a>(state: &'a AppState, key: &EmailKey) -> Option<&'a Email> {
+    state.emails.iter()
+        .find(|email| email.has_key(key))
+        .or_else(|| state.viewed_email.as_deref().filter(|email| email.has_key(key)))
+}
+
+/// Updates an email in the list and in the detail view.
+///
+/// The email is found by its account, folder and id, since the list may
+/// have been reloaded since the change started.
+///
+/// # Parameters
+/// - `state`: The application state
+/// - `email`: The email to update
+/// - `update`: The update to make
+fn update_listed_email(state: &mut AppState, email: &Email, update: impl Fn(&mut Email)) {
+    let key = email.key();
+    
+    // The list may be shared with the last render snapshot, so it's only copied if the email is in it
+    if state.emails.iter().any(|listed| listed.has_key(&key)) {
+        for listed in Arc::make_mut(&mut state.emails).iter_mut().filter(|listed| listed.has_key(&key)) {
+            update(listed);
+        }
+    }
+    if let Some(viewed) = state.viewed_email.as_mut().filter(|viewed| viewed.has_key(&key)) {
+        update(Arc::make_mut(viewed));
+    }
+}
+
+/// Adds the archive address to the blind copies of a message about to be sent.
+///
+/// The archive gets a copy of every message, whatever the compose form
+/// said, unless it's already one of the recipients.
+///
+/// # Parameters
+/// - `email`: The message to send
+/// - `settings`: The settings with the archive address
+///
+/// # Returns
+/// The message to hand to SMTP
+fn with_archive_bcc<'a>(email: &'a Email, settings: &AppSettings) -> Cow<'a, Email> {
+    let Some(archive) = settings.archive_address().and_then(Address::parse) else {
+        return Cow::Borrowed(email);
+    };
+    
+    // Compare the bare addresses, so "Archive <archive@example.com>" counts as archive@example.com
+    let present = email.to.iter().chain(&email.cc).chain(&email.bcc)
+        .filter_map(|recipient| Address::parse(recipient))
+        .any(|recipient| recipient.email.eq_ignore_ascii_case(&archive.email));
+    if present {
+        return Cow::Borrowed(email);
+    }
+    
+    let mut archived = email.clone();
+    archived.bcc.push(archive.to_header());
+    Cow::Owned(archived)
+}
+
+/// Describes how retrying each protocol of an account went.
+///
+/// # Parameters
+/// - `outcomes`: The protocols that were retried
+///
+/// # Returns
+/// A message for the status bar, e.g. "Retried IMAP: connected, SMTP: failed (timed out)"
+fn describe_retry(outcomes: &[RetryOutcome]) -> String {
+    let results: Vec<String> = outcomes.iter()
+        .map(|outcome| match &outcome.error {
+            None => format!("{}: connected", outcome.protocol),
+            Some(error) => format!("{}: failed ({})", outcome.protocol, error),
+        })
+        .collect();
+    
+    format!("Retried {}", results.join(", "))
 }
@@ -9,6 +9,7 @@ use tokio::sync::Mutex;
 use tokio::time::{Duration, interval};
 
 /// Manages connections to email servers.
+#[allow(dead_code)]
 pub struct ConnectionManager {
     /// Active accounts
     accounts: Vec<Arc<Mutex<Account>>>,
@@ -24,6 +25,7 @@ pub struct ConnectionManager {
     check_interval: u64,
 }
 
+#[allow(dead_code)]
 impl ConnectionManager {
     /// Creates a new ConnectionManager instance.
     ///
@@ -91,6 +93,12 @@ impl ConnectionManager {
                     let pop3_client = &pop3_clients[i];
                     let smtp_client = &smtp_clients[i];
                     
+                    // Accounts that don't connect on startup are left alone until they're opened
+                    let auto_connect = {
+                        let account_lock = account.lock().await;
+                        account_lock.config.auto_connect
+                    };
+                    
                     // Check IMAP connection
                     let has_imap = {
                         let account_lock = account.lock().await;
@@ -112,7 +120,7 @@ impl ConnectionManager {
                                 account_lock.imap_status = ConnectionStatus::Disconnected;
                                 error!("IMAP connection lost for account {}", account_lock.config.id);
                             }
-                        } else if imap_status == ConnectionStatus::Disconnected {
+                        } else if imap_status == ConnectionStatus::Disconnected && auto_connect {
                             // Try to reconnect
                             let mut client = imap_client.lock().await;
                             if let Err(e) = client.connect().await {
@@ -137,7 +145,7 @@ impl ConnectionManager {
                             account_lock.pop3_status
                         };
                         
-                        if pop3_status == ConnectionStatus::Disconnected {
+                        if pop3_status == ConnectionStatus::Disconnected && auto_connect {
                             // Try to reconnect
                             let mut client = pop3_client.lock().await;
                             if let Err(e) = client.connect().await {
@@ -156,7 +164,7 @@ impl ConnectionManager {
                         account_lock.smtp_status
                     };
                     
-                    if smtp_status == ConnectionStatus::Disconnected {
+                    if smtp_status == ConnectionStatus::Disconnected && auto_connect {
                         // Try to reconnect
                         let mut client = smtp_client.lock().await;
                         if let Err(e) = client.connect().await {
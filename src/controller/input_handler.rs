@@ -1,11 +1,28 @@
 //! Input handling for the Linksy email client.
 
-use crate::controller::AppController;
-use crate::state::View;
+use crate::config::{FolderView, QuitConfirmation, SavedSearch, SortMode, ViewFilter};
+use crate::controller::{AppController, FlagChange};
+use crate::models::{is_valid_tag, Email, SCHEDULED_FOLDER};
+use crate::state::{TypeAhead, View};
 use crate::ui::{is_key_with_modifier};
+use crate::ui::views::compose::{ComposeField, ComposeState};
+use crate::ui::views::find::{self, find_matches, FindState};
+use crate::ui::views;
+use crate::ui::views::attachments::{can_preview, preview_attachment, AttachmentPickerState};
+use crate::ui::views::grouping::{GroupBy, GroupRow, GroupingState};
+use crate::ui::views::links::{extract_links, LinkPickerState};
+use crate::ui::views::log::LogViewState;
+use crate::ui::views::prompt::{ConfirmAction, ConfirmState, PromptAction, PromptState};
+use crate::ui::views::reply::{build_forward, build_message_to_sender, build_reply};
+use crate::ui::views::search::search_prompt_title;
+use crate::ui::views::tags::TagPickerState;
+use crate::ui::views::templates::{fill_placeholders, TemplateFormState, TemplatePickerState};
 use anyhow::Result;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use log::error;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Handles user input.
 pub struct InputHandler {
@@ -27,6 +44,30 @@ impl InputHandler {
         }
     }
     
+    /// Runs a controller operation in the background.
+    ///
+    /// Keys are handled while the application state is locked, and most
+    /// controller operations lock the state themselves, so they have to be
+    /// spawned rather than awaited here. Failures are logged and shown in the
+    /// status bar.
+    ///
+    /// # Parameters
+    /// - `action`: Description of the operation, used in error messages
+    /// - `operation`: The operation to run with the controller
+    fn spawn_task<F, Fut>(&self, action: &'static str, operation: F)
+    where
+        F: FnOnce(Arc<AppController>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let controller = self.controller.clone();
+        tokio::spawn(async move {
+            if let Err(e) = operation(controller.clone()).await {
+                error!("{} failed: {}", action, e);
+                controller.set_status_message(format!("{} failed: {}", action, e)).await;
+            }
+        });
+    }
+    
     /// Handles a key event.
     ///
     /// # Parameters
@@ -38,8 +79,58 @@ impl InputHandler {
     pub async fn handle_key(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
         // Check for global keys
         if is_key_with_modifier(&key, KeyCode::Char('q'), KeyModifiers::CONTROL) {
-            // Quit application
-            state.set_running(false);
+            // Quit application, asking first as the confirm_quit setting says
+            let unsaved = state.get_compose_state().is_some_and(ComposeState::has_content);
+            match state.config_manager.get_config().settings.confirm_quit {
+                QuitConfirmation::Unsaved | QuitConfirmation::Always if unsaved => {
+                    let message = "Save the message you're writing as a draft before quitting? 'n' quits without it";
+                    state.set_confirm(Some(ConfirmState::new(message, ConfirmAction::SaveDraftAndQuit)));
+                },
+                // Waiting messages are asked about instead
+                QuitConfirmation::Always if state.outbox.is_empty() => {
+                    state.set_confirm(Some(ConfirmState::new("Quit Linksy?", ConfirmAction::Quit)));
+                },
+                _ => finish_quit(state),
+            }
+            return Ok(());
+        }
+        
+        if is_key_with_modifier(&key, KeyCode::Char('z'), KeyModifiers::CONTROL) {
+            // Undo send: take the last message back out of the outbox
+            self.controller.recall_last_email(state);
+            return Ok(());
+        }
+        
+        // An open prompt or confirmation takes all other keys
+        if state.get_prompt().is_some() {
+            self.handle_prompt_input(key, state);
+            return Ok(());
+        }
+        if state.get_confirm().is_some() {
+            self.handle_confirm_input(key, state);
+            return Ok(());
+        }
+        
+        // Alt+1..9 switches accounts while browsing mail, staying in the same view
+        let browsing = matches!(
+            state.get_current_view(),
+            View::Accounts | View::AccountStatus | View::Folders | View::Emails | View::EmailDetail
+        );
+        if let Some(number) = account_number(&key).filter(|_| browsing) {
+            if switch_account(number - 1, state) {
+                self.spawn_task("Switching account", |controller| async move {
+                    controller.connect_selected_account().await
+                });
+            }
+            return Ok(());
+        }
+        
+        // F2 opens the connection status dashboard while browsing mail
+        if key.code == KeyCode::F(2) && browsing {
+            if state.get_current_view() != View::AccountStatus {
+                state.status_opened_from = Some(state.get_current_view());
+                state.set_current_view(View::AccountStatus);
+            }
             return Ok(());
         }
         
@@ -51,7 +142,10 @@ impl InputHandler {
             View::EmailDetail => self.handle_email_detail_input(key, state).await?,
             View::ComposeEmail => self.handle_compose_email_input(key, state).await?,
             View::Settings => self.handle_settings_input(key, state).await?,
+            View::TemplateForm => self.handle_template_form_input(key, state).await?,
             View::AccountConfig => self.handle_account_config_input(key, state).await?,
+            View::AccountStatus => self.handle_account_status_input(key, state).await?,
+            View::Log => self.handle_log_input(key, state).await?,
         }
         
         Ok(())
@@ -65,7 +159,17 @@ impl InputHandler {
     ///
     /// # Returns
     /// A Result indicating success or failure
+    #[allow(clippy::collapsible_if, clippy::collapsible_match)]
     async fn handle_accounts_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        // Shift+Up/Down reorders the selected account
+        if key.modifiers.contains(KeyModifiers::SHIFT) && matches!(key.code, KeyCode::Up | KeyCode::Down) {
+            let up = key.code == KeyCode::Up;
+            self.spawn_task("Moving account", move |controller| async move {
+                controller.move_selected_account(up).await
+            });
+            return Ok(());
+        }
+        
         match key.code {
             KeyCode::Up => {
                 // Move selection up
@@ -78,28 +182,39 @@ impl InputHandler {
             KeyCode::Down => {
                 // Move selection down
                 if let Some(index) = state.get_selected_account() {
-                    if index < state.accounts.len() - 1 {
+                    if index + 1 < state.accounts.len() {
                         state.set_selected_account(Some(index + 1));
                     }
                 }
             },
+            KeyCode::Enter if state.config_manager.get_config().accounts.is_empty() => {
+                // Start onboarding with the add-account form
+                self.controller.create_account_form(state);
+            },
             KeyCode::Enter => {
                 // Select account and switch to folders view
                 if state.get_selected_account().is_some() {
-                    self.controller.connect_selected_account().await?;
+                    self.spawn_task("Connecting", |controller| async move {
+                        controller.connect_selected_account().await
+                    });
+                    state.selected_folder_index = 0;
                     state.set_current_view(View::Folders);
                 }
             },
             KeyCode::Char('a') => {
                 // Add new account
-                self.controller.create_account_form().await?;
+                self.controller.create_account_form(state);
+            },
+            KeyCode::Char('c') => {
+                // Add a new account with the servers of the selected one
+                self.controller.copy_account_form(state);
             },
             KeyCode::Char('e') => {
                 // Edit selected account
                 if state.get_selected_account().is_some() {
                     // Create a new empty account form state instead of trying to load the existing account
                     // This avoids blocking operations in the UI thread
-                    self.controller.create_account_form().await?;
+                    self.controller.create_account_form(state);
                     
                     // Set a status message to inform the user about the workaround
                     state.set_status_message("Creating new account instead of editing (blocking issue workaround)".to_string());
@@ -108,15 +223,126 @@ impl InputHandler {
             KeyCode::Char('d') => {
                 // Delete selected account
                 if state.get_selected_account().is_some() {
-                    self.controller.delete_selected_account().await?;
+                    self.spawn_task("Deleting account", |controller| async move {
+                        controller.delete_selected_account().await
+                    });
+                }
+            },
+            KeyCode::Char('s') => {
+                // Show the connection status dashboard
+                state.set_current_view(View::AccountStatus);
+            },
+            KeyCode::Char('L') => {
+                // Show the recent log messages
+                open_log_view(state);
+            },
+            KeyCode::Char('x') => {
+                // Suspend the selected account, or put it back in use
+                if state.get_selected_account().is_some() {
+                    self.spawn_task("Changing account", |controller| async move {
+                        controller.toggle_selected_account_enabled().await
+                    });
+                }
+            },
+            KeyCode::Char('R') => {
+                // Force a fresh connection for the selected account
+                self.reconnect_selected_account(state);
+            },
+            KeyCode::Char('o') => {
+                // Open the settings view
+                let has_templates = !state.config_manager.get_config().templates.is_empty();
+                state.selected_template = if has_templates { Some(0) } else { None };
+                state.set_current_view(View::Settings);
+            },
+            _ => {}
+        }
+        
+        Ok(())
+    }
+    
+    /// Handles input in the account status dashboard view.
+    ///
+    /// # Parameters
+    /// - `key`: The key event
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn handle_account_status_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        match key.code {
+            KeyCode::Up => {
+                // Move selection up
+                if let Some(index) = state.get_selected_account()
+                    && index > 0 {
+                    state.set_selected_account(Some(index - 1));
                 }
             },
+            KeyCode::Down => {
+                // Move selection down
+                if let Some(index) = state.get_selected_account()
+                    && index + 1 < state.accounts.len() {
+                    state.set_selected_account(Some(index + 1));
+                }
+            },
+            KeyCode::Char('R') => {
+                // Force a fresh connection for the selected account
+                self.reconnect_selected_account(state);
+            },
+            KeyCode::Char('L') => {
+                // Show the recent log messages
+                open_log_view(state);
+            },
+            KeyCode::Esc => {
+                // Go back to the view the dashboard was opened from, or the accounts view
+                let view = state.status_opened_from.take().unwrap_or(View::Accounts);
+                state.set_current_view(view);
+            },
             _ => {}
         }
         
         Ok(())
     }
     
+    /// Handles input in the log view.
+    ///
+    /// # Parameters
+    /// - `key`: The key event
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn handle_log_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        match key.code {
+            KeyCode::Char('v') => {
+                // List more or fewer details, and keep them from now on
+                change_log_level(state);
+            },
+            KeyCode::Esc => {
+                // Go back to the view the log was opened from
+                let previous_view = state.log_view.take().map_or(View::Accounts, |log_view| log_view.previous_view);
+                state.set_current_view(previous_view);
+            },
+            code => {
+                // Scroll through the messages, up being older
+                let count = crate::logging::entries(state.config_manager.get_config().settings.log_level).len();
+                let oldest = count.saturating_sub(1);
+                if let Some(log_view) = state.log_view.as_mut() {
+                    log_view.scroll = match code {
+                        KeyCode::Up => (log_view.scroll + 1).min(oldest),
+                        KeyCode::Down => log_view.scroll.saturating_sub(1),
+                        KeyCode::PageUp => (log_view.scroll + 10).min(oldest),
+                        KeyCode::PageDown => log_view.scroll.saturating_sub(10),
+                        KeyCode::Home => oldest,
+                        KeyCode::End => 0,
+                        _ => log_view.scroll,
+                    };
+                }
+            },
+        }
+        
+        Ok(())
+    }
+    
     /// Handles input in the account configuration view.
     ///
     /// # Parameters
@@ -125,8 +351,15 @@ impl InputHandler {
     ///
     /// # Returns
     /// A Result indicating success or failure
+    #[allow(clippy::collapsible_match)]
     async fn handle_account_config_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
         if let Some(form_state) = state.get_account_form_state_mut() {
+            // Ctrl+h shows or hides the password being edited
+            if is_key_with_modifier(&key, KeyCode::Char('h'), KeyModifiers::CONTROL) {
+                form_state.toggle_reveal_password();
+                return Ok(());
+            }
+            
             match key.code {
                 KeyCode::Esc => {
                     if form_state.editing {
@@ -156,7 +389,20 @@ impl InputHandler {
                     if field_name == "save_button" {
                         // Validate and save account
                         if form_state.validate() {
-                            self.controller.save_account_form().await?;
+                            // Warn before saving a second account for the same mailbox
+                            let account = form_state.finalize_account();
+                            let is_new_account = form_state.is_new_account;
+                            let duplicate = state.config_manager
+                                .find_duplicate_account(&account, is_new_account)
+                                .map(|existing| format!("Account \"{}\" already uses {} on this server. Save anyway?", existing.id, existing.email));
+                            
+                            if let Some(message) = duplicate {
+                                state.set_confirm(Some(ConfirmState::new(&message, ConfirmAction::SaveDuplicateAccount)));
+                            } else {
+                                self.spawn_task("Saving account", |controller| async move {
+                                    controller.save_account_form().await
+                                });
+                            }
                         } else {
                             state.set_status_message("Please fix validation errors".to_string());
                         }
@@ -204,6 +450,11 @@ impl InputHandler {
             }
         }
         
+        // Keep the selected field in view when the form is taller than the terminal
+        if let (Some(form_state), Ok(area)) = (state.get_account_form_state_mut(), crate::ui::content_area()) {
+            form_state.scroll_to_selected(area.height.saturating_sub(2));
+        }
+        
         Ok(())
     }
     
@@ -216,29 +467,91 @@ impl InputHandler {
     /// # Returns
     /// A Result indicating success or failure
     async fn handle_folders_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        let typing = !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        
         match key.code {
+            KeyCode::Char(c) if typing && state.folder_jump.is_active() => {
+                // Keep typing a folder name, even with letters that are shortcuts
+                jump_to_folder(c, state);
+            },
             KeyCode::Up => {
                 // Move selection up
-                // TODO: Implement folder selection
+                state.selected_folder_index = state.selected_folder_index.saturating_sub(1);
             },
             KeyCode::Down => {
-                // Move selection down
-                // TODO: Implement folder selection
+                // Move selection down, through the folders and then the saved searches
+                let (folders, _) = state.get_folder_list();
+                if state.selected_folder_index + 1 < folders.len() + state.saved_searches().len() {
+                    state.selected_folder_index += 1;
+                }
+            },
+            KeyCode::Enter if state.selected_saved_search().is_some() => {
+                // Run the saved search and list its results
+                if let Some(index) = state.selected_saved_search() {
+                    self.open_saved_search(index, state);
+                }
+            },
+            KeyCode::Char('e') if state.selected_saved_search().is_some() => {
+                // Edit the query of the saved search
+                if let Some(index) = state.selected_saved_search() {
+                    let saved = &state.saved_searches()[index];
+                    let title = format!("Query of {}", saved.name);
+                    let query = saved.query.clone();
+                    state.set_prompt(Some(PromptState::new(&title, query, PromptAction::EditSavedSearch(index))));
+                }
+            },
+            KeyCode::Char('n') if state.selected_saved_search().is_some() => {
+                // Rename the saved search
+                if let Some(index) = state.selected_saved_search() {
+                    let name = state.saved_searches()[index].name.clone();
+                    state.set_prompt(Some(PromptState::new("Rename saved search", name, PromptAction::RenameSavedSearch(index))));
+                }
+            },
+            KeyCode::Char('d') if state.selected_saved_search().is_some() => {
+                // Ask before deleting the saved search
+                if let Some(index) = state.selected_saved_search() {
+                    let message = format!("Delete saved search \"{}\"?", state.saved_searches()[index].name);
+                    state.set_confirm(Some(ConfirmState::new(&message, ConfirmAction::DeleteSavedSearch(index))));
+                }
             },
             KeyCode::Enter => {
                 // Select folder and switch to emails view
-                self.controller.load_emails().await?;
+                let (folders, _) = state.get_folder_list();
+                let index = state.selected_folder_index.min(folders.len().saturating_sub(1));
+                let Some(folder) = folders.get(index).cloned() else {
+                    return Ok(());
+                };
+                state.set_selected_folder(folder);
+                state.search = None;
+                state.folder_jump.clear();
+                self.spawn_task("Loading emails", |controller| async move {
+                    controller.load_emails().await
+                });
                 state.set_current_view(View::Emails);
             },
+            KeyCode::Char('c') => {
+                // Compose new email, continuing the draft if one was saved
+                open_new_compose(state);
+            },
             KeyCode::Char('r') => {
                 // Retry failed connections
                 state.set_status_message("Retrying failed connections...".to_string());
-                self.controller.retry_connections().await?;
+                self.spawn_task("Retrying connections", |controller| async move {
+                    controller.retry_connections().await
+                });
+            },
+            KeyCode::Char('R') => {
+                // Force a fresh connection for the selected account
+                self.reconnect_selected_account(state);
             },
             KeyCode::Esc => {
                 // Go back to accounts view
                 state.set_current_view(View::Accounts);
             },
+            KeyCode::Char(c) if typing && !c.is_whitespace() => {
+                // Start typing a folder name to jump to it
+                jump_to_folder(c, state);
+            },
             _ => {}
         }
         
@@ -253,20 +566,47 @@ impl InputHandler {
     ///
     /// # Returns
     /// A Result indicating success or failure
+    #[allow(clippy::collapsible_if)]
     async fn handle_emails_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        // The tag picker popup takes all keys while it is open
+        if state.get_tag_picker().is_some() {
+            return self.handle_tag_picker_input(key, state);
+        }
+        
+        // Scheduled messages are edited or cancelled rather than viewed
+        if state.get_selected_folder() == SCHEDULED_FOLDER {
+            let result = match key.code {
+                KeyCode::Enter => self.controller.edit_scheduled_email(state),
+                KeyCode::Char('d') => self.controller.cancel_scheduled_email(state),
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                state.set_status_message(e.to_string());
+            }
+            if matches!(key.code, KeyCode::Enter | KeyCode::Char('d')) {
+                return Ok(());
+            }
+        }
+        
+        // Grouped lists move between groups and act on whole groups
+        let grouped = state.grouping.is_some() && state.search.is_none() && state.get_selected_folder() != SCHEDULED_FOLDER;
+        if grouped && self.handle_grouped_emails_input(key, state) {
+            return Ok(());
+        }
+        
         match key.code {
             KeyCode::Up => {
-                // Move selection up
+                // Move selection up, from a valid selection if the list changed
+                state.clamp_selection();
                 if let Some(index) = state.get_selected_email() {
-                    if index > 0 {
-                        state.set_selected_email(Some(index - 1));
-                    }
+                    state.set_selected_email(Some(index.saturating_sub(1)));
                 }
             },
             KeyCode::Down => {
-                // Move selection down
+                // Move selection down, from a valid selection if the list changed
+                state.clamp_selection();
                 if let Some(index) = state.get_selected_email() {
-                    if index < state.emails.len() - 1 {
+                    if index + 1 < state.emails.len() {
                         state.set_selected_email(Some(index + 1));
                     }
                 }
@@ -274,47 +614,151 @@ impl InputHandler {
             KeyCode::Enter => {
                 // View selected email
                 if let Some(index) = state.get_selected_email() {
-                    if index < state.emails.len() {
-                        state.set_viewed_email(Some(state.emails[index].clone()));
-                        state.set_current_view(View::EmailDetail);
-                        state.set_email_scroll_offset(0);
-                        
-                        // Mark as read
-                        if !state.emails[index].is_read {
-                            self.controller.mark_email_as_read(index).await?;
-                        }
-                    }
+                    self.open_email(index, state);
                 }
             },
+            KeyCode::Char('n') if state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // Open the next unread email
+                self.open_next_unread(true, state);
+            },
+            KeyCode::Char('N') if state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // Open the previous unread email
+                self.open_next_unread(false, state);
+            },
             KeyCode::Char('c') => {
-                // Compose new email
-                state.set_current_view(View::ComposeEmail);
-                // TODO: Initialize compose state
+                // Compose new email, continuing the draft if one was saved
+                open_new_compose(state);
             },
             KeyCode::Char('r') => {
                 if key.modifiers.contains(KeyModifiers::CONTROL) {
                     // Retry failed connections
                     state.set_status_message("Retrying failed connections...".to_string());
-                    self.controller.retry_connections().await?;
+                    self.spawn_task("Retrying connections", |controller| async move {
+                        controller.retry_connections().await
+                    });
                 } else {
                     // Reply to selected email
-                    // TODO: Implement reply
-                    state.set_status_message("Reply not implemented yet".to_string());
+                    if let Some(email) = state.get_selected_email().and_then(|index| state.emails.get(index)).cloned() {
+                        open_reply(&email, state);
+                    }
+                }
+            },
+            KeyCode::F(5) => {
+                if let Some(search) = state.search.clone() {
+                    // Run the search again
+                    self.start_search(search.query, search.all_folders, search.name, state);
+                } else {
+                    // Refetch the current folder without reconnecting
+                    self.spawn_task("Refreshing", |controller| async move {
+                        controller.refresh_emails().await
+                    });
                 }
             },
+            KeyCode::Char('/') if state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // Ask what to search for, starting from the last search
+                let query = state.search.as_ref().map(|search| search.query.clone()).unwrap_or_default();
+                let title = search_prompt_title(state.search_all_folders, state.search_folder());
+                state.set_prompt(Some(PromptState::new(&title, query, PromptAction::Search)));
+            },
+            KeyCode::Char('s') if state.search.as_ref().is_some_and(|search| search.name.is_none()) => {
+                // Ask for a name to list the search under in the folders view
+                state.set_prompt(Some(PromptState::new("Save search as", String::new(), PromptAction::SaveSearch)));
+            },
             KeyCode::Char('f') => {
                 // Forward selected email
-                // TODO: Implement forward
-                state.set_status_message("Forward not implemented yet".to_string());
+                if let Some(email) = state.get_selected_email().and_then(|index| state.emails.get(index)).cloned() {
+                    open_forward(&email, state);
+                }
+            },
+            KeyCode::Char('m') => {
+                // Write a new message to the sender of the selected email
+                if let Some(email) = state.get_selected_email().and_then(|index| state.emails.get(index)).cloned() {
+                    open_message_to_sender(&email, state);
+                }
+            },
+            KeyCode::Char('e') if state.is_sent_folder_selected() => {
+                // Edit a copy of the sent message, to send it again
+                self.edit_as_new(state);
+            },
+            KeyCode::Char('o') if state.search.is_none() && state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // Sort the folder differently from now on
+                change_folder_view(state, |view| view.sort = view.sort.next());
+            },
+            KeyCode::Char('v') if state.search.is_none() && state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // List different emails of the folder from now on, fetching them again
+                change_folder_view(state, |view| view.filter = view.filter.next());
+                self.spawn_task("Loading emails", |controller| async move {
+                    controller.refresh_emails().await
+                });
+            },
+            KeyCode::Char('G') if state.search.is_none() && state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // Group the list by sender, by sender domain, or not at all
+                change_grouping(state);
+            },
+            KeyCode::Char('V') if state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // Mark or unmark the sender of the selected email as a VIP
+                if let Some(sender) = state.get_selected_email().and_then(|index| state.emails.get(index)).map(|email| email.from.clone()) {
+                    toggle_vip_sender(sender, state);
+                }
+            },
+            KeyCode::Char(c @ ('u' | '*')) if !state.selected_emails.is_empty() => {
+                // Mark all of the selected emails, or unmark them if they all are
+                let indexes = selected_email_indexes(state);
+                self.toggle_emails_flag(&indexes, c == '*', state);
+            },
+            KeyCode::Char('u') => {
+                // Toggle read/unread on the selected email
+                self.toggle_email_flag(false, state);
+            },
+            KeyCode::Char('*') => {
+                // Flag or unflag the selected email
+                self.toggle_email_flag(true, state);
+            },
+            KeyCode::Char(' ') if state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // Add the highlighted email to the selection, or take it out
+                if let Some(email) = state.get_selected_email().and_then(|index| state.emails.get(index)) {
+                    let key = email.key();
+                    if !state.selected_emails.remove(&key) {
+                        state.selected_emails.insert(key);
+                    }
+                }
+            },
+            KeyCode::Char('a') if is_key_with_modifier(&key, KeyCode::Char('a'), KeyModifiers::CONTROL) && state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // Select every listed email, which are only the ones the filter shows
+                state.selected_emails = state.emails.iter().map(Email::key).collect();
+            },
+            KeyCode::Char('!') if state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // Select the listed emails that aren't selected, and only those
+                state.selected_emails = state.emails.iter()
+                    .map(Email::key)
+                    .filter(|key| !state.selected_emails.contains(key))
+                    .collect();
+            },
+            KeyCode::Char('T') if state.get_selected_folder() != SCHEDULED_FOLDER => {
+                // Add or remove tags of the selected email
+                open_tag_picker(state);
             },
             KeyCode::Char('d') => {
                 // Delete selected email
                 // TODO: Implement delete
                 state.set_status_message("Delete not implemented yet".to_string());
             },
+            KeyCode::Esc if !state.selected_emails.is_empty() => {
+                // Clear the selection before leaving the list
+                state.selected_emails.clear();
+            },
             KeyCode::Esc => {
-                // Go back to folders view
-                state.set_current_view(View::Folders);
+                if let Some(search) = state.search.take_if(|search| search.name.is_some()) {
+                    // Go back to the folders view the saved search was opened from
+                    state.set_selected_folder(search.folder);
+                    state.set_current_view(View::Folders);
+                } else if state.search.is_some() {
+                    // Leave the search results for the folder the search started from
+                    self.clear_search(state);
+                } else {
+                    // Go back to folders view
+                    state.set_current_view(View::Folders);
+                }
             },
             _ => {}
         }
@@ -331,53 +775,202 @@ impl InputHandler {
     /// # Returns
     /// A Result indicating success or failure
     async fn handle_email_detail_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        // The link picker popup takes all keys while it is open
+        if state.get_link_picker().is_some() {
+            return self.handle_link_picker_input(key, state);
+        }
+        if state.get_attachment_picker().is_some() {
+            return self.handle_attachment_picker_input(key, state);
+        }
+        if state.get_tag_picker().is_some() {
+            return self.handle_tag_picker_input(key, state);
+        }
+        
         match key.code {
-            KeyCode::Up => {
-                // Scroll up
-                if state.get_email_scroll_offset() > 0 {
-                    state.set_email_scroll_offset(state.get_email_scroll_offset() - 1);
+            KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown | KeyCode::End => {
+                // Scroll within the body as it's laid out in the terminal now
+                let Some(viewport) = email_body_viewport(state)? else {
+                    return Ok(());
+                };
+                let page = i32::from(viewport.page(state.config_manager.get_config().settings.scroll_overlap));
+                let offset = state.get_email_scroll_offset();
+                let offset = match key.code {
+                    KeyCode::Up => viewport.scroll(offset, -1),
+                    KeyCode::Down => viewport.scroll(offset, 1),
+                    KeyCode::PageUp => viewport.scroll(offset, -page),
+                    KeyCode::PageDown => viewport.scroll(offset, page),
+                    _ => viewport.max_scroll(),
+                };
+                state.set_email_scroll_offset(offset);
+            },
+            KeyCode::Home => {
+                // Scroll to top
+                state.set_email_scroll_offset(0);
+            },
+            KeyCode::Char('l') => {
+                // Show the links in the email
+                if let Some(email) = state.get_viewed_email() {
+                    let links = extract_links(email);
+                    if links.is_empty() {
+                        state.set_status_message("No links in this email".to_string());
+                    } else {
+                        state.set_link_picker(Some(LinkPickerState::new(links)));
+                    }
                 }
             },
-            KeyCode::Down => {
-                // Scroll down
-                state.set_email_scroll_offset(state.get_email_scroll_offset() + 1);
+            KeyCode::Char('a') => {
+                // Show the attachments of the email
+                if let Some(email) = state.get_viewed_email() {
+                    if email.attachments.is_empty() {
+                        state.set_status_message("No attachments in this email".to_string());
+                    } else {
+                        state.set_attachment_picker(Some(AttachmentPickerState::default()));
+                    }
+                }
             },
-            KeyCode::PageUp => {
-                // Scroll up by page
-                if state.get_email_scroll_offset() > 10 {
-                    state.set_email_scroll_offset(state.get_email_scroll_offset() - 10);
-                } else {
-                    state.set_email_scroll_offset(0);
+            KeyCode::Char('s') => {
+                // Ask where to save the body
+                if let Some(email) = state.get_viewed_email() {
+                    let path = suggested_body_path(&email.subject);
+                    state.set_prompt(Some(PromptState::new("Save body to", path, PromptAction::SaveEmailBody)));
                 }
             },
-            KeyCode::PageDown => {
-                // Scroll down by page
-                state.set_email_scroll_offset(state.get_email_scroll_offset() + 10);
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Copy the whole body, including quoted text
+                if let Some(email) = state.get_viewed_email() {
+                    let prefer_html = state.config_manager.get_config().settings.prefer_html;
+                    let body = views::display_body_text(email, prefer_html, true, state.is_showing_remote_content());
+                    copy_to_clipboard("body", &body, state);
+                }
             },
-            KeyCode::Home => {
-                // Scroll to top
-                state.set_email_scroll_offset(0);
+            KeyCode::Char('y') => {
+                // Copy the sender address
+                if let Some(email) = state.get_viewed_email() {
+                    let from = email.from.clone();
+                    copy_to_clipboard("sender address", &from, state);
+                }
+            },
+            KeyCode::Char('Y') => {
+                // Copy the subject
+                if let Some(email) = state.get_viewed_email() {
+                    let subject = email.subject.clone();
+                    copy_to_clipboard("subject", &subject, state);
+                }
             },
-            KeyCode::End => {
-                // Scroll to bottom
-                // TODO: Calculate maximum scroll offset
-                state.set_email_scroll_offset(1000);
+            KeyCode::Char('t') => {
+                // Expand or collapse quoted text
+                let show = !state.is_showing_quoted_text();
+                state.set_show_quoted_text(show);
+            },
+            KeyCode::Char('R') => {
+                // Show the remote images of this email only
+                state.set_show_remote_content(true);
+            },
+            KeyCode::Char('A') => {
+                // Always show remote images from this sender
+                allow_remote_content(state);
             },
             KeyCode::Char('r') => {
                 // Reply to email
-                // TODO: Implement reply
-                state.set_status_message("Reply not implemented yet".to_string());
+                if let Some(email) = state.get_viewed_email().cloned() {
+                    open_reply(&email, state);
+                }
             },
             KeyCode::Char('f') => {
                 // Forward email
-                // TODO: Implement forward
-                state.set_status_message("Forward not implemented yet".to_string());
+                if let Some(email) = state.get_viewed_email().cloned() {
+                    open_forward(&email, state);
+                }
+            },
+            KeyCode::Char('m') => {
+                // Write a new message to the sender
+                if let Some(email) = state.get_viewed_email().cloned() {
+                    open_message_to_sender(&email, state);
+                }
+            },
+            KeyCode::Char('e') if state.is_sent_folder_selected() => {
+                // Edit a copy of the sent message, to send it again
+                self.edit_as_new(state);
+            },
+            KeyCode::Char('o') => {
+                // Open the original message in the system's mail viewer
+                if let Some(key) = state.get_viewed_email().map(Email::key) {
+                    self.spawn_task("Opening in the mail viewer", move |controller| async move {
+                        controller.open_in_viewer(key).await
+                    });
+                }
+            },
+            KeyCode::Char('g') if state.get_viewed_email().is_some_and(|email| email.truncated) => {
+                // Download the rest of a large message
+                if let Some(key) = state.get_viewed_email().map(Email::key) {
+                    state.set_status_message("Downloading the full message...".to_string());
+                    self.spawn_task("Downloading email", move |controller| async move {
+                        controller.load_email_body(key, true).await
+                    });
+                }
+            },
+            KeyCode::Char('/') => {
+                // Find text in the body
+                let query = state.body_find.as_ref().map(|find| find.query.clone()).unwrap_or_default();
+                state.set_prompt(Some(PromptState::new("Find in message", query, PromptAction::FindInBody)));
+            },
+            KeyCode::Char(c @ ('n' | 'N')) if state.body_find.is_some() => {
+                // Jump to the next or previous match of the text being found
+                let count = find_body_matches(state).len();
+                if let Some(find) = state.body_find.as_mut() {
+                    find.step(c == 'n', count);
+                }
+                scroll_to_match(state);
+            },
+            KeyCode::Char('n') => {
+                // Open the next unread email
+                self.open_next_unread(true, state);
+            },
+            KeyCode::Char('N') => {
+                // Open the previous unread email
+                self.open_next_unread(false, state);
+            },
+            KeyCode::Char('V') => {
+                // Mark or unmark the sender as a VIP
+                if let Some(sender) = state.get_viewed_email().map(|email| email.from.clone()) {
+                    toggle_vip_sender(sender, state);
+                }
+            },
+            KeyCode::Char('u') => {
+                // Toggle read/unread on the email
+                self.toggle_email_flag(false, state);
+            },
+            KeyCode::Char('*') => {
+                // Flag or unflag the email
+                self.toggle_email_flag(true, state);
+            },
+            KeyCode::Char('T') => {
+                // Add or remove tags of the email
+                open_tag_picker(state);
+            },
+            KeyCode::Char(c @ ('i' | 'I')) => {
+                // Accept or decline a calendar invitation, after asking
+                let title = state.get_viewed_email()
+                    .and_then(|email| email.invite.as_ref().filter(|event| event.can_reply()).map(|event| (email, event)))
+                    .map(|(email, event)| event.summary.clone().unwrap_or_else(|| email.subject.clone()));
+                match title {
+                    Some(title) => {
+                        let accept = c == 'i';
+                        let message = format!("{} the invitation to \"{}\"?", if accept { "Accept" } else { "Decline" }, title);
+                        state.set_confirm(Some(ConfirmState::new(&message, ConfirmAction::AnswerInvite(accept))));
+                    },
+                    None => state.set_status_message("This email has no invitation to answer".to_string()),
+                }
             },
             KeyCode::Char('d') => {
                 // Delete email
                 // TODO: Implement delete
                 state.set_status_message("Delete not implemented yet".to_string());
             },
+            KeyCode::Esc if state.body_find.is_some() => {
+                // Stop finding, leaving the body where it was scrolled to
+                state.body_find = None;
+            },
             KeyCode::Esc => {
                 // Go back to emails view
                 state.set_current_view(View::Emails);
@@ -388,50 +981,1920 @@ impl InputHandler {
         Ok(())
     }
     
-    /// Handles input in the compose email view.
+    /// Handles input in the input prompt.
     ///
     /// # Parameters
     /// - `key`: The key event
     /// - `state`: The application state
-    ///
-    /// # Returns
-    /// A Result indicating success or failure
-    async fn handle_compose_email_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+    fn handle_prompt_input(&self, key: KeyEvent, state: &mut crate::state::AppState) {
+        // Tab switches the search between the current folder and all folders
+        if key.code == KeyCode::Tab && state.prompt.as_ref().is_some_and(|p| p.action == PromptAction::Search) {
+            state.search_all_folders = !state.search_all_folders;
+            let title = search_prompt_title(state.search_all_folders, state.search_folder());
+            if let Some(prompt) = state.prompt.as_mut() {
+                prompt.title = title;
+            }
+            return;
+        }
+        
+        let Some(prompt) = state.prompt.as_mut() else {
+            return;
+        };
+        
         match key.code {
-            KeyCode::Esc => {
-                // Cancel and go back to emails view
-                state.set_current_view(View::Emails);
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => prompt.input.push(c),
+            KeyCode::Backspace => {
+                prompt.input.pop();
             },
-            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                // Send email
-                // TODO: Implement send
-                state.set_status_message("Send not implemented yet".to_string());
+            KeyCode::Esc => state.set_prompt(None),
+            KeyCode::Enter => {
+                if let Some(prompt) = state.prompt.take() {
+                    match prompt.action {
+                        PromptAction::SaveEmailBody => save_email_body(prompt.input.trim(), state),
+                        PromptAction::ScheduleSend => self.schedule_composed_email(prompt.input.trim(), state),
+                        PromptAction::Search => {
+                            let all_folders = state.search_all_folders;
+                            self.start_search(prompt.input.trim().to_string(), all_folders, None, state);
+                        },
+                        PromptAction::SaveSearch => save_search(prompt.input.trim(), state),
+                        PromptAction::EditSavedSearch(index) => edit_saved_search(index, prompt.input.trim(), state),
+                        PromptAction::RenameSavedSearch(index) => rename_saved_search(index, prompt.input.trim(), state),
+                        PromptAction::NewTag => self.add_new_tag(prompt.input.trim(), state),
+                        PromptAction::FindInBody => find_in_body(&prompt.input, state),
+                    }
+                }
             },
-            _ => {
-                // TODO: Handle compose email input
-            }
+            _ => {}
         }
-        
-        Ok(())
     }
     
-    /// Handles input in the settings view.
+    /// Handles input in the yes/no confirmation.
     ///
     /// # Parameters
     /// - `key`: The key event
     /// - `state`: The application state
-    ///
-    /// # Returns
-    /// A Result indicating success or failure
-    async fn handle_settings_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+    fn handle_confirm_input(&self, key: KeyEvent, state: &mut crate::state::AppState) {
         match key.code {
-            KeyCode::Esc => {
-                // Go back to accounts view
-                state.set_current_view(View::Accounts);
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                if let Some(confirm) = state.confirm.take() {
+                    match confirm.action {
+                        ConfirmAction::SendWithoutSubject => self.send_composed_email(state),
+                        ConfirmAction::DeleteTemplate(index) => delete_template(index, state),
+                        ConfirmAction::DeleteSavedSearch(index) => delete_saved_search(index, state),
+                        ConfirmAction::SaveDuplicateAccount => self.spawn_task("Saving account", |controller| async move {
+                            controller.save_account_form().await
+                        }),
+                        ConfirmAction::EmptyTrashOnExit => confirm_empty_trash_on_exit(state),
+                        ConfirmAction::Quit => finish_quit(state),
+                        ConfirmAction::QuitWithUnsent => confirm_empty_trash_and_quit(state),
+                        ConfirmAction::SaveDraftAndQuit => match self.controller.save_draft(state) {
+                            Ok(()) => finish_quit(state),
+                            Err(e) => state.set_status_message(format!("Cannot save the draft: {}", e)),
+                        },
+                        ConfirmAction::AnswerInvite(accept) => {
+                            if let Err(e) = self.controller.answer_invite(accept, state) {
+                                state.set_status_message(format!("Cannot answer the invitation: {}", e));
+                            }
+                        },
+                    }
+                }
+            },
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                // Declining to empty Trash still quits, leaving Trash as it is,
+                // and declining to save the draft quits without it
+                match state.confirm.take().map(|confirm| confirm.action) {
+                    Some(ConfirmAction::EmptyTrashOnExit) => state.set_running(false),
+                    Some(ConfirmAction::SaveDraftAndQuit) => finish_quit(state),
+                    _ => {},
+                }
             },
+            KeyCode::Esc => state.set_confirm(None),
             _ => {}
         }
+    }
+    
+    /// Starts a search in the background, or clears the search if the query is empty.
+    ///
+    /// # Parameters
+    /// - `query`: The query, as typed by the user
+    /// - `all_folders`: Whether to search every folder of the account
+    /// - `name`: The name of the saved search being run, if any
+    /// - `state`: The application state
+    fn start_search(&self, query: String, all_folders: bool, name: Option<String>, state: &mut crate::state::AppState) {
+        if query.is_empty() {
+            self.clear_search(state);
+            return;
+        }
         
-        Ok(())
+        self.spawn_task("Searching", move |controller| async move {
+            controller.search_emails(query, all_folders, name).await
+        });
+    }
+    
+    /// Opens a saved search from the folders view and runs it.
+    ///
+    /// # Parameters
+    /// - `index`: Index of the saved search
+    /// - `state`: The application state
+    fn open_saved_search(&self, index: usize, state: &mut crate::state::AppState) {
+        let Some(saved) = state.saved_searches().get(index).cloned() else {
+            return;
+        };
+        
+        // A search of a single folder starts from that folder
+        if let Some(folder) = &saved.folder {
+            state.set_selected_folder(folder.clone());
+        }
+        state.search = None;
+        state.emails = Arc::default();
+        state.clamp_selection();
+        state.set_current_view(View::Emails);
+        
+        let all_folders = saved.folder.is_none();
+        self.start_search(saved.query, all_folders, Some(saved.name), state);
+    }
+    
+    /// Opens an email of the list in the detail view.
+    ///
+    /// The body is downloaded if only the headers were listed, and the email
+    /// is marked as read as the auto_mark_read setting says.
+    ///
+    /// # Parameters
+    /// - `index`: Index of the email in the list
+    /// - `state`: The application state
+    fn open_email(&self, index: usize, state: &mut crate::state::AppState) {
+        let Some(email) = state.emails.get(index).cloned() else {
+            return;
+        };
+        
+        // Actions on a search result apply to the folder it was found in
+        if state.search.is_some() && !email.folder.is_empty() {
+            state.selected_folder = email.folder.clone();
+        }
+        
+        state.set_selected_email(Some(index));
+        state.set_viewed_email(Some(email.clone()));
+        state.set_current_view(View::EmailDetail);
+        state.set_email_scroll_offset(0);
+        state.set_show_quoted_text(false);
+        state.set_show_remote_content(false);
+        state.body_find = None;
+        
+        // Download the body if only the headers were listed, then mark as read
+        let unread = !email.is_read;
+        let policy = state.config_manager.get_config().settings.auto_mark_read;
+        let key = email.key();
+        if email.headers_only {
+            self.spawn_task("Downloading email", move |controller| async move {
+                controller.load_email_body(key.clone(), false).await?;
+                if unread {
+                    controller.mark_opened_email_as_read(key, policy).await?;
+                }
+                Ok(())
+            });
+        } else if unread {
+            self.spawn_task("Marking email as read", move |controller| async move {
+                controller.mark_opened_email_as_read(key, policy).await
+            });
+        }
+    }
+    
+    /// Opens the next or previous unread email after the selected one.
+    ///
+    /// The search wraps around the list, and the status says when it did.
+    ///
+    /// # Parameters
+    /// - `forward`: true for the next unread email, false for the previous one
+    /// - `state`: The application state
+    fn open_next_unread(&self, forward: bool, state: &mut crate::state::AppState) {
+        let len = state.emails.len();
+        if len == 0 {
+            state.set_status_message("No unread emails".to_string());
+            return;
+        }
+        
+        // Look at every other email once, starting next to the selected one
+        let current = state.get_selected_email().unwrap_or(0).min(len - 1);
+        let found = (1..=len)
+            .map(|offset| if forward { (current + offset) % len } else { (current + len - offset) % len })
+            .find(|&index| !state.emails[index].is_read);
+        
+        let Some(index) = found else {
+            state.set_status_message("No unread emails".to_string());
+            return;
+        };
+        
+        if forward && index <= current {
+            state.set_status_message("Wrapped around to the top".to_string());
+        } else if !forward && index >= current {
+            state.set_status_message("Wrapped around to the bottom".to_string());
+        }
+        self.open_email(index, state);
+    }
+    
+    /// Handles the keys of the email list that differ when it's grouped.
+    ///
+    /// # Parameters
+    /// - `key`: The key event
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// true if the key was handled, false if it works like in the plain list
+    fn handle_grouped_emails_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> bool {
+        let Some(grouping) = state.grouping.as_ref() else {
+            return false;
+        };
+        let rows = grouping.rows(&state.emails);
+        let current = grouping.selected_row(&rows, state.get_selected_email());
+        let row = current.and_then(|index| rows.get(index)).cloned();
+        
+        match (key.code, row) {
+            (KeyCode::Up | KeyCode::Down, _) => {
+                // Move to the previous or next line, groups included
+                let next = match current {
+                    None => 0,
+                    Some(index) if key.code == KeyCode::Up => index.saturating_sub(1),
+                    Some(index) => (index + 1).min(rows.len().saturating_sub(1)),
+                };
+                if let Some(row) = rows.get(next) {
+                    select_group_row(row, state);
+                }
+                true
+            },
+            (KeyCode::Enter | KeyCode::Char(' '), Some(GroupRow::Group { key, .. })) => {
+                // Expand or collapse the group
+                if let Some(grouping) = state.grouping.as_mut()
+                    && !grouping.expanded.remove(&key) {
+                    grouping.expanded.insert(key);
+                }
+                true
+            },
+            (KeyCode::Right, Some(GroupRow::Group { key, .. })) => {
+                // Expand the group
+                if let Some(grouping) = state.grouping.as_mut() {
+                    grouping.expanded.insert(key);
+                }
+                true
+            },
+            (KeyCode::Left, Some(row)) => {
+                // Collapse the group, keeping it selected
+                let group = match &row {
+                    GroupRow::Group { key, .. } => Some(key.clone()),
+                    GroupRow::Email(index) => state.emails.get(*index).map(|email| grouping.by.key(email)),
+                };
+                if let (Some(group), Some(grouping)) = (group, state.grouping.as_mut()) {
+                    grouping.expanded.remove(&group);
+                    grouping.selected_group = Some(group);
+                }
+                true
+            },
+            (KeyCode::Char(c @ ('u' | '*')), Some(GroupRow::Group { members, .. })) if state.selected_emails.is_empty() => {
+                // Mark all of the group's emails, or unmark them if they all are
+                self.toggle_emails_flag(&members, c == '*', state);
+                true
+            },
+            _ => false,
+        }
+    }
+    
+    /// Marks some emails read or flagged in the background, or unmarks them if they all are.
+    ///
+    /// # Parameters
+    /// - `indexes`: Indexes of the emails in the list
+    /// - `flagged`: true to change the flag, false to change read/unread
+    /// - `state`: The application state
+    fn toggle_emails_flag(&self, indexes: &[usize], flagged: bool, state: &crate::state::AppState) {
+        let emails: Vec<&crate::models::Email> = indexes.iter().filter_map(|&index| state.emails.get(index)).collect();
+        let change = if flagged {
+            FlagChange::Flagged(!emails.iter().all(|email| email.is_flagged))
+        } else {
+            FlagChange::Read(!emails.iter().all(|email| email.is_read))
+        };
+        let keys = emails.iter().map(|email| email.key()).collect();
+        self.spawn_task("Updating flags", move |controller| async move {
+            controller.change_email_flags(keys, change).await
+        });
+    }
+    
+    /// Opens a copy of the current sent message in compose, downloading its body in the background.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    fn edit_as_new(&self, state: &mut crate::state::AppState) {
+        if let Some(key) = current_email(state).map(Email::key) {
+            state.set_status_message("Loading the message...".to_string());
+            self.spawn_task("Opening the copy", move |controller| async move {
+                controller.edit_as_new(key).await
+            });
+        }
+    }
+    
+    /// Toggles the read or flagged state of the current email in the background.
+    ///
+    /// The change shows right away and is reverted if the server rejects it.
+    ///
+    /// # Parameters
+    /// - `flagged`: true to toggle the flag, false to toggle read/unread
+    /// - `state`: The application state
+    fn toggle_email_flag(&self, flagged: bool, state: &mut crate::state::AppState) {
+        if state.get_selected_folder() == SCHEDULED_FOLDER {
+            return;
+        }
+        let Some(email) = current_email(state) else {
+            return;
+        };
+        
+        let change = if flagged {
+            FlagChange::Flagged(!email.is_flagged)
+        } else {
+            FlagChange::Read(!email.is_read)
+        };
+        let key = email.key();
+        self.spawn_task("Updating flags", move |controller| async move {
+            controller.change_email_flag(key, change).await
+        });
+    }
+    
+    /// Clears the search results and lists the folder the search started from again.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    fn clear_search(&self, state: &mut crate::state::AppState) {
+        if let Some(search) = state.search.take() {
+            state.set_selected_folder(search.folder);
+            self.spawn_task("Loading emails", |controller| async move {
+                controller.load_emails().await
+            });
+        }
+    }
+    
+    /// Starts a full reconnect of the selected account in the background.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    fn reconnect_selected_account(&self, state: &mut crate::state::AppState) {
+        if state.get_selected_account().is_some() {
+            state.set_status_message("Reconnecting all protocols...".to_string());
+            self.spawn_task("Reconnecting", |controller| async move {
+                controller.reconnect_selected_account().await
+            });
+        }
+    }
+    
+    /// Queues the composed email, reporting problems in the status bar.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    fn send_composed_email(&self, state: &mut crate::state::AppState) {
+        if let Err(e) = self.controller.queue_composed_email(state) {
+            state.set_status_message(format!("Cannot send: {}", e));
+        }
+    }
+    
+    /// Schedules the composed email, reporting problems in the status bar.
+    ///
+    /// # Parameters
+    /// - `delay`: The delay entered by the user, e.g. "30m", "2h" or "1d"
+    /// - `state`: The application state
+    fn schedule_composed_email(&self, delay: &str, state: &mut crate::state::AppState) {
+        let Some(delay) = parse_send_delay(delay) else {
+            state.set_status_message(format!("Cannot schedule: \"{}\" is not a delay like 30m, 2h or 1d", delay));
+            return;
+        };
+        
+        match self.controller.schedule_composed_email(state, delay) {
+            Ok(()) => {
+                // Show the new message if the Scheduled folder is open
+                if state.get_selected_folder() == SCHEDULED_FOLDER {
+                    self.spawn_task("Loading scheduled messages", |controller| async move {
+                        controller.load_emails().await
+                    });
+                }
+            },
+            Err(e) => state.set_status_message(format!("Cannot schedule: {}", e)),
+        }
+    }
+    
+    /// Handles input in the attachment list popup and the preview.
+    ///
+    /// # Parameters
+    /// - `key`: The key event
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn handle_attachment_picker_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        let count = state.get_viewed_email().map_or(0, |email| email.attachments.len());
+        let Some(picker) = state.attachment_picker.as_mut() else {
+            return Ok(());
+        };
+        
+        // The preview scrolls until it's closed
+        if let Some(preview) = picker.preview.as_mut() {
+            match key.code {
+                KeyCode::Up => preview.scroll = preview.scroll.saturating_sub(1),
+                KeyCode::Down => preview.scroll = preview.scroll.saturating_add(1),
+                KeyCode::PageUp => preview.scroll = preview.scroll.saturating_sub(10),
+                KeyCode::PageDown => preview.scroll = preview.scroll.saturating_add(10),
+                KeyCode::Home => preview.scroll = 0,
+                KeyCode::Esc => picker.preview = None,
+                _ => {},
+            }
+            return Ok(());
+        }
+        
+        let index = match key.code {
+            KeyCode::Up => {
+                picker.select_previous();
+                None
+            },
+            KeyCode::Down => {
+                picker.select_next(count);
+                None
+            },
+            KeyCode::Enter => Some(picker.selected),
+            KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => {
+                // Preview an attachment directly by its number
+                let index = c as usize - '1' as usize;
+                (index < count).then_some(index)
+            },
+            KeyCode::Esc => {
+                state.set_attachment_picker(None);
+                None
+            },
+            _ => None,
+        };
+        
+        // Attachments that were only listed are downloaded first
+        let download = index
+            .and_then(|index| state.get_viewed_email()?.attachments.get(index))
+            .is_some_and(|attachment| attachment.needs_download() && can_preview(attachment));
+        let key = state.get_viewed_email().map(Email::key);
+        if let (Some(index), Some(key), true) = (index, key, download) {
+            self.spawn_task("Downloading the attachment", move |controller| async move {
+                controller.download_attachment(key, index).await
+            });
+        } else if let Some(index) = index {
+            let preview = state.get_viewed_email()
+                .and_then(|email| email.attachments.get(index))
+                .map(preview_attachment);
+            match preview {
+                Some(Ok(preview)) => {
+                    if let Some(picker) = state.attachment_picker.as_mut() {
+                        picker.selected = index;
+                        picker.preview = Some(preview);
+                    }
+                },
+                Some(Err(e)) => state.set_status_message(e.to_string()),
+                None => {},
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Handles input in the tag picker popup.
+    ///
+    /// # Parameters
+    /// - `key`: The key event
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn handle_tag_picker_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        let Some(picker) = state.tag_picker.as_mut() else {
+            return Ok(());
+        };
+        
+        match key.code {
+            KeyCode::Up => picker.select_previous(),
+            KeyCode::Down => picker.select_next(),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                // Add the selected tag, or remove it if the email has it
+                if let Some(tag) = picker.selected_tag().cloned() {
+                    self.tag_selected_email(tag, state);
+                }
+            },
+            KeyCode::Char('n') => {
+                // Ask for a tag that isn't listed yet
+                state.set_prompt(Some(PromptState::new("New tag (e.g. $Waiting)", String::new(), PromptAction::NewTag)));
+            },
+            KeyCode::Esc => state.set_tag_picker(None),
+            _ => {},
+        }
+        
+        Ok(())
+    }
+    
+    /// Adds a tag to the current email, or removes it if the email has it.
+    ///
+    /// # Parameters
+    /// - `tag`: The tag
+    /// - `state`: The application state
+    fn tag_selected_email(&self, tag: String, state: &mut crate::state::AppState) {
+        let Some(email) = current_email(state) else {
+            return;
+        };
+        
+        let add = !email.has_tag(&tag);
+        let key = email.key();
+        self.spawn_task("Tagging", move |controller| async move {
+            controller.change_email_tag(key, tag, add).await
+        });
+    }
+    
+    /// Adds a new tag to the tag list and tags the current email with it.
+    ///
+    /// # Parameters
+    /// - `tag`: The entered tag
+    /// - `state`: The application state
+    fn add_new_tag(&self, tag: &str, state: &mut crate::state::AppState) {
+        if !is_valid_tag(tag) {
+            state.set_status_message("Tags can't be empty or contain spaces or any of ( ) { % * \" \\ ]".to_string());
+            return;
+        }
+        
+        // Remember the tag for other emails
+        let settings = &mut state.config_manager.get_config_mut().settings;
+        if !settings.tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+            settings.tags.push(tag.to_string());
+            if let Err(e) = state.config_manager.save_config() {
+                state.set_status_message(format!("Failed to save configuration: {}", e));
+            }
+        }
+        
+        // List it in the picker, selected
+        if let Some(picker) = state.tag_picker.as_mut() {
+            match picker.tags.iter().position(|known| known.eq_ignore_ascii_case(tag)) {
+                Some(index) => picker.selected = index,
+                None => {
+                    picker.tags.push(tag.to_string());
+                    picker.selected = picker.tags.len() - 1;
+                },
+            }
+        }
+        
+        // Tag the email unless it already has the tag
+        if current_email(state).is_some_and(|email| !email.has_tag(tag)) {
+            self.tag_selected_email(tag.to_string(), state);
+        }
+    }
+    
+    /// Handles input in the link picker popup.
+    ///
+    /// # Parameters
+    /// - `key`: The key event
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn handle_link_picker_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        let Some(picker) = state.link_picker.as_mut() else {
+            return Ok(());
+        };
+        
+        let link = match key.code {
+            KeyCode::Up => {
+                picker.select_previous();
+                None
+            },
+            KeyCode::Down => {
+                picker.select_next();
+                None
+            },
+            KeyCode::Enter => picker.get_selected_link().cloned(),
+            KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => {
+                // Open a link directly by its number
+                let index = c as usize - '1' as usize;
+                picker.links.get(index).cloned()
+            },
+            KeyCode::Esc => {
+                state.set_link_picker(None);
+                None
+            },
+            _ => None,
+        };
+        
+        if let Some(link) = link {
+            // Hand the link to the system opener without waiting for it
+            state.set_link_picker(None);
+            match open::that_detached(&link) {
+                Ok(()) => state.set_status_message(format!("Opened {}", link)),
+                Err(e) => state.set_status_message(format!("Failed to open {}: {}", link, e)),
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Handles input in the compose email view.
+    ///
+    /// # Parameters
+    /// - `key`: The key event
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn handle_compose_email_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        // The template picker popup takes all keys while it is open
+        if state.get_template_picker().is_some() {
+            handle_template_picker_input(key, state);
+            return Ok(());
+        }
+        
+        // The recipient suggestions take the arrow keys, Enter and Esc while shown
+        if let Some(compose) = state.compose_state.as_mut().filter(|compose| !compose.suggestions.is_empty()) {
+            match key.code {
+                KeyCode::Down => {
+                    compose.select_next_suggestion();
+                    return Ok(());
+                },
+                KeyCode::Up => {
+                    compose.select_previous_suggestion();
+                    return Ok(());
+                },
+                KeyCode::Enter if compose.accept_suggestion() => return Ok(()),
+                KeyCode::Esc => {
+                    compose.clear_suggestions();
+                    return Ok(());
+                },
+                _ => {},
+            }
+        }
+        
+        match key.code {
+            KeyCode::Esc => {
+                // Cancel and go back to emails view
+                state.set_compose_state(None);
+                state.set_current_view(View::Emails);
+            },
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Queue the email in the outbox, checking first if the subject was forgotten
+                let no_subject = state.get_compose_state().is_some_and(|c| c.subject.trim().is_empty());
+                if no_subject {
+                    state.set_confirm(Some(ConfirmState::new("Send with no subject?", ConfirmAction::SendWithoutSubject)));
+                } else {
+                    self.send_composed_email(state);
+                }
+            },
+            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Ask when to send the email
+                state.set_prompt(Some(PromptState::new("Send in (e.g. 30m, 2h, 1d)", String::new(), PromptAction::ScheduleSend)));
+            },
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Edit the body in the external editor, which the main loop runs
+                state.request_external_editor();
+            },
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Cycle through normal, high and low priority
+                if let Some(compose) = state.get_compose_state_mut() {
+                    compose.priority = compose.priority.next();
+                }
+            },
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Pick a template to insert
+                if state.config_manager.get_config().templates.is_empty() {
+                    state.set_status_message("No templates yet - add them in the settings view ('o' in the accounts view)".to_string());
+                } else {
+                    state.template_picker = Some(TemplatePickerState::new());
+                }
+            },
+            _ => {
+                // Borrow the form apart from the recipient history it suggests from
+                let recent_recipients = &state.recent_recipients;
+                if let Some(compose) = state.compose_state.as_mut() {
+                    let in_body = compose.focused_field == ComposeField::Body;
+                    match key.code {
+                        KeyCode::Left if in_body => compose.move_cursor_left(),
+                        KeyCode::Right if in_body => compose.move_cursor_right(),
+                        KeyCode::Up if in_body => compose.move_cursor_up(),
+                        KeyCode::Down if in_body => compose.move_cursor_down(),
+                        KeyCode::Home if in_body => compose.move_cursor_home(),
+                        KeyCode::End if in_body => compose.move_cursor_end(),
+                        KeyCode::Tab => compose.focus_next_field(),
+                        KeyCode::BackTab => compose.focus_previous_field(),
+                        KeyCode::Enter => compose.insert_newline(),
+                        KeyCode::Backspace => compose.backspace(),
+                        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => compose.insert_char(c),
+                        _ => {}
+                    }
+                    
+                    // Suggest recent recipients while an address is typed
+                    match key.code {
+                        KeyCode::Char(_) | KeyCode::Backspace => compose.update_suggestions(recent_recipients),
+                        _ => compose.clear_suggestions(),
+                    }
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Handles input in the settings view.
+    ///
+    /// # Parameters
+    /// - `key`: The key event
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn handle_settings_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        let template_count = state.config_manager.get_config().templates.len();
+        
+        match key.code {
+            KeyCode::Up => {
+                // Move selection up
+                if let Some(index) = state.selected_template
+                    && index > 0 {
+                    state.selected_template = Some(index - 1);
+                }
+            },
+            KeyCode::Down => {
+                // Move selection down
+                if let Some(index) = state.selected_template
+                    && index + 1 < template_count {
+                    state.selected_template = Some(index + 1);
+                }
+            },
+            KeyCode::Char('a') => {
+                // Add a new template
+                state.template_form = Some(TemplateFormState::new());
+                state.set_current_view(View::TemplateForm);
+            },
+            KeyCode::Char('e') | KeyCode::Enter => {
+                // Edit the selected template
+                if let Some(index) = state.selected_template
+                    && let Some(template) = state.config_manager.get_config().templates.get(index) {
+                    state.template_form = Some(TemplateFormState::from_template(template.clone(), index));
+                    state.set_current_view(View::TemplateForm);
+                }
+            },
+            KeyCode::Char('d') => {
+                // Delete the selected template after confirmation
+                if let Some(index) = state.selected_template
+                    && let Some(template) = state.config_manager.get_config().templates.get(index) {
+                    let message = format!("Delete template \"{}\"?", template.name);
+                    state.set_confirm(Some(ConfirmState::new(&message, ConfirmAction::DeleteTemplate(index))));
+                }
+            },
+            KeyCode::Esc => {
+                // Go back to accounts view
+                state.set_current_view(View::Accounts);
+            },
+            _ => {}
+        }
+        
+        Ok(())
+    }
+    
+    /// Handles input in the template form view.
+    ///
+    /// # Parameters
+    /// - `key`: The key event
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn handle_template_form_input(&self, key: KeyEvent, state: &mut crate::state::AppState) -> Result<()> {
+        if is_key_with_modifier(&key, KeyCode::Char('s'), KeyModifiers::CONTROL) {
+            save_template_form(state);
+            return Ok(());
+        }
+        
+        if key.code == KeyCode::Esc {
+            // Discard changes and go back to the settings view
+            state.template_form = None;
+            state.set_current_view(View::Settings);
+            return Ok(());
+        }
+        
+        if let Some(form) = state.template_form.as_mut() {
+            match key.code {
+                KeyCode::Tab => form.focus_next_field(),
+                KeyCode::BackTab => form.focus_previous_field(),
+                KeyCode::Enter => form.insert_newline(),
+                KeyCode::Backspace => form.backspace(),
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => form.insert_char(c),
+                _ => {}
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Suggests a path to save an email body to, based on its subject.
+///
+/// # Parameters
+/// - `subject`: The email subject
+///
+/// # Returns
+/// A path in the home directory
+fn suggested_body_path(subject: &str) -> String {
+    let name: String = subject
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .take(50)
+        .collect();
+    let name = name.trim_matches('_');
+    let name = if name.is_empty() { "email" } else { name };
+    
+    let dir = dirs::home_dir().unwrap_or_default();
+    dir.join(format!("{}.txt", name)).to_string_lossy().to_string()
+}
+
+/// Parses a send delay such as "90s", "30m", "2h" or "1d".
+///
+/// A number without a unit is taken as minutes.
+///
+/// # Parameters
+/// - `text`: The delay entered by the user
+///
+/// # Returns
+/// An Option containing the delay, or None if it isn't a positive delay
+fn parse_send_delay(text: &str) -> Option<Duration> {
+    let text = text.trim().to_lowercase();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    
+    let unit_secs = match unit.trim() {
+        "s" | "sec" | "secs" => 1,
+        "" | "m" | "min" | "mins" => 60,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 60 * 60,
+        "d" | "day" | "days" => 60 * 60 * 24,
+        _ => return None,
+    };
+    
+    let secs = number.checked_mul(unit_secs)?;
+    if secs == 0 { None } else { Some(Duration::from_secs(secs)) }
+}
+
+/// Copies text to the system clipboard, reporting the outcome in the status bar.
+///
+/// # Parameters
+/// - `what`: Description of the copied text for the status message
+/// - `text`: The text to copy
+/// - `state`: The application state
+fn copy_to_clipboard(what: &str, text: &str, state: &mut crate::state::AppState) {
+    match crate::ui::clipboard::copy_text(text) {
+        Ok(()) => state.set_status_message(format!("Copied {} to the clipboard", what)),
+        Err(e) => {
+            error!("Failed to copy {}: {}", what, e);
+            state.set_status_message(format!("Cannot copy {}: {}", what, e));
+        },
+    }
+}
+
+/// Measures the body of the viewed email in the current terminal size.
+///
+/// # Parameters
+/// - `state`: The application state
+///
+/// # Returns
+/// A Result containing the body's viewport, or None if no email is viewed
+fn email_body_viewport(state: &crate::state::AppState) -> Result<Option<views::BodyViewport>> {
+    let Some(email) = state.get_viewed_email() else {
+        return Ok(None);
+    };
+    
+    Ok(Some(views::email_body_viewport(
+        crate::ui::content_area()?,
+        email,
+        &state.config_manager.get_config().settings,
+        state.is_showing_quoted_text(),
+        state.is_showing_remote_content(),
+    )))
+}
+
+/// Gets the emails of the list that are selected for a batch change.
+///
+/// # Parameters
+/// - `state`: The application state
+///
+/// # Returns
+/// The indexes of the selected emails in the list
+fn selected_email_indexes(state: &crate::state::AppState) -> Vec<usize> {
+    state.emails.iter()
+        .enumerate()
+        .filter(|(_, email)| state.selected_emails.contains(&email.key()))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Starts finding text in the body of the viewed email, scrolling to the first match.
+///
+/// # Parameters
+/// - `query`: The text to find; empty stops finding
+/// - `state`: The application state
+fn find_in_body(query: &str, state: &mut crate::state::AppState) {
+    if query.is_empty() {
+        state.body_find = None;
+        return;
+    }
+    
+    state.body_find = Some(FindState::new(query));
+    if find_body_matches(state).is_empty() {
+        state.set_status_message(format!("\"{}\" isn't in this message", query));
+        return;
+    }
+    scroll_to_match(state);
+}
+
+/// Finds the text being found in the body of the viewed email, as displayed.
+///
+/// # Parameters
+/// - `state`: The application state
+///
+/// # Returns
+/// The byte ranges of the matches in the displayed body
+fn find_body_matches(state: &crate::state::AppState) -> Vec<(usize, usize)> {
+    let (Some(email), Some(find)) = (state.get_viewed_email(), state.body_find.as_ref()) else {
+        return Vec::new();
+    };
+    
+    let body = views::email_detail_body(
+        email,
+        &state.config_manager.get_config().settings,
+        state.is_showing_quoted_text(),
+        state.is_showing_remote_content(),
+    );
+    find_matches(&body, &find.query)
+}
+
+/// Scrolls the body of the viewed email so the current match is in view.
+///
+/// A match that is already shown doesn't scroll the body; otherwise it's
+/// brought to a third of the way down.
+///
+/// # Parameters
+/// - `state`: The application state
+fn scroll_to_match(state: &mut crate::state::AppState) {
+    let matches = find_body_matches(state);
+    let Some(current) = state.body_find.as_ref().map(|find| find.current.min(matches.len().saturating_sub(1))) else {
+        return;
+    };
+    let (Some(&(_, end)), Some(email)) = (matches.get(current), state.get_viewed_email()) else {
+        return;
+    };
+    let viewport = match email_body_viewport(state) {
+        Ok(Some(viewport)) => viewport,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Failed to measure the email body: {}", e);
+            return;
+        },
+    };
+    
+    let settings = &state.config_manager.get_config().settings;
+    let body = views::email_detail_body(email, settings, state.is_showing_quoted_text(), state.is_showing_remote_content());
+    let line = find::match_line(&body, end, viewport.width);
+    
+    let offset = usize::from(state.get_email_scroll_offset());
+    if line < offset || line >= offset + usize::from(viewport.height) {
+        let top = line.saturating_sub(usize::from(viewport.height) / 3);
+        state.set_email_scroll_offset(viewport.scroll(0, i32::try_from(top).unwrap_or(i32::MAX)));
+    }
+    if let Some(find) = state.body_find.as_mut() {
+        find.current = current;
+    }
+}
+
+/// Saves the body of the viewed email, as displayed, to a file.
+///
+/// # Parameters
+/// - `path`: The path entered by the user; a leading "~/" means the home directory
+/// - `state`: The application state
+fn save_email_body(path: &str, state: &mut crate::state::AppState) {
+    let Some(email) = state.get_viewed_email() else {
+        return;
+    };
+    
+    let path = match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => std::path::PathBuf::from(path),
+    };
+    
+    let prefer_html = state.config_manager.get_config().settings.prefer_html;
+    let body = views::display_body_text(email, prefer_html, state.is_showing_quoted_text(), state.is_showing_remote_content());
+    
+    // Never replace an existing file, which a mistyped path could name
+    let written = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, body.as_bytes()));
+    match written {
+        Ok(()) => state.set_status_message(format!("Saved body to {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            state.set_status_message(format!("{} already exists; choose another name", path.display()));
+        },
+        Err(e) => state.set_status_message(format!("Failed to save body to {}: {}", path.display(), e)),
+    }
+}
+
+/// Handles input in the template picker popup.
+///
+/// # Parameters
+/// - `key`: The key event
+/// - `state`: The application state
+fn handle_template_picker_input(key: KeyEvent, state: &mut crate::state::AppState) {
+    let template_count = state.config_manager.get_config().templates.len();
+    let Some(picker) = state.template_picker.as_mut() else {
+        return;
+    };
+    
+    match key.code {
+        KeyCode::Up => picker.select_previous(),
+        KeyCode::Down => picker.select_next(template_count),
+        KeyCode::Enter => {
+            let selected = picker.selected;
+            state.template_picker = None;
+            
+            // Fill in the placeholders from the recipient and the sending account
+            let my_name = state.get_selected_account()
+                .and_then(|index| state.account_summaries.get(index))
+                .map(|summary| summary.name.clone())
+                .unwrap_or_default();
+            let Some(template) = state.config_manager.get_config().templates.get(selected).cloned() else {
+                return;
+            };
+            if let Some(compose) = state.get_compose_state_mut() {
+                let subject = fill_placeholders(&template.subject, &compose.to, &my_name);
+                let body = fill_placeholders(&template.body, &compose.to, &my_name);
+                compose.apply_template(&subject, &body);
+            }
+        },
+        KeyCode::Esc => state.template_picker = None,
+        _ => {}
+    }
+}
+
+/// Saves the template form to the configuration.
+///
+/// # Parameters
+/// - `state`: The application state
+fn save_template_form(state: &mut crate::state::AppState) {
+    let Some(form) = state.template_form.clone() else {
+        return;
+    };
+    
+    if form.template.name.trim().is_empty() {
+        state.set_status_message("Template name is required".to_string());
+        return;
+    }
+    
+    // Add or replace the template
+    let templates = &mut state.config_manager.get_config_mut().templates;
+    let index = match form.index {
+        Some(index) if index < templates.len() => {
+            templates[index] = form.template;
+            index
+        },
+        _ => {
+            templates.push(form.template);
+            templates.len() - 1
+        },
+    };
+    
+    match state.config_manager.save_config() {
+        Ok(()) => {
+            state.template_form = None;
+            state.selected_template = Some(index);
+            state.set_current_view(View::Settings);
+            state.set_status_message("Template saved".to_string());
+        },
+        Err(e) => state.set_status_message(format!("Failed to save template: {}", e)),
+    }
+}
+
+/// Deletes a template from the configuration.
+///
+/// # Parameters
+/// - `index`: Index of the template to delete
+/// - `state`: The application state
+fn delete_template(index: usize, state: &mut crate::state::AppState) {
+    let templates = &mut state.config_manager.get_config_mut().templates;
+    if index >= templates.len() {
+        return;
+    }
+    templates.remove(index);
+    let remaining = templates.len();
+    
+    // Keep the selection on a remaining template
+    state.selected_template = if remaining == 0 { None } else { Some(index.min(remaining - 1)) };
+    
+    match state.config_manager.save_config() {
+        Ok(()) => state.set_status_message("Template deleted".to_string()),
+        Err(e) => state.set_status_message(format!("Failed to save configuration: {}", e)),
+    }
+}
+
+/// Saves the listed search under a name, adding it to the folders view.
+///
+/// # Parameters
+/// - `name`: The name of the saved search
+/// - `state`: The application state
+fn save_search(name: &str, state: &mut crate::state::AppState) {
+    let Some(search) = state.search.clone() else {
+        return;
+    };
+    if name.is_empty() {
+        state.set_status_message("Saved search name is required".to_string());
+        return;
+    }
+    
+    let saved_searches = &mut state.config_manager.get_config_mut().saved_searches;
+    if saved_searches.iter().any(|saved| saved.name == name) {
+        state.set_status_message(format!("A saved search named \"{}\" already exists", name));
+        return;
+    }
+    saved_searches.push(SavedSearch {
+        name: name.to_string(),
+        query: search.query,
+        folder: if search.all_folders { None } else { Some(search.folder) },
+    });
+    
+    match state.config_manager.save_config() {
+        Ok(()) => {
+            // The listed results now belong to the saved search
+            if let Some(search) = state.search.as_mut() {
+                search.name = Some(name.to_string());
+            }
+            state.set_status_message(format!("Saved search \"{}\" added to the folders", name));
+        },
+        Err(e) => state.set_status_message(format!("Failed to save configuration: {}", e)),
+    }
+}
+
+/// Changes the query of a saved search.
+///
+/// # Parameters
+/// - `index`: Index of the saved search
+/// - `query`: The new query
+/// - `state`: The application state
+fn edit_saved_search(index: usize, query: &str, state: &mut crate::state::AppState) {
+    if query.is_empty() {
+        state.set_status_message("Saved search query is required".to_string());
+        return;
+    }
+    let Some(saved) = state.config_manager.get_config_mut().saved_searches.get_mut(index) else {
+        return;
+    };
+    saved.query = query.to_string();
+    
+    match state.config_manager.save_config() {
+        Ok(()) => state.set_status_message("Saved search updated".to_string()),
+        Err(e) => state.set_status_message(format!("Failed to save configuration: {}", e)),
+    }
+}
+
+/// Renames a saved search.
+///
+/// # Parameters
+/// - `index`: Index of the saved search
+/// - `name`: The new name
+/// - `state`: The application state
+fn rename_saved_search(index: usize, name: &str, state: &mut crate::state::AppState) {
+    if name.is_empty() {
+        state.set_status_message("Saved search name is required".to_string());
+        return;
+    }
+    let saved_searches = &mut state.config_manager.get_config_mut().saved_searches;
+    if saved_searches.iter().enumerate().any(|(i, saved)| i != index && saved.name == name) {
+        state.set_status_message(format!("A saved search named \"{}\" already exists", name));
+        return;
+    }
+    let Some(saved) = saved_searches.get_mut(index) else {
+        return;
+    };
+    saved.name = name.to_string();
+    
+    match state.config_manager.save_config() {
+        Ok(()) => state.set_status_message("Saved search renamed".to_string()),
+        Err(e) => state.set_status_message(format!("Failed to save configuration: {}", e)),
+    }
+}
+
+/// Deletes a saved search from the configuration.
+///
+/// # Parameters
+/// - `index`: Index of the saved search to delete
+/// - `state`: The application state
+fn delete_saved_search(index: usize, state: &mut crate::state::AppState) {
+    let saved_searches = &mut state.config_manager.get_config_mut().saved_searches;
+    if index >= saved_searches.len() {
+        return;
+    }
+    saved_searches.remove(index);
+    
+    // Keep the selection on a remaining entry of the folders view
+    if index == saved_searches.len() {
+        state.selected_folder_index = state.selected_folder_index.saturating_sub(1);
+    }
+    
+    match state.config_manager.save_config() {
+        Ok(()) => state.set_status_message("Saved search deleted".to_string()),
+        Err(e) => state.set_status_message(format!("Failed to save configuration: {}", e)),
+    }
+}
+
+/// Opens compose with a reply to an email.
+///
+/// # Parameters
+/// - `email`: The email to reply to
+/// - `state`: The application state
+fn open_reply(email: &crate::models::Email, state: &mut crate::state::AppState) {
+    let compose = build_reply(email, &state.config_manager.get_config().settings);
+    open_compose(compose, state);
+}
+
+/// Opens compose with an email to forward.
+///
+/// # Parameters
+/// - `email`: The email to forward
+/// - `state`: The application state
+fn open_forward(email: &crate::models::Email, state: &mut crate::state::AppState) {
+    let compose = build_forward(email, &state.config_manager.get_config().settings);
+    open_compose(compose, state);
+}
+
+/// Opens compose with a new message to the sender of an email.
+///
+/// # Parameters
+/// - `email`: The email whose sender is written to
+/// - `state`: The application state
+fn open_message_to_sender(email: &crate::models::Email, state: &mut crate::state::AppState) {
+    if email.from.is_empty() {
+        state.set_status_message("This email has no sender".to_string());
+        return;
+    }
+    open_compose(build_message_to_sender(email), state);
+}
+
+/// Opens compose with the saved draft, or with a new message if there is none.
+///
+/// # Parameters
+/// - `state`: The application state
+fn open_new_compose(state: &mut crate::state::AppState) {
+    match state.storage.take_draft() {
+        Ok(Some(draft)) => {
+            open_compose(ComposeState::from_email(&draft), state);
+            state.set_status_message("Continuing your saved draft".to_string());
+        },
+        Ok(None) => open_compose(ComposeState::new(), state),
+        Err(e) => {
+            error!("Failed to load the draft: {}", e);
+            open_compose(ComposeState::new(), state);
+        },
+    }
+}
+
+/// Opens compose with a new message from the selected account.
+///
+/// The account's automatic Cc and Bcc recipients are filled in, where they
+/// can still be removed for this message.
+///
+/// # Parameters
+/// - `compose`: The compose form to open
+/// - `state`: The application state
+fn open_compose(mut compose: ComposeState, state: &mut crate::state::AppState) {
+    if let Some(account) = state.get_selected_account_config() {
+        let bcc = account.bcc_self.then_some(account.email.as_str());
+        compose.add_default_recipients(account.auto_cc.as_deref(), bcc);
+    }
+    state.set_compose_state(Some(compose));
+    state.set_current_view(View::ComposeEmail);
+}
+
+/// Adds the sender of the viewed email to the senders whose remote images are always shown.
+///
+/// # Parameters
+/// - `state`: The application state
+fn allow_remote_content(state: &mut crate::state::AppState) {
+    let Some(sender) = state.get_viewed_email().map(|email| email.from.clone()) else {
+        return;
+    };
+    state.set_show_remote_content(true);
+    
+    let settings = &mut state.config_manager.get_config_mut().settings;
+    if settings.remote_content_senders.iter().any(|allowed| allowed.eq_ignore_ascii_case(&sender)) {
+        return;
+    }
+    settings.remote_content_senders.push(sender.clone());
+    
+    match state.config_manager.save_config() {
+        Ok(()) => state.set_status_message(format!("Remote images from {} will always be shown", sender)),
+        Err(e) => state.set_status_message(format!("Failed to save configuration: {}", e)),
+    }
+}
+
+/// Remembers that the user agreed to empty Trash on exit, and quits.
+///
+/// # Parameters
+/// - `state`: The application state
+fn confirm_empty_trash_on_exit(state: &mut crate::state::AppState) {
+    state.config_manager.get_config_mut().settings.empty_trash_confirmed = true;
+    if let Err(e) = state.config_manager.save_config() {
+        // Trash is still emptied this time, and the question comes back next time
+        error!("Failed to save configuration: {}", e);
+    }
+    state.set_running(false);
+}
+
+/// Quits the application, asking first about messages still in the outbox.
+///
+/// Unless confirm_quit is "never", the user is told that waiting messages
+/// are kept to be sent on the next start before Linksy quits.
+///
+/// # Parameters
+/// - `state`: The application state
+fn finish_quit(state: &mut crate::state::AppState) {
+    let never = state.config_manager.get_config().settings.confirm_quit == QuitConfirmation::Never;
+    if !state.outbox.is_empty() && !never {
+        let message = match state.outbox.len() {
+            1 => "A message hasn't been sent yet. Quit and send it the next time Linksy starts?".to_string(),
+            count => format!("{} messages haven't been sent yet. Quit and send them the next time Linksy starts?", count),
+        };
+        state.set_confirm(Some(ConfirmState::new(&message, ConfirmAction::QuitWithUnsent)));
+    } else {
+        confirm_empty_trash_and_quit(state);
+    }
+}
+
+/// Quits the application, asking first if Trash would be emptied for the first time.
+///
+/// # Parameters
+/// - `state`: The application state
+fn confirm_empty_trash_and_quit(state: &mut crate::state::AppState) {
+    let settings = &state.config_manager.get_config().settings;
+    if settings.empty_trash_on_exit && !settings.empty_trash_confirmed && !state.offline {
+        let message = "Empty Trash of every account each time you quit?";
+        state.set_confirm(Some(ConfirmState::new(message, ConfirmAction::EmptyTrashOnExit)));
+    } else {
+        state.set_running(false);
+    }
+}
+
+/// Selects the folder whose name starts with the letters typed so far.
+///
+/// A new name is looked for after the selected folder, so typing the
+/// same letter again moves to the next folder starting with it.
+///
+/// # Parameters
+/// - `c`: The typed letter
+/// - `state`: The application state
+fn jump_to_folder(c: char, state: &mut crate::state::AppState) {
+    let extending = state.folder_jump.is_active();
+    let typed = state.folder_jump.push(c).to_string();
+    let (folders, _) = state.get_folder_list();
+    let current = state.selected_folder_index.min(folders.len().saturating_sub(1));
+    
+    // A longer name may still match the selected folder; a new one starts after it
+    let start = if extending { current } else { current + 1 };
+    let found = TypeAhead::find_match(&folders, &typed, start).or_else(|| {
+        // Typing one letter repeatedly cycles through the folders starting with it
+        let first = typed.chars().next()?;
+        if typed.chars().all(|c| c == first) {
+            TypeAhead::find_match(&folders, &first.to_string(), current + 1)
+        } else {
+            None
+        }
+    });
+    
+    match found {
+        Some(index) => {
+            state.selected_folder_index = index;
+            state.clear_status_message();
+        },
+        None => state.set_status_message(format!("No folder starts with \"{}\"", typed)),
+    }
+}
+
+/// Gets the account number of an Alt+1..9 key.
+///
+/// # Parameters
+/// - `key`: The key event
+///
+/// # Returns
+/// The number from 1 to 9, or None for any other key
+fn account_number(key: &KeyEvent) -> Option<usize> {
+    match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::ALT) => {
+            c.to_digit(10).filter(|digit| *digit > 0).map(|digit| digit as usize)
+        },
+        _ => None,
+    }
+}
+
+/// Switches to another account, staying in the open folder if it has one by that name.
+///
+/// The email list is emptied until the account's emails are loaded, and an
+/// open email goes back to the list, since it belongs to the other account.
+///
+/// # Parameters
+/// - `index`: The index of the account to switch to
+/// - `state`: The application state
+///
+/// # Returns
+/// true if the account was switched and its emails should be loaded
+fn switch_account(index: usize, state: &mut crate::state::AppState) -> bool {
+    let Some(name) = state.account_summaries.get(index).map(|summary| summary.name.clone()) else {
+        state.set_status_message(format!("There is no account {}", index + 1));
+        return false;
+    };
+    if state.get_selected_account() == Some(index) {
+        state.set_status_message(format!("Already on {}", name));
+        return false;
+    }
+    
+    // Leave the other account's search and emails behind
+    let folder = state.search_folder().to_string();
+    state.set_selected_account(Some(index));
+    state.search = None;
+    state.folder_jump.clear();
+    state.emails = Arc::default();
+    state.set_selected_email(None);
+    state.set_viewed_email(None);
+    
+    // Stay in the same folder if the account has it, or else open its inbox
+    let (folders, _) = state.get_folder_list();
+    let folder = if state.is_selected_account_send_only() {
+        SCHEDULED_FOLDER.to_string()
+    } else if folders.contains(&folder) {
+        folder
+    } else {
+        "INBOX".to_string()
+    };
+    state.selected_folder_index = folders.iter().position(|name| *name == folder).unwrap_or(0);
+    state.set_selected_folder(folder);
+    
+    if state.get_current_view() == View::EmailDetail {
+        state.set_current_view(View::Emails);
+    }
+    state.set_status_message(format!("Switched to {}", name));
+    
+    true
+}
+
+/// Opens the log view, showing the newest messages.
+///
+/// # Parameters
+/// - `state`: The application state
+fn open_log_view(state: &mut crate::state::AppState) {
+    state.log_view = Some(LogViewState::new(state.get_current_view()));
+    state.set_current_view(View::Log);
+}
+
+/// Changes the least severe level of the log messages that are kept and listed.
+///
+/// # Parameters
+/// - `state`: The application state
+fn change_log_level(state: &mut crate::state::AppState) {
+    let settings = &mut state.config_manager.get_config_mut().settings;
+    settings.log_level = settings.log_level.next();
+    let level = settings.log_level;
+    crate::logging::set_level(level);
+    if let Some(log_view) = state.log_view.as_mut() {
+        log_view.scroll = 0;
+    }
+    
+    match state.config_manager.save_config() {
+        Ok(()) => state.set_status_message(format!("Logging {} messages and above", level.label())),
+        Err(e) => state.set_status_message(format!("Failed to save configuration: {}", e)),
+    }
+}
+
+/// Adds the sender of an email to the VIP senders, or removes them.
+///
+/// # Parameters
+/// - `sender`: The sender address
+/// - `state`: The application state
+fn toggle_vip_sender(sender: String, state: &mut crate::state::AppState) {
+    if sender.is_empty() {
+        state.set_status_message("This email has no sender".to_string());
+        return;
+    }
+    
+    let settings = &mut state.config_manager.get_config_mut().settings;
+    let was_vip = settings.is_vip(&sender);
+    if was_vip {
+        settings.vip_senders.retain(|vip| !vip.eq_ignore_ascii_case(&sender));
+    } else {
+        settings.vip_senders.push(sender.clone());
+    }
+    
+    // Move the sender's emails in or out of the top of the folder
+    if state.search.is_none() {
+        let view = state.folder_view();
+        rearrange_emails(&view, state);
+    }
+    
+    match state.config_manager.save_config() {
+        Ok(()) if was_vip => state.set_status_message(format!("{} is no longer a VIP", sender)),
+        Ok(()) => state.set_status_message(format!("{} is now a VIP", sender)),
+        Err(e) => state.set_status_message(format!("Failed to save configuration: {}", e)),
+    }
+}
+
+/// Sorts and filters the listed emails again, keeping the selected email selected.
+///
+/// # Parameters
+/// - `view`: The sort order and filter to apply
+/// - `state`: The application state
+fn rearrange_emails(view: &FolderView, state: &mut crate::state::AppState) {
+    let selected_id = state.get_selected_email()
+        .and_then(|index| state.emails.get(index))
+        .map(|email| email.id.clone());
+    let emails = Arc::unwrap_or_clone(std::mem::take(&mut state.emails));
+    state.emails = Arc::new(view.arrange(emails, &state.config_manager.get_config().settings));
+    state.selected_email = selected_id
+        .and_then(|id| state.emails.iter().position(|email| email.id == id));
+    state.clamp_selection();
+}
+
+/// Switches the email list to the next grouping: by sender, by sender
+/// domain, then not grouped.
+///
+/// # Parameters
+/// - `state`: The application state
+fn change_grouping(state: &mut crate::state::AppState) {
+    state.grouping = match state.grouping.as_ref().map(|grouping| grouping.by) {
+        None => Some(GroupingState::new(GroupBy::Sender)),
+        Some(GroupBy::Sender) => Some(GroupingState::new(GroupBy::Domain)),
+        Some(GroupBy::Domain) => None,
+    };
+    
+    let message = match &state.grouping {
+        Some(grouping) => format!("Emails grouped by {}", grouping.by.label()),
+        None => "Emails no longer grouped".to_string(),
+    };
+    state.set_status_message(message);
+}
+
+/// Selects a line of the grouped email list.
+///
+/// A group's first email is selected along with it, so actions on a single
+/// email, like writing to the sender, work from the group's line.
+///
+/// # Parameters
+/// - `row`: The line to select
+/// - `state`: The application state
+fn select_group_row(row: &GroupRow, state: &mut crate::state::AppState) {
+    let (group, email) = match row {
+        GroupRow::Group { key, members } => (Some(key.clone()), members.first().copied()),
+        GroupRow::Email(index) => (None, Some(*index)),
+    };
+    if let Some(grouping) = state.grouping.as_mut() {
+        grouping.selected_group = group;
+    }
+    state.set_selected_email(email);
+}
+
+/// Changes and saves the sort order or filter of the selected folder.
+///
+/// The listed emails are sorted and filtered again right away; emails the
+/// previous filter left out only appear once the folder is loaded again.
+///
+/// # Parameters
+/// - `state`: The application state
+/// - `change`: The change to make to the folder's view
+fn change_folder_view(state: &mut crate::state::AppState, change: impl FnOnce(&mut FolderView)) {
+    let mut view = state.folder_view();
+    change(&mut view);
+    rearrange_emails(&view, state);
+    
+    // Only views that differ from the default are kept in the configuration
+    let description = view.describe();
+    let views = &mut state.config_manager.get_config_mut().folder_views;
+    views.retain(|saved| saved.account != view.account || saved.folder != view.folder);
+    if view.sort != SortMode::default() || view.filter != ViewFilter::default() {
+        views.push(view);
+    }
+    
+    match state.config_manager.save_config() {
+        Ok(()) => state.set_status_message(format!("Emails {}", description)),
+        Err(e) => state.set_status_message(format!("Failed to save configuration: {}", e)),
+    }
+}
+
+/// Gets the email that keys act on: the viewed one in the detail view, or
+/// else the one selected in the list.
+///
+/// The detail view doesn't fall back to the list, whose selection a
+/// background reload can move to another email.
+///
+/// # Parameters
+/// - `state`: The application state
+///
+/// # Returns
+/// An Option containing the email
+fn current_email(state: &crate::state::AppState) -> Option<&Email> {
+    if state.get_current_view() == View::EmailDetail {
+        state.get_viewed_email()
+    } else {
+        state.get_selected_email().and_then(|index| state.emails.get(index))
+    }
+}
+
+/// Opens the tag picker for the current email.
+///
+/// # Parameters
+/// - `state`: The application state
+fn open_tag_picker(state: &mut crate::state::AppState) {
+    let Some(email) = current_email(state) else {
+        return;
+    };
+    
+    let picker = TagPickerState::new(&state.config_manager.get_config().settings.tags, email);
+    state.set_tag_picker(Some(picker));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigManager, EmailAccount};
+    use crate::models::Email;
+    use crate::state::{AccountManager, AppState, EmailManager, SharedState};
+    use crate::storage::EmailStorage;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    /// An input handler over an empty state in its own directory.
+    struct Harness {
+        handler: InputHandler,
+        state: Arc<SharedState>,
+        dir: PathBuf,
+    }
+
+    impl Harness {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("linksy-test-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let config_manager = ConfigManager::new(dir.join("config.json").to_str().unwrap()).unwrap();
+            let storage = EmailStorage::new(&dir.join("storage")).unwrap();
+            let email_manager = EmailManager::new(Arc::new(storage.clone()));
+
+            let state = Arc::new(SharedState::new(AppState::new(config_manager, storage, dir.clone())));
+            let controller = AppController::new(
+                state.clone(),
+                Arc::new(Mutex::new(AccountManager::new())),
+                Arc::new(Mutex::new(email_manager)),
+            );
+
+            Self {
+                handler: InputHandler::new(Arc::new(controller)),
+                state,
+                dir,
+            }
+        }
+
+        /// Creates a harness with accounts that have no servers to connect to.
+        async fn with_accounts(ids: &[&str]) -> Self {
+            let harness = Self::new();
+            {
+                let mut state = harness.state.write().await;
+                let config = state.config_manager.get_config_mut();
+                for id in ids {
+                    config.accounts.push(test_account(id));
+                }
+            }
+            harness.handler.controller.load_accounts().await.unwrap();
+            harness
+        }
+
+        /// Presses a key in a view, with a selection left over from a longer list.
+        async fn press(&self, view: View, code: KeyCode) {
+            let mut state = self.state.write().await;
+            state.set_current_view(view);
+            state.set_prompt(None);
+            state.set_confirm(None);
+            state.selected_account = Some(0);
+            state.selected_email = Some(0);
+            state.selected_folder_index = 3;
+
+            let key = KeyEvent::new(code, KeyModifiers::NONE);
+            self.handler.handle_key(key, &mut state).await.unwrap();
+        }
+    }
+
+    impl Drop for Harness {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn test_account(id: &str) -> EmailAccount {
+        let server = serde_json::json!({
+            "host": "", "port": 0, "username": "", "password": "", "use_ssl": false,
+        });
+        serde_json::from_value(serde_json::json!({
+            "id": id, "name": id, "email": format!("{}@example.com", id), "smtp": server,
+        })).unwrap()
+    }
+
+    /// Keys that move through or act on the listed items
+    const NAVIGATION_KEYS: &[KeyCode] = &[
+        KeyCode::Up,
+        KeyCode::Down,
+        KeyCode::PageUp,
+        KeyCode::PageDown,
+        KeyCode::Home,
+        KeyCode::End,
+        KeyCode::Enter,
+        KeyCode::Char(' '),
+        KeyCode::Char('d'),
+        KeyCode::Char('r'),
+        KeyCode::Char('f'),
+        KeyCode::Char('u'),
+        KeyCode::Char('*'),
+    ];
+
+    #[tokio::test]
+    async fn navigates_empty_lists_without_panicking() {
+        let harness = Harness::new();
+        for view in [View::Accounts, View::AccountStatus, View::Folders, View::Emails, View::EmailDetail] {
+            for &code in NAVIGATION_KEYS {
+                harness.press(view, code).await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_selection_within_empty_lists() {
+        let harness = Harness::new();
+
+        harness.press(View::Accounts, KeyCode::Down).await;
+        assert_eq!(harness.state.read().await.get_selected_account(), Some(0));
+
+        harness.press(View::Emails, KeyCode::Down).await;
+        let state = harness.state.read().await;
+        assert!(state.emails.is_empty());
+        assert!(state.get_selected_email().is_none_or(|index| index == 0));
+    }
+
+    #[tokio::test]
+    async fn asks_before_quitting_with_unsent_messages() {
+        let harness = Harness::new();
+        let mut state = harness.state.write().await;
+        state.outbox.queue(Email::new(), Duration::from_secs(30));
+
+        let quit = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL);
+        harness.handler.handle_key(quit, &mut state).await.unwrap();
+        assert_eq!(state.get_confirm().map(|confirm| confirm.action.clone()), Some(ConfirmAction::QuitWithUnsent));
+        assert!(state.running);
+
+        let yes = KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE);
+        harness.handler.handle_key(yes, &mut state).await.unwrap();
+        assert!(!state.running);
+    }
+
+    #[tokio::test]
+    async fn keeps_unsent_messages_for_the_next_start() {
+        let harness = Harness::new();
+        let mut email = Email::new();
+        email.subject = "Minutes".to_string();
+        harness.state.write().await.outbox.queue(email, Duration::from_secs(30));
+
+        harness.handler.controller.shutdown().await.unwrap();
+
+        let state = harness.state.read().await;
+        assert!(state.outbox.is_empty());
+        let kept = state.storage.get_all_scheduled_emails().unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].email.subject, "Minutes");
+        assert!(kept[0].is_due());
+    }
+
+    fn listed_email(folder: &str, id: &str) -> Email {
+        let mut email = Email::new();
+        email.account_id = "work".to_string();
+        email.folder = folder.to_string();
+        email.id = id.to_string();
+        email
+    }
+
+    #[tokio::test]
+    async fn selects_emails_by_folder_as_well_as_id() {
+        let harness = Harness::new();
+        let mut state = harness.state.write().await;
+        // Search results from two folders, with the same UID in both
+        state.emails = Arc::new(vec![listed_email("INBOX", "7"), listed_email("Archive", "7")]);
+        state.set_current_view(View::Emails);
+        state.set_selected_email(Some(0));
+
+        let space = KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE);
+        harness.handler.handle_key(space, &mut state).await.unwrap();
+        assert_eq!(selected_email_indexes(&state), vec![0]);
+
+        // Inverting picks the other folder's email, not the same UID again
+        let invert = KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE);
+        harness.handler.handle_key(invert, &mut state).await.unwrap();
+        assert_eq!(selected_email_indexes(&state), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn clears_the_selection_in_another_folder_or_account() {
+        let harness = Harness::new();
+        let mut state = harness.state.write().await;
+        let email = listed_email("INBOX", "7");
+
+        state.selected_emails.insert(email.key());
+        state.set_selected_folder("INBOX".to_string());
+        assert_eq!(state.selected_emails.len(), 1);
+        state.set_selected_folder("Archive".to_string());
+        assert!(state.selected_emails.is_empty());
+
+        state.selected_emails.insert(email.key());
+        state.set_selected_account(Some(1));
+        assert!(state.selected_emails.is_empty());
+    }
+
+    #[tokio::test]
+    async fn keeps_other_changes_when_disabling_an_account() {
+        let harness = Harness::with_accounts(&["home", "work"]).await;
+        {
+            let mut state = harness.state.write().await;
+            state.selected_account = Some(1);
+            state.config_manager.get_config_mut().settings.default_account = Some("home".to_string());
+        }
+
+        harness.handler.controller.toggle_selected_account_enabled().await.unwrap();
+
+        let config = ConfigManager::new(harness.dir.join("config.json").to_str().unwrap()).unwrap();
+        let config = config.get_config();
+        assert_eq!(config.accounts.iter().map(|a| a.enabled).collect::<Vec<_>>(), vec![true, false]);
+        assert_eq!(config.settings.default_account.as_deref(), Some("home"));
+        assert!(harness.state.read().await.is_selected_account_disabled());
+    }
+
+    #[tokio::test]
+    async fn moves_the_account_with_its_configuration() {
+        let harness = Harness::with_accounts(&["home", "work", "club"]).await;
+        {
+            let mut state = harness.state.write().await;
+            state.selected_account = Some(2);
+            state.config_manager.get_config_mut().settings.default_account = Some("club".to_string());
+        }
+
+        harness.handler.controller.move_selected_account(true).await.unwrap();
+
+        let config = ConfigManager::new(harness.dir.join("config.json").to_str().unwrap()).unwrap();
+        let config = config.get_config();
+        assert_eq!(config.accounts.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(), vec!["home", "club", "work"]);
+        assert_eq!(config.settings.default_account.as_deref(), Some("club"));
+
+        let state = harness.state.read().await;
+        assert_eq!(state.selected_account, Some(1));
+        assert_eq!(state.get_selected_account_config().map(|a| a.id.as_str()), Some("club"));
+        let names: Vec<_> = state.account_summaries.iter().map(|summary| summary.name.as_str()).collect();
+        assert_eq!(names, vec!["home", "club", "work"]);
+    }
+
+    #[tokio::test]
+    async fn keeps_a_busy_account_summary_with_its_account() {
+        let harness = Harness::with_accounts(&["home", "work"]).await;
+        let work = {
+            let mut state = harness.state.write().await;
+            state.selected_account = Some(0);
+            state.accounts[1].account.clone()
+        };
+
+        // The busy account can't be summarized again, so it keeps its last summary
+        let _busy = work.lock().await;
+        harness.handler.controller.move_selected_account(false).await.unwrap();
+
+        let state = harness.state.read().await;
+        let ids: Vec<_> = state.account_summaries.iter().map(|summary| summary.id.as_str()).collect();
+        assert_eq!(ids, vec!["work", "home"]);
+        assert_eq!(state.get_selected_account_id().as_deref(), Some("home"));
+    }
+
+    #[tokio::test]
+    async fn acts_on_the_viewed_email_after_a_reload_moves_the_selection() {
+        let harness = Harness::new();
+        {
+            let mut state = harness.state.write().await;
+            state.emails = Arc::new(vec![listed_email("INBOX", "1")]);
+            let mut viewed = listed_email("INBOX", "2");
+            viewed.local_tags = vec!["later".to_string()];
+            state.set_viewed_email(Some(viewed));
+        }
+
+        harness.press(View::EmailDetail, KeyCode::Char('T')).await;
+        let state = harness.state.read().await;
+        let picker = state.get_tag_picker().unwrap();
+        assert!(picker.tags.iter().any(|tag| tag == "later"));
+    }
+
+    #[tokio::test]
+    async fn does_nothing_in_the_detail_view_without_a_viewed_email() {
+        let harness = Harness::new();
+        harness.state.write().await.emails = Arc::new(vec![listed_email("INBOX", "1")]);
+
+        harness.press(View::EmailDetail, KeyCode::Char('T')).await;
+        assert!(harness.state.read().await.get_tag_picker().is_none());
     }
 }
@@ -2,6 +2,7 @@
 
 pub mod config;
 pub mod controller;
+pub mod logging;
 pub mod models;
 pub mod protocols;
 pub mod state;
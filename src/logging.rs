@@ -0,0 +1,165 @@
+//! In-app logging for the Linksy email client.
+//!
+//! Messages written to stderr are hidden behind the TUI, so log messages
+//! are kept in memory instead, where the log view lists them. Only the most
+//! recent ones are kept, down to the `log_level` setting. With `RUST_LOG`
+//! set, messages are also written to stderr like before, for redirecting
+//! to a file.
+
+use crate::config::LogLevel;
+use anyhow::{anyhow, Result};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+/// Number of log messages kept for the log view.
+const LOG_CAPACITY: usize = 1000;
+
+/// The kept log messages, oldest first.
+static ENTRIES: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+
+/// Least severe level that is kept, as a LevelFilter.
+static KEPT_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Info as usize);
+
+/// Filter of the stderr logger, if RUST_LOG is set.
+static STDERR_FILTER: AtomicUsize = AtomicUsize::new(LevelFilter::Off as usize);
+
+/// Represents a kept log message.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// When the message was logged
+    pub time: SystemTime,
+    /// Severity of the message
+    pub level: Level,
+    /// Module that logged the message
+    pub target: String,
+    /// The message
+    pub message: String,
+}
+
+/// Keeps log messages for the log view and passes them on to stderr.
+struct MemoryLogger {
+    /// The stderr logger, if RUST_LOG is set
+    stderr: Option<env_logger::Logger>,
+}
+
+impl Log for MemoryLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= kept_level() || self.stderr.as_ref().is_some_and(|stderr| stderr.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if let Some(stderr) = &self.stderr {
+            stderr.log(record);
+        }
+
+        if record.level() <= kept_level() {
+            let entry = LogEntry {
+                time: SystemTime::now(),
+                level: record.level(),
+                target: record.target().to_string(),
+                message: record.args().to_string(),
+            };
+            if let Ok(mut entries) = ENTRIES.lock() {
+                if entries.len() == LOG_CAPACITY {
+                    entries.pop_front();
+                }
+                entries.push_back(entry);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(stderr) = &self.stderr {
+            stderr.flush();
+        }
+    }
+}
+
+/// Installs the logger.
+///
+/// Messages of the default `log_level` are kept until set_level is called
+/// with the configured one.
+///
+/// # Returns
+/// A Result indicating success, or an error if a logger is already installed
+pub fn init() -> Result<()> {
+    let stderr = std::env::var_os("RUST_LOG").map(|_| env_logger::Builder::from_default_env().build());
+    if let Some(stderr) = &stderr {
+        STDERR_FILTER.store(stderr.filter() as usize, Ordering::Relaxed);
+    }
+
+    log::set_boxed_logger(Box::new(MemoryLogger { stderr }))
+        .map_err(|e| anyhow!("Failed to install the logger: {}", e))?;
+    set_level(LogLevel::default());
+
+    Ok(())
+}
+
+/// Sets the least severe level of the messages that are kept.
+///
+/// Messages that were kept before are still listed.
+///
+/// # Parameters
+/// - `level`: The level to keep messages down to
+pub fn set_level(level: LogLevel) {
+    KEPT_LEVEL.store(level.filter() as usize, Ordering::Relaxed);
+    log::set_max_level(level.filter().max(stderr_filter()));
+}
+
+/// Gets the kept log messages.
+///
+/// # Parameters
+/// - `level`: The least severe level to list
+///
+/// # Returns
+/// The messages of that level or more severe ones, oldest first
+pub fn entries(level: LogLevel) -> Vec<LogEntry> {
+    let Ok(entries) = ENTRIES.lock() else {
+        return Vec::new();
+    };
+
+    entries.iter()
+        .filter(|entry| entry.level <= level.filter())
+        .cloned()
+        .collect()
+}
+
+/// Gets the least severe level that is kept.
+///
+/// # Returns
+/// The level filter
+fn kept_level() -> LevelFilter {
+    level_filter(KEPT_LEVEL.load(Ordering::Relaxed))
+}
+
+/// Gets the filter of the stderr logger.
+///
+/// # Returns
+/// The level filter, or Off if RUST_LOG isn't set
+fn stderr_filter() -> LevelFilter {
+    level_filter(STDERR_FILTER.load(Ordering::Relaxed))
+}
+
+/// Converts a stored level filter back.
+///
+/// # Parameters
+/// - `value`: The LevelFilter as a number
+///
+/// # Returns
+/// The level filter
+fn level_filter(value: usize) -> LevelFilter {
+    [
+        LevelFilter::Off,
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info,
+        LevelFilter::Debug,
+        LevelFilter::Trace,
+    ]
+    .get(value)
+    .copied()
+    .unwrap_or(LevelFilter::Info)
+}
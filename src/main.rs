@@ -1,12 +1,5 @@
-mod config;
-mod controller;
-mod models;
-mod protocols;
-mod state;
-mod storage;
-mod ui;
-
 use anyhow::Result;
+use linksy::{config, controller, logging, state, storage, ui};
 use log::info;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -15,8 +8,8 @@ use tokio::sync::Mutex;
 /// Initializes logging, sets up the application, and starts the UI.
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    env_logger::init();
+    // Keep log messages for the log view; RUST_LOG also writes them to stderr
+    logging::init()?;
     info!("Starting Linksy email client");
 
     // Determine base directory
@@ -31,21 +24,30 @@ async fn main() -> Result<()> {
     // Create configuration manager
     let config_path = base_dir.join("config.json");
     let config_manager = config::ConfigManager::new(config_path.to_str().unwrap())?;
+    logging::set_level(config_manager.get_config().settings.log_level);
     
     // Create a single storage instance
     let storage_path = base_dir.join("storage");
     let storage = storage::EmailStorage::new(&storage_path)?;
     
+    // Keep emails in the database or in Maildir, as configured
+    let email_store = storage::open_email_store(&config_manager.get_config().settings, &storage, &base_dir)?;
+    
     // Create state
-    let app_state = state::AppState::new(config_manager, storage.clone(), base_dir);
-    let app_state = Arc::new(Mutex::new(app_state));
+    let mut app_state = state::AppState::new(config_manager, storage, base_dir);
+    
+    // Browse cached mail without touching the network
+    if std::env::args().skip(1).any(|arg| arg == "--offline") {
+        app_state.offline = true;
+    }
+    let app_state = Arc::new(state::SharedState::new(app_state));
     
     // Create managers
     let account_manager = state::AccountManager::new();
     let account_manager = Arc::new(Mutex::new(account_manager));
     
-    // Use a clone of the storage instance for the email manager
-    let email_manager = state::EmailManager::new(storage);
+    // Hand the email store to the email manager
+    let email_manager = state::EmailManager::new(email_store);
     let email_manager = Arc::new(Mutex::new(email_manager));
     
     // Create controller
@@ -65,33 +67,80 @@ async fn main() -> Result<()> {
     // Initialize the application
     app_controller.initialize().await?;
     
+    // Connect the accounts that connect on startup
+    app_controller.start_auto_connect();
+    
+    // Connect the account whose inbox is opened on startup
+    app_controller.start_startup_connect();
+    
+    // Hand scheduled messages to the outbox when they are due
+    app_controller.start_scheduler();
+    
+    // Check the inboxes for new mail in the background
+    app_controller.start_mail_checker();
+    
     // Initialize terminal
     let mut terminal = ui::init_terminal()?;
     
+    // Show the unread count in the terminal title
+    ui::save_title()?;
+    let mut title = String::new();
+    
+    // Take the first snapshot to draw from
+    let mut snapshot = state::RenderSnapshot::new(&*app_state.read().await);
+    let mut generation = app_state.generation();
+    
     // Main event loop
-    while app_state.lock().await.is_running() {
-        // Draw UI
-        let state = app_state.lock().await;
+    while app_state.read().await.is_running() {
+        // Update the snapshot unless a background task is using the state,
+        // in which case the previous one is drawn again rather than waiting
+        if let Ok(mut state) = app_state.try_write_for_draw() {
+            // Pick up connection status changes made by the protocol clients
+            let summaries_changed = state.update_account_summaries();
+            
+            // Copy the state only if it changed since the last snapshot
+            if summaries_changed || app_state.generation() != generation {
+                snapshot = state::RenderSnapshot::new(&state);
+                generation = app_state.generation();
+            } else {
+                snapshot.refresh(&state);
+            }
+        }
+        
+        // Update the title when the unread count changes
+        let unread_title = ui::unread_title(snapshot.total_unread);
+        if unread_title != title {
+            ui::set_title(&unread_title)?;
+            title = unread_title;
+        }
+        
+        // Draw UI without holding any lock
         terminal.draw(|f| {
-            if let Err(e) = app_renderer.render(f, &state) {
+            if let Err(e) = app_renderer.render(f, &snapshot) {
                 eprintln!("Error rendering UI: {}", e);
             }
         })?;
-        drop(state);
         
         // Handle input
         if let Some(key) = ui::wait_for_key(Some(std::time::Duration::from_millis(100)))? {
-            let mut state = app_state.lock().await;
+            let mut state = app_state.write().await;
             if let Err(e) = input_handler.handle_key(key, &mut state).await {
                 eprintln!("Error handling input: {}", e);
             }
         }
+        
+        // Send outbox messages whose send delay has passed
+        app_controller.flush_outbox().await;
+        
+        // Hand the terminal to the external editor if compose asked for it
+        terminal = ui::editor::run_pending_editor(terminal, &app_state).await?;
     }
     
     // Shutdown the application
     app_controller.shutdown().await?;
     
     // Restore terminal
+    ui::restore_title()?;
     ui::restore_terminal(terminal)?;
     
     info!("Linksy email client shut down successfully");
@@ -3,6 +3,8 @@
 use crate::config::{EmailAccount, ServerConfig};
 use crate::models::AccountSummary;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::SystemTime;
 
 /// Represents the status of an email account connection.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -36,6 +38,15 @@ pub struct Account {
     pub total_count: usize,
     /// Available folders/mailboxes
     pub folders: Vec<String>,
+    /// The folder deleted messages are moved to, if the server has one
+    #[serde(default)]
+    pub trash_folder: Option<String>,
+    /// The folder sent messages are kept in, if the server has one
+    #[serde(default)]
+    pub sent_folder: Option<String>,
+    /// Time of the last successful email fetch, per folder
+    #[serde(default)]
+    pub last_synced: HashMap<String, SystemTime>,
 }
 
 impl Account {
@@ -56,6 +67,9 @@ impl Account {
             unread_count: 0,
             total_count: 0,
             folders: vec!["INBOX".to_string()],
+            trash_folder: None,
+            sent_folder: None,
+            last_synced: HashMap::new(),
         }
     }
     
@@ -71,7 +85,6 @@ impl Account {
     ///
     /// # Returns
     /// A string describing the connection status
-    #[allow(dead_code)]
     pub fn get_status_summary(&self) -> String {
         let imap_status = match self.imap_status {
             ConnectionStatus::Connected => "✓",
@@ -110,6 +123,33 @@ impl Account {
         }
     }
     
+    /// Records a successful sync of the specified folder.
+    ///
+    /// # Parameters
+    /// - `folder`: The folder that was synced
+    pub fn mark_synced(&mut self, folder: &str) {
+        self.last_synced.insert(folder.to_string(), SystemTime::now());
+    }
+    
+    /// Gets the time the specified folder was last synced.
+    ///
+    /// # Parameters
+    /// - `folder`: The folder/mailbox
+    ///
+    /// # Returns
+    /// An Option containing the time of the last successful fetch
+    pub fn get_last_synced(&self, folder: &str) -> Option<SystemTime> {
+        self.last_synced.get(folder).copied()
+    }
+    
+    /// Gets the time any folder of this account was last synced.
+    ///
+    /// # Returns
+    /// An Option containing the time of the most recent successful fetch
+    pub fn get_latest_sync(&self) -> Option<SystemTime> {
+        self.last_synced.values().max().copied()
+    }
+    
     /// Checks if the account has IMAP configured.
     ///
     /// # Returns
@@ -126,6 +166,27 @@ impl Account {
         self.config.pop3.is_some()
     }
     
+    /// Checks if every protocol the account receives mail with is connected.
+    ///
+    /// # Returns
+    /// true if the IMAP and POP3 servers configured, or SMTP for a send-only account, are connected
+    pub fn is_connected(&self) -> bool {
+        if self.is_send_only() {
+            return self.smtp_status == ConnectionStatus::Connected;
+        }
+        
+        (!self.has_imap() || self.imap_status == ConnectionStatus::Connected)
+            && (!self.has_pop3() || self.pop3_status == ConnectionStatus::Connected)
+    }
+    
+    /// Checks if the account can only send, having neither IMAP nor POP3.
+    ///
+    /// # Returns
+    /// true if no incoming protocol is configured, false otherwise
+    pub fn is_send_only(&self) -> bool {
+        !self.has_imap() && !self.has_pop3()
+    }
+    
     /// Gets the IMAP server configuration.
     ///
     /// # Returns
@@ -164,6 +225,11 @@ impl Account {
             pop3_status: self.pop3_status,
             unread_count: self.unread_count,
             total_count: self.total_count,
+            last_error: self.last_error.clone(),
+            last_synced: self.get_latest_sync(),
+            send_only: self.is_send_only(),
+            enabled: self.config.enabled,
+            sent_folder: self.sent_folder.clone(),
         }
     }
 }
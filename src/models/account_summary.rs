@@ -5,9 +5,10 @@
 
 use crate::models::ConnectionStatus;
 use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
 
 /// Represents a lightweight summary of an email account for rendering.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AccountSummary {
     /// Account ID
     pub id: String,
@@ -25,6 +26,16 @@ pub struct AccountSummary {
     pub unread_count: usize,
     /// Total number of messages
     pub total_count: usize,
+    /// Last error message (if any)
+    pub last_error: Option<String>,
+    /// Time of the last successful email fetch
+    pub last_synced: Option<SystemTime>,
+    /// Whether the account has neither IMAP nor POP3 and can only send
+    pub send_only: bool,
+    /// Whether the account is in use, rather than disabled
+    pub enabled: bool,
+    /// The folder sent messages are kept in, if the server has one
+    pub sent_folder: Option<String>,
 }
 
 impl AccountSummary {
@@ -36,6 +47,27 @@ impl AccountSummary {
         format!("{} <{}>", self.name, self.email)
     }
     
+    /// Gets the state of the account's connections as a whole.
+    ///
+    /// # Returns
+    /// Failed if a protocol failed, otherwise Connected if one is connected,
+    /// Connecting if one is still connecting, and Disconnected if none is
+    pub fn connection_status(&self) -> ConnectionStatus {
+        let statuses = [self.imap_status, self.pop3_status, self.smtp_status];
+        [ConnectionStatus::Failed, ConnectionStatus::Connected, ConnectionStatus::Connecting]
+            .into_iter()
+            .find(|status| statuses.contains(status))
+            .unwrap_or(ConnectionStatus::Disconnected)
+    }
+    
+    /// Checks if any of the account's protocols is still connecting.
+    ///
+    /// # Returns
+    /// true while a connection attempt is in progress, false otherwise
+    pub fn is_connecting(&self) -> bool {
+        [self.imap_status, self.pop3_status, self.smtp_status].contains(&ConnectionStatus::Connecting)
+    }
+    
     /// Gets the connection status summary.
     ///
     /// # Returns
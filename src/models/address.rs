@@ -0,0 +1,312 @@
+//! Email address model for the Linksy email client.
+//!
+//! Recipient lists are parsed as typed in compose, e.g.
+//! `Alice <alice@example.com>, "Doe, Bob" <bob@example.com>`. Commas and
+//! semicolons separate addresses except inside quotes, angle brackets and
+//! comments, and a group such as `Team: a@example.com, b@example.com;` is
+//! replaced by its members. Without angle brackets, the last word with an
+//! `@` is the address and the words around it are the name, so
+//! `Alice alice@example.com` works too.
+
+/// Characters that must be quoted in a display name.
+const SPECIALS: &str = "()<>[]:;@\\,.\"";
+
+/// Represents an email address with an optional display name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    /// The display name, without quotes
+    pub name: Option<String>,
+    /// The address, e.g. alice@example.com
+    pub email: String,
+}
+
+impl Address {
+    /// Parses a list of addresses.
+    ///
+    /// # Parameters
+    /// - `text`: The list, as typed by the user
+    ///
+    /// # Returns
+    /// The addresses in the list, skipping empty entries
+    pub fn parse_list(text: &str) -> Vec<Address> {
+        split_entries(text)
+            .iter()
+            .filter_map(|entry| Address::parse(entry))
+            .collect()
+    }
+
+    /// Parses a single address.
+    ///
+    /// A comment in parentheses, e.g. `alice@example.com (Alice)`, is used
+    /// as the name if there isn't another one.
+    ///
+    /// # Parameters
+    /// - `text`: The address, as typed by the user
+    ///
+    /// # Returns
+    /// The address, or None if the text is empty
+    pub fn parse(text: &str) -> Option<Address> {
+        let mut words: Vec<(String, bool)> = Vec::new();
+        let mut word = String::new();
+        let mut word_quoted = false;
+        let mut angle: Option<String> = None;
+        let mut comment = String::new();
+        let mut in_quotes = false;
+        let mut in_angle = false;
+        let mut comment_depth = 0;
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                // A backslash keeps the next character as it is
+                '\\' if in_quotes || comment_depth > 0 => {
+                    let escaped = chars.next().unwrap_or('\\');
+                    if comment_depth > 0 {
+                        comment.push(escaped);
+                    } else {
+                        word.push(escaped);
+                    }
+                },
+                '"' if comment_depth == 0 && !in_angle => {
+                    in_quotes = !in_quotes;
+                    word_quoted = true;
+                },
+                _ if in_quotes => word.push(c),
+                '(' => {
+                    if comment_depth > 0 {
+                        comment.push(c);
+                    }
+                    comment_depth += 1;
+                },
+                ')' if comment_depth > 0 => {
+                    comment_depth -= 1;
+                    if comment_depth > 0 {
+                        comment.push(c);
+                    }
+                },
+                _ if comment_depth > 0 => comment.push(c),
+                '<' if angle.is_none() => {
+                    in_angle = true;
+                    angle = Some(String::new());
+                },
+                '>' if in_angle => in_angle = false,
+                _ if in_angle => angle.get_or_insert_with(String::new).push(c),
+                c if c.is_whitespace() => {
+                    if !word.is_empty() || word_quoted {
+                        words.push((std::mem::take(&mut word), word_quoted));
+                    }
+                    word_quoted = false;
+                },
+                c => word.push(c),
+            }
+        }
+        if !word.is_empty() || word_quoted {
+            words.push((word, word_quoted));
+        }
+
+        // The address is in the angle brackets, or else the last word with an @
+        let email = match angle {
+            Some(angle) => angle.trim().to_string(),
+            None => {
+                let position = words.iter()
+                    .rposition(|(word, quoted)| !quoted && word.contains('@'))
+                    .unwrap_or(words.len().saturating_sub(1));
+                if words.is_empty() {
+                    String::new()
+                } else {
+                    words.remove(position).0
+                }
+            },
+        };
+        if email.is_empty() {
+            return None;
+        }
+
+        let name = words.iter()
+            .map(|(word, _)| word.trim())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let name = if name.is_empty() { comment.trim().to_string() } else { name };
+
+        Some(Address {
+            name: (!name.is_empty()).then_some(name),
+            email,
+        })
+    }
+
+    /// Formats the address for a message header.
+    ///
+    /// # Returns
+    /// "Name <address>", with the name quoted if needed, or just the address
+    pub fn to_header(&self) -> String {
+        match &self.name {
+            Some(name) if name.chars().any(|c| SPECIALS.contains(c)) => {
+                format!("\"{}\" <{}>", name.replace('\\', "\\\\").replace('"', "\\\""), self.email)
+            },
+            Some(name) => format!("{} <{}>", name, self.email),
+            None => self.email.clone(),
+        }
+    }
+}
+
+/// Splits a recipient list into its addresses.
+///
+/// # Parameters
+/// - `text`: The list, as typed by the user
+///
+/// # Returns
+/// The text of each address, with group names removed
+fn split_entries(text: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut entry = String::new();
+    let mut in_quotes = false;
+    let mut in_angle = false;
+    let mut comment_depth = 0;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_quotes || comment_depth > 0 => {
+                entry.push(c);
+                entry.extend(chars.next());
+                continue;
+            },
+            '"' if comment_depth == 0 && !in_angle => in_quotes = !in_quotes,
+            _ if in_quotes => {},
+            '(' => comment_depth += 1,
+            ')' if comment_depth > 0 => comment_depth -= 1,
+            _ if comment_depth > 0 => {},
+            '<' => in_angle = true,
+            '>' => in_angle = false,
+            _ if in_angle => {},
+            ',' | ';' => {
+                entries.push(std::mem::take(&mut entry));
+                continue;
+            },
+            // Text before a colon names a group of the addresses that follow
+            ':' => {
+                entry.clear();
+                continue;
+            },
+            _ => {},
+        }
+        entry.push(c);
+    }
+    entries.push(entry);
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(name: Option<&str>, email: &str) -> Address {
+        Address {
+            name: name.map(str::to_string),
+            email: email.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_names_and_addresses() {
+        assert_eq!(
+            Address::parse_list("Alice <alice@example.com>, Bob <bob@example.com>"),
+            vec![address(Some("Alice"), "alice@example.com"), address(Some("Bob"), "bob@example.com")],
+        );
+    }
+
+    #[test]
+    fn keeps_commas_in_quoted_names() {
+        assert_eq!(
+            Address::parse_list("\"Doe, Bob\" <bob@example.com>; \"Roe, \\\"Rick\\\"\" <rick@example.com>"),
+            vec![address(Some("Doe, Bob"), "bob@example.com"), address(Some("Roe, \"Rick\""), "rick@example.com")],
+        );
+    }
+
+    #[test]
+    fn parses_bare_addresses() {
+        assert_eq!(
+            Address::parse_list("alice@example.com,bob@example.com ; carol@example.com"),
+            vec![
+                address(None, "alice@example.com"),
+                address(None, "bob@example.com"),
+                address(None, "carol@example.com"),
+            ],
+        );
+    }
+
+    #[test]
+    fn parses_addresses_without_brackets_around_them() {
+        assert_eq!(
+            Address::parse_list("Alice Smith alice@example.com, bob@example.com (Bob)"),
+            vec![address(Some("Alice Smith"), "alice@example.com"), address(Some("Bob"), "bob@example.com")],
+        );
+    }
+
+    #[test]
+    fn parses_angle_only_addresses() {
+        assert_eq!(
+            Address::parse_list("<alice@example.com>, < bob@example.com >"),
+            vec![address(None, "alice@example.com"), address(None, "bob@example.com")],
+        );
+    }
+
+    #[test]
+    fn replaces_groups_by_their_members() {
+        assert_eq!(
+            Address::parse_list("Team: alice@example.com, Bob <bob@example.com>;, carol@example.com"),
+            vec![
+                address(None, "alice@example.com"),
+                address(Some("Bob"), "bob@example.com"),
+                address(None, "carol@example.com"),
+            ],
+        );
+    }
+
+    #[test]
+    fn skips_empty_entries() {
+        assert!(Address::parse_list("").is_empty());
+        assert!(Address::parse_list("  , ;  ,").is_empty());
+        assert!(Address::parse_list("<>").is_empty());
+        assert_eq!(Address::parse_list(", alice@example.com,,"), vec![address(None, "alice@example.com")]);
+    }
+
+    #[test]
+    fn recovers_from_malformed_input() {
+        // An unclosed bracket still ends at the end of the entry
+        assert_eq!(Address::parse_list("Alice <alice@example.com"), vec![address(Some("Alice"), "alice@example.com")]);
+
+        // Commas inside brackets don't split the list
+        assert_eq!(
+            Address::parse_list("Alice <alice@example.com, bob@example.com>"),
+            vec![address(Some("Alice"), "alice@example.com, bob@example.com")],
+        );
+
+        // A name on its own is kept as the address, which sending then rejects
+        assert_eq!(Address::parse_list("Alice"), vec![address(None, "Alice")]);
+
+        // An unclosed comment or quote swallows the rest of the text
+        assert!(Address::parse_list("(Alice alice@example.com").is_empty());
+        assert_eq!(
+            Address::parse_list("\"Alice alice@example.com, bob@example.com"),
+            vec![address(None, "Alice alice@example.com, bob@example.com")],
+        );
+    }
+
+    #[test]
+    fn quotes_names_with_specials_in_headers() {
+        assert_eq!(address(Some("Alice"), "alice@example.com").to_header(), "Alice <alice@example.com>");
+        assert_eq!(address(Some("Doe, Bob"), "bob@example.com").to_header(), "\"Doe, Bob\" <bob@example.com>");
+        assert_eq!(address(Some("Say \"hi\""), "a@example.com").to_header(), "\"Say \\\"hi\\\"\" <a@example.com>");
+        assert_eq!(address(None, "alice@example.com").to_header(), "alice@example.com");
+    }
+
+    #[test]
+    fn round_trips_through_headers() {
+        let list = vec![address(Some("Doe, Bob"), "bob@example.com"), address(None, "alice@example.com")];
+        let header = list.iter().map(Address::to_header).collect::<Vec<_>>().join(", ");
+        assert_eq!(Address::parse_list(&header), list);
+    }
+}
@@ -1,5 +1,6 @@
 //! Email attachment model for the Linksy email client.
 
+use mail_parser::{MessageParser, PartType};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
@@ -18,6 +19,15 @@ pub struct Attachment {
     #[serde(skip)]
     #[allow(dead_code)]
     pub data: Vec<u8>,
+    /// Content-ID of the part, which an HTML body refers to as "cid:..."
+    #[serde(default)]
+    pub content_id: Option<String>,
+    /// Whether the part is shown in the body, like an inline image, rather than attached
+    #[serde(default)]
+    pub inline: bool,
+    /// IMAP section of the part, to download it on its own when it's needed
+    #[serde(default)]
+    pub section: Option<String>,
 }
 
 impl Attachment {
@@ -58,6 +68,9 @@ impl Attachment {
             content_type,
             size: data.len(),
             data,
+            content_id: None,
+            inline: false,
+            section: None,
         })
     }
     
@@ -74,20 +87,81 @@ impl Attachment {
         Ok(())
     }
     
+    /// Checks if the attachment's data still has to be downloaded.
+    ///
+    /// # Returns
+    /// true if the data is missing and the part can be fetched by its section
+    pub fn needs_download(&self) -> bool {
+        self.data.is_empty() && self.section.is_some()
+    }
+    
+    /// Sets the data of the attachment from its downloaded MIME part.
+    ///
+    /// # Parameters
+    /// - `entity`: The part's MIME headers followed by its encoded body
+    ///
+    /// # Returns
+    /// A Result indicating success, or an error if the part couldn't be decoded
+    pub fn set_data_from_part(&mut self, entity: &[u8]) -> anyhow::Result<()> {
+        let message = MessageParser::default().parse(entity)
+            .ok_or_else(|| anyhow::anyhow!("Couldn't decode {}", self.filename))?;
+        let part = message.parts.first()
+            .ok_or_else(|| anyhow::anyhow!("Couldn't decode {}", self.filename))?;
+        
+        // The body is decoded from its transfer encoding, and text to UTF-8
+        self.data = match &part.body {
+            PartType::Text(text) | PartType::Html(text) => text.as_bytes().to_vec(),
+            PartType::Binary(binary) | PartType::InlineBinary(binary) => binary.to_vec(),
+            _ => entity[part.offset_body.min(entity.len())..].to_vec(),
+        };
+        self.size = self.data.len();
+        
+        Ok(())
+    }
+    
+    /// Checks if the part is referred to by a "cid:" URL.
+    ///
+    /// # Parameters
+    /// - `url`: The URL, e.g. the source of an image
+    ///
+    /// # Returns
+    /// true if the URL names the part's Content-ID, false otherwise
+    pub fn matches_cid(&self, url: &str) -> bool {
+        let Some(content_id) = &self.content_id else {
+            return false;
+        };
+        let url = url.trim();
+        if !url.get(..4).is_some_and(|scheme| scheme.eq_ignore_ascii_case("cid:")) {
+            return false;
+        }
+        url[4..].trim_matches(|c| c == '<' || c == '>') == content_id
+    }
+    
     /// Gets a human-readable size string.
     ///
     /// # Returns
     /// A string representing the attachment size in a human-readable format
     #[allow(dead_code)]
     pub fn get_size_string(&self) -> String {
-        if self.size < 1024 {
-            format!("{} B", self.size)
-        } else if self.size < 1024 * 1024 {
-            format!("{:.1} KB", self.size as f64 / 1024.0)
-        } else if self.size < 1024 * 1024 * 1024 {
-            format!("{:.1} MB", self.size as f64 / (1024.0 * 1024.0))
-        } else {
-            format!("{:.1} GB", self.size as f64 / (1024.0 * 1024.0 * 1024.0))
-        }
+        format_size(self.size)
+    }
+}
+
+/// Formats a size in bytes for display.
+///
+/// # Parameters
+/// - `size`: The size in bytes
+///
+/// # Returns
+/// The size in B, KB, MB or GB, e.g. "12.3 KB"
+pub fn format_size(size: usize) -> String {
+    if size < 1024 {
+        format!("{} B", size)
+    } else if size < 1024 * 1024 {
+        format!("{:.1} KB", size as f64 / 1024.0)
+    } else if size < 1024 * 1024 * 1024 {
+        format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} GB", size as f64 / (1024.0 * 1024.0 * 1024.0))
     }
 }
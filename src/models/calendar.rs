@@ -0,0 +1,356 @@
+//! Calendar invitation summaries for the Linksy email client.
+//!
+//! Invitations come as a text/calendar part or an .ics attachment. The
+//! event in the iCalendar data is shown above the body, or in its place if
+//! the message has no text body, and invitations can be accepted or
+//! declined with a METHOD=REPLY message to the organizer.
+
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Content types of iCalendar parts.
+pub const CALENDAR_TYPES: [&str; 2] = ["text/calendar", "application/ics"];
+
+/// A property of an iCalendar component.
+struct Property<'a> {
+    /// The property name, uppercase, e.g. "DTSTART"
+    name: String,
+    /// The parameters after the name, e.g. "TZID=Europe/Berlin"
+    params: Vec<&'a str>,
+    /// The value after the colon
+    value: &'a str,
+}
+
+impl Property<'_> {
+    /// Gets a parameter of the property.
+    ///
+    /// # Parameters
+    /// - `name`: The parameter name, e.g. "TZID"
+    ///
+    /// # Returns
+    /// The parameter value, or None if the property doesn't have it
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim_matches('"'))
+        })
+    }
+}
+
+/// Represents the event of a calendar invitation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    /// What the sender asks for, uppercase, e.g. "REQUEST", "CANCEL" or "REPLY"
+    pub method: Option<String>,
+    /// The event's unique ID, which a reply refers to
+    pub uid: Option<String>,
+    /// The revision of the event, which a reply refers to
+    pub sequence: Option<String>,
+    /// The event's title
+    pub summary: Option<String>,
+    /// When the event starts, e.g. "2024-01-02 10:00 (Europe/Berlin)"
+    pub start: Option<String>,
+    /// When the event ends
+    pub end: Option<String>,
+    /// Where the event takes place
+    pub location: Option<String>,
+    /// The organizer's address, which replies are sent to
+    pub organizer: Option<String>,
+    /// The organizer's name
+    pub organizer_name: Option<String>,
+    /// The attendees, by name or address
+    pub attendees: Vec<String>,
+    /// The event's description
+    pub description: Option<String>,
+    /// The DTSTART, DTEND and ORGANIZER lines as they were sent, repeated in a reply
+    pub reply_lines: Vec<String>,
+}
+
+impl CalendarEvent {
+    /// Parses the first event of an iCalendar object.
+    ///
+    /// # Parameters
+    /// - `data`: The iCalendar data
+    ///
+    /// # Returns
+    /// The event, or None if the data has no event
+    pub fn parse(data: &str) -> Option<Self> {
+        // Long lines are folded onto lines starting with a space or a tab
+        let mut lines: Vec<String> = Vec::new();
+        for line in data.lines() {
+            match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+                (Some(rest), Some(last)) => last.push_str(rest),
+                _ => lines.push(line.to_string()),
+            }
+        }
+
+        let properties: Vec<(&str, Property)> = lines.iter()
+            .filter_map(|line| Some((line.as_str(), parse_property(line)?)))
+            .collect();
+        let method = properties.iter()
+            .find(|(_, property)| property.name == "METHOD")
+            .map(|(_, property)| property.value.to_ascii_uppercase());
+
+        // Only the properties of the first event are used
+        let start = properties.iter()
+            .position(|(_, property)| property.name == "BEGIN" && property.value.eq_ignore_ascii_case("VEVENT"))?;
+        let event: Vec<&(&str, Property)> = properties[start + 1..].iter()
+            .take_while(|(_, property)| !(property.name == "END" && property.value.eq_ignore_ascii_case("VEVENT")))
+            .collect();
+        let find = |name: &str| event.iter().find(|(_, property)| property.name == name).map(|(_, property)| property);
+        let text = |name: &str| find(name).map(|property| unescape(property.value)).filter(|value| !value.is_empty());
+
+        // A reply repeats these lines as they were sent
+        let reply_lines = event.iter()
+            .filter(|(_, property)| ["DTSTART", "DTEND", "ORGANIZER"].contains(&property.name.as_str()))
+            .map(|(line, _)| line.to_string())
+            .collect();
+
+        Some(Self {
+            method,
+            uid: find("UID").map(|property| property.value.to_string()),
+            sequence: find("SEQUENCE").map(|property| property.value.to_string()),
+            summary: text("SUMMARY"),
+            start: find("DTSTART").map(format_date),
+            end: find("DTEND").map(format_date),
+            location: text("LOCATION"),
+            organizer: find("ORGANIZER").map(|property| strip_mailto(property.value).to_string()),
+            organizer_name: find("ORGANIZER").and_then(|property| property.param("CN")).map(str::to_string),
+            attendees: event.iter()
+                .filter(|(_, property)| property.name == "ATTENDEE")
+                .map(|(_, property)| property.param("CN").unwrap_or_else(|| strip_mailto(property.value)).to_string())
+                .collect(),
+            description: text("DESCRIPTION"),
+            reply_lines,
+        })
+    }
+
+    /// Gets the heading the event is shown under.
+    ///
+    /// # Returns
+    /// What the message does with the event, e.g. "Calendar invitation"
+    pub fn heading(&self) -> &'static str {
+        match self.method.as_deref() {
+            Some("REQUEST") => "Calendar invitation",
+            Some("CANCEL") => "Calendar event cancelled",
+            Some("REPLY") => "Calendar reply",
+            _ => "Calendar event",
+        }
+    }
+
+    /// Checks if the event can be accepted or declined.
+    ///
+    /// # Returns
+    /// true for an invitation with an organizer to reply to, false otherwise
+    pub fn can_reply(&self) -> bool {
+        self.method.as_deref() == Some("REQUEST") && self.organizer.is_some() && self.uid.is_some()
+    }
+
+    /// Gets when the event takes place.
+    ///
+    /// # Returns
+    /// The start and end, with only the end time if the event ends on the day
+    /// it starts, or None if the start isn't known
+    pub fn when(&self) -> Option<String> {
+        let start = self.start.as_ref()?;
+        Some(match &self.end {
+            Some(end) => match (start.get(..11), end.get(11..)) {
+                (Some(day), Some(time)) if end.starts_with(day) && day.ends_with(' ') => format!("{} to {}", start, time),
+                _ => format!("{} to {}", start, end),
+            },
+            None => start.clone(),
+        })
+    }
+
+    /// Gets the organizer for display.
+    ///
+    /// # Returns
+    /// The organizer's name and address, or None if there is no organizer
+    pub fn organizer_display(&self) -> Option<String> {
+        let address = self.organizer.as_ref()?;
+        Some(match &self.organizer_name {
+            Some(name) => format!("{} <{}>", name, address),
+            None => address.clone(),
+        })
+    }
+
+    /// Lists the details of the event as labeled lines.
+    ///
+    /// # Returns
+    /// The title, time, place, organizer and attendees the event has
+    pub fn details(&self) -> Vec<(&'static str, String)> {
+        let attendees = (!self.attendees.is_empty()).then(|| self.attendees.join(", "));
+        [
+            ("Event", self.summary.clone()),
+            ("When", self.when()),
+            ("Where", self.location.clone()),
+            ("Organizer", self.organizer_display()),
+            ("Attendees", attendees),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| Some((label, value?)))
+        .collect()
+    }
+
+    /// Builds the reply to an invitation.
+    ///
+    /// # Parameters
+    /// - `attendee`: The address of the attendee replying
+    /// - `accept`: true to accept the invitation, false to decline it
+    ///
+    /// # Returns
+    /// The iCalendar data of the reply, sent with METHOD=REPLY to the organizer
+    pub fn reply(&self, attendee: &str, accept: bool) -> String {
+        let stamp = chrono::DateTime::<chrono::Utc>::from(SystemTime::now()).format("%Y%m%dT%H%M%SZ");
+        let status = if accept { "ACCEPTED" } else { "DECLINED" };
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "PRODID:-//Linksy//Linksy//EN".to_string(),
+            "VERSION:2.0".to_string(),
+            "METHOD:REPLY".to_string(),
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", self.uid.as_deref().unwrap_or_default()),
+            format!("DTSTAMP:{}", stamp),
+        ];
+        if let Some(sequence) = &self.sequence {
+            lines.push(format!("SEQUENCE:{}", sequence));
+        }
+        if let Some(summary) = &self.summary {
+            lines.push(format!("SUMMARY:{}", escape(summary)));
+        }
+        lines.extend(self.reply_lines.iter().cloned());
+        lines.push(format!("ATTENDEE;PARTSTAT={}:mailto:{}", status, attendee));
+        lines.push("END:VEVENT".to_string());
+        lines.push("END:VCALENDAR".to_string());
+
+        let mut data = lines.join("\r\n");
+        data.push_str("\r\n");
+        data
+    }
+
+    /// Builds the text shown in place of a missing body.
+    ///
+    /// # Returns
+    /// The heading, the details and the description
+    pub fn to_text(&self) -> String {
+        let mut text = vec![format!("[{}]", self.heading()), String::new()];
+        text.extend(self.details().into_iter().map(|(label, value)| format!("{}: {}", label, value)));
+        if let Some(description) = &self.description {
+            text.push(String::new());
+            text.push(description.clone());
+        }
+        text.join("\n")
+    }
+}
+
+/// Parses a content line such as "DTSTART;TZID=Europe/Berlin:20240102T100000".
+///
+/// # Parameters
+/// - `line`: The unfolded line
+///
+/// # Returns
+/// The property, or None if the line has no value
+fn parse_property(line: &str) -> Option<Property<'_>> {
+    // Parameter values can be quoted and contain colons, so the value starts
+    // at the first colon outside quotes
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        },
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+
+    let mut head = line[..colon].split(';');
+    Some(Property {
+        name: head.next()?.trim().to_ascii_uppercase(),
+        params: head.collect(),
+        value: line[colon + 1..].trim(),
+    })
+}
+
+/// Formats a date or date-time value of a property.
+///
+/// # Parameters
+/// - `property`: A DTSTART or DTEND property
+///
+/// # Returns
+/// The date as "2024-01-02" or "2024-01-02 10:00", with "UTC" or the time
+/// zone added if the value has one, or the value as it is if it isn't a date
+fn format_date(property: &Property) -> String {
+    let value = property.value;
+    let digits = |range: std::ops::Range<usize>| value.get(range).filter(|part| part.bytes().all(|b| b.is_ascii_digit()));
+
+    let (Some(year), Some(month), Some(day)) = (digits(0..4), digits(4..6), digits(6..8)) else {
+        return value.to_string();
+    };
+    let mut date = format!("{}-{}-{}", year, month, day);
+
+    if value.get(8..9) == Some("T") {
+        if let (Some(hour), Some(minute)) = (digits(9..11), digits(11..13)) {
+            date.push_str(&format!(" {}:{}", hour, minute));
+        }
+        if value.ends_with('Z') {
+            date.push_str(" UTC");
+        } else if let Some(zone) = property.param("TZID") {
+            date.push_str(&format!(" ({})", zone));
+        }
+    }
+
+    date
+}
+
+/// Removes the "mailto:" scheme from an address.
+///
+/// # Parameters
+/// - `value`: The value of an ORGANIZER or ATTENDEE property
+///
+/// # Returns
+/// The address
+fn strip_mailto(value: &str) -> &str {
+    match value.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &value[7..],
+        _ => value,
+    }
+}
+
+/// Escapes a text value.
+///
+/// # Parameters
+/// - `value`: The text
+///
+/// # Returns
+/// The value with backslashes, commas, semicolons and line breaks escaped
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace('\n', "\\n")
+}
+
+/// Unescapes a text value.
+///
+/// # Parameters
+/// - `value`: The value, with "\n", "\,", "\;" and "\\" escapes
+///
+/// # Returns
+/// The text
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(escaped) => text.push(escaped),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
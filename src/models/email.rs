@@ -1,10 +1,102 @@
 //! Email message model for the Linksy email client.
 
-use crate::models::attachment::Attachment;
-use mail_parser::{MessageParser, MimeHeaders};
+use crate::models::attachment::{format_size, Attachment};
+use crate::models::{CalendarEvent, MimePart, CALENDAR_TYPES};
+use mail_parser::{Message, MessagePart, MessageParser, MimeHeaders, PartType};
 use serde::{Deserialize, Serialize};
 use std::time::SystemTime;
 
+/// The priority of a message, from its X-Priority and Importance headers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Priority {
+    /// High priority or importance
+    High,
+    /// No priority set
+    #[default]
+    Normal,
+    /// Low priority or importance
+    Low,
+}
+
+impl Priority {
+    /// Determines the priority from a message's headers.
+    ///
+    /// X-Priority is a number from 1 (highest) to 5 (lowest); Importance is
+    /// "high", "normal" or "low". X-Priority wins if both are present.
+    ///
+    /// # Parameters
+    /// - `x_priority`: The X-Priority header, if present
+    /// - `importance`: The Importance header, if present
+    ///
+    /// # Returns
+    /// The priority, Normal if neither header sets one
+    pub fn from_headers(x_priority: Option<&str>, importance: Option<&str>) -> Self {
+        let x_priority = x_priority
+            .and_then(|value| value.trim().chars().next())
+            .and_then(|digit| digit.to_digit(10));
+        match x_priority {
+            Some(1 | 2) => return Priority::High,
+            Some(4 | 5) => return Priority::Low,
+            Some(_) => return Priority::Normal,
+            None => {},
+        }
+        
+        match importance.map(|value| value.trim().to_lowercase()).as_deref() {
+            Some("high") => Priority::High,
+            Some("low") => Priority::Low,
+            _ => Priority::Normal,
+        }
+    }
+    
+    /// Gets the next priority, for cycling through them in compose.
+    ///
+    /// # Returns
+    /// High after Normal, Low after High and Normal after Low
+    pub fn next(&self) -> Self {
+        match self {
+            Priority::Normal => Priority::High,
+            Priority::High => Priority::Low,
+            Priority::Low => Priority::Normal,
+        }
+    }
+    
+    /// Gets the label shown for the priority.
+    ///
+    /// # Returns
+    /// The priority name
+    pub fn label(&self) -> &'static str {
+        match self {
+            Priority::High => "High",
+            Priority::Normal => "Normal",
+            Priority::Low => "Low",
+        }
+    }
+    
+    /// Gets the value written to the X-Priority header.
+    ///
+    /// # Returns
+    /// The header value
+    pub fn x_priority(&self) -> &'static str {
+        match self {
+            Priority::High => "1 (Highest)",
+            Priority::Normal => "3 (Normal)",
+            Priority::Low => "5 (Lowest)",
+        }
+    }
+    
+    /// Gets the value written to the Importance header.
+    ///
+    /// # Returns
+    /// The header value
+    pub fn importance(&self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        }
+    }
+}
+
 /// Represents an email message.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Email {
@@ -22,6 +114,9 @@ pub struct Email {
     pub cc: Vec<String>,
     /// List of BCC recipient email addresses
     pub bcc: Vec<String>,
+    /// Addresses the sender asked replies to go to, from the Reply-To header
+    #[serde(default)]
+    pub reply_to: Vec<String>,
     /// Email body in plain text format
     pub body_text: Option<String>,
     /// Email body in HTML format
@@ -34,12 +129,56 @@ pub struct Email {
     pub is_read: bool,
     /// Whether the email has been flagged
     pub is_flagged: bool,
+    /// Keywords set on the message on the IMAP server, e.g. "$Todo"
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Tags kept only in the local cache, for servers that don't store keywords
+    #[serde(default)]
+    pub local_tags: Vec<String>,
     /// Email headers
     pub headers: Vec<(String, String)>,
     /// Account ID this email belongs to
     pub account_id: String,
     /// Folder/mailbox this email belongs to
     pub folder: String,
+    /// Size of the raw message in bytes, if known
+    #[serde(default)]
+    pub size: Option<usize>,
+    /// Whether only the headers were fetched; the body is fetched when the email is opened
+    #[serde(default)]
+    pub headers_only: bool,
+    /// The parts of the message, from its IMAP body structure
+    #[serde(default)]
+    pub structure: Option<MimePart>,
+    /// The event of a calendar invitation in the message
+    #[serde(default)]
+    pub invite: Option<CalendarEvent>,
+    /// Priority of the message
+    #[serde(default)]
+    pub priority: Priority,
+    /// Whether only the start of the message was downloaded because it's large
+    #[serde(default)]
+    pub truncated: bool,
+    /// The raw message, kept only when it couldn't be parsed
+    #[serde(default)]
+    pub raw_source: Option<String>,
+    /// Whether a change to the read or flagged state is waiting for the server
+    #[serde(skip)]
+    pub sync_pending: bool,
+}
+
+/// Identifies an email among those of every account and folder.
+///
+/// An email ID, such as an IMAP UID, is only unique within its folder, so
+/// the account and folder are part of the key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EmailKey {
+    /// ID of the account the email belongs to
+    pub account_id: String,
+    /// Folder containing the email
+    pub folder: String,
+    /// ID of the email within the folder
+    pub id: String,
 }
 
 impl Email {
@@ -56,20 +195,57 @@ impl Email {
             to: Vec::new(),
             cc: Vec::new(),
             bcc: Vec::new(),
+            reply_to: Vec::new(),
             body_text: None,
             body_html: None,
             date: SystemTime::now(),
             attachments: Vec::new(),
             is_read: false,
             is_flagged: false,
+            keywords: Vec::new(),
+            local_tags: Vec::new(),
             headers: Vec::new(),
             account_id: String::new(),
             folder: "INBOX".to_string(),
+            size: None,
+            headers_only: false,
+            structure: None,
+            invite: None,
+            priority: Priority::Normal,
+            truncated: false,
+            raw_source: None,
+            sync_pending: false,
         }
     }
     
+    /// Gets the key that identifies the email among all accounts and folders.
+    ///
+    /// # Returns
+    /// The email's account, folder and ID
+    pub fn key(&self) -> EmailKey {
+        EmailKey {
+            account_id: self.account_id.clone(),
+            folder: self.folder.clone(),
+            id: self.id.clone(),
+        }
+    }
+    
+    /// Checks if the email is the one a key identifies.
+    ///
+    /// # Parameters
+    /// - `key`: The key
+    ///
+    /// # Returns
+    /// true if the account, folder and ID match the key, false otherwise
+    pub fn has_key(&self, key: &EmailKey) -> bool {
+        self.id == key.id && self.folder == key.folder && self.account_id == key.account_id
+    }
+    
     /// Parses an email from raw message data.
     ///
+    /// A message without any headers can't be parsed; it is kept with its
+    /// raw source so it can still be read.
+    ///
     /// # Parameters
     /// - `raw_data`: Raw email message data
     /// - `account_id`: ID of the account this email belongs to
@@ -77,9 +253,12 @@ impl Email {
     ///
     /// # Returns
     /// A Result containing the parsed Email or an error
+    #[allow(clippy::collapsible_if)]
     pub fn parse_from_raw(raw_data: &[u8], account_id: &str, folder: &str) -> anyhow::Result<Self> {
-        let message = MessageParser::default().parse(raw_data)
-            .ok_or_else(|| anyhow::anyhow!("Failed to parse email message"))?;
+        let message = match MessageParser::default().parse(raw_data) {
+            Some(message) if !message.headers().is_empty() => message,
+            _ => return Ok(Self::unparseable(raw_data, account_id, folder)),
+        };
         
         let mut email = Email::new();
         
@@ -88,6 +267,7 @@ impl Email {
         email.subject = message.subject().unwrap_or_default().to_string();
         email.account_id = account_id.to_string();
         email.folder = folder.to_string();
+        email.size = Some(raw_data.len());
         
         // Set sender information
         if let Some(from) = message.from() {
@@ -116,25 +296,44 @@ impl Email {
                 .collect();
         }
         
+        if let Some(reply_to) = message.reply_to() {
+            email.reply_to = reply_to.iter()
+                .filter_map(|addr| addr.address().map(|s| s.to_string()))
+                .collect();
+        }
+        
+        // Set priority
+        email.priority = Priority::from_headers(message.header_raw("X-Priority"), message.header_raw("Importance"));
+        
         // Set date
         if let Some(date) = message.date() {
             email.date = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(date.to_timestamp() as u64);
         }
         
-        // Set body content
-        let text_bodies: Vec<_> = message.text_bodies().collect();
-        if !text_bodies.is_empty() {
-            email.body_text = Some(String::from_utf8_lossy(text_bodies[0].contents()).to_string());
-        }
+        // Set body content, leaving out binary parts and calendar data the parser offers as the body
+        let readable = |part: &&MessagePart| matches!(part.body, PartType::Text(_) | PartType::Html(_)) && !is_calendar(part);
+        email.body_text = message.text_bodies()
+            .find(readable)
+            .map(|part| String::from_utf8_lossy(part.contents()).to_string());
+        email.body_html = message.html_bodies()
+            .find(readable)
+            .map(|part| String::from_utf8_lossy(part.contents()).to_string());
         
-        let html_bodies: Vec<_> = message.html_bodies().collect();
-        if !html_bodies.is_empty() {
-            email.body_html = Some(String::from_utf8_lossy(html_bodies[0].contents()).to_string());
+        // Find a calendar invitation; without a text body its event is shown instead
+        let calendar_part = message.parts.iter().find(|part| is_calendar(part));
+        email.invite = calendar_part.and_then(|part| CalendarEvent::parse(&part_text(part)));
+        if email.body_text.is_none() && email.body_html.is_none() {
+            email.body_text = Some(match &email.invite {
+                Some(invite) => invite.to_text(),
+                None => fallback_body(&message),
+            });
         }
         
-        // Set attachments
+        // Set attachments, and inline parts the HTML body refers to by Content-ID
         for attachment in message.attachments() {
-            if let Some(filename) = attachment.attachment_name() {
+            let content_id = attachment.content_id()
+                .map(|id| id.trim_matches(|c| c == '<' || c == '>').to_string());
+            if let Some(filename) = attachment.attachment_name().map(str::to_string).or_else(|| content_id.clone()) {
                 let content_type = attachment.content_type()
                     .map(|ct| {
                         let ctype = ct.ctype();
@@ -149,17 +348,39 @@ impl Email {
                     _ => &[]
                 };
                 
+                // Parts with a Content-ID are inline unless they are explicitly attached
+                let attached = attachment.content_disposition().is_some_and(|disposition| disposition.is_attachment());
                 let new_attachment = Attachment {
                     id: uuid::Uuid::new_v4().to_string(),
-                    filename: filename.to_string(),
+                    filename,
                     content_type,
                     size: body_bytes.len(),
                     data: body_bytes.to_vec(),
+                    inline: content_id.is_some() && !attached,
+                    content_id,
+                    section: None,
                 };
                 email.attachments.push(new_attachment);
             }
         }
         
+        // Keep the invitation with the message, as a file if it came as the body
+        let has_calendar_file = email.attachments.iter()
+            .any(|attachment| CALENDAR_TYPES.iter().any(|calendar| attachment.content_type.eq_ignore_ascii_case(calendar)));
+        if let Some(part) = calendar_part.filter(|_| email.invite.is_some() && !has_calendar_file) {
+            let data = part_text(part).into_bytes();
+            email.attachments.push(Attachment {
+                id: uuid::Uuid::new_v4().to_string(),
+                filename: "invite.ics".to_string(),
+                content_type: "text/calendar".to_string(),
+                size: data.len(),
+                data,
+                content_id: None,
+                inline: false,
+                section: None,
+            });
+        }
+        
         // Set headers
         for header in message.headers() {
             let name = header.name();
@@ -170,21 +391,176 @@ impl Email {
         Ok(email)
     }
     
+    /// Creates an email for a message that couldn't be parsed.
+    ///
+    /// # Parameters
+    /// - `raw_data`: Raw email message data
+    /// - `account_id`: ID of the account this email belongs to
+    /// - `folder`: Folder/mailbox this email belongs to
+    ///
+    /// # Returns
+    /// An Email with only the raw source, size, account and folder set
+    fn unparseable(raw_data: &[u8], account_id: &str, folder: &str) -> Self {
+        let mut email = Email::new();
+        email.id = uuid::Uuid::new_v4().to_string();
+        email.account_id = account_id.to_string();
+        email.folder = folder.to_string();
+        email.size = Some(raw_data.len());
+        email.raw_source = Some(String::from_utf8_lossy(raw_data).to_string());
+        email
+    }
+    
+    /// Parses an email from the headers of a message, without its body.
+    ///
+    /// # Parameters
+    /// - `header`: Raw header data
+    /// - `account_id`: ID of the account this email belongs to
+    /// - `folder`: Folder/mailbox this email belongs to
+    ///
+    /// # Returns
+    /// A Result containing the headers-only Email or an error
+    pub fn parse_headers(header: &[u8], account_id: &str, folder: &str) -> anyhow::Result<Self> {
+        let mut email = Self::parse_from_raw(header, account_id, folder)?;
+        
+        // The size and attachments of the headers alone are meaningless
+        email.body_text = None;
+        email.body_html = None;
+        email.attachments.clear();
+        email.size = None;
+        email.raw_source = None;
+        email.headers_only = true;
+        
+        Ok(email)
+    }
+    
+    /// Sets the body of a headers-only email from its downloaded text parts.
+    ///
+    /// The attachments stay listed from the body structure, to be downloaded
+    /// when they're opened.
+    ///
+    /// # Parameters
+    /// - `entities`: The text parts and any calendar part, each with its MIME
+    ///   headers followed by its encoded body
+    pub fn set_body_parts(&mut self, entities: &[Vec<u8>]) {
+        for entity in entities {
+            let Some(part) = MessageParser::default().parse(entity.as_slice()).and_then(|message| message.parts.into_iter().next()) else {
+                continue;
+            };
+            if is_calendar(&part) {
+                self.invite = CalendarEvent::parse(&part_text(&part));
+                continue;
+            }
+            match part.body {
+                PartType::Text(text) => self.body_text = Some(text.into_owned()),
+                PartType::Html(html) => self.body_html = Some(html.into_owned()),
+                _ => {},
+            }
+        }
+        
+        // Without a text body the invitation's event is shown instead
+        if self.body_text.is_none() && self.body_html.is_none() {
+            self.body_text = self.invite.as_ref().map(CalendarEvent::to_text);
+        }
+        self.headers_only = false;
+    }
+    
+    /// Gets a human-readable size string.
+    ///
+    /// # Returns
+    /// The size of the raw message, e.g. "12.3 KB", or None if it isn't known
+    pub fn get_size_string(&self) -> Option<String> {
+        self.size.map(format_size)
+    }
+    
     /// Gets a summary of the email for display in lists.
     ///
+    /// Only the attachment metadata is used, so the summary works for cached
+    /// emails whose attachment data isn't loaded.
+    ///
     /// # Returns
     /// A string containing a summary of the email
     pub fn get_summary(&self) -> String {
-        let from_display = if let Some(name) = &self.from_name {
-            format!("{} <{}>", name, self.from)
-        } else {
-            self.from.clone()
-        };
-        
+        let pending = if self.sync_pending { "⟳ " } else { "" };
+        let priority = if self.priority == Priority::High { "! " } else { "" };
         let flag = if self.is_flagged { "🚩 " } else { "" };
         let read = if self.is_read { "" } else { "📩 " };
+        let attachments = match self.attachments.iter().filter(|attachment| !attachment.inline).count() {
+            0 => String::new(),
+            1 => "📎 ".to_string(),
+            count => format!("📎{} ", count),
+        };
         
-        format!("{}{}{} - {}", flag, read, from_display, self.subject)
+        format!("{}{}{}{}{}{} - {}", pending, priority, flag, read, attachments, self.get_sender_display(), self.get_subject_display())
+    }
+    
+    /// Gets the tags of the email, from the server and the local cache.
+    ///
+    /// # Returns
+    /// The keywords, followed by the local tags that aren't also keywords
+    pub fn tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = self.keywords.iter().map(String::as_str).collect();
+        for tag in &self.local_tags {
+            if !tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+    
+    /// Checks if the email has a tag, on the server or locally.
+    ///
+    /// # Parameters
+    /// - `tag`: The tag, matched ignoring case like IMAP keywords
+    ///
+    /// # Returns
+    /// true if the email has the tag, false otherwise
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.keywords.iter().chain(&self.local_tags).any(|known| known.eq_ignore_ascii_case(tag))
+    }
+    
+    /// Checks if a tag of the email is only kept in the local cache.
+    ///
+    /// # Parameters
+    /// - `tag`: The tag
+    ///
+    /// # Returns
+    /// true if the tag is a local tag and not a keyword on the server
+    pub fn is_local_tag(&self, tag: &str) -> bool {
+        self.local_tags.iter().any(|local| local.eq_ignore_ascii_case(tag))
+            && !self.keywords.iter().any(|keyword| keyword.eq_ignore_ascii_case(tag))
+    }
+    
+    /// Checks if replies are asked to go to someone other than the sender.
+    ///
+    /// # Returns
+    /// true if the Reply-To header names an address other than the sender's
+    pub fn has_other_reply_to(&self) -> bool {
+        self.reply_to.iter().any(|address| !address.eq_ignore_ascii_case(&self.from))
+    }
+    
+    /// Gets the sender for display.
+    ///
+    /// # Returns
+    /// "Name <address>", the address alone, or "(unknown sender)" if there is neither
+    pub fn get_sender_display(&self) -> String {
+        match (self.from_name.as_deref().map(str::trim).filter(|name| !name.is_empty()), self.from.trim()) {
+            (Some(name), "") => name.to_string(),
+            (Some(name), from) => format!("{} <{}>", name, from),
+            (None, "") => "(unknown sender)".to_string(),
+            (None, from) => from.to_string(),
+        }
+    }
+    
+    /// Gets the subject for display.
+    ///
+    /// # Returns
+    /// The subject, or "(no subject)" if it's empty
+    pub fn get_subject_display(&self) -> &str {
+        if self.subject.trim().is_empty() {
+            "(no subject)"
+        } else {
+            &self.subject
+        }
     }
 }
 
@@ -193,3 +569,217 @@ impl Default for Email {
         Self::new()
     }
 }
+
+/// Gets the content type of a message part.
+///
+/// # Parameters
+/// - `part`: The part
+///
+/// # Returns
+/// The lowercase type, "text/plain" if the part doesn't have one
+fn part_content_type(part: &MessagePart) -> String {
+    part.content_type()
+        .map(|ct| format!("{}/{}", ct.ctype(), ct.subtype().unwrap_or("plain")).to_lowercase())
+        .unwrap_or_else(|| "text/plain".to_string())
+}
+
+/// Checks if a message part holds iCalendar data.
+///
+/// # Parameters
+/// - `part`: The part
+///
+/// # Returns
+/// true for text/calendar and application/ics parts and .ics files, false otherwise
+fn is_calendar(part: &MessagePart) -> bool {
+    CALENDAR_TYPES.contains(&part_content_type(part).as_str())
+        || part.attachment_name().is_some_and(|name| name.to_ascii_lowercase().ends_with(".ics"))
+}
+
+/// Gets the contents of a message part as text.
+///
+/// # Parameters
+/// - `part`: The part
+///
+/// # Returns
+/// The decoded text, with invalid UTF-8 in binary parts replaced
+fn part_text(part: &MessagePart) -> String {
+    match &part.body {
+        PartType::Text(text) | PartType::Html(text) => text.to_string(),
+        PartType::Binary(data) | PartType::InlineBinary(data) => String::from_utf8_lossy(data).into_owned(),
+        _ => String::new(),
+    }
+}
+
+/// Builds the body shown for a message without a text or HTML body.
+///
+/// Any text part that isn't an attached file is shown; otherwise the body
+/// says which parts the message has.
+///
+/// # Parameters
+/// - `message`: The parsed message
+///
+/// # Returns
+/// The text shown as the body
+fn fallback_body(message: &Message) -> String {
+    // Text that isn't an attached file, e.g. text/enriched
+    let text = message.parts.iter().find_map(|part| match &part.body {
+        PartType::Text(text) if part.attachment_name().is_none() && !text.trim().is_empty() => Some(text.to_string()),
+        _ => None,
+    });
+    if let Some(text) = text {
+        return text;
+    }
+    
+    let parts: Vec<String> = message.parts.iter()
+        .filter(|part| !matches!(part.body, PartType::Multipart(_)))
+        .map(|part| match part.attachment_name() {
+            Some(name) => format!("{} ({})", part_content_type(part), name),
+            None => part_content_type(part),
+        })
+        .collect();
+    if parts.is_empty() {
+        "[This message has no content]".to_string()
+    } else {
+        format!("[This message has no text to show - it contains {}]", parts.join(", "))
+    }
+}
+
+/// Checks if a tag can be stored as an IMAP keyword.
+///
+/// Keywords are atoms: printable ASCII without spaces or the characters
+/// IMAP gives a meaning to. A leading backslash would make it a system flag.
+///
+/// # Parameters
+/// - `tag`: The tag
+///
+/// # Returns
+/// true if the tag is a valid keyword, false otherwise
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && !tag.starts_with('\\')
+        && tag.chars().all(|c| c.is_ascii_graphic() && !"(){%*\"\\]".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An invitation sent with METHOD=REQUEST, as calendar clients do.
+    const INVITE: &str = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        PRODID:-//Example//Calendar//EN\r\n\
+        METHOD:REQUEST\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:42@example.com\r\n\
+        DTSTART:20240102T100000Z\r\n\
+        DTEND:20240102T110000Z\r\n\
+        SUMMARY:Quarterly planning\r\n\
+        LOCATION:Room 4\r\n\
+        ORGANIZER;CN=Alice:mailto:alice@example.com\r\n\
+        ATTENDEE;CN=Bob:mailto:bob@example.com\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    /// Parses a message with the given content headers and body.
+    fn parse(content: &str) -> Email {
+        let raw = format!(
+            "From: Alice <alice@example.com>\r\nTo: bob@example.com\r\nSubject: Planning\r\nMIME-Version: 1.0\r\n{}",
+            content,
+        );
+        Email::parse_from_raw(raw.as_bytes(), "work", "INBOX").unwrap()
+    }
+
+    #[test]
+    fn shows_the_event_of_an_invitation_without_a_text_body() {
+        let email = parse(&format!("Content-Type: text/calendar; method=REQUEST; charset=utf-8\r\n\r\n{}", INVITE));
+
+        let body = email.body_text.as_deref().unwrap();
+        assert!(body.starts_with("[Calendar invitation]"), "{}", body);
+        assert!(body.contains("Event: Quarterly planning"), "{}", body);
+        assert!(body.contains("Where: Room 4"), "{}", body);
+        assert!(email.body_html.is_none());
+
+        let invite = email.invite.as_ref().unwrap();
+        assert!(invite.can_reply());
+        assert_eq!(invite.summary.as_deref(), Some("Quarterly planning"));
+
+        // The invitation is kept as a file too
+        assert_eq!(email.attachments.len(), 1);
+        assert_eq!(email.attachments[0].filename, "invite.ics");
+        assert_eq!(email.attachments[0].content_type, "text/calendar");
+    }
+
+    #[test]
+    fn shows_the_event_of_an_attached_ics_file() {
+        let email = parse(&format!(
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n\r\n\
+            --b\r\n\
+            Content-Type: application/ics; name=\"invite.ics\"\r\n\
+            Content-Disposition: attachment; filename=\"invite.ics\"\r\n\r\n\
+            {}\r\n\
+            --b--\r\n",
+            INVITE,
+        ));
+
+        assert!(email.body_text.as_deref().unwrap().contains("Event: Quarterly planning"));
+        assert!(email.invite.is_some());
+
+        // The attached file isn't added a second time
+        assert_eq!(email.attachments.len(), 1);
+        assert_eq!(email.attachments[0].filename, "invite.ics");
+    }
+
+    #[test]
+    fn keeps_the_text_body_of_an_invitation() {
+        let email = parse(&format!(
+            "Content-Type: multipart/alternative; boundary=\"b\"\r\n\r\n\
+            --b\r\n\
+            Content-Type: text/plain; charset=utf-8\r\n\r\n\
+            You're invited to the planning.\r\n\
+            --b\r\n\
+            Content-Type: text/calendar; method=REQUEST; charset=utf-8\r\n\r\n\
+            {}\r\n\
+            --b--\r\n",
+            INVITE,
+        ));
+
+        assert_eq!(email.body_text.as_deref().map(str::trim), Some("You're invited to the planning."));
+        assert!(email.invite.is_some());
+    }
+
+    #[test]
+    fn shows_other_text_parts_without_a_text_body() {
+        let email = parse("Content-Type: text/enriched\r\n\r\n<bold>Hello</bold> there\r\n");
+
+        assert!(email.body_text.as_deref().unwrap().contains("Hello"));
+        assert!(email.invite.is_none());
+    }
+
+    #[test]
+    fn describes_the_parts_of_a_message_without_text() {
+        let email = parse(
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n\r\n\
+            --b\r\n\
+            Content-Type: application/pdf; name=\"report.pdf\"\r\n\
+            Content-Disposition: attachment; filename=\"report.pdf\"\r\n\
+            Content-Transfer-Encoding: base64\r\n\r\n\
+            JVBERi0xLjQK\r\n\
+            --b--\r\n",
+        );
+
+        assert_eq!(
+            email.body_text.as_deref(),
+            Some("[This message has no text to show - it contains application/pdf (report.pdf)]"),
+        );
+        assert_eq!(email.attachments.len(), 1);
+    }
+
+    #[test]
+    fn shows_calendar_data_without_an_event_as_text() {
+        let calendar = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nEND:VCALENDAR\r\n";
+        let email = parse(&format!("Content-Type: text/calendar\r\n\r\n{}", calendar));
+
+        assert!(email.invite.is_none());
+        assert_eq!(email.body_text.as_deref(), Some(calendar));
+    }
+}
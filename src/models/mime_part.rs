@@ -0,0 +1,101 @@
+//! MIME structure model for the Linksy email client.
+
+use crate::models::{Attachment, CALENDAR_TYPES};
+use serde::{Deserialize, Serialize};
+
+/// Represents a part of a message, as described by its IMAP body structure.
+///
+/// The structure is known from listing the message, before its body is
+/// downloaded, so the parts can be fetched one at a time by their section.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MimePart {
+    /// IMAP section of the part, e.g. "1.2", or empty for the message itself
+    pub section: String,
+    /// Lowercase MIME type, e.g. "text/plain" or "multipart/mixed"
+    pub content_type: String,
+    /// Encoded size of the part in bytes, 0 for a multipart
+    pub size: usize,
+    /// File name from the disposition or the content type, if it has one
+    pub filename: Option<String>,
+    /// Content-ID of the part, without the angle brackets
+    pub content_id: Option<String>,
+    /// Whether the disposition says the part is attached
+    pub attached: bool,
+    /// The parts of a multipart
+    pub parts: Vec<MimePart>,
+}
+
+impl MimePart {
+    /// Checks if the part is made of other parts.
+    ///
+    /// # Returns
+    /// true for multipart types, false otherwise
+    pub fn is_multipart(&self) -> bool {
+        self.content_type.starts_with("multipart/")
+    }
+
+    /// Lists the parts that aren't made of other parts, in message order.
+    ///
+    /// # Returns
+    /// The part itself if it's not a multipart, otherwise its leaf parts
+    pub fn leaves(&self) -> Vec<&MimePart> {
+        if !self.is_multipart() {
+            return vec![self];
+        }
+        self.parts.iter().flat_map(|part| part.leaves()).collect()
+    }
+
+    /// Checks if the part is an attachment rather than a body.
+    ///
+    /// Like the full parse, parts with a file name or a Content-ID are
+    /// attachments.
+    ///
+    /// # Returns
+    /// true if the part is listed as an attachment, false otherwise
+    pub fn is_attachment(&self) -> bool {
+        self.filename.is_some() || self.content_id.is_some()
+    }
+
+    /// Gets the sections of the text and HTML bodies and of a calendar invitation.
+    ///
+    /// # Returns
+    /// The section of the first plain text body, of the first HTML body and
+    /// of the first iCalendar part, for the ones the message has
+    pub fn body_sections(&self) -> Vec<String> {
+        let leaves = self.leaves();
+        let body = |content_type: &str| leaves.iter()
+            .find(|part| part.content_type == content_type && !part.is_attachment() && !part.attached);
+        let calendar = leaves.iter()
+            .find(|part| CALENDAR_TYPES.contains(&part.content_type.as_str())
+                || part.filename.as_ref().is_some_and(|name| name.to_ascii_lowercase().ends_with(".ics")));
+
+        [body("text/plain"), body("text/html"), calendar].into_iter()
+            .flatten()
+            .map(|part| part.section.clone())
+            .collect()
+    }
+
+    /// Lists the attachments of the message.
+    ///
+    /// Their data isn't downloaded, so only the name, type, encoded size
+    /// and section are known. A message that isn't a multipart is downloaded
+    /// whole, so its attachment has no section of its own.
+    ///
+    /// # Returns
+    /// An attachment for each leaf part with a file name or a Content-ID
+    pub fn attachments(&self) -> Vec<Attachment> {
+        self.leaves().into_iter()
+            .filter(|part| part.is_attachment())
+            .map(|part| Attachment {
+                id: uuid::Uuid::new_v4().to_string(),
+                filename: part.filename.clone().or_else(|| part.content_id.clone()).unwrap_or_default(),
+                content_type: part.content_type.clone(),
+                size: part.size,
+                data: Vec::new(),
+                inline: part.content_id.is_some() && !part.attached,
+                content_id: part.content_id.clone(),
+                section: self.is_multipart().then(|| part.section.clone()),
+            })
+            .collect()
+    }
+}
@@ -7,7 +7,20 @@ mod email;
 mod account;
 mod attachment;
 mod account_summary;
+mod scheduled_email;
+mod search_query;
+mod address;
+mod recent_recipient;
+mod mime_part;
+mod calendar;
 
 pub use email::*;
+pub use attachment::*;
 pub use account::*;
 pub use account_summary::*;
+pub use scheduled_email::*;
+pub use search_query::*;
+pub use address::*;
+pub use recent_recipient::*;
+pub use mime_part::*;
+pub use calendar::*;
@@ -0,0 +1,15 @@
+//! Recent recipient model for the Linksy email client.
+
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Represents an address mail was sent to, suggested when composing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentRecipient {
+    /// The address as it was last sent to, with its display name if it had one
+    pub address: String,
+    /// When mail was last sent to the address
+    pub last_used: SystemTime,
+    /// Number of messages sent to the address
+    pub count: u32,
+}
@@ -0,0 +1,51 @@
+//! Scheduled email model for the Linksy email client.
+
+use crate::models::Email;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Name of the virtual folder that lists scheduled messages.
+pub const SCHEDULED_FOLDER: &str = "Scheduled";
+
+/// Represents a message waiting to be sent at a later time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledEmail {
+    /// The message to send; its account_id picks the SMTP account
+    pub email: Email,
+    /// When the message should be sent
+    pub send_at: SystemTime,
+}
+
+impl ScheduledEmail {
+    /// Creates a new ScheduledEmail.
+    ///
+    /// # Parameters
+    /// - `email`: The message to send
+    /// - `send_at`: When the message should be sent
+    ///
+    /// # Returns
+    /// A new ScheduledEmail instance
+    pub fn new(email: Email, send_at: SystemTime) -> Self {
+        Self { email, send_at }
+    }
+    
+    /// Checks if the message is due to be sent.
+    ///
+    /// # Returns
+    /// true if the send time has passed, false otherwise
+    pub fn is_due(&self) -> bool {
+        self.send_at <= SystemTime::now()
+    }
+    
+    /// Converts the message into an entry for the Scheduled folder's email list.
+    ///
+    /// # Returns
+    /// The message dated at its send time
+    pub fn to_list_entry(&self) -> Email {
+        let mut email = self.email.clone();
+        email.date = self.send_at;
+        email.folder = SCHEDULED_FOLDER.to_string();
+        email.is_read = true;
+        email
+    }
+}
@@ -0,0 +1,262 @@
+//! Search query model for the Linksy email client.
+//!
+//! A query is a list of terms that must all match. Besides plain text, terms
+//! can be `from:`, `to:` and `subject:` filters and the flags `flagged` (or
+//! `is:flagged`), `unread` (or `is:unread`) and `is:read`. Two terms joined
+//! by `OR` match if either does. Double quotes keep words together and make
+//! them plain text, e.g. `from:"Jane Doe"` or `"OR"`.
+
+use crate::models::Email;
+
+/// Represents a single condition of a search query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchTerm {
+    /// The text appears in the headers or body
+    Text(String),
+    /// The text appears in the sender
+    From(String),
+    /// The text appears in the recipients
+    To(String),
+    /// The text appears in the subject
+    Subject(String),
+    /// The email is flagged
+    Flagged,
+    /// The email hasn't been read
+    Unread,
+    /// The email has been read
+    Read,
+    /// Either term matches
+    Or(Box<SearchTerm>, Box<SearchTerm>),
+}
+
+impl SearchTerm {
+    /// Parses a single word of a query.
+    ///
+    /// # Parameters
+    /// - `word`: The word, with quotes removed
+    /// - `quoted`: Whether the word was quoted, which makes it plain text
+    ///
+    /// # Returns
+    /// The term the word stands for
+    fn parse(word: String, quoted: bool) -> Self {
+        if quoted {
+            return SearchTerm::Text(word);
+        }
+
+        match word.to_lowercase().as_str() {
+            "flagged" | "is:flagged" => return SearchTerm::Flagged,
+            "unread" | "is:unread" => return SearchTerm::Unread,
+            "is:read" => return SearchTerm::Read,
+            _ => {},
+        }
+
+        match word.split_once(':') {
+            Some((key, value)) if !value.is_empty() => match key.to_lowercase().as_str() {
+                "from" => SearchTerm::From(value.to_string()),
+                "to" => SearchTerm::To(value.to_string()),
+                "subject" => SearchTerm::Subject(value.to_string()),
+                _ => SearchTerm::Text(word),
+            },
+            _ => SearchTerm::Text(word),
+        }
+    }
+
+    /// Builds the IMAP SEARCH criteria for the term.
+    ///
+    /// # Returns
+    /// The criteria, with texts as quoted strings
+    fn to_imap_criteria(&self) -> String {
+        match self {
+            SearchTerm::Text(text) => format!("TEXT {}", quote(text)),
+            SearchTerm::From(text) => format!("FROM {}", quote(text)),
+            SearchTerm::To(text) => format!("TO {}", quote(text)),
+            SearchTerm::Subject(text) => format!("SUBJECT {}", quote(text)),
+            SearchTerm::Flagged => "FLAGGED".to_string(),
+            SearchTerm::Unread => "UNSEEN".to_string(),
+            SearchTerm::Read => "SEEN".to_string(),
+            SearchTerm::Or(a, b) => format!("OR ({}) ({})", a.to_imap_criteria(), b.to_imap_criteria()),
+        }
+    }
+
+    /// Checks if an email matches the term, ignoring case.
+    ///
+    /// # Parameters
+    /// - `email`: The email
+    ///
+    /// # Returns
+    /// true if the email matches, false otherwise
+    fn matches(&self, email: &Email) -> bool {
+        match self {
+            SearchTerm::Text(text) => {
+                let contains = |field: &str| contains_ignore_case(field, text);
+                contains(&email.subject)
+                    || contains(&email.from)
+                    || email.from_name.as_deref().is_some_and(contains)
+                    || email.to.iter().any(|to| contains(to))
+                    || email.body_text.as_deref().is_some_and(contains)
+                    || email.body_html.as_deref().is_some_and(contains)
+            },
+            SearchTerm::From(text) => {
+                contains_ignore_case(&email.from, text)
+                    || email.from_name.as_deref().is_some_and(|name| contains_ignore_case(name, text))
+            },
+            SearchTerm::To(text) => email.to.iter().chain(&email.cc).any(|to| contains_ignore_case(to, text)),
+            SearchTerm::Subject(text) => contains_ignore_case(&email.subject, text),
+            SearchTerm::Flagged => email.is_flagged,
+            SearchTerm::Unread => !email.is_read,
+            SearchTerm::Read => email.is_read,
+            SearchTerm::Or(a, b) => a.matches(email) || b.matches(email),
+        }
+    }
+}
+
+/// Represents a parsed search query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// The terms, all of which must match
+    pub terms: Vec<SearchTerm>,
+}
+
+impl SearchQuery {
+    /// Parses a query as typed by the user.
+    ///
+    /// `OR` only joins terms when written in capitals; anywhere it can't
+    /// join two terms it is searched for as text.
+    ///
+    /// # Parameters
+    /// - `query`: The query text
+    ///
+    /// # Returns
+    /// The parsed query
+    pub fn parse(query: &str) -> Self {
+        let mut terms: Vec<SearchTerm> = Vec::new();
+        let mut pending_or = false;
+
+        for (word, quoted) in split_words(query) {
+            if !quoted && word == "OR" {
+                if terms.is_empty() || pending_or {
+                    terms.push(SearchTerm::Text(word));
+                } else {
+                    pending_or = true;
+                }
+                continue;
+            }
+
+            let term = SearchTerm::parse(word, quoted);
+            match terms.pop() {
+                Some(previous) if pending_or => terms.push(SearchTerm::Or(Box::new(previous), Box::new(term))),
+                previous => {
+                    terms.extend(previous);
+                    terms.push(term);
+                },
+            }
+            pending_or = false;
+        }
+
+        // A trailing OR has nothing to join
+        if pending_or {
+            terms.push(SearchTerm::Text("OR".to_string()));
+        }
+
+        Self { terms }
+    }
+
+    /// Builds the IMAP SEARCH criteria for the query.
+    ///
+    /// # Returns
+    /// The criteria, declaring UTF-8 if any text isn't ASCII; ALL for an empty query
+    pub fn to_imap_criteria(&self) -> String {
+        if self.terms.is_empty() {
+            return "ALL".to_string();
+        }
+
+        let criteria = self.terms.iter()
+            .map(SearchTerm::to_imap_criteria)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if criteria.is_ascii() {
+            criteria
+        } else {
+            format!("CHARSET UTF-8 {}", criteria)
+        }
+    }
+
+    /// Checks if an email matches every term of the query.
+    ///
+    /// # Parameters
+    /// - `email`: The email
+    ///
+    /// # Returns
+    /// true if the email matches, false otherwise
+    pub fn matches(&self, email: &Email) -> bool {
+        self.terms.iter().all(|term| term.matches(email))
+    }
+}
+
+/// Splits a query into words, keeping quoted text together.
+///
+/// # Parameters
+/// - `query`: The query text
+///
+/// # Returns
+/// The words without quotes, each with whether it started with a quote
+fn split_words(query: &str) -> Vec<(String, bool)> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut started = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                if !started {
+                    quoted = true;
+                }
+                started = true;
+                in_quotes = !in_quotes;
+            },
+            c if c.is_whitespace() && !in_quotes => {
+                if started {
+                    words.push((std::mem::take(&mut word), quoted));
+                }
+                quoted = false;
+                started = false;
+            },
+            c => {
+                started = true;
+                word.push(c);
+            },
+        }
+    }
+
+    if started {
+        words.push((word, quoted));
+    }
+
+    words
+}
+
+/// Quotes a text for an IMAP command.
+///
+/// # Parameters
+/// - `text`: The text
+///
+/// # Returns
+/// The text in double quotes, with quotes and backslashes escaped
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Checks if a text contains another, ignoring case.
+///
+/// # Parameters
+/// - `text`: The text to look in
+/// - `needle`: The text to look for
+///
+/// # Returns
+/// true if the text contains the needle, false otherwise
+fn contains_ignore_case(text: &str, needle: &str) -> bool {
+    text.to_lowercase().contains(&needle.to_lowercase())
+}
@@ -0,0 +1,28 @@
+//! Results of listing emails for the Linksy email client.
+//!
+//! A fetch that is interrupted, e.g. by a dropped connection, keeps the
+//! messages it got before the interruption instead of failing as a whole.
+
+use crate::models::Email;
+
+/// Represents the emails fetched from a server, which may be incomplete.
+#[derive(Debug, Default)]
+pub struct FetchedEmails {
+    /// The emails fetched, newest first
+    pub emails: Vec<Email>,
+    /// Why the fetch stopped early, or None if it completed
+    pub interrupted: Option<String>,
+    /// Number of unread emails that weren't cached before, or 0 the first time a folder is fetched
+    pub new_unread: usize,
+    /// The mailbox's UIDVALIDITY, if the server sent one
+    pub uid_validity: Option<u32>,
+}
+
+/// Represents the emails a search found.
+#[derive(Debug, Default)]
+pub struct SearchResults {
+    /// The matching emails of all folders searched, newest first
+    pub emails: Vec<Email>,
+    /// Why the server refused the search, if the cache was searched instead for some folders
+    pub rejected: Option<String>,
+}
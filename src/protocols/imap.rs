@@ -1,19 +1,265 @@
 //! IMAP protocol implementation for the Linksy email client.
 
-use crate::config::ServerConfig;
-use crate::models::{Account, ConnectionStatus, Email};
+use crate::config::{ProxyConfig, ServerConfig};
+use crate::protocols::{proxy, utf7, FetchedEmails};
+use crate::models::{Account, ConnectionStatus, Email, MimePart, SearchQuery};
 use anyhow::{anyhow, Result};
-use imap::types::{Flag};
-use log::{debug, error};
+use imap::types::{Flag, Mailbox, Names};
+use imap_proto::types::{BodyStructure, MessageSection, NameAttribute, SectionPath};
+use log::{debug, error, warn};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// Normalizes a mailbox name before it is sent to the server.
+///
+/// INBOX is case-insensitive in IMAP, but some servers only accept the
+/// uppercase spelling, so any casing of it maps to "INBOX". Other mailbox
+/// names are case-sensitive and are kept exactly as they are, to be encoded
+/// in modified UTF-7 when a command is sent.
+///
+/// # Parameters
+/// - `name`: The mailbox name
+///
+/// # Returns
+/// The name to use with the server
+pub fn normalize_mailbox_name(name: &str) -> &str {
+    if name.eq_ignore_ascii_case("INBOX") {
+        "INBOX"
+    } else {
+        name
+    }
+}
+
+/// A command the server answered with NO or BAD.
+///
+/// Servers differ in what they support, e.g. in search criteria, so a
+/// refused SEARCH or FETCH says what was refused, and a search can tell
+/// it should look in the cache instead.
+#[derive(Debug, thiserror::Error)]
+#[error("Server rejected the {command} ({reason})")]
+pub struct CommandRejected {
+    /// What was asked for, e.g. "search query"
+    pub command: &'static str,
+    /// The text the server gave with its answer
+    pub reason: String,
+}
+
+/// Turns a NO or BAD answer to a command into a CommandRejected error.
+///
+/// # Parameters
+/// - `command`: What was asked for, to tell the user
+///
+/// # Returns
+/// A function converting the command's error; other errors are kept as they are
+fn rejected(command: &'static str) -> impl FnOnce(imap::Error) -> anyhow::Error {
+    move |e| match e {
+        imap::Error::No(no) => CommandRejected { command, reason: no.information }.into(),
+        imap::Error::Bad(bad) => CommandRejected { command, reason: bad.information }.into(),
+        e => e.into(),
+    }
+}
+
+/// How a mailbox is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MailboxAccess {
+    /// Opened with EXAMINE, to read messages without changing them
+    ReadOnly,
+    /// Opened with SELECT, to change the messages' flags or delete them
+    ReadWrite,
+    /// Opened with SELECT where the server allows it, and read-only otherwise
+    PreferReadWrite,
+}
+
+/// Opens a mailbox on the server.
+///
+/// Reading uses EXAMINE, which works on folders the user may only read,
+/// such as shared or public ones, and leaves the \Recent flags of new
+/// messages for other clients. SELECT is only used when messages are
+/// changed, and fails if the server opens the mailbox read-only. Every
+/// mailbox is opened here, so INBOX is always sent the way strict servers
+/// expect it.
+///
+/// # Parameters
+/// - `session`: The IMAP session
+/// - `mailbox`: The mailbox name
+/// - `access`: Whether the mailbox's messages are changed
+///
+/// # Returns
+/// A Result containing the mailbox's status, or an error if it couldn't be
+/// opened or is read-only when it's opened to change its messages
+fn open_mailbox<T: std::io::Read + std::io::Write>(
+    session: &mut imap::Session<T>,
+    mailbox: &str,
+    access: MailboxAccess,
+) -> Result<Mailbox> {
+    let mailbox = normalize_mailbox_name(mailbox);
+    match access {
+        MailboxAccess::ReadOnly => Ok(session.examine(utf7::encode(mailbox))?),
+        MailboxAccess::ReadWrite => {
+            let mailbox_data = session.select(utf7::encode(mailbox))?;
+            if mailbox_data.is_read_only {
+                return Err(anyhow!("The folder {} is read-only", mailbox));
+            }
+            Ok(mailbox_data)
+        },
+        MailboxAccess::PreferReadWrite => Ok(session.select(utf7::encode(mailbox))?),
+    }
+}
+
+/// Names commonly used for the Trash folder by servers without special-use attributes.
+const TRASH_FOLDER_NAMES: [&str; 4] = ["Trash", "Deleted Items", "Deleted Messages", "Deleted"];
+
+/// Names commonly used for the Sent folder by servers without special-use attributes.
+const SENT_FOLDER_NAMES: [&str; 4] = ["Sent", "Sent Items", "Sent Messages", "Sent Mail"];
+
+/// Finds a special folder, such as Trash or Sent, among the listed mailboxes.
+///
+/// The folder the server marks with the special-use attribute is
+/// preferred; otherwise a folder with a common name is used, at the top
+/// level or under another folder such as "[Gmail]/Trash".
+///
+/// # Parameters
+/// - `mailboxes`: The mailboxes listed by the server
+/// - `special_use`: The attribute the server marks the folder with
+/// - `names`: The common names of the folder
+///
+/// # Returns
+/// The name of the folder, or None if there isn't one
+fn find_special_folder(mailboxes: &Names, special_use: NameAttribute, names: &[&str]) -> Option<String> {
+    let marked = mailboxes.iter()
+        .find(|m| m.attributes().contains(&special_use));
+    let by_name = || mailboxes.iter().find(|m| {
+        let name = utf7::decode(m.name());
+        let leaf = match m.delimiter() {
+            Some(delimiter) => name.rsplit(delimiter).next().unwrap_or(&name),
+            None => &name,
+        };
+        names.iter().any(|common| leaf.eq_ignore_ascii_case(common))
+    });
+    
+    marked.or_else(by_name).map(|m| utf7::decode(m.name()))
+}
+
+/// Items fetched to list emails without downloading their bodies.
+const LIST_FETCH_ITEMS: &str = "(BODY.PEEK[HEADER] BODYSTRUCTURE RFC822.SIZE FLAGS UID)";
+
+/// Number of messages whose headers are fetched with one command.
+const FETCH_BATCH_SIZE: usize = 10;
+
+/// Keywords that servers and clients set for their own bookkeeping, which
+/// aren't shown as tags.
+const HIDDEN_KEYWORDS: [&str; 9] = [
+    "$Forwarded", "$MDNSent", "$Junk", "$NotJunk", "$Phishing", "$SubmitPending", "$Submitted", "Junk", "NonJunk",
+];
+
+/// Lists the keywords among a message's flags.
+///
+/// # Parameters
+/// - `flags`: The flags of the message
+///
+/// # Returns
+/// The keywords shown as tags, without system flags
+fn tag_keywords(flags: &[Flag]) -> Vec<String> {
+    flags.iter()
+        .filter_map(|flag| match flag {
+            Flag::Custom(name) if !name.starts_with('\\') => Some(name.to_string()),
+            _ => None,
+        })
+        .filter(|name| !HIDDEN_KEYWORDS.iter().any(|hidden| hidden.eq_ignore_ascii_case(name)))
+        .collect()
+}
+
+/// Parses a message's body structure into its parts.
+///
+/// # Parameters
+/// - `structure`: The body structure, or one of its parts
+/// - `section`: The IMAP section of the part, empty for the message itself
+///
+/// # Returns
+/// The part, with the parts of a multipart numbered below its section
+fn parse_structure(structure: &BodyStructure, section: &str) -> MimePart {
+    let (common, other) = match structure {
+        BodyStructure::Multipart { common, bodies, .. } => {
+            let parts = bodies.iter()
+                .enumerate()
+                .map(|(i, body)| match section {
+                    "" => parse_structure(body, &(i + 1).to_string()),
+                    section => parse_structure(body, &format!("{}.{}", section, i + 1)),
+                })
+                .collect();
+            return MimePart {
+                section: section.to_string(),
+                content_type: format!("multipart/{}", common.ty.subtype).to_lowercase(),
+                parts,
+                ..MimePart::default()
+            };
+        },
+        BodyStructure::Basic { common, other, .. }
+        | BodyStructure::Text { common, other, .. }
+        | BodyStructure::Message { common, other, .. } => (common, other),
+    };
+    
+    // The file name is in the disposition, or in the content type for older mailers
+    let param = |params: &Option<Vec<(std::borrow::Cow<str>, std::borrow::Cow<str>)>>, key: &str| {
+        params.as_ref()?.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.to_string())
+    };
+    let filename = common.disposition.as_ref()
+        .and_then(|disposition| param(&disposition.params, "filename"))
+        .or_else(|| param(&common.ty.params, "name"));
+    
+    MimePart {
+        // The body of a message that isn't a multipart is its first part
+        section: if section.is_empty() { "1".to_string() } else { section.to_string() },
+        content_type: format!("{}/{}", common.ty.ty, common.ty.subtype).to_lowercase(),
+        size: other.octets as usize,
+        filename,
+        content_id: other.id.as_ref().map(|id| id.trim_matches(|c| c == '<' || c == '>').to_string()),
+        attached: common.disposition.as_ref().is_some_and(|disposition| disposition.ty.eq_ignore_ascii_case("attachment")),
+        parts: Vec::new(),
+    }
+}
+
+/// Parses a section such as "1.2" into the part numbers of a fetch response.
+///
+/// # Parameters
+/// - `section`: The section
+///
+/// # Returns
+/// The part numbers, or None if the section isn't made of numbers
+fn section_numbers(section: &str) -> Option<Vec<u32>> {
+    section.split('.').map(|number| number.parse().ok()).collect()
+}
+
+/// An IMAP session over TLS.
+type ImapSession = imap::Session<native_tls::TlsStream<std::net::TcpStream>>;
+
+/// What listing a mailbox found, before the messages are parsed.
+struct MailboxListing {
+    /// Number of messages in the mailbox
+    exists: usize,
+    /// Number of unread messages, or None if the server wouldn't count them
+    unread: Option<usize>,
+    /// The mailbox's UIDVALIDITY, if the server sent one
+    uid_validity: Option<u32>,
+    /// Whether the UIDVALIDITY changed since the cached messages were fetched
+    renumbered: bool,
+    /// UID, read state, flagged state and keywords of the listed messages, newest first
+    listed: Vec<(u32, bool, bool, Vec<String>)>,
+    /// The headers of the messages that weren't cached, in batches
+    batches: Vec<imap::types::Fetches>,
+    /// Why fetching the headers stopped early, or None if it completed
+    interrupted: Option<String>,
+}
+
 /// Represents an IMAP client connection.
 pub struct ImapClient {
     /// The account this client is connected to
     account: Arc<Mutex<Account>>,
     /// The IMAP session
-    session: Option<Arc<Mutex<imap::Session<native_tls::TlsStream<std::net::TcpStream>>>>>,
+    session: Option<Arc<Mutex<ImapSession>>>,
 }
 
 impl ImapClient {
@@ -44,6 +290,7 @@ impl ImapClient {
         
         // Clone the config for later use
         let imap_config = imap_config.clone();
+        let proxy = account.config.proxy.clone();
         
         // Update connection status
         let mut account = account;
@@ -51,7 +298,7 @@ impl ImapClient {
         drop(account); // Release the lock
         
         // Connect to the server
-        let client = self.create_client(&imap_config).await?;
+        let client = self.create_client(&imap_config, proxy.as_ref()).await?;
         
         // Store the session
         self.session = Some(Arc::new(Mutex::new(client)));
@@ -68,32 +315,45 @@ impl ImapClient {
     ///
     /// # Parameters
     /// - `config`: The server configuration
+    /// - `proxy`: The proxy to connect through, if any
     ///
     /// # Returns
     /// A Result containing the IMAP session or an error
-    async fn create_client(&self, config: &ServerConfig) -> Result<imap::Session<native_tls::TlsStream<std::net::TcpStream>>> {
-        // Create TLS connector
-        let tls = native_tls::TlsConnector::builder().build()?;
-        
-        // Connect to the server using TLS
-        let tls_stream = tls.connect(&config.host, std::net::TcpStream::connect(format!("{}:{}", config.host, config.port))?)?;
-        
-        // Create a new client with the TLS stream
-        let client = imap::Client::new(tls_stream);
-        
-        // Login to the server
-        let mut imap_session = client.login(&config.username, &config.password)
-            .map_err(|e| anyhow!("Login failed: {:?}", e))?;
-        
-        // List available mailboxes
-        let mailboxes = imap_session.list(None, Some("*"))?;
+    async fn create_client(&self, config: &ServerConfig, proxy: Option<&ProxyConfig>) -> Result<ImapSession> {
+        let config = config.clone();
+        let proxy = proxy.cloned();
+        
+        // Connecting, logging in and listing block on the socket, so they run off the runtime
+        let (imap_session, mailboxes) = tokio::task::spawn_blocking(move || -> Result<(ImapSession, Names)> {
+            // Create TLS connector
+            let tls = native_tls::TlsConnector::builder().build()?;
+            
+            // Connect to the server using TLS
+            let (host, port) = proxy::split_host_port(&config.host, config.port)?;
+            let tcp_stream = proxy::connect_std(proxy.as_ref(), &host, port)?;
+            let tls_stream = tls.connect(&host, tcp_stream)?;
+            
+            // Create a new client with the TLS stream
+            let client = imap::Client::new(tls_stream);
+            
+            // Login to the server
+            let mut imap_session = client.login(&config.username, &config.password)
+                .map_err(|e| anyhow!("Login failed: {:?}", e))?;
+            
+            // List available mailboxes
+            let mailboxes = imap_session.list(None, Some("*"))?;
+            
+            Ok((imap_session, mailboxes))
+        }).await??;
         
-        // Update account with available folders
+        // Update account with available folders, decoding international names
         let mut account = self.account.lock().await;
         account.folders = mailboxes.iter()
-            .filter_map(|m| Some(m.name()))
-            .map(|n| n.to_string())
+            .map(|m| utf7::decode(m.name()))
+            .map(|n| normalize_mailbox_name(&n).to_string())
             .collect();
+        account.trash_folder = find_special_folder(&mailboxes, NameAttribute::Trash, &TRASH_FOLDER_NAMES);
+        account.sent_folder = find_special_folder(&mailboxes, NameAttribute::Sent, &SENT_FOLDER_NAMES);
             
         // Return the session
         Ok(imap_session)
@@ -104,73 +364,374 @@ impl ImapClient {
     /// # Returns
     /// A Result indicating success or failure
     pub async fn disconnect(&mut self) -> Result<()> {
-        if let Some(session) = &self.session {
-            let mut session = session.lock().await;
-            session.logout()?;
-        }
+        let session = self.session.take();
         
-        self.session = None;
+        {
+            let mut account = self.account.lock().await;
+            account.imap_status = ConnectionStatus::Disconnected;
+        }
         
-        let mut account = self.account.lock().await;
-        account.imap_status = ConnectionStatus::Disconnected;
+        if let Some(session) = session {
+            // LOGOUT blocks on the socket, so it runs on its own thread where an
+            // unresponsive server can't stall the runtime or keep the process alive
+            let (sender, receiver) = tokio::sync::oneshot::channel();
+            std::thread::spawn(move || {
+                let result = session.blocking_lock().logout();
+                let _ = sender.send(result);
+            });
+            
+            receiver.await.map_err(|_| anyhow!("IMAP logout thread stopped"))??;
+        }
         
         Ok(())
     }
     
+    /// Runs commands on the IMAP session on the blocking pool.
+    ///
+    /// The imap crate blocks on its socket, so the commands run off the
+    /// runtime's worker threads, where a slow server would hold up key
+    /// handling and every other account.
+    ///
+    /// # Parameters
+    /// - `commands`: The commands to run
+    ///
+    /// # Returns
+    /// A Result containing what the commands returned, or an error if not connected
+    async fn with_session<T, F>(&self, commands: F) -> Result<T>
+    where
+        F: FnOnce(&mut ImapSession) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let session = self.session.clone()
+            .ok_or_else(|| anyhow!("Not connected to IMAP server"))?;
+        let mut session = session.lock_owned().await;
+        
+        tokio::task::spawn_blocking(move || commands(&mut session)).await?
+    }
+    
     /// Fetches emails from the specified mailbox.
     ///
+    /// Only the headers and body structure are fetched, so the emails have
+    /// their attachment names and sizes but no body; fetch_body downloads the
+    /// rest when an email is opened. Cached emails are only refreshed with
+    /// their current flags, so a fetch that was interrupted resumes with the
+    /// messages it didn't get to. If the mailbox's UIDVALIDITY changed, its
+    /// UIDs point at other messages now, so nothing cached is reused. Headers
+    /// are fetched in batches, and if a batch fails the emails fetched before
+    /// it are still returned.
+    ///
     /// # Parameters
     /// - `mailbox`: The mailbox to fetch emails from
     /// - `limit`: Maximum number of emails to fetch
+    /// - `cached`: The emails of the mailbox already in storage
+    /// - `uid_validity`: The UIDVALIDITY the cached emails were fetched with, if known
     ///
     /// # Returns
-    /// A Result containing a vector of emails or an error
-    pub async fn fetch_emails(&self, mailbox: &str, limit: usize) -> Result<Vec<Email>> {
-        let session_arc = self.session.as_ref()
-            .ok_or_else(|| anyhow!("Not connected to IMAP server"))?;
-            
-        let mut session = session_arc.lock().await;
+    /// A Result containing the fetched emails, or an error if the mailbox couldn't be opened
+    pub async fn fetch_emails(&self, mailbox: &str, limit: usize, cached: &[Email], uid_validity: Option<u32>) -> Result<FetchedEmails> {
+        let mut cached_ids: HashSet<String> = cached.iter().map(|email| email.id.clone()).collect();
+        let name = mailbox.to_string();
         
-        // Select the mailbox
-        let mailbox_data = session.select(mailbox)?;
-        debug!("Selected mailbox: {} with {} messages", mailbox, mailbox_data.exists);
+        let listing = self.with_session(move |session| {
+            let mailbox = name.as_str();
+            
+            // Open the mailbox read-only
+            let mailbox_data = open_mailbox(session, mailbox, MailboxAccess::ReadOnly)?;
+            debug!("Examined mailbox: {} with {} messages", mailbox, mailbox_data.exists);
+            
+            // The server renumbered the mailbox, so the cached UIDs mean other messages now
+            let renumbered = uid_validity.zip(mailbox_data.uid_validity).is_some_and(|(cached, current)| cached != current);
+            if renumbered {
+                warn!("The UIDVALIDITY of {} changed, so its cached emails are fetched again", mailbox);
+                cached_ids.clear();
+            }
+            
+            // Count unread messages, keeping the last count if the server won't
+            let unread = match session.search("UNSEEN").map_err(rejected("search for unread messages")) {
+                Ok(unseen) => Some(unseen.len()),
+                Err(e) if e.is::<CommandRejected>() => {
+                    warn!("Couldn't count the unread messages of {}: {}", mailbox, e);
+                    None
+                },
+                Err(e) => return Err(e),
+            };
+            
+            let mut listing = MailboxListing {
+                exists: mailbox_data.exists as usize,
+                unread,
+                uid_validity: mailbox_data.uid_validity,
+                renumbered,
+                listed: Vec::new(),
+                batches: Vec::new(),
+                interrupted: None,
+            };
+            if mailbox_data.exists == 0 {
+                return Ok(listing);
+            }
+            
+            // List the UIDs and flags of the most recent messages, newest first
+            let sequence = if mailbox_data.exists > limit as u32 {
+                format!("{}:{}", mailbox_data.exists - limit as u32 + 1, mailbox_data.exists)
+            } else {
+                "1:*".to_string()
+            };
+            
+            listing.listed = session.fetch(sequence, "(UID FLAGS)").map_err(rejected("request for the message list"))?
+                .iter()
+                .filter_map(|message| {
+                    let flags = message.flags();
+                    message.uid.map(|uid| (uid, flags.contains(&Flag::Seen), flags.contains(&Flag::Flagged), tag_keywords(flags)))
+                })
+                .collect();
+            listing.listed.sort_unstable_by_key(|(uid, _, _, _)| std::cmp::Reverse(*uid));
+            
+            // Fetch the headers of the messages that aren't cached yet
+            let missing: Vec<String> = listing.listed.iter()
+                .map(|(uid, _, _, _)| uid.to_string())
+                .filter(|uid| !cached_ids.contains(uid))
+                .collect();
+            
+            for batch in missing.chunks(FETCH_BATCH_SIZE) {
+                match session.uid_fetch(batch.join(","), LIST_FETCH_ITEMS).map_err(rejected("request for the new messages")) {
+                    Ok(messages) => listing.batches.push(messages),
+                    Err(e) => {
+                        warn!("Fetching {} was interrupted after {} batches of new messages: {}", mailbox, listing.batches.len(), e);
+                        listing.interrupted = Some(e.to_string());
+                        break;
+                    },
+                }
+            }
+            
+            Ok(listing)
+        }).await?;
         
         // Update account with message counts
-        let mut account = self.account.lock().await;
-        account.total_count = mailbox_data.exists as usize;
+        {
+            let mut account = self.account.lock().await;
+            account.total_count = listing.exists;
+            if let Some(unread) = listing.unread {
+                account.unread_count = unread;
+            }
+        }
         
-        // Count unread messages
-        let unseen = session.search("UNSEEN")?;
-        account.unread_count = unseen.len();
-        drop(account); // Release the lock
+        let mut fetched = HashMap::new();
+        for messages in &listing.batches {
+            for email in self.parse_messages(messages, mailbox).await {
+                fetched.insert(email.id.clone(), email);
+            }
+        }
         
-        // Fetch the most recent messages
-        let sequence = if mailbox_data.exists > limit as u32 {
-            format!("{}:{}", mailbox_data.exists - limit as u32 + 1, mailbox_data.exists)
-        } else {
-            "1:*".to_string()
-        };
+        // List cached and new emails in the server's order, with their current flags
+        let cached: HashMap<&str, &Email> = cached.iter()
+            .filter(|_| !listing.renumbered)
+            .map(|email| (email.id.as_str(), email))
+            .collect();
+        let emails = listing.listed.into_iter()
+            .filter_map(|(uid, seen, flagged, keywords)| {
+                let uid = uid.to_string();
+                let mut email = fetched.remove(&uid).or_else(|| cached.get(uid.as_str()).map(|email| (*email).clone()))?;
+                email.is_read = seen;
+                email.is_flagged = flagged;
+                email.keywords = keywords;
+                Some(email)
+            })
+            .collect();
         
-        let messages = session.fetch(sequence, "(RFC822 FLAGS UID)")?;
+        Ok(FetchedEmails {
+            emails,
+            interrupted: listing.interrupted,
+            uid_validity: listing.uid_validity,
+            ..FetchedEmails::default()
+        })
+    }
+    
+    /// Fetches the message of an email whose headers were listed.
+    ///
+    /// Messages larger than the limit are cut off after that many bytes and
+    /// marked as truncated.
+    ///
+    /// # Parameters
+    /// - `mailbox`: The mailbox containing the email
+    /// - `email_id`: The UID of the email
+    /// - `max_bytes`: How much of the message to fetch, or None for all of it
+    ///
+    /// # Returns
+    /// A Result containing the email with its body and attachments, or an error
+    pub async fn fetch_body(&self, mailbox: &str, email_id: &str, max_bytes: Option<usize>) -> Result<Email> {
+        let (name, uid) = (mailbox.to_string(), email_id.to_string());
+        let messages = self.with_session(move |session| {
+            // Open the mailbox read-only; BODY.PEEK leaves the read state alone
+            open_mailbox(session, &name, MailboxAccess::ReadOnly)?;
+            
+            // Only ask for the start of the message if there is a limit
+            let query = match max_bytes {
+                Some(max_bytes) => format!("(BODY.PEEK[]<0.{}> RFC822.SIZE FLAGS UID)", max_bytes),
+                None => "(BODY.PEEK[] RFC822.SIZE FLAGS UID)".to_string(),
+            };
+            session.uid_fetch(&uid, query).map_err(rejected("request for the message"))
+        }).await?;
+        
+        let mut email = self.parse_messages(&messages, mailbox).await
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Message {} not found in {}", email_id, mailbox))?;
+        
+        email.truncated = max_bytes.zip(email.size).is_some_and(|(max_bytes, size)| size > max_bytes);
+        Ok(email)
+    }
+    
+    /// Fetches a message exactly as the server keeps it.
+    ///
+    /// # Parameters
+    /// - `mailbox`: The mailbox containing the email
+    /// - `email_id`: The UID of the email
+    ///
+    /// # Returns
+    /// A Result containing the raw message, or an error
+    pub async fn fetch_raw(&self, mailbox: &str, email_id: &str) -> Result<Vec<u8>> {
+        let (name, uid) = (mailbox.to_string(), email_id.to_string());
+        self.with_session(move |session| {
+            // Open the mailbox read-only; BODY.PEEK leaves the read state alone
+            open_mailbox(session, &name, MailboxAccess::ReadOnly)?;
+            
+            let messages = session.uid_fetch(&uid, "(BODY.PEEK[] UID)").map_err(rejected("request for the original message"))?;
+            messages.iter()
+                .next()
+                .and_then(|message| message.body())
+                .map(|body| body.to_vec())
+                .ok_or_else(|| anyhow!("Message {} not found in {}", uid, name))
+        }).await
+    }
+    
+    /// Fetches parts of a message by their sections.
+    ///
+    /// Each part comes with its MIME headers, so it can be decoded on its
+    /// own. This downloads the text of a message without its attachments,
+    /// or a single attachment when it's opened.
+    ///
+    /// # Parameters
+    /// - `mailbox`: The mailbox containing the email
+    /// - `email_id`: The UID of the email
+    /// - `sections`: The sections of the parts, e.g. "1.2"
+    ///
+    /// # Returns
+    /// A Result containing the headers and body of each part, in the order
+    /// of the sections, or an error
+    pub async fn fetch_parts(&self, mailbox: &str, email_id: &str, sections: &[String]) -> Result<Vec<Vec<u8>>> {
+        let (name, uid, sections) = (mailbox.to_string(), email_id.to_string(), sections.to_vec());
+        self.with_session(move |session| {
+            // Open the mailbox read-only; BODY.PEEK leaves the read state alone
+            open_mailbox(session, &name, MailboxAccess::ReadOnly)?;
+            
+            // Ask for the MIME headers and the body of every part at once
+            let items: Vec<String> = sections.iter()
+                .map(|section| format!("BODY.PEEK[{0}.MIME] BODY.PEEK[{0}]", section))
+                .collect();
+            let messages = session.uid_fetch(&uid, format!("({} UID)", items.join(" "))).map_err(rejected("request for the message parts"))?;
+            let message = messages.iter()
+                .next()
+                .ok_or_else(|| anyhow!("Message {} not found in {}", uid, name))?;
+            
+            sections.iter()
+                .map(|section| {
+                    let numbers = section_numbers(section)
+                        .ok_or_else(|| anyhow!("Invalid section {}", section))?;
+                    let headers = message.section(&SectionPath::Part(numbers.clone(), Some(MessageSection::Mime)));
+                    let body = message.section(&SectionPath::Part(numbers, None));
+                    match (headers, body) {
+                        (Some(headers), Some(body)) => Ok([headers, body].concat()),
+                        _ => Err(anyhow!("The server didn't send part {} of message {}", section, uid)),
+                    }
+                })
+                .collect()
+        }).await
+    }
+    
+    /// Searches a mailbox on the server for emails matching a query.
+    ///
+    /// # Parameters
+    /// - `mailbox`: The mailbox to search
+    /// - `query`: The query to match
+    /// - `limit`: Maximum number of matching emails to fetch, newest first
+    ///
+    /// # Returns
+    /// A Result containing the matching emails or an error
+    pub async fn search_emails(&self, mailbox: &str, query: &SearchQuery, limit: usize) -> Result<Vec<Email>> {
+        let (name, criteria) = (mailbox.to_string(), query.to_imap_criteria());
+        let messages = self.with_session(move |session| {
+            // Open the mailbox read-only
+            open_mailbox(session, &name, MailboxAccess::ReadOnly)?;
+            
+            // Keep the newest matches; UIDs grow as messages arrive
+            let mut uids: Vec<u32> = session.uid_search(criteria)
+                .map_err(rejected("search query"))?
+                .into_iter()
+                .collect();
+            if uids.is_empty() {
+                return Ok(None);
+            }
+            uids.sort_unstable_by(|a, b| b.cmp(a));
+            uids.truncate(limit);
+            
+            let uid_set = uids.iter().map(|uid| uid.to_string()).collect::<Vec<_>>().join(",");
+            session.uid_fetch(uid_set, LIST_FETCH_ITEMS).map_err(rejected("request for the matching messages")).map(Some)
+        }).await?;
         
+        match messages {
+            Some(messages) => Ok(self.parse_messages(&messages, mailbox).await),
+            None => Ok(Vec::new()),
+        }
+    }
+    
+    /// Parses fetched messages into emails.
+    ///
+    /// Messages fetched with only their headers become headers-only emails
+    /// with their parts and attachments listed from the body structure.
+    ///
+    /// # Parameters
+    /// - `messages`: The messages fetched with the full message or the headers, RFC822.SIZE, FLAGS and UID
+    /// - `mailbox`: The mailbox the messages are in
+    ///
+    /// # Returns
+    /// The emails, newest first; messages that fail to parse are skipped
+    async fn parse_messages(&self, messages: &imap::types::Fetches, mailbox: &str) -> Vec<Email> {
         // Parse emails
         let mut emails = Vec::new();
         let account = self.account.lock().await;
         
         for message in messages.iter() {
-            if let Some(body) = message.body() {
-                match Email::parse_from_raw(body, &account.config.id, mailbox) {
+            let parsed = match (message.body(), message.header()) {
+                (Some(body), _) => Some(Email::parse_from_raw(body, &account.config.id, mailbox)),
+                (None, Some(header)) => Some(Email::parse_headers(header, &account.config.id, mailbox).map(|mut email| {
+                    if let Some(structure) = message.bodystructure() {
+                        let structure = parse_structure(structure, "");
+                        email.attachments = structure.attachments();
+                        email.structure = Some(structure);
+                    }
+                    email
+                })),
+                (None, None) => None,
+            };
+            
+            if let Some(parsed) = parsed {
+                match parsed {
                     Ok(mut email) => {
                         // Set flags
                         let flags = message.flags();
-                        email.is_read = flags.iter().any(|flag| *flag == Flag::Seen);
-                        email.is_flagged = flags.iter().any(|flag| *flag == Flag::Flagged);
+                        email.is_read = flags.contains(&Flag::Seen);
+                        email.is_flagged = flags.contains(&Flag::Flagged);
+                        email.keywords = tag_keywords(flags);
                         
                         // Set ID from UID
                         if let Some(uid) = message.uid {
                             email.id = uid.to_string();
                         }
                         
+                        // Prefer the size reported by the server
+                        if let Some(size) = message.size {
+                            email.size = Some(size as usize);
+                        }
+                        
                         emails.push(email);
                     },
                     Err(e) => {
@@ -181,9 +742,9 @@ impl ImapClient {
         }
         
         // Sort emails by date (newest first)
-        emails.sort_by(|a, b| b.date.cmp(&a.date));
+        emails.sort_by_key(|e| std::cmp::Reverse(e.date));
         
-        Ok(emails)
+        emails
     }
     
     /// Marks an email as read.
@@ -195,18 +756,15 @@ impl ImapClient {
     /// # Returns
     /// A Result indicating success or failure
     pub async fn mark_as_read(&self, mailbox: &str, email_id: &str) -> Result<()> {
-        let session_arc = self.session.as_ref()
-            .ok_or_else(|| anyhow!("Not connected to IMAP server"))?;
+        let (name, uid) = (mailbox.to_string(), email_id.to_string());
+        self.with_session(move |session| {
+            open_mailbox(session, &name, MailboxAccess::ReadWrite)?;
             
-        let mut session = session_arc.lock().await;
-        
-        // Select the mailbox
-        session.select(mailbox)?;
-        
-        // Mark the message as read
-        session.uid_store(email_id, "+FLAGS (\\Seen)")?;
-        
-        Ok(())
+            // Mark the message as read
+            session.uid_store(&uid, "+FLAGS (\\Seen)")?;
+            
+            Ok(())
+        }).await
     }
     
     /// Marks an email as unread.
@@ -219,18 +777,15 @@ impl ImapClient {
     /// A Result indicating success or failure
     #[allow(dead_code)]
     pub async fn mark_as_unread(&self, mailbox: &str, email_id: &str) -> Result<()> {
-        let session_arc = self.session.as_ref()
-            .ok_or_else(|| anyhow!("Not connected to IMAP server"))?;
+        let (name, uid) = (mailbox.to_string(), email_id.to_string());
+        self.with_session(move |session| {
+            open_mailbox(session, &name, MailboxAccess::ReadWrite)?;
             
-        let mut session = session_arc.lock().await;
-        
-        // Select the mailbox
-        session.select(mailbox)?;
-        
-        // Mark the message as unread
-        session.uid_store(email_id, "-FLAGS (\\Seen)")?;
-        
-        Ok(())
+            // Mark the message as unread
+            session.uid_store(&uid, "-FLAGS (\\Seen)")?;
+            
+            Ok(())
+        }).await
     }
     
     /// Flags an email.
@@ -243,18 +798,15 @@ impl ImapClient {
     /// A Result indicating success or failure
     #[allow(dead_code)]
     pub async fn flag_email(&self, mailbox: &str, email_id: &str) -> Result<()> {
-        let session_arc = self.session.as_ref()
-            .ok_or_else(|| anyhow!("Not connected to IMAP server"))?;
+        let (name, uid) = (mailbox.to_string(), email_id.to_string());
+        self.with_session(move |session| {
+            open_mailbox(session, &name, MailboxAccess::ReadWrite)?;
             
-        let mut session = session_arc.lock().await;
-        
-        // Select the mailbox
-        session.select(mailbox)?;
-        
-        // Flag the message
-        session.uid_store(email_id, "+FLAGS (\\Flagged)")?;
-        
-        Ok(())
+            // Flag the message
+            session.uid_store(&uid, "+FLAGS (\\Flagged)")?;
+            
+            Ok(())
+        }).await
     }
     
     /// Unflags an email.
@@ -267,18 +819,64 @@ impl ImapClient {
     /// A Result indicating success or failure
     #[allow(dead_code)]
     pub async fn unflag_email(&self, mailbox: &str, email_id: &str) -> Result<()> {
-        let session_arc = self.session.as_ref()
-            .ok_or_else(|| anyhow!("Not connected to IMAP server"))?;
+        let (name, uid) = (mailbox.to_string(), email_id.to_string());
+        self.with_session(move |session| {
+            open_mailbox(session, &name, MailboxAccess::ReadWrite)?;
             
-        let mut session = session_arc.lock().await;
-        
-        // Select the mailbox
-        session.select(mailbox)?;
-        
-        // Unflag the message
-        session.uid_store(email_id, "-FLAGS (\\Flagged)")?;
-        
-        Ok(())
+            // Unflag the message
+            session.uid_store(&uid, "-FLAGS (\\Flagged)")?;
+            
+            Ok(())
+        }).await
+    }
+    
+    /// Adds a keyword to an email or removes it.
+    ///
+    /// Servers list the keywords they keep in the mailbox's permanent flags,
+    /// with `\*` if new ones can be created. A keyword the mailbox doesn't
+    /// keep, that the server refuses to store, or in a read-only mailbox
+    /// isn't changed.
+    ///
+    /// # Parameters
+    /// - `mailbox`: The mailbox containing the email
+    /// - `email_id`: The ID of the email
+    /// - `keyword`: The keyword, e.g. "$Todo"
+    /// - `add`: true to add the keyword, false to remove it
+    ///
+    /// # Returns
+    /// A Result containing whether the server stored the change, or an error
+    /// if the server couldn't be reached
+    pub async fn store_keyword(&self, mailbox: &str, email_id: &str, keyword: &str, add: bool) -> Result<bool> {
+        let (name, uid, keyword) = (mailbox.to_string(), email_id.to_string(), keyword.to_string());
+        self.with_session(move |session| {
+            let mailbox_data = open_mailbox(session, &name, MailboxAccess::PreferReadWrite)?;
+            
+            // A folder the user may only read keeps the tag locally
+            if mailbox_data.is_read_only {
+                debug!("{} is read-only, so the keyword {} isn't stored", name, keyword);
+                return Ok(false);
+            }
+            let kept = mailbox_data.permanent_flags.iter().any(|flag| match flag {
+                Flag::MayCreate => true,
+                Flag::Custom(known) => known.eq_ignore_ascii_case(&keyword),
+                _ => false,
+            });
+            if !kept {
+                debug!("{} doesn't keep the keyword {}", name, keyword);
+                return Ok(false);
+            }
+            
+            // Change the keyword, treating a refusal as a server without keywords
+            let query = format!("{}FLAGS ({})", if add { "+" } else { "-" }, keyword);
+            match session.uid_store(&uid, query) {
+                Ok(_) => Ok(true),
+                Err(e @ (imap::Error::No(_) | imap::Error::Bad(_))) => {
+                    warn!("The server refused the keyword {}: {}", keyword, e);
+                    Ok(false)
+                },
+                Err(e) => Err(e.into()),
+            }
+        }).await
     }
     
     /// Deletes an email.
@@ -291,21 +889,41 @@ impl ImapClient {
     /// A Result indicating success or failure
     #[allow(dead_code)]
     pub async fn delete_email(&self, mailbox: &str, email_id: &str) -> Result<()> {
-        let session_arc = self.session.as_ref()
-            .ok_or_else(|| anyhow!("Not connected to IMAP server"))?;
+        let (name, uid) = (mailbox.to_string(), email_id.to_string());
+        self.with_session(move |session| {
+            open_mailbox(session, &name, MailboxAccess::ReadWrite)?;
             
-        let mut session = session_arc.lock().await;
-        
-        // Select the mailbox
-        session.select(mailbox)?;
-        
-        // Mark the message for deletion
-        session.uid_store(email_id, "+FLAGS (\\Deleted)")?;
-        
-        // Expunge the mailbox to remove deleted messages
-        session.expunge()?;
-        
-        Ok(())
+            // Mark the message for deletion
+            session.uid_store(&uid, "+FLAGS (\\Deleted)")?;
+            
+            // Expunge the mailbox to remove deleted messages
+            session.expunge()?;
+            
+            Ok(())
+        }).await
+    }
+    
+    /// Permanently deletes every message in a mailbox.
+    ///
+    /// # Parameters
+    /// - `mailbox`: The mailbox to empty
+    ///
+    /// # Returns
+    /// A Result containing the number of messages deleted or an error
+    pub async fn empty_mailbox(&self, mailbox: &str) -> Result<usize> {
+        let name = mailbox.to_string();
+        self.with_session(move |session| {
+            let selected = open_mailbox(session, &name, MailboxAccess::ReadWrite)?;
+            if selected.exists == 0 {
+                return Ok(0);
+            }
+            
+            // Mark every message for deletion and expunge them
+            session.store("1:*", "+FLAGS.SILENT (\\Deleted)")?;
+            session.expunge()?;
+            
+            Ok(selected.exists as usize)
+        }).await
     }
     
     /// Checks if the client is connected.
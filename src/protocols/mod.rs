@@ -3,10 +3,14 @@
 //! This module contains implementations for the various email protocols
 //! used by the application, including IMAP, POP3, and SMTP.
 
+mod fetch;
 mod imap;
 mod pop3;
+mod proxy;
 mod smtp;
+mod utf7;
 
+pub use fetch::*;
 pub use imap::*;
 pub use pop3::*;
 pub use smtp::*;
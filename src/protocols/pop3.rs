@@ -1,9 +1,10 @@
 //! POP3 protocol implementation for the Linksy email client.
 
-use crate::config::ServerConfig;
+use crate::config::{ProxyConfig, ServerConfig};
+use crate::protocols::{proxy, FetchedEmails};
 use crate::models::{Account, ConnectionStatus, Email};
 use anyhow::{anyhow, Result};
-use log::{debug, error};
+use log::{debug, error, warn};
 use std::sync::Arc;
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
@@ -57,6 +58,7 @@ impl Pop3Client {
             
         // Clone the config for later use
         let pop3_config = pop3_config.clone();
+        let proxy = account.config.proxy.clone();
         
         // Update connection status
         let mut account = account;
@@ -64,7 +66,7 @@ impl Pop3Client {
         drop(account); // Release the lock
         
         // Connect to the server
-        let connection = self.create_connection(&pop3_config).await?;
+        let connection = self.create_connection(&pop3_config, proxy.as_ref()).await?;
         
         // Store the connection
         self.connection = Some(Arc::new(Mutex::new(connection)));
@@ -81,13 +83,15 @@ impl Pop3Client {
     ///
     /// # Parameters
     /// - `config`: The server configuration
+    /// - `proxy`: The proxy to connect through, if any
     ///
     /// # Returns
     /// A Result containing the POP3 connection or an error
-    async fn create_connection(&self, config: &ServerConfig) -> Result<Pop3Connection> {
+    #[allow(clippy::collapsible_if)]
+    async fn create_connection(&self, config: &ServerConfig, proxy: Option<&ProxyConfig>) -> Result<Pop3Connection> {
         // Connect to the server
-        let addr = format!("{}:{}", config.host, config.port);
-        let tcp_stream = TcpStream::connect(&addr).await?;
+        let (host, port) = proxy::split_host_port(&config.host, config.port)?;
+        let tcp_stream = proxy::connect_tokio(proxy, &host, port).await?;
         
         let mut connection = if config.use_ssl {
             // Create TLS connector
@@ -95,7 +99,7 @@ impl Pop3Client {
             let connector = tokio_native_tls::TlsConnector::from(connector);
             
             // Connect with TLS
-            let tls_stream = connector.connect(&config.host, tcp_stream).await?;
+            let tls_stream = connector.connect(&host, tcp_stream).await?;
             let reader = BufReader::new(tls_stream);
             Pop3Connection::Tls(reader)
         } else {
@@ -282,12 +286,18 @@ impl Pop3Client {
     
     /// Fetches emails from the server.
     ///
+    /// Messages that are already cached are recognized from their headers
+    /// and not downloaded again, so a fetch that was interrupted resumes with
+    /// the messages it didn't get to. If the connection fails partway, the
+    /// emails fetched before are still returned.
+    ///
     /// # Parameters
     /// - `limit`: Maximum number of emails to fetch
+    /// - `cached`: The emails already in storage
     ///
     /// # Returns
-    /// A Result containing a vector of emails or an error
-    pub async fn fetch_emails(&self, limit: usize) -> Result<Vec<Email>> {
+    /// A Result containing the fetched emails, or an error if the mailbox couldn't be read
+    pub async fn fetch_emails(&self, limit: usize, cached: &[Email]) -> Result<FetchedEmails> {
         let connection_arc = self.connection.as_ref()
             .ok_or_else(|| anyhow!("Not connected to POP3 server"))?;
             
@@ -317,45 +327,81 @@ impl Pop3Client {
         drop(account); // Release the lock
         
         // Determine which messages to fetch
-        let start = if count > limit {
-            count - limit
-        } else {
-            0
-        };
+        let start = count.saturating_sub(limit);
         
-        let mut emails = Vec::new();
+        let mut fetched = FetchedEmails::default();
         
-        // Fetch messages
+        // Fetch messages, stopping at the first connection error
         for i in start..count {
             let msg_num = i + 1; // POP3 message numbers are 1-based
             
-            // Retrieve the message
-            self.send_command(&mut connection, &format!("RETR {}", msg_num)).await?;
-            let response = self.read_response(&mut connection).await?;
-            if !response.starts_with("+OK") {
-                error!("RETR command failed for message {}: {}", msg_num, response);
-                continue;
-            }
-            
-            // Read the message
-            let lines = self.read_multiline_response(&mut connection).await?;
-            let raw_data = lines.join("\r\n").into_bytes();
-            
-            // Parse the email
-            match Email::parse_from_raw(&raw_data, &account_id, "INBOX") {
-                Ok(email) => {
-                    emails.push(email);
-                },
+            match self.fetch_message(&mut connection, msg_num, &account_id, cached).await {
+                Ok(Some(email)) => fetched.emails.push(email),
+                Ok(None) => {},
                 Err(e) => {
-                    error!("Failed to parse email: {}", e);
-                }
+                    warn!("Fetching from POP3 was interrupted after {} messages: {}", fetched.emails.len(), e);
+                    fetched.interrupted = Some(e.to_string());
+                    break;
+                },
             }
         }
         
         // Sort emails by date (newest first)
-        emails.sort_by(|a, b| b.date.cmp(&a.date));
+        fetched.emails.sort_by_key(|e| std::cmp::Reverse(e.date));
         
-        Ok(emails)
+        Ok(fetched)
+    }
+    
+    /// Fetches a single message, reusing the cached copy if there is one.
+    ///
+    /// # Parameters
+    /// - `connection`: The connection to the server
+    /// - `msg_num`: The message number
+    /// - `account_id`: ID of the account
+    /// - `cached`: The emails already in storage
+    ///
+    /// # Returns
+    /// A Result containing the email, None if the server refused or it couldn't be parsed, or a connection error
+    async fn fetch_message(
+        &self,
+        connection: &mut Pop3Connection,
+        msg_num: usize,
+        account_id: &str,
+        cached: &[Email],
+    ) -> Result<Option<Email>> {
+        // Look at the headers first to skip messages that were downloaded before
+        if !cached.is_empty() {
+            self.send_command(connection, &format!("TOP {} 0", msg_num)).await?;
+            let response = self.read_response(connection).await?;
+            if response.starts_with("+OK") {
+                let header = self.read_multiline_response(connection).await?.join("\r\n").into_bytes();
+                let id = Email::parse_headers(&header, account_id, "INBOX").map(|email| email.id).unwrap_or_default();
+                if let Some(email) = cached.iter().find(|email| !id.is_empty() && email.id == id) {
+                    return Ok(Some(email.clone()));
+                }
+            }
+        }
+        
+        // Retrieve the message
+        self.send_command(connection, &format!("RETR {}", msg_num)).await?;
+        let response = self.read_response(connection).await?;
+        if !response.starts_with("+OK") {
+            error!("RETR command failed for message {}: {}", msg_num, response);
+            return Ok(None);
+        }
+        
+        // Read the message
+        let lines = self.read_multiline_response(connection).await?;
+        let raw_data = lines.join("\r\n").into_bytes();
+        
+        // Parse the email
+        match Email::parse_from_raw(&raw_data, account_id, "INBOX") {
+            Ok(email) => Ok(Some(email)),
+            Err(e) => {
+                error!("Failed to parse email: {}", e);
+                Ok(None)
+            }
+        }
     }
     
     /// Deletes an email from the server.
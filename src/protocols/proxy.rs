@@ -0,0 +1,346 @@
+//! SOCKS5 proxy support for the Linksy email client.
+//!
+//! This module opens the TCP connections used by the protocol clients,
+//! either directly or through a SOCKS5 proxy. The proxy resolves the server
+//! name itself, so no DNS lookups leak when it is used with Tor. It also
+//! splits configured hosts such as `[::1]:993` into a host and port.
+
+use crate::config::ProxyConfig;
+use anyhow::{anyhow, Result};
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv6Addr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// How long to wait for a server or proxy to accept a blocking connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a blocking connection waits for a read or write before giving up.
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// SOCKS protocol version
+const SOCKS_VERSION: u8 = 5;
+
+/// Authentication method: none
+const METHOD_NONE: u8 = 0x00;
+
+/// Authentication method: username and password
+const METHOD_PASSWORD: u8 = 0x02;
+
+/// Reply of the proxy when none of the offered methods are acceptable
+const METHOD_UNACCEPTABLE: u8 = 0xff;
+
+/// Splits a configured host into the host to connect to and the port.
+///
+/// Accepts host names, IPv4 addresses and IPv6 addresses, bare or in
+/// brackets, optionally followed by `:port`, e.g. `imap.example.com:993`
+/// or `[::1]:993`. A port in the host takes precedence over the port setting.
+///
+/// # Parameters
+/// - `host`: The host as configured
+/// - `default_port`: The port to use if the host doesn't include one
+///
+/// # Returns
+/// A Result containing the host without brackets and the port, or an error
+pub fn split_host_port(host: &str, default_port: u16) -> Result<(String, u16)> {
+    let host = host.trim();
+
+    // Bracketed IPv6 address, e.g. "[::1]" or "[::1]:993"
+    if let Some(rest) = host.strip_prefix('[') {
+        let (address, after) = rest.split_once(']')
+            .ok_or_else(|| anyhow!("Missing ']' in host {}", host))?;
+        address.parse::<Ipv6Addr>()
+            .map_err(|_| anyhow!("Invalid IPv6 address {}", address))?;
+
+        let port = match after {
+            "" => default_port,
+            _ => {
+                let port = after.strip_prefix(':')
+                    .ok_or_else(|| anyhow!("Unexpected text after ']' in host {}", host))?;
+                parse_port(port)?
+            },
+        };
+        return Ok((address.to_string(), port));
+    }
+
+    // Several colons without brackets can only be a bare IPv6 address
+    if host.matches(':').count() > 1 {
+        host.parse::<Ipv6Addr>()
+            .map_err(|_| anyhow!("Invalid IPv6 address {}; put it in brackets to add a port", host))?;
+        return Ok((host.to_string(), default_port));
+    }
+
+    match host.split_once(':') {
+        Some((name, port)) => Ok((name.to_string(), parse_port(port)?)),
+        None => Ok((host.to_string(), default_port)),
+    }
+}
+
+/// Parses the port part of a host.
+///
+/// # Parameters
+/// - `port`: The text after the colon
+///
+/// # Returns
+/// A Result containing the port, or an error if it isn't between 1 and 65535
+fn parse_port(port: &str) -> Result<u16> {
+    match port.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(anyhow!("Invalid port {}", port)),
+    }
+}
+
+/// Opens a blocking TCP connection to a server, through the proxy if one is configured.
+///
+/// The connection gives up on a server that stops answering, rather than
+/// blocking its thread until the system timeout.
+///
+/// # Parameters
+/// - `proxy`: The proxy to connect through, or None to connect directly
+/// - `host`: The server host, as returned by split_host_port
+/// - `port`: The server port
+///
+/// # Returns
+/// A Result containing the connected stream or an error
+pub fn connect_std(proxy: Option<&ProxyConfig>, host: &str, port: u16) -> Result<TcpStream> {
+    let Some(proxy) = proxy else {
+        return Ok(connect_with_timeout(host, port)?);
+    };
+
+    let (proxy_host, proxy_port) = split_host_port(&proxy.host, proxy.port)?;
+    let mut stream = connect_with_timeout(&proxy_host, proxy_port)
+        .map_err(|e| anyhow!("Could not reach proxy {}:{}: {}", proxy.host, proxy.port, e))?;
+
+    authenticate(&mut stream, proxy)?;
+    request_connect(&mut stream, host, port)?;
+
+    Ok(stream)
+}
+
+/// Opens a blocking TCP connection with connect, read and write timeouts.
+///
+/// Each address the host resolves to is tried in turn.
+///
+/// # Parameters
+/// - `host`: The host
+/// - `port`: The port
+///
+/// # Returns
+/// A Result containing the connected stream, or the error of the last address tried
+fn connect_with_timeout(host: &str, port: u16) -> std::io::Result<TcpStream> {
+    let mut last_error = None;
+
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(IO_TIMEOUT))?;
+                stream.set_write_timeout(Some(IO_TIMEOUT))?;
+                return Ok(stream);
+            },
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("{} has no addresses", host),
+    )))
+}
+
+/// Opens an async TCP connection to a server, through the proxy if one is configured.
+///
+/// # Parameters
+/// - `proxy`: The proxy to connect through, or None to connect directly
+/// - `host`: The server host, as returned by split_host_port
+/// - `port`: The server port
+///
+/// # Returns
+/// A Result containing the connected stream or an error
+pub async fn connect_tokio(proxy: Option<&ProxyConfig>, host: &str, port: u16) -> Result<tokio::net::TcpStream> {
+    let Some(proxy) = proxy.cloned() else {
+        return Ok(tokio::net::TcpStream::connect((host, port)).await?);
+    };
+
+    // The handshake is a few small blocking exchanges, so run it off the runtime
+    let host = host.to_string();
+    let stream = tokio::task::spawn_blocking(move || connect_std(Some(&proxy), &host, port)).await??;
+
+    stream.set_nonblocking(true)?;
+    Ok(tokio::net::TcpStream::from_std(stream)?)
+}
+
+/// Agrees on an authentication method with the proxy and logs in if needed.
+///
+/// # Parameters
+/// - `stream`: The connection to the proxy
+/// - `proxy`: The proxy configuration
+///
+/// # Returns
+/// A Result indicating success or failure
+fn authenticate(stream: &mut TcpStream, proxy: &ProxyConfig) -> Result<()> {
+    // Only offer password authentication if credentials are configured
+    let credentials = proxy.username.as_deref().zip(proxy.password.as_deref());
+    let greeting: &[u8] = if credentials.is_some() {
+        &[SOCKS_VERSION, 2, METHOD_NONE, METHOD_PASSWORD]
+    } else {
+        &[SOCKS_VERSION, 1, METHOD_NONE]
+    };
+    stream.write_all(greeting)?;
+
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply)?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(anyhow!("Proxy is not a SOCKS5 proxy"));
+    }
+
+    match (reply[1], credentials) {
+        (METHOD_NONE, _) => Ok(()),
+        (METHOD_PASSWORD, Some((username, password))) => {
+            if username.len() > 255 || password.len() > 255 {
+                return Err(anyhow!("Proxy username and password must be at most 255 bytes"));
+            }
+
+            // Username/password subnegotiation (RFC 1929)
+            let mut request = vec![1, username.len() as u8];
+            request.extend_from_slice(username.as_bytes());
+            request.push(password.len() as u8);
+            request.extend_from_slice(password.as_bytes());
+            stream.write_all(&request)?;
+
+            let mut reply = [0u8; 2];
+            stream.read_exact(&mut reply)?;
+            if reply[1] != 0 {
+                return Err(anyhow!("Proxy rejected the username or password"));
+            }
+            Ok(())
+        },
+        (METHOD_UNACCEPTABLE, _) => Err(anyhow!("Proxy requires authentication that isn't configured")),
+        (method, _) => Err(anyhow!("Proxy chose unsupported authentication method {}", method)),
+    }
+}
+
+/// Asks the proxy to connect to a server.
+///
+/// # Parameters
+/// - `stream`: The authenticated connection to the proxy
+/// - `host`: The server host, resolved by the proxy
+/// - `port`: The server port
+///
+/// # Returns
+/// A Result indicating success or failure
+fn request_connect(stream: &mut TcpStream, host: &str, port: u16) -> Result<()> {
+    // CONNECT to an IP address or, for the proxy to resolve, a domain name
+    let mut request = vec![SOCKS_VERSION, 0x01, 0x00];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(address)) => {
+            request.push(0x01);
+            request.extend_from_slice(&address.octets());
+        },
+        Ok(IpAddr::V6(address)) => {
+            request.push(0x04);
+            request.extend_from_slice(&address.octets());
+        },
+        Err(_) => {
+            if host.len() > 255 {
+                return Err(anyhow!("Host name is too long for the proxy"));
+            }
+            request.push(0x03);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        },
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request)?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply)?;
+    if reply[1] != 0 {
+        return Err(anyhow!("Proxy could not connect to {}:{}: {}", host, port, reply_message(reply[1])));
+    }
+
+    // Skip the bound address and port that follow the reply
+    let address_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        },
+        other => return Err(anyhow!("Proxy sent unknown address type {}", other)),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound)?;
+
+    Ok(())
+}
+
+/// Describes a SOCKS5 reply code.
+///
+/// # Parameters
+/// - `code`: The reply code
+///
+/// # Returns
+/// A description of the failure
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        0x01 => "general failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_host_name_and_port() {
+        assert_eq!(split_host_port("imap.example.com:143", 993).unwrap(), ("imap.example.com".to_string(), 143));
+        assert_eq!(split_host_port(" imap.example.com ", 993).unwrap(), ("imap.example.com".to_string(), 993));
+    }
+
+    #[test]
+    fn splits_ipv4_address_and_port() {
+        assert_eq!(split_host_port("192.0.2.1:995", 110).unwrap(), ("192.0.2.1".to_string(), 995));
+        assert_eq!(split_host_port("192.0.2.1", 110).unwrap(), ("192.0.2.1".to_string(), 110));
+    }
+
+    #[test]
+    fn splits_bracketed_ipv6_address() {
+        assert_eq!(split_host_port("[::1]:993", 143).unwrap(), ("::1".to_string(), 993));
+        assert_eq!(split_host_port("[2001:db8::1]", 143).unwrap(), ("2001:db8::1".to_string(), 143));
+    }
+
+    #[test]
+    fn keeps_bare_ipv6_address() {
+        assert_eq!(split_host_port("2001:db8::1", 993).unwrap(), ("2001:db8::1".to_string(), 993));
+    }
+
+    #[test]
+    fn uses_default_port_when_missing() {
+        assert_eq!(split_host_port("mail.example.com", 587).unwrap(), ("mail.example.com".to_string(), 587));
+    }
+
+    #[test]
+    fn rejects_bad_ports() {
+        assert!(split_host_port("mail.example.com:", 587).is_err());
+        assert!(split_host_port("mail.example.com:0", 587).is_err());
+        assert!(split_host_port("mail.example.com:70000", 587).is_err());
+        assert!(split_host_port("mail.example.com:smtp", 587).is_err());
+        assert!(split_host_port("[::1]:abc", 993).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_ipv6_addresses() {
+        assert!(split_host_port("[::1", 993).is_err());
+        assert!(split_host_port("[::1]993", 993).is_err());
+        assert!(split_host_port("[not-an-address]:993", 993).is_err());
+        assert!(split_host_port("fe80::zz", 993).is_err());
+    }
+}
@@ -1,11 +1,16 @@
 //! SMTP protocol implementation for the Linksy email client.
 
-use crate::config::ServerConfig;
-use crate::models::{Account, ConnectionStatus, Email};
+use crate::config::{AppSettings, ProxyConfig, ServerConfig, TransferEncoding};
+use crate::models::{Account, ConnectionStatus, Email, Priority};
+use crate::protocols::proxy;
 use anyhow::{anyhow, Result};
 use lettre::{
-    message::{header, MultiPart, SinglePart},
-    transport::smtp::{authentication::Credentials, client::TlsParameters},
+    message::{header, header::{ContentTransferEncoding, Header, HeaderName, HeaderValue}, Body, MultiPart, SinglePart},
+    transport::smtp::{
+        authentication::{Credentials, Mechanism},
+        client::{AsyncSmtpConnection, TlsParameters},
+        extension::ClientId,
+    },
     AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
 };
 use std::sync::Arc;
@@ -16,7 +21,20 @@ pub struct SmtpClient {
     /// The account this client is connected to
     account: Arc<Mutex<Account>>,
     /// The SMTP transport
-    transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    transport: Option<SmtpRoute>,
+}
+
+/// How messages reach the SMTP server.
+enum SmtpRoute {
+    /// Lettre's transport, connecting directly
+    Direct(AsyncSmtpTransport<Tokio1Executor>),
+    /// A new connection through a SOCKS5 proxy for each message
+    Proxied {
+        /// The server configuration
+        config: ServerConfig,
+        /// The proxy to connect through
+        proxy: ProxyConfig,
+    },
 }
 
 impl SmtpClient {
@@ -43,16 +61,28 @@ impl SmtpClient {
         
         // Get SMTP configuration
         let smtp_config = account.get_smtp_config().clone();
+        let proxy = account.config.proxy.clone();
         
         // Update connection status
         account.smtp_status = ConnectionStatus::Connecting;
         drop(account); // Release the lock
         
-        // Create SMTP transport
-        let transport = self.create_transport(&smtp_config)?;
-        
-        // Test the connection
-        transport.test_connection().await?;
+        let transport = match proxy {
+            Some(proxy) => {
+                // Test the connection and login through the proxy
+                let mut connection = Self::open_proxied_connection(&smtp_config, &proxy).await?;
+                let _ = connection.quit().await;
+                SmtpRoute::Proxied { config: smtp_config, proxy }
+            },
+            None => {
+                // Create SMTP transport
+                let transport = self.create_transport(&smtp_config)?;
+                
+                // Test the connection
+                transport.test_connection().await?;
+                SmtpRoute::Direct(transport)
+            },
+        };
         
         // Store the transport
         self.transport = Some(transport);
@@ -77,11 +107,12 @@ impl SmtpClient {
         let creds = Credentials::new(config.username.clone(), config.password.clone());
         
         // Create TLS parameters
-        let tls_parameters = TlsParameters::new(config.host.clone())?;
+        let (host, port) = proxy::split_host_port(&config.host, config.port)?;
+        let tls_parameters = TlsParameters::new(host.clone())?;
         
         // Create transport builder
-        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?
-            .port(config.port)
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?
+            .port(port)
             .credentials(creds);
             
         // Configure TLS if needed
@@ -94,6 +125,32 @@ impl SmtpClient {
         Ok(builder.build())
     }
     
+    /// Opens an authenticated SMTP connection through a proxy.
+    ///
+    /// Like the direct transport, `use_ssl` requires STARTTLS.
+    ///
+    /// # Parameters
+    /// - `config`: The server configuration
+    /// - `proxy`: The proxy to connect through
+    ///
+    /// # Returns
+    /// A Result containing the SMTP connection or an error
+    async fn open_proxied_connection(config: &ServerConfig, proxy: &ProxyConfig) -> Result<AsyncSmtpConnection> {
+        let (host, port) = proxy::split_host_port(&config.host, config.port)?;
+        let stream = proxy::connect_tokio(Some(proxy), &host, port).await?;
+        let hello_name = ClientId::default();
+        let mut connection = AsyncSmtpConnection::connect_with_transport(Box::new(stream), &hello_name).await?;
+        
+        if config.use_ssl {
+            connection.starttls(TlsParameters::new(host)?, &hello_name).await?;
+        }
+        
+        let creds = Credentials::new(config.username.clone(), config.password.clone());
+        connection.auth(&[Mechanism::Plain, Mechanism::Login], &creds).await?;
+        
+        Ok(connection)
+    }
+    
     /// Disconnects from the SMTP server.
     ///
     /// # Returns
@@ -111,11 +168,11 @@ impl SmtpClient {
     ///
     /// # Parameters
     /// - `email`: The email to send
+    /// - `settings`: The settings with the line endings, transfer encoding and charset of text parts
     ///
     /// # Returns
     /// A Result indicating success or failure
-    #[allow(dead_code)]
-    pub async fn send_email(&self, email: &Email) -> Result<()> {
+    pub async fn send_email(&self, email: &Email, settings: &AppSettings) -> Result<()> {
         let transport = self.transport.as_ref()
             .ok_or_else(|| anyhow!("Not connected to SMTP server"))?;
             
@@ -142,18 +199,26 @@ impl SmtpClient {
             builder = builder.bcc(bcc.parse()?);
         }
         
+        // Point replies elsewhere if the account asks for it
+        if let Some(reply_to) = &account.config.reply_to {
+            builder = builder.reply_to(reply_to.parse()?);
+        }
+        
+        // Mark the priority in both headers mail clients look at
+        if email.priority != Priority::Normal {
+            builder = builder
+                .header(XPriority(email.priority.x_priority().to_string()))
+                .header(Importance(email.priority.importance().to_string()));
+        }
+        
         // Create email body
         let message = if !email.attachments.is_empty() {
             // For emails with attachments
             if let (Some(text), Some(html)) = (&email.body_text, &email.body_html) {
                 // Both text and HTML with attachments
-                let text_part = SinglePart::builder()
-                    .header(header::ContentType::TEXT_PLAIN)
-                    .body(text.clone());
+                let text_part = build_text_part(text, "plain", settings)?;
                 
-                let html_part = SinglePart::builder()
-                    .header(header::ContentType::TEXT_HTML)
-                    .body(html.clone());
+                let html_part = build_text_part(html, "html", settings)?;
                 
                 // Create alternative part for text and HTML
                 let alternative = MultiPart::alternative()
@@ -176,9 +241,7 @@ impl SmtpClient {
                 builder.multipart(mixed)?
             } else if let Some(text) = &email.body_text {
                 // Text only with attachments
-                let text_part = SinglePart::builder()
-                    .header(header::ContentType::TEXT_PLAIN)
-                    .body(text.clone());
+                let text_part = build_text_part(text, "plain", settings)?;
                 
                 // Create mixed part for content and attachments
                 let mut mixed = MultiPart::mixed().singlepart(text_part);
@@ -196,9 +259,7 @@ impl SmtpClient {
                 builder.multipart(mixed)?
             } else if let Some(html) = &email.body_html {
                 // HTML only with attachments
-                let html_part = SinglePart::builder()
-                    .header(header::ContentType::TEXT_HTML)
-                    .body(html.clone());
+                let html_part = build_text_part(html, "html", settings)?;
                 
                 // Create mixed part for content and attachments
                 let mut mixed = MultiPart::mixed().singlepart(html_part);
@@ -240,13 +301,9 @@ impl SmtpClient {
             }
         } else if let (Some(text), Some(html)) = (&email.body_text, &email.body_html) {
             // Both text and HTML without attachments
-            let text_part = SinglePart::builder()
-                .header(header::ContentType::TEXT_PLAIN)
-                .body(text.clone());
+            let text_part = build_text_part(text, "plain", settings)?;
             
-            let html_part = SinglePart::builder()
-                .header(header::ContentType::TEXT_HTML)
-                .body(html.clone());
+            let html_part = build_text_part(html, "html", settings)?;
             
             // Create alternative part for text and HTML
             let alternative = MultiPart::alternative()
@@ -256,19 +313,28 @@ impl SmtpClient {
             builder.multipart(alternative)?
         } else if let Some(html) = &email.body_html {
             // HTML only
-            builder.header(header::ContentType::TEXT_HTML)
-                .body(html.clone())?
+            builder.header(text_content_type("html", settings)?)
+                .body(text_body(html, settings)?)?
         } else if let Some(text) = &email.body_text {
             // Plain text email
-            builder.header(header::ContentType::TEXT_PLAIN)
-                .body(text.clone())?
+            builder.header(text_content_type("plain", settings)?)
+                .body(text_body(text, settings)?)?
         } else {
             // Empty email
             builder.body("".to_string())?
         };
         
         // Send the email
-        transport.send(message).await?;
+        match transport {
+            SmtpRoute::Direct(transport) => {
+                transport.send(message).await?;
+            },
+            SmtpRoute::Proxied { config, proxy } => {
+                let mut connection = Self::open_proxied_connection(config, proxy).await?;
+                connection.send(message.envelope(), &message.formatted()).await?;
+                let _ = connection.quit().await;
+            },
+        }
         
         Ok(())
     }
@@ -296,9 +362,108 @@ impl SmtpClient {
     ///
     /// # Returns
     /// true if connected, false otherwise
-    #[allow(dead_code)]
     pub async fn is_connected(&self) -> bool {
         let account = self.account.lock().await;
         account.smtp_status == ConnectionStatus::Connected
     }
 }
+
+/// The X-Priority header of an outgoing message.
+#[derive(Clone)]
+struct XPriority(String);
+
+impl Header for XPriority {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("X-Priority")
+    }
+    
+    fn parse(s: &str) -> std::result::Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self(s.to_string()))
+    }
+    
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), self.0.clone())
+    }
+}
+
+/// The Importance header of an outgoing message.
+#[derive(Clone)]
+struct Importance(String);
+
+impl Header for Importance {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("Importance")
+    }
+    
+    fn parse(s: &str) -> std::result::Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self(s.to_string()))
+    }
+    
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), self.0.clone())
+    }
+}
+
+/// Creates the Content-Type header of an outgoing text part.
+///
+/// # Parameters
+/// - `subtype`: The text subtype, "plain" or "html"
+/// - `settings`: The settings with the charset
+///
+/// # Returns
+/// A Result containing the header or an error
+fn text_content_type(subtype: &str, settings: &AppSettings) -> Result<header::ContentType> {
+    Ok(header::ContentType::parse(&format!("text/{}; charset={}", subtype, settings.charset.trim()))?)
+}
+
+/// Creates an outgoing text part.
+///
+/// # Parameters
+/// - `text`: The text
+/// - `subtype`: The text subtype, "plain" or "html"
+/// - `settings`: The settings with the line endings, transfer encoding and charset
+///
+/// # Returns
+/// A Result containing the part or an error
+fn build_text_part(text: &str, subtype: &str, settings: &AppSettings) -> Result<SinglePart> {
+    Ok(SinglePart::builder()
+        .header(text_content_type(subtype, settings)?)
+        .body(text_body(text, settings)?))
+}
+
+/// Encodes the text of an outgoing part.
+///
+/// # Parameters
+/// - `text`: The text
+/// - `settings`: The settings with the line endings, transfer encoding and charset
+///
+/// # Returns
+/// A Result containing the encoded body, or an error if the text can't be
+/// written in the charset or sent with the transfer encoding
+fn text_body(text: &str, settings: &AppSettings) -> Result<Body> {
+    // Line endings are converted here so lettre keeps them as they are
+    let text = if settings.crlf_line_endings {
+        text.replace("\r\n", "\n").replace('\n', "\r\n")
+    } else {
+        text.to_string()
+    };
+    
+    let charset = settings.charset.trim();
+    let encoding = encoding_rs::Encoding::for_label(charset.as_bytes())
+        .ok_or_else(|| anyhow!("Unknown charset {}", charset))?;
+    let (bytes, _, unmappable) = encoding.encode(&text);
+    if unmappable {
+        return Err(anyhow!("The message has characters that can't be written in {}", charset));
+    }
+    let bytes = bytes.into_owned();
+    
+    let body = match settings.transfer_encoding {
+        TransferEncoding::Auto => Body::new_with_encoding(bytes, ContentTransferEncoding::SevenBit)
+            .or_else(|bytes| Body::new_with_encoding(bytes, ContentTransferEncoding::QuotedPrintable)),
+        TransferEncoding::EightBit => Body::new_with_encoding(bytes, ContentTransferEncoding::EightBit),
+        TransferEncoding::QuotedPrintable => Body::new_with_encoding(bytes, ContentTransferEncoding::QuotedPrintable),
+        TransferEncoding::Base64 => Body::new_with_encoding(bytes, ContentTransferEncoding::Base64),
+    };
+    
+    body.map_err(|_| anyhow!("The message can't be sent as 8bit; use quoted-printable or base64"))
+}
@@ -0,0 +1,200 @@
+//! Modified UTF-7 for IMAP mailbox names.
+//!
+//! IMAP servers list mailbox names in a variant of UTF-7 (RFC 3501, section
+//! 5.1.3). Printable ASCII stands for itself, except "&", which is written
+//! "&-". Any other text is written as UTF-16 in base64 between "&" and "-",
+//! with "," in place of "/", so "Entwürfe" is "Entw&APw-rfe". Linksy shows
+//! and stores the decoded names, and encodes them again for the server.
+
+/// The base64 alphabet of modified UTF-7.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+,";
+
+/// Decodes a mailbox name listed by the server.
+///
+/// A name that isn't valid modified UTF-7 is kept as it is, since some
+/// servers send UTF-8 names instead.
+///
+/// # Parameters
+/// - `name`: The name as sent by the server
+///
+/// # Returns
+/// The name to show
+pub fn decode(name: &str) -> String {
+    try_decode(name).unwrap_or_else(|| name.to_string())
+}
+
+/// Encodes a mailbox name to send to the server.
+///
+/// # Parameters
+/// - `name`: The name as shown
+///
+/// # Returns
+/// The name in modified UTF-7
+pub fn encode(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    let mut pending: Vec<u16> = Vec::new();
+
+    for c in name.chars() {
+        if (' '..='~').contains(&c) {
+            // Printable ASCII ends a run of other characters
+            encode_utf16(&mut pending, &mut encoded);
+            if c == '&' {
+                encoded.push_str("&-");
+            } else {
+                encoded.push(c);
+            }
+        } else {
+            let mut units = [0; 2];
+            pending.extend_from_slice(c.encode_utf16(&mut units));
+        }
+    }
+    encode_utf16(&mut pending, &mut encoded);
+
+    encoded
+}
+
+/// Decodes a mailbox name, failing on anything that isn't modified UTF-7.
+///
+/// # Parameters
+/// - `name`: The name as sent by the server
+///
+/// # Returns
+/// The decoded name, or None if it isn't valid
+fn try_decode(name: &str) -> Option<String> {
+    let mut decoded = String::with_capacity(name.len());
+    let mut rest = name;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        let end = start + 1 + rest[start + 1..].find('-')?;
+        match &rest[start + 1..end] {
+            "" => decoded.push('&'),
+            encoded => decoded.push_str(&decode_utf16(encoded)?),
+        }
+        rest = &rest[end + 1..];
+    }
+    decoded.push_str(rest);
+
+    Some(decoded)
+}
+
+/// Decodes a base64 run between "&" and "-".
+///
+/// # Parameters
+/// - `encoded`: The base64 text
+///
+/// # Returns
+/// The text it stands for, or None if it isn't valid UTF-16 in base64
+fn decode_utf16(encoded: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut count = 0;
+
+    for c in encoded.bytes() {
+        let value = ALPHABET.iter().position(|&letter| letter == c)? as u32;
+        bits = (bits << 6) | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+
+    // Leftover bits only pad the last character and must be zero
+    if bits != 0 || bytes.len() % 2 != 0 {
+        return None;
+    }
+
+    let units: Vec<u16> = bytes.chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).ok()
+}
+
+/// Writes a run of non-ASCII characters as base64 between "&" and "-".
+///
+/// # Parameters
+/// - `units`: The UTF-16 code units of the run, emptied afterwards
+/// - `encoded`: The encoded name to add to
+fn encode_utf16(units: &mut Vec<u16>, encoded: &mut String) {
+    if units.is_empty() {
+        return;
+    }
+
+    encoded.push('&');
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    for byte in units.drain(..).flat_map(u16::to_be_bytes) {
+        bits = (bits << 8) | u32::from(byte);
+        count += 8;
+        while count >= 6 {
+            count -= 6;
+            encoded.push(ALPHABET[((bits >> count) & 0x3f) as usize] as char);
+        }
+        bits &= (1 << count) - 1;
+    }
+    if count > 0 {
+        encoded.push(ALPHABET[((bits << (6 - count)) & 0x3f) as usize] as char);
+    }
+    encoded.push('-');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Names with their modified UTF-7 form, from RFC 3501 and common servers
+    const KNOWN: &[(&str, &str)] = &[
+        ("INBOX", "INBOX"),
+        ("Sent Items", "Sent Items"),
+        ("Tom & Jerry", "Tom &- Jerry"),
+        ("&", "&-"),
+        ("Entwürfe", "Entw&APw-rfe"),
+        ("Åäö", "&AMUA5AD2-"),
+        ("日本語", "&ZeVnLIqe-"),
+        ("~peter/mail/台北/日本語", "~peter/mail/&U,BTFw-/&ZeVnLIqe-"),
+        ("😀", "&2D3eAA-"),
+    ];
+
+    #[test]
+    fn encodes_known_names() {
+        for (name, encoded) in KNOWN {
+            assert_eq!(encode(name), *encoded, "encoding {}", name);
+        }
+    }
+
+    #[test]
+    fn decodes_known_names() {
+        for (name, encoded) in KNOWN {
+            assert_eq!(decode(encoded), *name, "decoding {}", encoded);
+        }
+    }
+
+    #[test]
+    fn round_trips_non_ascii_names() {
+        for name in ["Входящие", "Черновики/Старые", "a&b&&c", "Ünïcödé & more", "tab\there", ""] {
+            assert_eq!(decode(&encode(name)), name);
+        }
+    }
+
+    #[test]
+    fn keeps_invalid_names_as_they_are() {
+        // Not in the alphabet: "/" is written as "," in modified UTF-7
+        assert_eq!(decode("&U/BTFw-"), "&U/BTFw-");
+        assert_eq!(decode("&Jj!-"), "&Jj!-");
+
+        // No "-" to end the run
+        assert_eq!(decode("Entw&APw"), "Entw&APw");
+
+        // Leftover bits that aren't zero, or half a UTF-16 code unit
+        assert_eq!(decode("&APx-"), "&APx-");
+        assert_eq!(decode("&AP-"), "&AP-");
+
+        // A lone surrogate
+        assert_eq!(decode("&2D0-"), "&2D0-");
+
+        // UTF-8 names sent by some servers
+        assert_eq!(decode("Entwürfe"), "Entwürfe");
+    }
+}
@@ -4,101 +4,62 @@ use crate::config::{EmailAccount, ConfigManager};
 use crate::models::{Account, ConnectionStatus};
 use crate::protocols::{ImapClient, Pop3Client, SmtpClient};
 use anyhow::Result;
-use log::error;
+use log::{error, warn};
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
-/// Manages email accounts and their connections.
-pub struct AccountManager {
-    /// Active accounts
-    accounts: Vec<Arc<Mutex<Account>>>,
-    /// IMAP clients for each account
-    imap_clients: Vec<Arc<Mutex<ImapClient>>>,
-    /// POP3 clients for each account
-    pop3_clients: Vec<Arc<Mutex<Pop3Client>>>,
-    /// SMTP clients for each account
-    smtp_clients: Vec<Arc<Mutex<SmtpClient>>>,
+/// How long each protocol gets to disconnect before it's abandoned.
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// An account together with its protocol clients.
+#[derive(Clone)]
+pub struct AccountBundle {
+    /// ID of the account in the configuration
+    pub id: String,
+    /// The account
+    pub account: Arc<Mutex<Account>>,
+    /// IMAP client for the account
+    pub imap_client: Arc<Mutex<ImapClient>>,
+    /// POP3 client for the account
+    pub pop3_client: Arc<Mutex<Pop3Client>>,
+    /// SMTP client for the account
+    pub smtp_client: Arc<Mutex<SmtpClient>>,
 }
 
-impl AccountManager {
-    /// Creates a new AccountManager instance.
-    ///
-    /// # Returns
-    /// A new AccountManager instance
-    pub fn new() -> Self {
-        Self {
-            accounts: Vec::new(),
-            imap_clients: Vec::new(),
-            pop3_clients: Vec::new(),
-            smtp_clients: Vec::new(),
-        }
-    }
-    
-    /// Loads accounts from configuration.
+impl AccountBundle {
+    /// Creates an account and its protocol clients from a configuration.
     ///
     /// # Parameters
-    /// - `config_manager`: The configuration manager
+    /// - `account_config`: The account configuration
     ///
     /// # Returns
-    /// A Result indicating success or failure
-    pub async fn load_accounts(&mut self, config_manager: &ConfigManager) -> Result<()> {
-        // Clear existing accounts and clients
-        self.accounts.clear();
-        self.imap_clients.clear();
-        self.pop3_clients.clear();
-        self.smtp_clients.clear();
-        
-        // Get accounts from configuration
-        let config = config_manager.get_config();
+    /// A new AccountBundle instance
+    fn new(account_config: EmailAccount) -> Self {
+        let id = account_config.id.clone();
+        let account = Arc::new(Mutex::new(Account::new(account_config)));
         
-        // Create account objects
-        for account_config in &config.accounts {
-            let account = Account::new(account_config.clone());
-            let account_arc = Arc::new(Mutex::new(account));
-            
-            // Create protocol clients
-            let imap_client = Arc::new(Mutex::new(ImapClient::new(account_arc.clone())));
-            let pop3_client = Arc::new(Mutex::new(Pop3Client::new(account_arc.clone())));
-            let smtp_client = Arc::new(Mutex::new(SmtpClient::new(account_arc.clone())));
-            
-            // Store clients
-            self.accounts.push(account_arc);
-            self.imap_clients.push(imap_client);
-            self.pop3_clients.push(pop3_client);
-            self.smtp_clients.push(smtp_client);
+        Self {
+            id,
+            imap_client: Arc::new(Mutex::new(ImapClient::new(account.clone()))),
+            pop3_client: Arc::new(Mutex::new(Pop3Client::new(account.clone()))),
+            smtp_client: Arc::new(Mutex::new(SmtpClient::new(account.clone()))),
+            account,
         }
-        
-        Ok(())
     }
     
-    /// Gets the accounts.
+    /// Connects the account's clients.
     ///
-    /// # Returns
-    /// A reference to the accounts
-    pub fn get_accounts(&self) -> &Vec<Arc<Mutex<Account>>> {
-        &self.accounts
-    }
-    
-    /// Gets a specific account.
-    ///
-    /// # Parameters
-    /// - `index`: The account index
-    ///
-    /// # Returns
-    /// An Option containing a reference to the account
-    pub fn get_account(&self, index: usize) -> Option<&Arc<Mutex<Account>>> {
-        self.accounts.get(index)
-    }
-    
-    /// Connects to the specified account.
-    ///
-    /// # Parameters
-    /// - `index`: The account index
+    /// Only the account and its own clients are locked, so several
+    /// accounts can connect at the same time. Clients that are already
+    /// connected are left as they are.
     ///
     /// # Returns
     /// A Result containing a boolean indicating if any connection was successful
-    pub async fn connect_account(&self, index: usize) -> Result<bool> {
-        if index >= self.accounts.len() {
+    pub async fn connect(&self) -> Result<bool> {
+        // A disabled account stays disconnected
+        if !self.account.lock().await.config.enabled {
             return Ok(false);
         }
         
@@ -107,23 +68,28 @@ impl AccountManager {
         
         // Check if account has IMAP
         let has_imap = {
-            let account = &self.accounts[index];
+            let account = &self.account;
             account.lock().await.has_imap()
         };
         
         if has_imap {
             // Connect to IMAP
             let result = {
-                let imap_client = &self.imap_clients[index];
+                let imap_client = &self.imap_client;
                 let mut client = imap_client.lock().await;
-                client.connect().await
+                // Another task may have connected it while this one waited for the client
+                if self.account.lock().await.imap_status == ConnectionStatus::Connected {
+                    Ok(())
+                } else {
+                    client.connect().await
+                }
             };
             
             // Handle result
             if let Err(e) = result {
                 let error_msg = format!("IMAP connection failed: {}", e);
                 {
-                    let account = &self.accounts[index];
+                    let account = &self.account;
                     let mut account_lock = account.lock().await;
                     account_lock.imap_status = ConnectionStatus::Failed;
                     account_lock.last_error = Some(e.to_string());
@@ -136,23 +102,28 @@ impl AccountManager {
         
         // Check if account has POP3
         let has_pop3 = {
-            let account = &self.accounts[index];
+            let account = &self.account;
             account.lock().await.has_pop3()
         };
         
         if has_pop3 {
             // Connect to POP3
             let result = {
-                let pop3_client = &self.pop3_clients[index];
+                let pop3_client = &self.pop3_client;
                 let mut client = pop3_client.lock().await;
-                client.connect().await
+                // Another task may have connected it while this one waited for the client
+                if self.account.lock().await.pop3_status == ConnectionStatus::Connected {
+                    Ok(())
+                } else {
+                    client.connect().await
+                }
             };
             
             // Handle result
             if let Err(e) = result {
                 let error_msg = format!("POP3 connection failed: {}", e);
                 {
-                    let account = &self.accounts[index];
+                    let account = &self.account;
                     let mut account_lock = account.lock().await;
                     account_lock.pop3_status = ConnectionStatus::Failed;
                     account_lock.last_error = Some(e.to_string());
@@ -165,16 +136,21 @@ impl AccountManager {
         
         // Connect to SMTP
         let result = {
-            let smtp_client = &self.smtp_clients[index];
+            let smtp_client = &self.smtp_client;
             let mut client = smtp_client.lock().await;
-            client.connect().await
+            // Another task may have connected it while this one waited for the client
+            if self.account.lock().await.smtp_status == ConnectionStatus::Connected {
+                Ok(())
+            } else {
+                client.connect().await
+            }
         };
         
         // Handle result
         if let Err(e) = result {
             let error_msg = format!("SMTP connection failed: {}", e);
             {
-                let account = &self.accounts[index];
+                let account = &self.account;
                 let mut account_lock = account.lock().await;
                 account_lock.smtp_status = ConnectionStatus::Failed;
                 account_lock.last_error = Some(e.to_string());
@@ -187,53 +163,19 @@ impl AccountManager {
         Ok(any_connection_successful)
     }
     
-    /// Disconnects all clients.
+    /// Retries connecting the protocols whose connection failed.
     ///
     /// # Returns
-    /// A Result indicating success or failure
-    pub async fn disconnect_all_clients(&self) -> Result<()> {
-        for i in 0..self.accounts.len() {
-            let imap_client = &self.imap_clients[i];
-            let pop3_client = &self.pop3_clients[i];
-            let smtp_client = &self.smtp_clients[i];
-            
-            // Disconnect IMAP
-            let mut client = imap_client.lock().await;
-            if let Err(e) = client.disconnect().await {
-                error!("IMAP disconnect failed: {}", e);
-            }
-            
-            // Disconnect POP3
-            let mut client = pop3_client.lock().await;
-            if let Err(e) = client.disconnect().await {
-                error!("POP3 disconnect failed: {}", e);
-            }
-            
-            // Disconnect SMTP
-            let mut client = smtp_client.lock().await;
-            if let Err(e) = client.disconnect().await {
-                error!("SMTP disconnect failed: {}", e);
-            }
-        }
-        
-        Ok(())
-    }
-    
-    /// Attempts to retry connecting to failed services for the specified account.
-    ///
-    /// # Parameters
-    /// - `index`: The account index
-    ///
-    /// # Returns
-    /// A Result indicating success or failure
-    pub async fn retry_connections(&self, index: usize) -> Result<()> {
-        if index >= self.accounts.len() {
-            return Ok(());
+    /// Each protocol that was retried and whether it connected, none if nothing had failed
+    pub async fn retry_failed(&self) -> Vec<RetryOutcome> {
+        let mut outcomes = Vec::new();
+        if !self.account.lock().await.config.enabled {
+            return outcomes;
         }
         
         // Check and retry IMAP if needed
         let retry_imap = {
-            let account = &self.accounts[index];
+            let account = &self.account;
             let account_lock = account.lock().await;
             account_lock.has_imap() && account_lock.imap_status == ConnectionStatus::Failed
         };
@@ -241,26 +183,27 @@ impl AccountManager {
         if retry_imap {
             // Retry IMAP connection
             let result = {
-                let imap_client = &self.imap_clients[index];
+                let imap_client = &self.imap_client;
                 let mut client = imap_client.lock().await;
                 client.connect().await
             };
             
             // Handle result
-            if let Err(e) = result {
+            if let Err(e) = &result {
                 let error_msg = format!("IMAP connection retry failed: {}", e);
                 {
-                    let account = &self.accounts[index];
+                    let account = &self.account;
                     let mut account_lock = account.lock().await;
                     account_lock.last_error = Some(e.to_string());
                 }
                 error!("{}", error_msg);
             }
+            outcomes.push(RetryOutcome { protocol: "IMAP", error: result.err().map(|e| e.to_string()) });
         }
         
         // Check and retry POP3 if needed
         let retry_pop3 = {
-            let account = &self.accounts[index];
+            let account = &self.account;
             let account_lock = account.lock().await;
             account_lock.has_pop3() && account_lock.pop3_status == ConnectionStatus::Failed
         };
@@ -268,26 +211,27 @@ impl AccountManager {
         if retry_pop3 {
             // Retry POP3 connection
             let result = {
-                let pop3_client = &self.pop3_clients[index];
+                let pop3_client = &self.pop3_client;
                 let mut client = pop3_client.lock().await;
                 client.connect().await
             };
             
             // Handle result
-            if let Err(e) = result {
+            if let Err(e) = &result {
                 let error_msg = format!("POP3 connection retry failed: {}", e);
                 {
-                    let account = &self.accounts[index];
+                    let account = &self.account;
                     let mut account_lock = account.lock().await;
                     account_lock.last_error = Some(e.to_string());
                 }
                 error!("{}", error_msg);
             }
+            outcomes.push(RetryOutcome { protocol: "POP3", error: result.err().map(|e| e.to_string()) });
         }
         
         // Check and retry SMTP if needed
         let retry_smtp = {
-            let account = &self.accounts[index];
+            let account = &self.account;
             let account_lock = account.lock().await;
             account_lock.smtp_status == ConnectionStatus::Failed
         };
@@ -295,21 +239,226 @@ impl AccountManager {
         if retry_smtp {
             // Retry SMTP connection
             let result = {
-                let smtp_client = &self.smtp_clients[index];
+                let smtp_client = &self.smtp_client;
                 let mut client = smtp_client.lock().await;
                 client.connect().await
             };
             
             // Handle result
-            if let Err(e) = result {
+            if let Err(e) = &result {
                 let error_msg = format!("SMTP connection retry failed: {}", e);
                 {
-                    let account = &self.accounts[index];
+                    let account = &self.account;
                     let mut account_lock = account.lock().await;
                     account_lock.last_error = Some(e.to_string());
                 }
                 error!("{}", error_msg);
             }
+            outcomes.push(RetryOutcome { protocol: "SMTP", error: result.err().map(|e| e.to_string()) });
+        }
+        
+        outcomes
+    }
+    
+    /// Drops and reopens every connection of the account.
+    ///
+    /// Unlike retrying, this also replaces sessions that report being
+    /// connected but have stopped responding.
+    ///
+    /// # Returns
+    /// A Result containing a boolean indicating if any connection was successful
+    pub async fn reconnect(&self) -> Result<bool> {
+        self.disconnect().await;
+        self.connect().await
+    }
+    
+    /// Enables or disables the account, disconnecting it when it's disabled.
+    ///
+    /// Only the account itself changes; the caller saves the configuration.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether the account is in use
+    pub async fn set_enabled(&self, enabled: bool) {
+        self.account.lock().await.config.enabled = enabled;
+        if !enabled {
+            self.disconnect().await;
+        }
+    }
+    
+    /// Disconnects all of the account's clients, logging failures.
+    ///
+    /// Each protocol is given a short timeout, so an unresponsive server
+    /// can't hold up the others or the exit.
+    pub async fn disconnect(&self) {
+        disconnect_with_timeout("IMAP", async {
+            self.imap_client.lock().await.disconnect().await
+        }).await;
+        
+        disconnect_with_timeout("POP3", async {
+            self.pop3_client.lock().await.disconnect().await
+        }).await;
+        
+        disconnect_with_timeout("SMTP", async {
+            self.smtp_client.lock().await.disconnect().await
+        }).await;
+        
+        // A protocol that timed out counts as disconnected, so connecting replaces it
+        let mut account = self.account.lock().await;
+        let account = &mut *account;
+        for status in [&mut account.imap_status, &mut account.pop3_status, &mut account.smtp_status] {
+            if *status == ConnectionStatus::Connected {
+                *status = ConnectionStatus::Disconnected;
+            }
+        }
+    }
+}
+
+/// The result of retrying one protocol of an account.
+#[derive(Debug, Clone)]
+pub struct RetryOutcome {
+    /// Name of the protocol, e.g. "IMAP"
+    pub protocol: &'static str,
+    /// Why connecting failed again, or None if it connected
+    pub error: Option<String>,
+}
+
+/// Runs a protocol disconnect, giving up after DISCONNECT_TIMEOUT.
+///
+/// # Parameters
+/// - `protocol`: Name of the protocol, for logging
+/// - `disconnect`: The disconnect to run
+async fn disconnect_with_timeout(protocol: &str, disconnect: impl Future<Output = Result<()>>) {
+    match tokio::time::timeout(DISCONNECT_TIMEOUT, disconnect).await {
+        Ok(Ok(())) => {},
+        Ok(Err(e)) => error!("{} disconnect failed: {}", protocol, e),
+        Err(_) => warn!("{} disconnect timed out after {}s", protocol, DISCONNECT_TIMEOUT.as_secs()),
+    }
+}
+
+/// Manages email accounts and their connections.
+///
+/// Each account is kept in one bundle with its clients, and accounts are
+/// looked up by ID rather than by position, so the clients can't drift out
+/// of step with the accounts they belong to, and an operation can't reach
+/// another account when the list changes while it waits.
+pub struct AccountManager {
+    /// Active accounts with their clients, in display order
+    bundles: Vec<AccountBundle>,
+}
+
+impl AccountManager {
+    /// Creates a new AccountManager instance.
+    ///
+    /// # Returns
+    /// A new AccountManager instance
+    pub fn new() -> Self {
+        Self {
+            bundles: Vec::new(),
+        }
+    }
+    
+    /// Loads accounts from configuration.
+    ///
+    /// # Parameters
+    /// - `config_manager`: The configuration manager
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn load_accounts(&mut self, config_manager: &ConfigManager) -> Result<()> {
+        // Create accounts and their clients from the configuration
+        let config = config_manager.get_config();
+        self.bundles = config.accounts
+            .iter()
+            .map(|account_config| AccountBundle::new(account_config.clone()))
+            .collect();
+        
+        Ok(())
+    }
+    
+    /// Gets the number of accounts.
+    ///
+    /// # Returns
+    /// The number of accounts
+    pub fn account_count(&self) -> usize {
+        self.bundles.len()
+    }
+    
+    /// Gets a specific account.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    ///
+    /// # Returns
+    /// An Option containing a reference to the account
+    pub fn get_account(&self, account_id: &str) -> Option<&Arc<Mutex<Account>>> {
+        self.get_bundle(account_id).map(|bundle| &bundle.account)
+    }
+    
+    /// Gets a specific account with its clients.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    ///
+    /// # Returns
+    /// An Option containing a reference to the account bundle
+    pub fn get_bundle(&self, account_id: &str) -> Option<&AccountBundle> {
+        self.bundles.iter().find(|bundle| bundle.id == account_id)
+    }
+    
+    /// Gets every account with its clients.
+    ///
+    /// # Returns
+    /// The account bundles, in display order
+    pub fn get_bundles(&self) -> Vec<AccountBundle> {
+        self.bundles.clone()
+    }
+    
+    /// Finds the index of an account by its ID.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    ///
+    /// # Returns
+    /// An Option containing the account index
+    pub fn find_account_index(&self, account_id: &str) -> Option<usize> {
+        self.bundles.iter().position(|bundle| bundle.id == account_id)
+    }
+    
+    /// Checks if an account is still one of the managed accounts.
+    ///
+    /// Editing an account replaces it and deleting one removes it, so an
+    /// operation that started before either can tell its results are stale.
+    ///
+    /// # Parameters
+    /// - `account`: The account the operation started with
+    ///
+    /// # Returns
+    /// true if the account is still managed as it was, false otherwise
+    pub fn is_current(&self, account: &Arc<Mutex<Account>>) -> bool {
+        self.bundles.iter().any(|bundle| Arc::ptr_eq(&bundle.account, account))
+    }
+    
+    /// Connects to the specified account.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    ///
+    /// # Returns
+    /// A Result containing a boolean indicating if any connection was successful
+    pub async fn connect_account(&self, account_id: &str) -> Result<bool> {
+        match self.get_bundle(account_id) {
+            Some(bundle) => bundle.connect().await,
+            None => Ok(false),
+        }
+    }
+    
+    /// Disconnects all clients.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn disconnect_all_clients(&self) -> Result<()> {
+        for bundle in &self.bundles {
+            bundle.disconnect().await;
         }
         
         Ok(())
@@ -318,196 +467,94 @@ impl AccountManager {
     /// Gets the IMAP client for the specified account.
     ///
     /// # Parameters
-    /// - `index`: The account index
+    /// - `account_id`: The account ID
     ///
     /// # Returns
     /// An Option containing a reference to the IMAP client
-    pub fn get_imap_client(&self, index: usize) -> Option<&Arc<Mutex<ImapClient>>> {
-        self.imap_clients.get(index)
+    pub fn get_imap_client(&self, account_id: &str) -> Option<&Arc<Mutex<ImapClient>>> {
+        self.get_bundle(account_id).map(|bundle| &bundle.imap_client)
     }
     
     /// Gets the POP3 client for the specified account.
     ///
     /// # Parameters
-    /// - `index`: The account index
+    /// - `account_id`: The account ID
     ///
     /// # Returns
     /// An Option containing a reference to the POP3 client
-    pub fn get_pop3_client(&self, index: usize) -> Option<&Arc<Mutex<Pop3Client>>> {
-        self.pop3_clients.get(index)
+    pub fn get_pop3_client(&self, account_id: &str) -> Option<&Arc<Mutex<Pop3Client>>> {
+        self.get_bundle(account_id).map(|bundle| &bundle.pop3_client)
     }
     
     /// Gets the SMTP client for the specified account.
     ///
     /// # Parameters
-    /// - `index`: The account index
+    /// - `account_id`: The account ID
     ///
     /// # Returns
     /// An Option containing a reference to the SMTP client
-    pub fn get_smtp_client(&self, index: usize) -> Option<&Arc<Mutex<SmtpClient>>> {
-        self.smtp_clients.get(index)
+    pub fn get_smtp_client(&self, account_id: &str) -> Option<&Arc<Mutex<SmtpClient>>> {
+        self.get_bundle(account_id).map(|bundle| &bundle.smtp_client)
     }
     
     /// Adds a new account.
     ///
+    /// Only the account and its clients are created; the caller saves the
+    /// configuration.
+    ///
     /// # Parameters
     /// - `account_config`: The account configuration
-    /// - `config_manager`: The configuration manager
-    ///
-    /// # Returns
-    /// A Result indicating success or failure
-    pub async fn add_account(&mut self, account_config: EmailAccount, config_manager: &mut ConfigManager) -> Result<()> {
-        // Add account to configuration
-        let config = config_manager.get_config_mut();
-        config.accounts.push(account_config.clone());
-        
-        // Save configuration
-        config_manager.save_config()?;
-        
-        // Create account object
-        let account = Account::new(account_config);
-        let account_arc = Arc::new(Mutex::new(account));
-        
-        // Create protocol clients
-        let imap_client = Arc::new(Mutex::new(ImapClient::new(account_arc.clone())));
-        let pop3_client = Arc::new(Mutex::new(Pop3Client::new(account_arc.clone())));
-        let smtp_client = Arc::new(Mutex::new(SmtpClient::new(account_arc.clone())));
-        
-        // Store clients
-        self.accounts.push(account_arc);
-        self.imap_clients.push(imap_client);
-        self.pop3_clients.push(pop3_client);
-        self.smtp_clients.push(smtp_client);
-        
-        Ok(())
+    pub fn add_account(&mut self, account_config: EmailAccount) {
+        self.bundles.push(AccountBundle::new(account_config));
     }
     
     /// Updates an existing account.
     ///
+    /// The account gets new clients rather than reusing the old ones, so
+    /// a fetch that started before the edit keeps its own account and
+    /// clients, and can tell it's stale with `is_current`. The old clients
+    /// are handed back to be disconnected once the manager is released.
+    ///
     /// # Parameters
-    /// - `index`: The account index
-    /// - `account_config`: The updated account configuration
-    /// - `config_manager`: The configuration manager
+    /// - `account_config`: The updated account configuration, with the ID of the account it replaces
     ///
     /// # Returns
-    /// A Result indicating success or failure
-    pub async fn update_account(&mut self, index: usize, account_config: EmailAccount, config_manager: &mut ConfigManager) -> Result<()> {
-        if index >= self.accounts.len() {
-            return Ok(());
-        }
-        
-        // Update account in configuration
-        let config = config_manager.get_config_mut();
-        if let Some(existing_config) = config.accounts.get_mut(index) {
-            *existing_config = account_config.clone();
-        } else {
-            return Ok(());
-        }
-        
-        // Save configuration
-        config_manager.save_config()?;
-        
-        // Disconnect existing clients
-        {
-            let imap_client = &self.imap_clients[index];
-            let mut client = imap_client.lock().await;
-            if let Err(e) = client.disconnect().await {
-                error!("IMAP disconnect failed: {}", e);
-            }
-        }
-        
-        {
-            let pop3_client = &self.pop3_clients[index];
-            let mut client = pop3_client.lock().await;
-            if let Err(e) = client.disconnect().await {
-                error!("POP3 disconnect failed: {}", e);
-            }
-        }
-        
-        {
-            let smtp_client = &self.smtp_clients[index];
-            let mut client = smtp_client.lock().await;
-            if let Err(e) = client.disconnect().await {
-                error!("SMTP disconnect failed: {}", e);
-            }
-        }
-        
-        // Update account object
-        {
-            let account = &self.accounts[index];
-            let mut account_lock = account.lock().await;
-            *account_lock = Account::new(account_config);
+    /// An Option containing the replaced account bundle
+    pub fn update_account(&mut self, account_config: EmailAccount) -> Option<AccountBundle> {
+        let bundle = self.bundles.iter_mut().find(|bundle| bundle.id == account_config.id)?;
+        Some(std::mem::replace(bundle, AccountBundle::new(account_config)))
+    }
+    
+    /// Moves an account with its clients to a new position.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `new_index`: The index to move the account to
+    pub fn move_account(&mut self, account_id: &str, new_index: usize) {
+        let Some(index) = self.find_account_index(account_id) else {
+            return;
+        };
+        if new_index >= self.bundles.len() {
+            return;
         }
         
-        Ok(())
+        let bundle = self.bundles.remove(index);
+        self.bundles.insert(new_index, bundle);
     }
     
     /// Deletes an account.
     ///
+    /// The removed clients are handed back to be disconnected once the
+    /// manager is released; the caller saves the configuration.
+    ///
     /// # Parameters
-    /// - `index`: The account index
-    /// - `config_manager`: The configuration manager
+    /// - `account_id`: The account ID
     ///
     /// # Returns
-    /// A Result indicating success or failure
-    pub async fn delete_account(&mut self, index: usize, config_manager: &mut ConfigManager) -> Result<()> {
-        if index >= self.accounts.len() {
-            return Ok(());
-        }
-        
-        // Get account ID
-        let account_id = {
-            let account = &self.accounts[index];
-            let account_lock = account.lock().await;
-            account_lock.config.id.clone()
-        };
-        
-        // Disconnect clients
-        {
-            let imap_client = &self.imap_clients[index];
-            let mut client = imap_client.lock().await;
-            if let Err(e) = client.disconnect().await {
-                error!("IMAP disconnect failed: {}", e);
-            }
-        }
-        
-        {
-            let pop3_client = &self.pop3_clients[index];
-            let mut client = pop3_client.lock().await;
-            if let Err(e) = client.disconnect().await {
-                error!("POP3 disconnect failed: {}", e);
-            }
-        }
-        
-        {
-            let smtp_client = &self.smtp_clients[index];
-            let mut client = smtp_client.lock().await;
-            if let Err(e) = client.disconnect().await {
-                error!("SMTP disconnect failed: {}", e);
-            }
-        }
-        
-        // Remove account from configuration
-        let config = config_manager.get_config_mut();
-        config.accounts.remove(index);
-        
-        // If the removed account was the default, clear the default
-        if let Some(default_id) = &config.settings.default_account {
-            if default_id == &account_id {
-                config.settings.default_account = None;
-            }
-        }
-        
-        // Save configuration
-        config_manager.save_config()?;
-        
-        // Remove clients
-        self.accounts.remove(index);
-        self.imap_clients.remove(index);
-        self.pop3_clients.remove(index);
-        self.smtp_clients.remove(index);
-        
-        Ok(())
+    /// An Option containing the removed account bundle
+    pub fn delete_account(&mut self, account_id: &str) -> Option<AccountBundle> {
+        let index = self.find_account_index(account_id)?;
+        Some(self.bundles.remove(index))
     }
 }
 
@@ -1,11 +1,26 @@
 //! Application state management for the Linksy email client.
 
-use crate::config::ConfigManager;
-use crate::models::{Account, AccountSummary, Email};
+use crate::config::{ConfigManager, EmailAccount, FolderView, SavedSearch};
+use crate::models::{Account, AccountSummary, Email, EmailKey, RecentRecipient, SCHEDULED_FOLDER};
+use crate::state::{AccountBundle, Outbox, TypeAhead};
 use crate::storage::EmailStorage;
 use crate::ui::views::account_config::AccountFormState;
+use crate::ui::views::attachments::AttachmentPickerState;
+use crate::ui::views::compose::ComposeState;
+use crate::ui::views::find::FindState;
+use crate::ui::views::grouping::GroupingState;
+use crate::ui::views::links::LinkPickerState;
+use crate::ui::views::log::LogViewState;
+use crate::ui::views::prompt::{ConfirmState, PromptState};
+use crate::ui::views::search::SearchState;
+use crate::ui::views::tags::TagPickerState;
+use crate::ui::views::templates::{TemplateFormState, TemplatePickerState};
+use anyhow::Result;
+use log::error;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::SystemTime;
 use tokio::sync::Mutex;
 
 /// Represents the different views in the application.
@@ -22,10 +37,15 @@ pub enum View {
     /// Compose email view
     ComposeEmail,
     /// Settings view
-    #[allow(dead_code)]
     Settings,
+    /// Template editing form
+    TemplateForm,
     /// Account configuration view
     AccountConfig,
+    /// Account connection status dashboard view
+    AccountStatus,
+    /// Recent log messages view
+    Log,
 }
 
 /// Represents the main application state.
@@ -34,32 +54,84 @@ pub struct AppState {
     pub config_manager: ConfigManager,
     /// Email storage
     pub storage: EmailStorage,
-    /// Active accounts
-    pub accounts: Vec<Arc<Mutex<Account>>>,
+    /// Active accounts with their clients, as listed by the account manager
+    pub accounts: Vec<AccountBundle>,
     /// Account summaries for rendering (non-mutex-protected)
     pub account_summaries: Vec<AccountSummary>,
     /// Currently selected account index
     pub selected_account: Option<usize>,
     /// Currently selected folder
     pub selected_folder: String,
+    /// Index of the highlighted entry in the folder list
+    pub selected_folder_index: usize,
+    /// Letters typed in the folders view to jump to a folder
+    pub folder_jump: TypeAhead,
     /// Currently selected email index
     pub selected_email: Option<usize>,
-    /// Currently displayed emails
-    pub emails: Vec<Email>,
-    /// Currently viewed email
-    pub viewed_email: Option<Email>,
+    /// The listed emails selected for a batch change
+    pub selected_emails: HashSet<EmailKey>,
+    /// Messages written to temporary files for the mail viewer, removed on exit
+    pub viewer_files: Vec<PathBuf>,
+    /// Currently displayed emails, shared with the render snapshot
+    pub emails: Arc<Vec<Email>>,
+    /// Currently viewed email, shared with the render snapshot
+    pub viewed_email: Option<Arc<Email>>,
     /// Application running state
     pub running: bool,
     /// Current view
     pub current_view: View,
     /// Status message
     pub status_message: Option<String>,
+    /// Message shown while a background fetch is in progress
+    pub loading_message: Option<String>,
     /// Base directory for configuration and storage
     pub base_dir: PathBuf,
     /// Email body scroll offset
     pub email_scroll_offset: u16,
+    /// Whether quoted text in the viewed email is expanded
+    pub show_quoted_text: bool,
+    /// Whether remote images in the viewed email were loaded on request
+    pub show_remote_content: bool,
+    /// The text being found in the viewed email, if any
+    pub body_find: Option<FindState>,
     /// Account configuration form state
     pub account_form_state: Option<AccountFormState>,
+    /// Link picker popup state, when the popup is open
+    pub link_picker: Option<LinkPickerState>,
+    /// Attachment list popup state, when the popup is open
+    pub attachment_picker: Option<AttachmentPickerState>,
+    /// Tag picker popup state, when the popup is open
+    pub tag_picker: Option<TagPickerState>,
+    /// Grouping of the email list by sender, when it's turned on
+    pub grouping: Option<GroupingState>,
+    /// Input prompt state, when a prompt is open
+    pub prompt: Option<PromptState>,
+    /// Yes/no confirmation state, when a confirmation is open
+    pub confirm: Option<ConfirmState>,
+    /// Compose form state
+    pub compose_state: Option<ComposeState>,
+    /// Whether the compose body should be opened in the external editor
+    pub external_editor_requested: bool,
+    /// Messages waiting to be sent
+    pub outbox: Outbox,
+    /// Index of the template selected in the settings view
+    pub selected_template: Option<usize>,
+    /// Template form state
+    pub template_form: Option<TemplateFormState>,
+    /// Template picker popup state, when the popup is open
+    pub template_picker: Option<TemplatePickerState>,
+    /// Whether connections are skipped and only cached mail is shown
+    pub offline: bool,
+    /// The search whose results are listed, if any
+    pub search: Option<SearchState>,
+    /// Whether the next search covers every folder instead of the current one
+    pub search_all_folders: bool,
+    /// Log view state, while the log is open
+    pub log_view: Option<LogViewState>,
+    /// The view the status dashboard was opened from with F2, returned to when it's closed
+    pub status_opened_from: Option<View>,
+    /// Addresses mail was sent to, most recently used first, suggested in compose
+    pub recent_recipients: Vec<RecentRecipient>,
 }
 
 impl AppState {
@@ -73,6 +145,12 @@ impl AppState {
     /// # Returns
     /// A new AppState instance
     pub fn new(config_manager: ConfigManager, storage: EmailStorage, base_dir: PathBuf) -> Self {
+        let offline = config_manager.get_config().settings.offline;
+        let recent_recipients = storage.get_recent_recipients().unwrap_or_else(|e| {
+            error!("Failed to load recent recipients: {}", e);
+            Vec::new()
+        });
+        
         Self {
             config_manager,
             storage,
@@ -80,32 +158,100 @@ impl AppState {
             account_summaries: Vec::new(),
             selected_account: None,
             selected_folder: "INBOX".to_string(),
+            selected_folder_index: 0,
+            folder_jump: TypeAhead::new(),
             selected_email: None,
-            emails: Vec::new(),
+            selected_emails: HashSet::new(),
+            viewer_files: Vec::new(),
+            emails: Arc::new(Vec::new()),
             viewed_email: None,
             running: true,
             current_view: View::Accounts,
             status_message: None,
+            loading_message: None,
             base_dir,
             email_scroll_offset: 0,
+            show_quoted_text: false,
+            show_remote_content: false,
+            body_find: None,
             account_form_state: None,
+            link_picker: None,
+            attachment_picker: None,
+            tag_picker: None,
+            grouping: None,
+            prompt: None,
+            confirm: None,
+            compose_state: None,
+            external_editor_requested: false,
+            outbox: Outbox::new(),
+            selected_template: None,
+            template_form: None,
+            template_picker: None,
+            offline,
+            search: None,
+            search_all_folders: false,
+            log_view: None,
+            status_opened_from: None,
+            recent_recipients,
         }
     }
     
+    /// Remembers the recipients of a sent email for suggestions in compose.
+    ///
+    /// # Parameters
+    /// - `email`: The sent email
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn record_recipients(&mut self, email: &Email) -> Result<()> {
+        let addresses: Vec<String> = email.to.iter().chain(&email.cc).chain(&email.bcc).cloned().collect();
+        self.storage.record_recipients(&addresses)?;
+        self.recent_recipients = self.storage.get_recent_recipients()?;
+        
+        Ok(())
+    }
+    
+    /// Sets the accounts to the account manager's list and updates their summaries.
+    ///
+    /// # Parameters
+    /// - `accounts`: The account bundles, in display order
+    pub fn set_accounts(&mut self, accounts: Vec<AccountBundle>) {
+        self.accounts = accounts;
+        self.update_account_summaries();
+    }
+    
     /// Updates account summaries from the accounts.
     /// This should be called whenever accounts are updated.
-    pub fn update_account_summaries(&mut self) {
-        // Clear existing summaries
-        self.account_summaries.clear();
+    ///
+    /// Accounts that are currently locked keep their previous summary so the
+    /// list doesn't shrink or shift while a connection is in progress.
+    ///
+    /// # Returns
+    /// true if any summary changed, false otherwise
+    pub fn update_account_summaries(&mut self) -> bool {
+        let previous = std::mem::take(&mut self.account_summaries);
         
         // Create new summaries from accounts
-        for account_mutex in &self.accounts {
+        for bundle in &self.accounts {
             // Try to get a non-blocking lock on the account
-            if let Ok(account) = account_mutex.try_lock() {
+            if let Ok(account) = bundle.account.try_lock() {
                 // Create a summary and add it to the list
                 self.account_summaries.push(account.to_summary());
+            } else if let Some(summary) = previous.iter().find(|summary| summary.id == bundle.id) {
+                // Fall back to the account's last known summary, wherever it was listed
+                self.account_summaries.push(summary.clone());
             }
         }
+        
+        self.account_summaries != previous
+    }
+    
+    /// Gets the number of unread emails across all accounts.
+    ///
+    /// # Returns
+    /// The sum of the unread counts in the account summaries
+    pub fn total_unread(&self) -> usize {
+        self.account_summaries.iter().map(|summary| summary.unread_count).sum()
     }
     
     /// Sets a status message.
@@ -129,6 +275,22 @@ impl AppState {
         self.status_message.as_ref()
     }
     
+    /// Sets or clears the loading indicator message.
+    ///
+    /// # Parameters
+    /// - `message`: The message to show, or None to hide the indicator
+    pub fn set_loading_message(&mut self, message: Option<String>) {
+        self.loading_message = message;
+    }
+    
+    /// Gets the loading indicator message.
+    ///
+    /// # Returns
+    /// An Option containing the loading message
+    pub fn get_loading_message(&self) -> Option<&String> {
+        self.loading_message.as_ref()
+    }
+    
     /// Gets the current view.
     ///
     /// # Returns
@@ -169,11 +331,26 @@ impl AppState {
         self.selected_account
     }
     
+    /// Gets the ID of the selected account.
+    ///
+    /// # Returns
+    /// An Option containing the selected account ID
+    pub fn get_selected_account_id(&self) -> Option<String> {
+        self.selected_account
+            .and_then(|index| self.accounts.get(index))
+            .map(|bundle| bundle.id.clone())
+    }
+    
     /// Sets the selected account index.
     ///
+    /// A different account clears the batch selection.
+    ///
     /// # Parameters
     /// - `index`: The index to set
     pub fn set_selected_account(&mut self, index: Option<usize>) {
+        if self.selected_account != index {
+            self.selected_emails.clear();
+        }
         self.selected_account = index;
     }
     
@@ -187,12 +364,138 @@ impl AppState {
     
     /// Sets the selected folder.
     ///
+    /// A different folder clears the batch selection.
+    ///
     /// # Parameters
     /// - `folder`: The folder to set
     pub fn set_selected_folder(&mut self, folder: String) {
+        if self.selected_folder != folder {
+            self.selected_emails.clear();
+        }
         self.selected_folder = folder;
     }
     
+    /// Gets the configuration of the selected account.
+    ///
+    /// # Returns
+    /// The account configuration, or None if no account is selected
+    pub fn get_selected_account_config(&self) -> Option<&EmailAccount> {
+        let summary = self.selected_account.and_then(|index| self.account_summaries.get(index))?;
+        self.config_manager.get_config().accounts.iter().find(|account| account.id == summary.id)
+    }
+    
+    /// Gets the folders listed for the selected account.
+    ///
+    /// The account's folders come first, followed by the Scheduled folder.
+    /// INBOX is listed if the account is busy or has no folders yet. A
+    /// send-only account only has the Scheduled folder.
+    ///
+    /// # Returns
+    /// The folder names and the time each folder was last synced
+    pub fn get_folder_list(&self) -> (Vec<String>, HashMap<String, SystemTime>) {
+        if self.is_selected_account_send_only() {
+            return (vec![SCHEDULED_FOLDER.to_string()], HashMap::new());
+        }
+        
+        // Try to get a non-blocking lock on the account
+        let account_folders = self.selected_account
+            .and_then(|index| self.accounts.get(index))
+            .and_then(|bundle| bundle.account.try_lock().ok())
+            .map(|account| (account.folders.clone(), account.last_synced.clone()));
+        
+        let (mut folders, last_synced) = match account_folders {
+            Some((folders, last_synced)) if !folders.is_empty() => (folders, last_synced),
+            _ => (vec!["INBOX".to_string()], HashMap::new()),
+        };
+        folders.push(SCHEDULED_FOLDER.to_string());
+        
+        (folders, last_synced)
+    }
+    
+    /// Gets the sort order and filter of the selected folder.
+    ///
+    /// # Returns
+    /// The saved preferences, or the default newest-first view of every email
+    pub fn folder_view(&self) -> FolderView {
+        let account = self.selected_account
+            .and_then(|index| self.account_summaries.get(index))
+            .map(|summary| summary.id.clone())
+            .unwrap_or_default();
+        
+        self.config_manager.get_config().folder_views.iter()
+            .find(|view| view.account == account && view.folder == self.selected_folder)
+            .cloned()
+            .unwrap_or(FolderView {
+                account,
+                folder: self.selected_folder.clone(),
+                ..FolderView::default()
+            })
+    }
+    
+    /// Gets the saved searches listed below the folders.
+    ///
+    /// # Returns
+    /// The saved searches, or none for a send-only account
+    pub fn saved_searches(&self) -> &[SavedSearch] {
+        if self.is_selected_account_send_only() {
+            &[]
+        } else {
+            &self.config_manager.get_config().saved_searches
+        }
+    }
+    
+    /// Gets the saved search selected in the folders view.
+    ///
+    /// # Returns
+    /// The index of the saved search, or None if a folder is selected
+    pub fn selected_saved_search(&self) -> Option<usize> {
+        let (folders, _) = self.get_folder_list();
+        self.selected_folder_index.checked_sub(folders.len())
+            .filter(|index| *index < self.saved_searches().len())
+    }
+    
+    /// Checks if the selected account can only send, having neither IMAP nor POP3.
+    ///
+    /// # Returns
+    /// true if the selected account is send-only, false otherwise
+    pub fn is_selected_account_send_only(&self) -> bool {
+        self.selected_account
+            .and_then(|index| self.account_summaries.get(index))
+            .is_some_and(|summary| summary.send_only)
+    }
+    
+    /// Checks if the selected folder is the selected account's Sent folder.
+    ///
+    /// # Returns
+    /// true if the selected folder holds the account's sent messages, false otherwise
+    pub fn is_sent_folder_selected(&self) -> bool {
+        self.selected_account
+            .and_then(|index| self.account_summaries.get(index))
+            .and_then(|summary| summary.sent_folder.as_deref())
+            .is_some_and(|sent| sent == self.selected_folder)
+    }
+    
+    /// Checks if the selected account is disabled.
+    ///
+    /// # Returns
+    /// true if the selected account is disabled, false otherwise
+    pub fn is_selected_account_disabled(&self) -> bool {
+        self.selected_account
+            .and_then(|index| self.account_summaries.get(index))
+            .is_some_and(|summary| !summary.enabled)
+    }
+    
+    /// Gets the folder a search starts from.
+    ///
+    /// While search results are listed, this is the folder the search was
+    /// started from rather than the folder of the last opened result.
+    ///
+    /// # Returns
+    /// The folder name
+    pub fn search_folder(&self) -> &str {
+        self.search.as_ref().map_or(&self.selected_folder, |search| &search.folder)
+    }
+    
     /// Gets the selected email index.
     ///
     /// # Returns
@@ -201,6 +504,19 @@ impl AppState {
         self.selected_email
     }
     
+    /// Checks if an account is the selected one.
+    ///
+    /// # Parameters
+    /// - `account`: The account to check
+    ///
+    /// # Returns
+    /// true if the account is listed at the selected index, false otherwise
+    pub fn is_selected_account(&self, account: &Arc<Mutex<Account>>) -> bool {
+        self.selected_account
+            .and_then(|index| self.accounts.get(index))
+            .is_some_and(|selected| Arc::ptr_eq(&selected.account, account))
+    }
+    
     /// Sets the selected email index.
     ///
     /// # Parameters
@@ -209,12 +525,30 @@ impl AppState {
         self.selected_email = index;
     }
     
+    /// Keeps the selected email index within the listed emails.
+    ///
+    /// Call this after changing the email list. An index past the end
+    /// selects the last email and no index selects the first; with no
+    /// emails listed, nothing is selected.
+    pub fn clamp_selection(&mut self) {
+        self.selected_email = match self.emails.len() {
+            0 => None,
+            len => Some(self.selected_email.unwrap_or(0).min(len - 1)),
+        };
+        
+        // Emails that left the list leave the batch selection too
+        if !self.selected_emails.is_empty() {
+            let listed: HashSet<EmailKey> = self.emails.iter().map(Email::key).collect();
+            self.selected_emails.retain(|key| listed.contains(key));
+        }
+    }
+    
     /// Gets the viewed email.
     ///
     /// # Returns
     /// An Option containing a reference to the viewed email
     pub fn get_viewed_email(&self) -> Option<&Email> {
-        self.viewed_email.as_ref()
+        self.viewed_email.as_deref()
     }
     
     /// Sets the viewed email.
@@ -222,7 +556,7 @@ impl AppState {
     /// # Parameters
     /// - `email`: The email to set
     pub fn set_viewed_email(&mut self, email: Option<Email>) {
-        self.viewed_email = email;
+        self.viewed_email = email.map(Arc::new);
     }
     
     /// Gets the email body scroll offset.
@@ -241,6 +575,43 @@ impl AppState {
         self.email_scroll_offset = offset;
     }
     
+    /// Checks if quoted text in the viewed email is expanded.
+    ///
+    /// # Returns
+    /// true if quoted text is shown, false if it is collapsed
+    pub fn is_showing_quoted_text(&self) -> bool {
+        self.show_quoted_text
+    }
+    
+    /// Sets whether quoted text in the viewed email is expanded.
+    ///
+    /// # Parameters
+    /// - `show`: Whether to show quoted text
+    pub fn set_show_quoted_text(&mut self, show: bool) {
+        self.show_quoted_text = show;
+    }
+    
+    /// Checks if remote images in the viewed email are shown.
+    ///
+    /// They are shown if the user asked for them, if the sender is always
+    /// allowed or if remote content isn't blocked at all.
+    ///
+    /// # Returns
+    /// true if remote images are shown, false if they are blocked
+    pub fn is_showing_remote_content(&self) -> bool {
+        let settings = &self.config_manager.get_config().settings;
+        self.show_remote_content
+            || self.viewed_email.as_ref().is_some_and(|email| settings.allows_remote_content(&email.from))
+    }
+    
+    /// Sets whether remote images in the viewed email were loaded on request.
+    ///
+    /// # Parameters
+    /// - `show`: Whether to show remote images
+    pub fn set_show_remote_content(&mut self, show: bool) {
+        self.show_remote_content = show;
+    }
+    
     /// Gets the account configuration form state.
     ///
     /// # Returns
@@ -264,4 +635,137 @@ impl AppState {
     pub fn set_account_form_state(&mut self, state: Option<AccountFormState>) {
         self.account_form_state = state;
     }
+    
+    /// Gets the link picker popup state.
+    ///
+    /// # Returns
+    /// An Option containing a reference to the link picker state
+    pub fn get_link_picker(&self) -> Option<&LinkPickerState> {
+        self.link_picker.as_ref()
+    }
+    
+    /// Sets the link picker popup state.
+    ///
+    /// # Parameters
+    /// - `picker`: The state to set, or None to close the popup
+    pub fn set_link_picker(&mut self, picker: Option<LinkPickerState>) {
+        self.link_picker = picker;
+    }
+    
+    /// Gets the attachment list popup state.
+    ///
+    /// # Returns
+    /// An Option containing a reference to the attachment list state
+    pub fn get_attachment_picker(&self) -> Option<&AttachmentPickerState> {
+        self.attachment_picker.as_ref()
+    }
+    
+    /// Sets the attachment list popup state.
+    ///
+    /// # Parameters
+    /// - `picker`: The state to set, or None to close the popup
+    pub fn set_attachment_picker(&mut self, picker: Option<AttachmentPickerState>) {
+        self.attachment_picker = picker;
+    }
+    
+    /// Gets the tag picker popup state.
+    ///
+    /// # Returns
+    /// An Option containing a reference to the tag picker state
+    pub fn get_tag_picker(&self) -> Option<&TagPickerState> {
+        self.tag_picker.as_ref()
+    }
+    
+    /// Sets the tag picker popup state.
+    ///
+    /// # Parameters
+    /// - `picker`: The state to set, or None to close the popup
+    pub fn set_tag_picker(&mut self, picker: Option<TagPickerState>) {
+        self.tag_picker = picker;
+    }
+    
+    /// Gets the input prompt state.
+    ///
+    /// # Returns
+    /// An Option containing a reference to the prompt state
+    pub fn get_prompt(&self) -> Option<&PromptState> {
+        self.prompt.as_ref()
+    }
+    
+    /// Sets the input prompt state.
+    ///
+    /// # Parameters
+    /// - `prompt`: The prompt to open, or None to close it
+    pub fn set_prompt(&mut self, prompt: Option<PromptState>) {
+        self.prompt = prompt;
+    }
+    
+    /// Gets the yes/no confirmation state.
+    ///
+    /// # Returns
+    /// An Option containing a reference to the confirmation state
+    pub fn get_confirm(&self) -> Option<&ConfirmState> {
+        self.confirm.as_ref()
+    }
+    
+    /// Sets the yes/no confirmation state.
+    ///
+    /// # Parameters
+    /// - `confirm`: The confirmation to open, or None to close it
+    pub fn set_confirm(&mut self, confirm: Option<ConfirmState>) {
+        self.confirm = confirm;
+    }
+    
+    /// Gets the compose form state.
+    ///
+    /// # Returns
+    /// An Option containing a reference to the compose form state
+    pub fn get_compose_state(&self) -> Option<&ComposeState> {
+        self.compose_state.as_ref()
+    }
+    
+    /// Gets a mutable reference to the compose form state.
+    ///
+    /// # Returns
+    /// An Option containing a mutable reference to the compose form state
+    pub fn get_compose_state_mut(&mut self) -> Option<&mut ComposeState> {
+        self.compose_state.as_mut()
+    }
+    
+    /// Sets the compose form state.
+    ///
+    /// # Parameters
+    /// - `state`: The state to set
+    pub fn set_compose_state(&mut self, state: Option<ComposeState>) {
+        self.compose_state = state;
+    }
+    
+    /// Asks the main loop to open the compose body in the external editor.
+    pub fn request_external_editor(&mut self) {
+        self.external_editor_requested = true;
+    }
+    
+    /// Takes a pending external editor request.
+    ///
+    /// # Returns
+    /// true if the external editor was requested since the last call
+    pub fn take_external_editor_request(&mut self) -> bool {
+        std::mem::take(&mut self.external_editor_requested)
+    }
+    
+    /// Gets the template form state.
+    ///
+    /// # Returns
+    /// An Option containing a reference to the template form state
+    pub fn get_template_form(&self) -> Option<&TemplateFormState> {
+        self.template_form.as_ref()
+    }
+    
+    /// Gets the template picker popup state.
+    ///
+    /// # Returns
+    /// An Option containing a reference to the template picker state
+    pub fn get_template_picker(&self) -> Option<&TemplatePickerState> {
+        self.template_picker.as_ref()
+    }
 }
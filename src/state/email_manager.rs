@@ -1,17 +1,18 @@
 //! Email management for the Linksy email client.
 
-use crate::models::{Account, ConnectionStatus, Email};
-use crate::protocols::{ImapClient, Pop3Client};
-use crate::storage::EmailStorage;
-use anyhow::Result;
-use log::error;
+use crate::models::{Account, ConnectionStatus, Email, SearchQuery};
+use crate::protocols::{CommandRejected, FetchedEmails, ImapClient, Pop3Client, SearchResults};
+use crate::storage::EmailStore;
+use anyhow::{anyhow, Result};
+use log::{debug, error, warn};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 /// Manages email operations.
+#[derive(Clone)]
 pub struct EmailManager {
-    /// Email storage
-    storage: EmailStorage,
+    /// Email storage, in the database or in Maildir
+    storage: Arc<dyn EmailStore>,
 }
 
 impl EmailManager {
@@ -22,7 +23,7 @@ impl EmailManager {
     ///
     /// # Returns
     /// A new EmailManager instance
-    pub fn new(storage: EmailStorage) -> Self {
+    pub fn new(storage: Arc<dyn EmailStore>) -> Self {
         Self {
             storage,
         }
@@ -30,15 +31,23 @@ impl EmailManager {
     
     /// Loads emails for the specified account and folder.
     ///
+    /// The cached emails are returned if the server isn't connected or the
+    /// fetch fails. If the fetch is interrupted, the emails fetched so far
+    /// are stored and returned, and the next load resumes from there.
+    /// After a fetch, the cache of the folder is trimmed to `cache_limit`,
+    /// and no more emails than that are listed, so the ones evicted aren't
+    /// downloaded again.
+    ///
     /// # Parameters
     /// - `account`: The account
     /// - `imap_client`: The IMAP client
     /// - `pop3_client`: The POP3 client
     /// - `folder`: The folder/mailbox
     /// - `limit`: Maximum number of emails to fetch
+    /// - `cache_limit`: Most emails to keep cached for the folder, or None for all of them
     ///
     /// # Returns
-    /// A Result containing a vector of emails
+    /// A Result containing the emails and why fetching them was interrupted, if it was
     pub async fn load_emails(
         &self,
         account: &Arc<Mutex<Account>>,
@@ -46,7 +55,8 @@ impl EmailManager {
         pop3_client: Option<&Arc<Mutex<Pop3Client>>>,
         folder: &str,
         limit: usize,
-    ) -> Result<Vec<Email>> {
+        cache_limit: Option<usize>,
+    ) -> Result<FetchedEmails> {
         // Get account ID and check connection status
         let account_id;
         let has_imap;
@@ -64,7 +74,7 @@ impl EmailManager {
         }
         
         // Try to load emails from storage first
-        let mut emails = match self.storage.get_emails(&account_id, folder) {
+        let mut cached = match self.storage.get_emails(&account_id, folder) {
             Ok(emails) => emails,
             Err(e) => {
                 error!("Failed to load emails from storage: {}", e);
@@ -72,52 +82,249 @@ impl EmailManager {
             }
         };
         
-        // Check if account has IMAP and is connected
-        if has_imap && imap_status == ConnectionStatus::Connected && imap_client.is_some() {
-            // Fetch emails from IMAP
-            if let Some(imap_client) = imap_client {
-                let client = imap_client.lock().await;
-                
-                match client.fetch_emails(folder, limit).await {
-                    Ok(fetched_emails) => {
-                        // Store emails in storage
-                        for email in &fetched_emails {
-                            if let Err(e) = self.storage.store_email(email) {
-                                error!("Failed to store email: {}", e);
-                            }
-                        }
-                        
-                        emails = fetched_emails;
-                    },
-                    Err(e) => {
-                        error!("Failed to fetch emails: {}", e);
-                    }
+        // List no more emails than the cache keeps
+        let limit = cache_limit.map_or(limit, |keep| limit.min(keep));
+        
+        // Fetch emails from IMAP or POP3 if connected
+        let (result, by_uid) = match (imap_client, pop3_client) {
+            (Some(imap_client), _) if has_imap && imap_status == ConnectionStatus::Connected => {
+                let uid_validity = self.storage.get_uid_validity(&account_id, folder).unwrap_or_else(|e| {
+                    error!("Failed to read the UIDVALIDITY of {}: {}", folder, e);
+                    None
+                });
+                (imap_client.lock().await.fetch_emails(folder, limit, &cached, uid_validity).await.map(|fetched| (fetched, uid_validity)), true)
+            },
+            (_, Some(pop3_client)) if has_pop3 && pop3_status == ConnectionStatus::Connected => {
+                (pop3_client.lock().await.fetch_emails(limit, &cached).await.map(|fetched| (fetched, None)), false)
+            },
+            _ => return Ok(FetchedEmails { emails: cached, ..FetchedEmails::default() }),
+        };
+        
+        let (mut fetched, cached_uid_validity) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                error!("Failed to fetch emails: {}", e);
+                return Ok(FetchedEmails { emails: cached, ..FetchedEmails::default() });
+            }
+        };
+        
+        // Drop the cache of a renumbered mailbox, whose UIDs now mean other messages
+        if let Some(uid_validity) = fetched.uid_validity.filter(|current| cached_uid_validity != Some(*current)) {
+            if cached_uid_validity.is_some() {
+                if let Err(e) = self.storage.delete_emails(&account_id, folder) {
+                    error!("Failed to clear the cache of {}: {}", folder, e);
                 }
+                cached.clear();
+            }
+            if let Err(e) = self.storage.store_uid_validity(&account_id, folder, uid_validity) {
+                error!("Failed to store the UIDVALIDITY of {}: {}", folder, e);
             }
-        } else if has_pop3 && pop3_status == ConnectionStatus::Connected && pop3_client.is_some() {
-            // Fetch emails from POP3
-            if let Some(pop3_client) = pop3_client {
-                let client = pop3_client.lock().await;
-                
-                match client.fetch_emails(limit).await {
-                    Ok(fetched_emails) => {
-                        // Store emails in storage
-                        for email in &fetched_emails {
-                            if let Err(e) = self.storage.store_email(email) {
-                                error!("Failed to store email: {}", e);
-                            }
-                        }
-                        
-                        emails = fetched_emails;
-                    },
-                    Err(e) => {
-                        error!("Failed to fetch emails: {}", e);
+        }
+        
+        // Store emails in storage, including the ones fetched before an interruption
+        for email in &fetched.emails {
+            if let Err(e) = self.storage.store_email(email) {
+                error!("Failed to store email: {}", e);
+            }
+        }
+        
+        if fetched.interrupted.is_none() {
+            account.lock().await.mark_synced(folder);
+        }
+        
+        // Keep the cache of the folder within its limit
+        if let Some(keep) = cache_limit {
+            match self.storage.trim_emails(&account_id, folder, keep, &fetched.emails) {
+                Ok(0) => {},
+                Ok(removed) => debug!("Removed {} old emails of {} from the cache", removed, folder),
+                Err(e) => error!("Failed to trim the cache of {}: {}", folder, e),
+            }
+        }
+        
+        // Count the unread emails that arrived since the last fetch
+        if !cached.is_empty() {
+            fetched.new_unread = count_new_unread(&fetched.emails, &cached, by_uid);
+        }
+        
+        Ok(fetched)
+    }
+    
+    /// Searches folders of an account for emails matching a query.
+    ///
+    /// The server is searched when IMAP is connected; otherwise the cached
+    /// emails are searched. A folder whose search the server rejects, e.g.
+    /// for criteria it doesn't support, is searched in the cache instead.
+    /// Folders that can't be searched at all are skipped.
+    ///
+    /// # Parameters
+    /// - `account`: The account
+    /// - `imap_client`: The IMAP client, or None to search the cache
+    /// - `folders`: The folders to search
+    /// - `query`: The query to match
+    /// - `limit`: Maximum number of matching emails per folder
+    ///
+    /// # Returns
+    /// A Result containing the matching emails of all folders, and why the server rejected the search if it did
+    pub async fn search_emails(
+        &self,
+        account: &Arc<Mutex<Account>>,
+        imap_client: Option<&Arc<Mutex<ImapClient>>>,
+        folders: &[String],
+        query: &SearchQuery,
+        limit: usize,
+    ) -> Result<SearchResults> {
+        let (account_id, imap_connected) = {
+            let account_lock = account.lock().await;
+            (account_lock.config.id.clone(), account_lock.has_imap() && account_lock.imap_status == ConnectionStatus::Connected)
+        };
+        
+        let mut results = SearchResults::default();
+        let search_cache = |folder: &str| self.storage.get_emails(&account_id, folder).map(|emails| {
+            emails.into_iter()
+                .filter(|email| query.matches(email))
+                .take(limit)
+                .collect::<Vec<_>>()
+        });
+        
+        for folder in folders {
+            let found = match imap_client.filter(|_| imap_connected) {
+                Some(imap_client) => {
+                    let client = imap_client.lock().await;
+                    match client.search_emails(folder, query, limit).await {
+                        Err(e) if e.is::<CommandRejected>() => {
+                            warn!("Searching the cache of {}: {}", folder, e);
+                            results.rejected.get_or_insert_with(|| e.to_string());
+                            search_cache(folder)
+                        },
+                        found => found,
                     }
-                }
+                },
+                None => search_cache(folder),
+            };
+            
+            match found {
+                Ok(emails) => results.emails.extend(emails),
+                Err(e) => error!("Failed to search {}: {}", folder, e),
             }
         }
         
-        Ok(emails)
+        // Sort emails by date (newest first)
+        results.emails.sort_by_key(|e| std::cmp::Reverse(e.date));
+        
+        Ok(results)
+    }
+    
+    /// Downloads the body of an email whose headers were listed and caches it.
+    ///
+    /// # Parameters
+    /// - `imap_client`: The IMAP client
+    /// - `email`: The headers-only or truncated email
+    /// - `folder`: The folder/mailbox
+    /// - `max_bytes`: How much of the message to download, or None for all of it
+    ///
+    /// # Returns
+    /// A Result containing the downloaded email or an error
+    pub async fn fetch_body(
+        &self,
+        imap_client: &Arc<Mutex<ImapClient>>,
+        email: &Email,
+        folder: &str,
+        max_bytes: Option<usize>,
+    ) -> Result<Email> {
+        let client = imap_client.lock().await;
+        let mut full_email = client.fetch_body(folder, &email.id, max_bytes).await?;
+        
+        // Attachments past the cut-off are still known from the listed body structure
+        if full_email.truncated && email.attachments.len() > full_email.attachments.len() {
+            full_email.attachments = email.attachments.clone();
+        }
+        
+        // Local tags aren't on the server, and the body structure is only fetched with the headers
+        full_email.local_tags = email.local_tags.clone();
+        full_email.structure = email.structure.clone();
+        
+        // Update email in storage so reopening it is instant
+        if let Err(e) = self.storage.store_email(&full_email) {
+            error!("Failed to store email: {}", e);
+        }
+        
+        Ok(full_email)
+    }
+    
+    /// Downloads an email again exactly as the server keeps it.
+    ///
+    /// The cache only has what was parsed out of the message, so this
+    /// always goes to the server.
+    ///
+    /// # Parameters
+    /// - `imap_client`: The IMAP client
+    /// - `email`: The email
+    ///
+    /// # Returns
+    /// A Result containing the raw message, or an error
+    pub async fn fetch_raw(&self, imap_client: &Arc<Mutex<ImapClient>>, email: &Email) -> Result<Vec<u8>> {
+        imap_client.lock().await.fetch_raw(&email.folder, &email.id).await
+    }
+    
+    /// Downloads only the text of an email whose headers were listed and caches it.
+    ///
+    /// # Parameters
+    /// - `imap_client`: The IMAP client
+    /// - `email`: The headers-only email, with its body structure
+    /// - `sections`: The sections of the text parts
+    ///
+    /// # Returns
+    /// A Result containing the email with its text, or an error
+    pub async fn fetch_body_parts(
+        &self,
+        imap_client: &Arc<Mutex<ImapClient>>,
+        email: &Email,
+        sections: &[String],
+    ) -> Result<Email> {
+        let entities = imap_client.lock().await.fetch_parts(&email.folder, &email.id, sections).await?;
+        let mut updated_email = email.clone();
+        updated_email.set_body_parts(&entities);
+        
+        // Update email in storage so reopening it is instant
+        if let Err(e) = self.storage.store_email(&updated_email) {
+            error!("Failed to store email: {}", e);
+        }
+        
+        Ok(updated_email)
+    }
+    
+    /// Downloads an attachment that was only listed and caches it.
+    ///
+    /// # Parameters
+    /// - `imap_client`: The IMAP client
+    /// - `email`: The email
+    /// - `index`: Index of the attachment
+    ///
+    /// # Returns
+    /// A Result containing the email with the attachment's data, or an error
+    pub async fn fetch_attachment(
+        &self,
+        imap_client: &Arc<Mutex<ImapClient>>,
+        email: &Email,
+        index: usize,
+    ) -> Result<Email> {
+        let mut updated_email = email.clone();
+        let attachment = updated_email.attachments.get_mut(index)
+            .ok_or_else(|| anyhow!("No attachment {}", index + 1))?;
+        let section = attachment.section.clone()
+            .ok_or_else(|| anyhow!("{} can't be downloaded on its own", attachment.filename))?;
+        
+        let entities = imap_client.lock().await.fetch_parts(&email.folder, &email.id, &[section]).await?;
+        let entity = entities.first()
+            .ok_or_else(|| anyhow!("The server didn't send {}", attachment.filename))?;
+        attachment.set_data_from_part(entity)?;
+        
+        // Update email in storage
+        if let Err(e) = self.storage.store_email(&updated_email) {
+            error!("Failed to store email: {}", e);
+        }
+        
+        Ok(updated_email)
     }
     
     /// Marks an email as read.
@@ -140,7 +347,7 @@ impl EmailManager {
         
         if let Err(e) = client.mark_as_read(folder, &email.id).await {
             error!("Failed to mark email as read: {}", e);
-            return Err(e.into());
+            return Err(e);
         }
         
         // Update email in storage
@@ -174,7 +381,7 @@ impl EmailManager {
         
         if let Err(e) = client.mark_as_unread(folder, &email.id).await {
             error!("Failed to mark email as unread: {}", e);
-            return Err(e.into());
+            return Err(e);
         }
         
         // Update email in storage
@@ -208,7 +415,7 @@ impl EmailManager {
         
         if let Err(e) = client.flag_email(folder, &email.id).await {
             error!("Failed to flag email: {}", e);
-            return Err(e.into());
+            return Err(e);
         }
         
         // Update email in storage
@@ -242,7 +449,7 @@ impl EmailManager {
         
         if let Err(e) = client.unflag_email(folder, &email.id).await {
             error!("Failed to unflag email: {}", e);
-            return Err(e.into());
+            return Err(e);
         }
         
         // Update email in storage
@@ -256,6 +463,63 @@ impl EmailManager {
         Ok(())
     }
     
+    /// Adds a tag to an email or removes it.
+    ///
+    /// Tags are stored as IMAP keywords when the server keeps them, and
+    /// otherwise only in the local cache, also for accounts without IMAP.
+    ///
+    /// # Parameters
+    /// - `imap_client`: The IMAP client, or None to only tag locally
+    /// - `email`: The email to tag
+    /// - `tag`: The tag
+    /// - `add`: true to add the tag, false to remove it
+    ///
+    /// # Returns
+    /// A Result containing the email with its new tags, or an error
+    pub async fn change_tag(
+        &self,
+        imap_client: Option<&Arc<Mutex<ImapClient>>>,
+        email: &Email,
+        tag: &str,
+        add: bool,
+    ) -> Result<Email> {
+        let mut updated_email = email.clone();
+        let is_keyword = email.keywords.iter().any(|keyword| keyword.eq_ignore_ascii_case(tag));
+        
+        if add && !email.has_tag(tag) {
+            // Try the server first and keep the tag locally if it won't store it
+            let stored = match imap_client {
+                Some(imap_client) => imap_client.lock().await.store_keyword(&email.folder, &email.id, tag, true).await?,
+                None => false,
+            };
+            if stored {
+                updated_email.keywords.push(tag.to_string());
+            } else {
+                updated_email.local_tags.push(tag.to_string());
+            }
+        } else if !add {
+            // A keyword can only be removed on the server
+            if is_keyword {
+                let Some(imap_client) = imap_client else {
+                    return Err(anyhow!("Can't remove the keyword {} without an IMAP connection", tag));
+                };
+                if !imap_client.lock().await.store_keyword(&email.folder, &email.id, tag, false).await? {
+                    return Err(anyhow!("The server wouldn't remove the keyword {}", tag));
+                }
+                updated_email.keywords.retain(|keyword| !keyword.eq_ignore_ascii_case(tag));
+            }
+            updated_email.local_tags.retain(|local| !local.eq_ignore_ascii_case(tag));
+        }
+        
+        // Update email in storage
+        if let Err(e) = self.storage.update_email(&updated_email) {
+            error!("Failed to update email in storage: {}", e);
+            return Err(e);
+        }
+        
+        Ok(updated_email)
+    }
+    
     /// Deletes an email.
     ///
     /// # Parameters
@@ -276,7 +540,7 @@ impl EmailManager {
         
         if let Err(e) = client.delete_email(folder, &email.id).await {
             error!("Failed to delete email: {}", e);
-            return Err(e.into());
+            return Err(e);
         }
         
         // Delete email from storage
@@ -288,6 +552,33 @@ impl EmailManager {
         Ok(())
     }
     
+    /// Permanently deletes every message in a folder and clears its cache.
+    ///
+    /// # Parameters
+    /// - `imap_client`: The IMAP client
+    /// - `account_id`: The account ID
+    /// - `folder`: The folder/mailbox to empty
+    ///
+    /// # Returns
+    /// A Result containing the number of messages deleted or an error
+    pub async fn empty_folder(
+        &self,
+        imap_client: &Arc<Mutex<ImapClient>>,
+        account_id: &str,
+        folder: &str,
+    ) -> Result<usize> {
+        // Delete the messages on the server
+        let deleted = imap_client.lock().await.empty_mailbox(folder).await?;
+        
+        // Drop the cached copies
+        if let Err(e) = self.storage.delete_emails(account_id, folder) {
+            error!("Failed to delete emails from storage: {}", e);
+            return Err(e);
+        }
+        
+        Ok(deleted)
+    }
+    
     /// Gets an email from storage.
     ///
     /// # Parameters
@@ -320,3 +611,65 @@ impl EmailManager {
         self.storage.close()
     }
 }
+
+/// Counts the unread emails that arrived since the emails cached before a fetch.
+///
+/// IMAP UIDs grow as messages arrive, so only UIDs above the highest cached
+/// one are new; emails evicted from the cache and listed again aren't.
+/// POP3 IDs don't grow, so there an email is new if it wasn't cached.
+///
+/// # Parameters
+/// - `emails`: The emails listed by the fetch
+/// - `cached`: The emails cached before the fetch
+/// - `by_uid`: Whether the IDs are IMAP UIDs
+///
+/// # Returns
+/// The number of new unread emails
+fn count_new_unread(emails: &[Email], cached: &[Email], by_uid: bool) -> usize {
+    let highest_uid = cached.iter()
+        .filter_map(|email| email.id.parse::<u32>().ok())
+        .max()
+        .filter(|_| by_uid);
+    
+    emails.iter()
+        .filter(|email| !email.is_read)
+        .filter(|email| match (highest_uid, email.id.parse::<u32>()) {
+            (Some(highest), Ok(uid)) => uid > highest,
+            _ => !cached.iter().any(|known| known.id == email.id),
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(id: &str, is_read: bool) -> Email {
+        let mut email = Email::new();
+        email.id = id.to_string();
+        email.is_read = is_read;
+        email
+    }
+
+    #[test]
+    fn counts_unread_uids_above_the_cached_ones() {
+        let cached = vec![email("7", true), email("8", false)];
+        let listed = vec![email("7", true), email("8", false), email("9", false), email("10", true), email("11", false)];
+        assert_eq!(count_new_unread(&listed, &cached, true), 2);
+    }
+
+    #[test]
+    fn leaves_out_evicted_emails_listed_again() {
+        // 5 and 6 were evicted from the cache after the last fetch
+        let cached = vec![email("7", false), email("8", false)];
+        let listed = vec![email("5", false), email("6", false), email("7", false), email("8", false)];
+        assert_eq!(count_new_unread(&listed, &cached, true), 0);
+    }
+
+    #[test]
+    fn counts_pop3_emails_that_werent_cached() {
+        let cached = vec![email("<b@example.com>", false), email("20", false)];
+        let listed = vec![email("<a@example.com>", false), email("<b@example.com>", false), email("3", false)];
+        assert_eq!(count_new_unread(&listed, &cached, false), 2);
+    }
+}
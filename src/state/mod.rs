@@ -6,7 +6,15 @@
 mod app_state;
 mod account_manager;
 mod email_manager;
+mod outbox;
+mod render_snapshot;
+mod shared_state;
+mod type_ahead;
 
 pub use app_state::*;
 pub use account_manager::*;
 pub use email_manager::*;
+pub use outbox::*;
+pub use render_snapshot::*;
+pub use shared_state::*;
+pub use type_ahead::*;
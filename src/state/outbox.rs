@@ -0,0 +1,233 @@
+//! Outbox for messages waiting to be sent by the Linksy email client.
+//!
+//! Messages are queued here when the user sends them and are handed to SMTP
+//! once their send time arrives, which gives a window to recall them.
+
+use crate::models::Email;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Delay before a failed message is tried again.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// A message waiting in the outbox.
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    /// Unique identifier of the entry within the outbox
+    pub id: u64,
+    /// The message to send; its account_id picks the SMTP account
+    pub email: Email,
+    /// When the message should be handed to SMTP
+    pub send_at: Instant,
+    /// Error from the last failed attempt, if any
+    pub last_error: Option<String>,
+}
+
+/// Queue of messages waiting to be sent.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    /// Messages waiting to be sent, in the order they were queued
+    entries: Vec<OutboxEntry>,
+    /// Email IDs of the messages being sent, by entry ID
+    sending: HashMap<u64, String>,
+    /// Identifier for the next queued message
+    next_id: u64,
+}
+
+impl Outbox {
+    /// Creates a new, empty Outbox.
+    ///
+    /// # Returns
+    /// A new Outbox instance
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a message to be sent after a delay.
+    ///
+    /// # Parameters
+    /// - `email`: The message to send
+    /// - `delay`: How long to wait before sending; zero sends on the next flush
+    ///
+    /// # Returns
+    /// The identifier of the queued entry
+    pub fn queue(&mut self, email: Email, delay: Duration) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.entries.push(OutboxEntry {
+            id,
+            email,
+            send_at: Instant::now() + delay,
+            last_error: None,
+        });
+
+        id
+    }
+
+    /// Puts back a message whose send attempt failed, to be tried again later.
+    ///
+    /// # Parameters
+    /// - `entry`: The entry that failed
+    /// - `error`: The error from the attempt
+    pub fn requeue_failed(&mut self, mut entry: OutboxEntry, error: String) {
+        self.sending.remove(&entry.id);
+        entry.send_at = Instant::now() + RETRY_DELAY;
+        entry.last_error = Some(error);
+        self.entries.push(entry);
+    }
+
+    /// Removes and returns the messages that are due to be sent.
+    ///
+    /// The messages count as being sent until `finish_sending` or
+    /// `requeue_failed` is called for them, so they aren't queued again
+    /// meanwhile.
+    ///
+    /// # Returns
+    /// The due entries, oldest first
+    pub fn take_due(&mut self) -> Vec<OutboxEntry> {
+        let now = Instant::now();
+        let (due, pending): (Vec<OutboxEntry>, Vec<OutboxEntry>) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|entry| entry.send_at <= now);
+        self.entries = pending;
+        for entry in &due {
+            self.sending.insert(entry.id, entry.email.id.clone());
+        }
+        due
+    }
+
+    /// Checks if any message is due to be sent.
+    ///
+    /// # Returns
+    /// true if `take_due` would return a message, false otherwise
+    pub fn has_due(&self) -> bool {
+        let now = Instant::now();
+        self.entries.iter().any(|entry| entry.send_at <= now)
+    }
+
+    /// Marks a message taken with `take_due` as sent.
+    ///
+    /// # Parameters
+    /// - `entry_id`: The identifier of the entry
+    pub fn finish_sending(&mut self, entry_id: u64) {
+        self.sending.remove(&entry_id);
+    }
+
+    /// Removes the most recently queued message that hasn't been sent yet.
+    ///
+    /// # Returns
+    /// An Option containing the recalled message
+    pub fn recall_latest(&mut self) -> Option<Email> {
+        let index = self.entries
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, entry)| entry.id)
+            .map(|(index, _)| index)?;
+
+        Some(self.entries.remove(index).email)
+    }
+
+    /// Gets the message that is sent next, if it's still waiting on its delay.
+    ///
+    /// # Returns
+    /// An Option containing the next entry and the time left until it is sent
+    pub fn next_pending(&self) -> Option<(&OutboxEntry, Duration)> {
+        let now = Instant::now();
+        self.entries
+            .iter()
+            .filter(|entry| entry.last_error.is_none() && entry.send_at > now)
+            .min_by_key(|entry| entry.send_at)
+            .map(|entry| (entry, entry.send_at - now))
+    }
+
+    /// Checks if a message is waiting in the outbox or being sent.
+    ///
+    /// # Parameters
+    /// - `email_id`: The message ID
+    ///
+    /// # Returns
+    /// true if a message with the ID is queued or being sent, false otherwise
+    pub fn contains(&self, email_id: &str) -> bool {
+        !email_id.is_empty()
+            && (self.entries.iter().any(|entry| entry.email.id == email_id)
+                || self.sending.values().any(|id| id == email_id))
+    }
+    
+    /// Removes and returns the messages that are waiting to be sent.
+    ///
+    /// # Returns
+    /// The waiting entries, in the order they were queued; messages being sent are kept
+    pub fn take_waiting(&mut self) -> Vec<OutboxEntry> {
+        std::mem::take(&mut self.entries)
+    }
+
+    /// Checks if any message is being sent.
+    ///
+    /// # Returns
+    /// true if a message taken with `take_due` hasn't finished, false otherwise
+    pub fn is_sending(&self) -> bool {
+        !self.sending.is_empty()
+    }
+    
+    /// Gets the number of messages in the outbox.
+    ///
+    /// # Returns
+    /// The number of queued messages and messages being sent
+    pub fn len(&self) -> usize {
+        self.entries.len() + self.sending.len()
+    }
+
+    /// Checks if the outbox is empty.
+    ///
+    /// # Returns
+    /// true if no messages are queued or being sent, false otherwise
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.sending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str) -> Email {
+        let mut email = Email::new();
+        email.id = id.to_string();
+        email
+    }
+
+    #[test]
+    fn counts_messages_being_sent() {
+        let mut outbox = Outbox::new();
+        outbox.queue(message("scheduled-1"), Duration::ZERO);
+
+        let due = outbox.take_due();
+        assert_eq!(due.len(), 1);
+        assert!(outbox.contains("scheduled-1"));
+        assert!(outbox.is_sending());
+        assert_eq!(outbox.len(), 1);
+
+        // Only waiting messages are taken; the one being sent stays
+        assert!(outbox.take_waiting().is_empty());
+        assert!(!outbox.is_empty());
+
+        outbox.finish_sending(due[0].id);
+        assert!(!outbox.contains("scheduled-1"));
+        assert!(outbox.is_empty());
+    }
+
+    #[test]
+    fn requeues_failed_messages() {
+        let mut outbox = Outbox::new();
+        outbox.queue(message("scheduled-1"), Duration::ZERO);
+
+        let entry = outbox.take_due().remove(0);
+        outbox.requeue_failed(entry, "Connection refused".to_string());
+
+        // Waiting on the retry delay, not being sent
+        assert!(outbox.take_due().is_empty());
+        assert!(outbox.contains("scheduled-1"));
+        assert_eq!(outbox.len(), 1);
+    }
+}
@@ -0,0 +1,230 @@
+//! Render snapshots for the Linksy email client.
+//!
+//! The draw loop renders from a copy of everything a frame shows instead of
+//! the live state, so a frame never waits on an account or the state lock.
+//! A new snapshot is taken when the state is free and has changed since the
+//! last one; while a background task is updating it, the previous snapshot
+//! is drawn again. The email list and the viewed email are shared with the
+//! state rather than copied.
+
+use crate::config::{AppSettings, EmailTemplate, FolderView, SavedSearch};
+use crate::logging::{self, LogEntry};
+use crate::models::{AccountSummary, Email, EmailKey};
+use crate::state::{AppState, View};
+use crate::ui::views::account_config::AccountFormState;
+use crate::ui::views::attachments::AttachmentPickerState;
+use crate::ui::views::compose::ComposeState;
+use crate::ui::views::find::FindState;
+use crate::ui::views::grouping::GroupingState;
+use crate::ui::views::links::LinkPickerState;
+use crate::ui::views::log::LogViewState;
+use crate::ui::views::prompt::{ConfirmState, PromptState};
+use crate::ui::views::search::SearchState;
+use crate::ui::views::tags::TagPickerState;
+use crate::ui::views::templates::{TemplateFormState, TemplatePickerState};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// A message waiting in the outbox, as shown in the status bar.
+#[derive(Debug, Clone)]
+pub struct PendingSend {
+    /// The subject of the message
+    pub subject: String,
+    /// The number of seconds until it's sent
+    pub seconds: u64,
+}
+
+/// Everything a frame shows, copied from the application state.
+#[derive(Clone)]
+pub struct RenderSnapshot {
+    /// Current view
+    pub current_view: View,
+    /// Account summaries
+    pub account_summaries: Vec<AccountSummary>,
+    /// Currently selected account index
+    pub selected_account: Option<usize>,
+    /// Whether no accounts are configured yet
+    pub no_accounts: bool,
+    /// Path of the configuration file, shown on first run
+    pub config_path: PathBuf,
+    /// The application settings
+    pub settings: AppSettings,
+    /// The email templates
+    pub templates: Vec<EmailTemplate>,
+    /// Folders listed for the selected account
+    pub folders: Vec<String>,
+    /// The time each folder was last synced
+    pub last_synced: HashMap<String, SystemTime>,
+    /// Saved searches listed below the folders
+    pub saved_searches: Vec<SavedSearch>,
+    /// Index of the highlighted entry in the folder list
+    pub selected_folder_index: usize,
+    /// Whether the highlighted entry in the folder list is a saved search
+    pub saved_search_selected: bool,
+    /// Whether the selected account can only send
+    pub send_only: bool,
+    /// Whether the selected folder is the account's Sent folder
+    pub sent_folder_selected: bool,
+    /// Currently selected folder
+    pub selected_folder: String,
+    /// Sort order and filter of the selected folder
+    pub folder_view: FolderView,
+    /// The search whose results are listed, if any
+    pub search: Option<SearchState>,
+    /// Currently displayed emails, shared with the state until it changes them
+    pub emails: Arc<Vec<Email>>,
+    /// Grouping of the email list by sender, if it's turned on
+    pub grouping: Option<GroupingState>,
+    /// Currently selected email index
+    pub selected_email: Option<usize>,
+    /// The listed emails selected for a batch change
+    pub selected_emails: HashSet<EmailKey>,
+    /// Currently viewed email, shared with the state until it changes it
+    pub viewed_email: Option<Arc<Email>>,
+    /// Email body scroll offset
+    pub email_scroll_offset: u16,
+    /// Whether quoted text in the viewed email is expanded
+    pub show_quoted_text: bool,
+    /// Whether remote images in the viewed email are shown
+    pub show_remote_content: bool,
+    /// The text being found in the viewed email
+    pub body_find: Option<FindState>,
+    /// Compose form
+    pub compose_state: Option<ComposeState>,
+    /// Index of the selected template in the settings view
+    pub selected_template: Option<usize>,
+    /// Template form
+    pub template_form: Option<TemplateFormState>,
+    /// Account form
+    pub account_form_state: Option<AccountFormState>,
+    /// Link picker popup
+    pub link_picker: Option<LinkPickerState>,
+    /// Attachment list popup
+    pub attachment_picker: Option<AttachmentPickerState>,
+    /// Tag picker popup
+    pub tag_picker: Option<TagPickerState>,
+    /// Prompt popup
+    pub prompt: Option<PromptState>,
+    /// Template picker popup
+    pub template_picker: Option<TemplatePickerState>,
+    /// Confirmation popup
+    pub confirm: Option<ConfirmState>,
+    /// Status message
+    pub status_message: Option<String>,
+    /// Message shown while a background fetch is in progress
+    pub loading_message: Option<String>,
+    /// The next message waiting to be sent, if any
+    pub pending_send: Option<PendingSend>,
+    /// Whether the application is offline
+    pub offline: bool,
+    /// Number of unread emails across all accounts
+    pub total_unread: usize,
+    /// Log view state, while the log is open
+    pub log_view: Option<LogViewState>,
+    /// The listed log messages, only taken while the log is open
+    pub log_entries: Vec<LogEntry>,
+}
+
+impl RenderSnapshot {
+    /// Takes a snapshot of the application state.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A new RenderSnapshot instance
+    pub fn new(state: &AppState) -> Self {
+        let config = state.config_manager.get_config();
+        let (folders, last_synced) = state.get_folder_list();
+
+        Self {
+            current_view: state.get_current_view(),
+            account_summaries: state.account_summaries.clone(),
+            selected_account: state.get_selected_account(),
+            no_accounts: config.accounts.is_empty(),
+            config_path: state.base_dir.join("config.json"),
+            settings: config.settings.clone(),
+            templates: config.templates.clone(),
+            folders,
+            last_synced,
+            saved_searches: state.saved_searches().to_vec(),
+            selected_folder_index: state.selected_folder_index,
+            saved_search_selected: state.selected_saved_search().is_some(),
+            send_only: state.is_selected_account_send_only(),
+            sent_folder_selected: state.is_sent_folder_selected(),
+            selected_folder: state.get_selected_folder().to_string(),
+            folder_view: state.folder_view(),
+            search: state.search.clone(),
+            emails: state.emails.clone(),
+            grouping: state.grouping.clone(),
+            selected_email: state.get_selected_email(),
+            selected_emails: state.selected_emails.clone(),
+            viewed_email: state.viewed_email.clone(),
+            email_scroll_offset: state.get_email_scroll_offset(),
+            show_quoted_text: state.is_showing_quoted_text(),
+            show_remote_content: state.is_showing_remote_content(),
+            body_find: state.body_find.clone(),
+            compose_state: state.get_compose_state().cloned(),
+            selected_template: state.selected_template,
+            template_form: state.get_template_form().cloned(),
+            account_form_state: state.get_account_form_state().cloned(),
+            link_picker: state.get_link_picker().cloned(),
+            attachment_picker: state.get_attachment_picker().cloned(),
+            tag_picker: state.get_tag_picker().cloned(),
+            prompt: state.get_prompt().cloned(),
+            template_picker: state.get_template_picker().cloned(),
+            confirm: state.get_confirm().cloned(),
+            status_message: state.get_status_message().cloned(),
+            loading_message: state.get_loading_message().cloned(),
+            pending_send: pending_send(state),
+            offline: state.offline,
+            total_unread: state.total_unread(),
+            log_view: state.log_view.clone(),
+            log_entries: match state.log_view {
+                Some(_) => logging::entries(config.settings.log_level),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    /// Updates what a frame shows that changes without the state changing.
+    ///
+    /// The outbox countdown runs on a clock, and the folders and log
+    /// messages are changed by the protocol clients and the whole
+    /// application, so they are taken for every frame. The rest is only
+    /// copied again when the state changes.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    pub fn refresh(&mut self, state: &AppState) {
+        (self.folders, self.last_synced) = state.get_folder_list();
+        self.pending_send = pending_send(state);
+        if state.log_view.is_some() {
+            self.log_entries = logging::entries(state.config_manager.get_config().settings.log_level);
+        }
+    }
+
+    /// Gets the time the selected folder was last synced.
+    ///
+    /// # Returns
+    /// The sync time, or None if the folder hasn't been synced yet
+    pub fn folder_last_synced(&self) -> Option<SystemTime> {
+        self.last_synced.get(&self.selected_folder).copied()
+    }
+}
+
+/// Describes the next message waiting to be sent, for the status bar.
+///
+/// # Parameters
+/// - `state`: The application state
+///
+/// # Returns
+/// An Option containing the message's subject and the seconds until it's sent
+fn pending_send(state: &AppState) -> Option<PendingSend> {
+    state.outbox.next_pending().map(|(entry, remaining)| PendingSend {
+        subject: entry.email.subject.clone(),
+        seconds: remaining.as_secs() + 1,
+    })
+}
@@ -0,0 +1,137 @@
+//! Shared application state for the Linksy email client.
+//!
+//! The draw loop and the background tasks share one AppState behind a
+//! lock. Every time the state is taken for writing its generation goes up,
+//! so the draw loop can tell when a new render snapshot is needed instead
+//! of copying the state for every frame.
+
+use crate::state::AppState;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+
+/// The application state, with a count of the times it was changed.
+pub struct SharedState {
+    /// The application state
+    state: RwLock<AppState>,
+    /// Incremented each time the state is taken for writing
+    generation: AtomicU64,
+}
+
+impl SharedState {
+    /// Creates a new SharedState.
+    ///
+    /// # Parameters
+    /// - `state`: The application state
+    ///
+    /// # Returns
+    /// A new SharedState instance
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state: RwLock::new(state),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Takes the state for reading.
+    ///
+    /// # Returns
+    /// A guard giving read access to the state
+    pub async fn read(&self) -> RwLockReadGuard<'_, AppState> {
+        self.state.read().await
+    }
+
+    /// Takes the state for writing, which counts as a change.
+    ///
+    /// # Returns
+    /// A guard giving write access to the state
+    pub async fn write(&self) -> RwLockWriteGuard<'_, AppState> {
+        let guard = self.state.write().await;
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        guard
+    }
+
+    /// Takes the state for writing if it's free, without counting it as a change.
+    ///
+    /// Only for the draw loop's own bookkeeping, such as refreshing the
+    /// account summaries, which it compares itself.
+    ///
+    /// # Returns
+    /// A guard giving write access to the state, or an error if it's in use
+    pub fn try_write_for_draw(&self) -> Result<RwLockWriteGuard<'_, AppState>, TryLockError> {
+        self.state.try_write()
+    }
+
+    /// Gets the number of times the state was taken for writing.
+    ///
+    /// While the caller holds the state, every change counted here is complete.
+    ///
+    /// # Returns
+    /// The generation of the state
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+    use crate::models::Email;
+    use crate::state::RenderSnapshot;
+    use crate::storage::EmailStorage;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    /// A shared state in its own directory, removed on drop.
+    struct TestState {
+        shared: SharedState,
+        dir: PathBuf,
+    }
+
+    impl TestState {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("linksy-test-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let config_manager = ConfigManager::new(dir.join("config.json").to_str().unwrap()).unwrap();
+            let storage = EmailStorage::new(&dir.join("storage")).unwrap();
+            let shared = SharedState::new(AppState::new(config_manager, storage, dir.clone()));
+            Self { shared, dir }
+        }
+    }
+
+    impl Drop for TestState {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn counts_only_writes_as_changes() {
+        let test = TestState::new();
+        let shared = &test.shared;
+        let start = shared.generation();
+
+        drop(shared.read().await);
+        drop(shared.try_write_for_draw().unwrap());
+        assert_eq!(shared.generation(), start);
+
+        drop(shared.write().await);
+        assert_eq!(shared.generation(), start + 1);
+    }
+
+    #[tokio::test]
+    async fn shares_emails_with_the_snapshot() {
+        let test = TestState::new();
+        let mut state = test.shared.write().await;
+        state.emails = Arc::new(vec![Email::new()]);
+        state.set_viewed_email(Some(Email::new()));
+
+        let snapshot = RenderSnapshot::new(&state);
+        assert!(Arc::ptr_eq(&snapshot.emails, &state.emails));
+        assert!(Arc::ptr_eq(snapshot.viewed_email.as_ref().unwrap(), state.viewed_email.as_ref().unwrap()));
+
+        // Changing the list leaves the snapshot's copy as it was
+        Arc::make_mut(&mut state.emails).clear();
+        assert_eq!(snapshot.emails.len(), 1);
+    }
+}
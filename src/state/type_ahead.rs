@@ -0,0 +1,85 @@
+//! Type-ahead selection for the Linksy email client.
+//!
+//! Typing the first letters of a name selects the first entry of a list
+//! that starts with them, like in a file manager. The typed letters are
+//! forgotten after a short pause, so typing again starts a new search.
+
+use std::time::{Duration, Instant};
+
+/// Pause after which the typed letters are forgotten.
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Letters typed to jump to an entry of a list.
+#[derive(Debug, Default)]
+pub struct TypeAhead {
+    /// The letters typed so far
+    text: String,
+    /// When the last letter was typed
+    last_typed: Option<Instant>,
+}
+
+impl TypeAhead {
+    /// Creates a new, empty TypeAhead.
+    ///
+    /// # Returns
+    /// A new TypeAhead instance
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks if a letter was typed recently enough to be continued.
+    ///
+    /// # Returns
+    /// true if the next letter extends the typed text, false if it starts a new one
+    pub fn is_active(&self) -> bool {
+        self.last_typed.is_some_and(|time| time.elapsed() < TYPE_AHEAD_TIMEOUT)
+    }
+
+    /// Adds a typed letter, starting over after a pause.
+    ///
+    /// # Parameters
+    /// - `c`: The typed letter
+    ///
+    /// # Returns
+    /// The letters typed so far
+    pub fn push(&mut self, c: char) -> &str {
+        if !self.is_active() {
+            self.text.clear();
+        }
+        self.text.push(c);
+        self.last_typed = Some(Instant::now());
+        &self.text
+    }
+
+    /// Forgets the typed letters.
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.last_typed = None;
+    }
+
+    /// Finds the next name that starts with the typed letters.
+    ///
+    /// Matching ignores case, and a name under another folder, such as
+    /// "[Gmail]/Sent Mail", also matches on its last part. The search wraps
+    /// around to the start of the list.
+    ///
+    /// # Parameters
+    /// - `names`: The names to search
+    /// - `typed`: The typed letters
+    /// - `start`: The index to start searching at
+    ///
+    /// # Returns
+    /// The index of the matching name, or None if no name matches
+    pub fn find_match(names: &[String], typed: &str, start: usize) -> Option<usize> {
+        let typed = typed.to_lowercase();
+        let matches = |name: &String| {
+            let name = name.to_lowercase();
+            let leaf = name.rsplit('/').next().unwrap_or(&name);
+            name.starts_with(&typed) || leaf.starts_with(&typed)
+        };
+
+        (0..names.len())
+            .map(|offset| (start + offset) % names.len())
+            .find(|&index| matches(&names[index]))
+    }
+}
@@ -0,0 +1,784 @@
+//! Maildir storage for the Linksy email client.
+//!
+//! Emails are kept as message files other mail tools can read, one Maildir
+//! per account and folder: `<root>/<account ID>/<folder>/{cur,new,tmp}`.
+//! Subfolders such as "[Gmail]/Sent Mail" become nested directories. The
+//! file name starts with the email ID and ends with the standard flags, `S`
+//! for read and `F` for flagged, so `42:2,FS` is a read, flagged email.
+//! Flags set by other tools are kept when an email is rewritten.
+//!
+//! The message files of a folder are listed once and then looked up by ID,
+//! so storing a batch of emails doesn't list the folder for each one.
+//!
+//! The UIDVALIDITY the cached emails of a folder were fetched with is kept
+//! in a `.linksy-uidvalidity` file in its Maildir, which other tools ignore.
+//!
+//! The messages are rebuilt from what was fetched. Details that a message
+//! can't carry, such as its size on the server, are kept in `X-Linksy-`
+//! headers, and the IMAP section and size of an attachment that wasn't
+//! downloaded yet in parameters of its content type.
+
+use crate::models::{Address, Email, Priority};
+use crate::storage::EmailStore;
+use anyhow::{anyhow, Result};
+use mail_parser::{MessageParser, MimeHeaders};
+use lettre::message::{
+    header::{self, ContentType, HeaderName, HeaderValue, Headers},
+    MultiPart, SinglePart,
+};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Separates the unique part of a file name from its flags.
+const INFO_SEPARATOR: char = if cfg!(windows) { ';' } else { ':' };
+
+/// Header with the size of the message on the server.
+const SIZE_HEADER: &str = "X-Linksy-Size";
+
+/// Header marking an email whose body wasn't fetched.
+const HEADERS_ONLY_HEADER: &str = "X-Linksy-Headers-Only";
+
+/// Header marking an email of which only the start was downloaded.
+const TRUNCATED_HEADER: &str = "X-Linksy-Truncated";
+
+/// Header with the IMAP keywords of the message, separated by spaces.
+const KEYWORDS_HEADER: &str = "X-Linksy-Keywords";
+
+/// Header with the tags kept only locally, separated by spaces.
+const LOCAL_TAGS_HEADER: &str = "X-Linksy-Local-Tags";
+
+/// Content type parameter with the IMAP section of an attachment that wasn't downloaded.
+const SECTION_PARAM: &str = "x-linksy-section";
+
+/// Content type parameter with the size of an attachment that wasn't downloaded.
+const PART_SIZE_PARAM: &str = "x-linksy-size";
+
+/// File in a Maildir with the UIDVALIDITY its emails were fetched with.
+const UID_VALIDITY_FILE: &str = ".linksy-uidvalidity";
+
+/// Flags Linksy sets; other flags are left as they are.
+const MANAGED_FLAGS: [char; 2] = ['F', 'S'];
+
+/// The message files of the Maildirs listed so far, by Maildir and email ID.
+type FileIndex = HashMap<PathBuf, HashMap<String, Vec<PathBuf>>>;
+
+/// Represents email storage in Maildir directories.
+#[derive(Debug, Clone)]
+pub struct MaildirStorage {
+    /// The directory holding a directory per account
+    root: PathBuf,
+    /// The message files by Maildir and email ID; also held while a file is
+    /// replaced, so two stores of the same email don't race
+    index: Arc<Mutex<FileIndex>>,
+}
+
+impl MaildirStorage {
+    /// Creates a new MaildirStorage instance.
+    ///
+    /// # Parameters
+    /// - `root`: The directory to keep the Maildirs in
+    ///
+    /// # Returns
+    /// A Result containing the MaildirStorage or an error
+    pub fn new(root: &Path) -> Result<Self> {
+        fs::create_dir_all(root)?;
+
+        Ok(Self { root: root.to_path_buf(), index: Arc::new(Mutex::new(HashMap::new())) })
+    }
+
+    /// Gets the Maildir of an account and folder.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `folder`: The folder/mailbox
+    ///
+    /// # Returns
+    /// The path of the Maildir
+    fn folder_dir(&self, account_id: &str, folder: &str) -> PathBuf {
+        let mut dir = self.root.join(escape_name(account_id));
+        for part in folder.split('/') {
+            dir.push(escape_name(part));
+        }
+        dir
+    }
+
+    /// Locks the index of message files.
+    ///
+    /// # Returns
+    /// A Result containing the locked index or an error
+    fn lock_index(&self) -> Result<std::sync::MutexGuard<'_, FileIndex>> {
+        self.index.lock().map_err(|_| anyhow!("Maildir index lock poisoned"))
+    }
+
+    /// Lists the message files of a Maildir into the index.
+    ///
+    /// # Parameters
+    /// - `index`: The index
+    /// - `dir`: The Maildir
+    ///
+    /// # Returns
+    /// A Result containing the paths of the files, or an error
+    fn index_dir(index: &mut FileIndex, dir: &Path) -> Result<Vec<PathBuf>> {
+        let paths = Self::message_files(dir)?;
+        let mut files: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for path in &paths {
+            files.entry(file_id(path)).or_default().push(path.clone());
+        }
+        index.insert(dir.to_path_buf(), files);
+
+        Ok(paths)
+    }
+
+    /// Finds the files of an email in a Maildir.
+    ///
+    /// The Maildir is listed again if a file was renamed or removed by
+    /// another tool since it was indexed.
+    ///
+    /// # Parameters
+    /// - `index`: The index
+    /// - `dir`: The Maildir
+    /// - `email_id`: The email ID
+    ///
+    /// # Returns
+    /// A Result containing the paths of the email's files, usually one, or an error
+    fn find_files(index: &mut FileIndex, dir: &Path, email_id: &str) -> Result<Vec<PathBuf>> {
+        let indexed = |index: &FileIndex| index.get(dir)
+            .map(|files| files.get(email_id).cloned().unwrap_or_default());
+
+        if let Some(files) = indexed(index).filter(|files| files.iter().all(|path| path.exists())) {
+            return Ok(files);
+        }
+        Self::index_dir(index, dir)?;
+
+        Ok(indexed(index).unwrap_or_default())
+    }
+
+    /// Lists the message files of a Maildir.
+    ///
+    /// # Parameters
+    /// - `dir`: The Maildir
+    ///
+    /// # Returns
+    /// A Result containing the paths of the files in `cur` and `new`, or an error
+    fn message_files(dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+
+        for subdir in ["cur", "new"] {
+            let entries = match fs::read_dir(dir.join(subdir)) {
+                Ok(entries) => entries,
+                // A folder that was never stored has no emails
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            for entry in entries {
+                let entry = entry?;
+                let name = entry.file_name();
+                // Skip hidden files, which aren't messages
+                if entry.file_type()?.is_file() && !name.to_string_lossy().starts_with('.') {
+                    files.push(entry.path());
+                }
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Reads an email from a message file.
+    ///
+    /// # Parameters
+    /// - `path`: The message file
+    /// - `account_id`: The account ID
+    /// - `folder`: The folder/mailbox
+    ///
+    /// # Returns
+    /// A Result containing the Email or an error
+    fn read_email(path: &Path, account_id: &str, folder: &str) -> Result<Email> {
+        let raw = fs::read(path)?;
+        let mut email = Email::parse_from_raw(&raw, account_id, folder)?;
+
+        // The file name holds the ID and the flags
+        email.id = file_id(path);
+        let flags = file_flags(path);
+        email.is_read = flags.contains('S');
+        email.is_flagged = flags.contains('F');
+
+        // Restore what the message itself can't carry
+        if let Some(size) = header_value(&raw, SIZE_HEADER).and_then(|size| size.parse().ok()) {
+            email.size = Some(size);
+        }
+        email.truncated = header_value(&raw, TRUNCATED_HEADER).is_some();
+        let tags = |name| header_value(&raw, name)
+            .map(|value| value.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+        email.keywords = tags(KEYWORDS_HEADER);
+        email.local_tags = tags(LOCAL_TAGS_HEADER);
+        restore_attachment_sections(&raw, &mut email);
+        email.headers_only = header_value(&raw, HEADERS_ONLY_HEADER).is_some();
+        if email.headers_only {
+            email.body_text = None;
+            email.body_html = None;
+            email.attachments.clear();
+        }
+        email.headers.retain(|(name, _)| !name.to_ascii_lowercase().starts_with("x-linksy-"));
+
+        Ok(email)
+    }
+}
+
+impl EmailStore for MaildirStorage {
+    fn store_email(&self, email: &Email) -> Result<()> {
+        let dir = self.folder_dir(&email.account_id, &email.folder);
+        for subdir in ["cur", "new", "tmp"] {
+            fs::create_dir_all(dir.join(subdir))?;
+        }
+
+        // Keep the flags other tools set on the previous copy
+        let mut index = self.lock_index()?;
+        let existing = Self::find_files(&mut index, &dir, &email.id)?;
+        let mut flags: Vec<char> = existing.iter()
+            .flat_map(|path| file_flags(path).chars().collect::<Vec<_>>())
+            .filter(|flag| !MANAGED_FLAGS.contains(flag))
+            .collect();
+        if email.is_read {
+            flags.push('S');
+        }
+        if email.is_flagged {
+            flags.push('F');
+        }
+        flags.sort_unstable();
+        flags.dedup();
+
+        // Write the message to a name no other writer uses in tmp, then move it into cur
+        let unique = escape_name(&email.id);
+        let name = format!("{}{}2,{}", unique, INFO_SEPARATOR, flags.into_iter().collect::<String>());
+        let tmp_path = dir.join("tmp").join(format!("{}.{}.{}", unique, std::process::id(), uuid::Uuid::new_v4().simple()));
+        let path = dir.join("cur").join(name);
+        if let Err(e) = fs::write(&tmp_path, format_message(email)?).and_then(|_| fs::rename(&tmp_path, &path)) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+
+        // Remove the previous copy if its flags were different
+        for old_path in existing.into_iter().filter(|old_path| *old_path != path) {
+            remove_message_file(&old_path)?;
+        }
+        if let Some(files) = index.get_mut(&dir) {
+            files.insert(email.id.clone(), vec![path]);
+        }
+
+        Ok(())
+    }
+
+    fn get_email(&self, account_id: &str, folder: &str, email_id: &str) -> Result<Option<Email>> {
+        let dir = self.folder_dir(account_id, folder);
+        let files = Self::find_files(&mut *self.lock_index()?, &dir, email_id)?;
+
+        match files.first() {
+            Some(path) => Ok(Some(Self::read_email(path, account_id, folder)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_emails(&self, account_id: &str, folder: &str) -> Result<Vec<Email>> {
+        let dir = self.folder_dir(account_id, folder);
+        let mut emails = Vec::new();
+        let mut skipped = 0;
+
+        // The folder is listed anyway, so bring its index up to date
+        let paths = Self::index_dir(&mut *self.lock_index()?, &dir)?;
+        for path in paths {
+            match Self::read_email(&path, account_id, folder) {
+                Ok(email) => emails.push(email),
+                Err(e) => {
+                    debug!("Skipping unreadable message {:?}: {}", path, e);
+                    skipped += 1;
+                },
+            }
+        }
+        if skipped > 0 {
+            warn!("Skipped {} unreadable emails in {} of account {}", skipped, folder, account_id);
+        }
+
+        // Sort emails by date (newest first)
+        emails.sort_by_key(|e| std::cmp::Reverse(e.date));
+
+        Ok(emails)
+    }
+
+    fn delete_email(&self, account_id: &str, folder: &str, email_id: &str) -> Result<()> {
+        let dir = self.folder_dir(account_id, folder);
+        let mut index = self.lock_index()?;
+
+        for path in Self::find_files(&mut index, &dir, email_id)? {
+            remove_message_file(&path)?;
+        }
+        if let Some(files) = index.get_mut(&dir) {
+            files.remove(email_id);
+        }
+
+        Ok(())
+    }
+
+    fn delete_emails(&self, account_id: &str, folder: &str) -> Result<()> {
+        let dir = self.folder_dir(account_id, folder);
+
+        let mut index = self.lock_index()?;
+
+        // Remove the messages but keep the Maildir, and its subfolders
+        for path in Self::message_files(&dir)? {
+            remove_message_file(&path)?;
+        }
+        index.insert(dir, HashMap::new());
+
+        Ok(())
+    }
+
+    fn get_uid_validity(&self, account_id: &str, folder: &str) -> Result<Option<u32>> {
+        let path = self.folder_dir(account_id, folder).join(UID_VALIDITY_FILE);
+
+        match fs::read_to_string(&path) {
+            Ok(value) => Ok(Some(value.trim().parse()?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store_uid_validity(&self, account_id: &str, folder: &str, uid_validity: u32) -> Result<()> {
+        let dir = self.folder_dir(account_id, folder);
+        fs::create_dir_all(&dir)?;
+
+        fs::write(dir.join(UID_VALIDITY_FILE), uid_validity.to_string())?;
+
+        Ok(())
+    }
+}
+
+/// Formats an email as a message file.
+///
+/// A message that couldn't be parsed is written as it was received.
+///
+/// # Parameters
+/// - `email`: The email
+///
+/// # Returns
+/// A Result containing the message or an error
+fn format_message(email: &Email) -> Result<Vec<u8>> {
+    if let Some(raw_source) = &email.raw_source {
+        return Ok(raw_source.as_bytes().to_vec());
+    }
+
+    let mut headers = Headers::new();
+
+    // Set the addresses as they were parsed
+    let sender = Address { name: email.from_name.clone(), email: email.from.clone() };
+    if !email.from.is_empty() {
+        insert_header(&mut headers, "From", sender.to_header())?;
+    }
+    for (name, addresses) in [("To", &email.to), ("Cc", &email.cc), ("Bcc", &email.bcc), ("Reply-To", &email.reply_to)] {
+        if !addresses.is_empty() {
+            insert_header(&mut headers, name, addresses.join(", "))?;
+        }
+    }
+    headers.set(header::Subject::from(email.subject.clone()));
+    headers.set(header::Date::new(email.date));
+    if email.priority != Priority::Normal {
+        insert_header(&mut headers, "X-Priority", email.priority.x_priority().to_string())?;
+        insert_header(&mut headers, "Importance", email.priority.importance().to_string())?;
+    }
+
+    // Keep what the message itself can't carry
+    if let Some(size) = email.size {
+        insert_header(&mut headers, SIZE_HEADER, size.to_string())?;
+    }
+    if email.headers_only {
+        insert_header(&mut headers, HEADERS_ONLY_HEADER, "yes".to_string())?;
+    }
+    if email.truncated {
+        insert_header(&mut headers, TRUNCATED_HEADER, "yes".to_string())?;
+    }
+    if !email.keywords.is_empty() {
+        insert_header(&mut headers, KEYWORDS_HEADER, email.keywords.join(" "))?;
+    }
+    if !email.local_tags.is_empty() {
+        insert_header(&mut headers, LOCAL_TAGS_HEADER, email.local_tags.join(" "))?;
+    }
+    insert_header(&mut headers, "MIME-Version", "1.0".to_string())?;
+
+    let mut message = headers.to_string().into_bytes();
+    message.extend(format_body(email)?);
+
+    Ok(message)
+}
+
+/// Formats the body and attachments of an email as MIME parts.
+///
+/// # Parameters
+/// - `email`: The email
+///
+/// # Returns
+/// A Result containing the parts, with their own headers, or an error
+fn format_body(email: &Email) -> Result<Vec<u8>> {
+    let text_part = email.body_text.as_ref().map(|text| SinglePart::plain(text.clone()));
+    let html_part = email.body_html.as_ref().map(|html| SinglePart::html(html.clone()));
+
+    // A body without attachments doesn't need a mixed part
+    if email.attachments.is_empty() {
+        return Ok(match (text_part, html_part) {
+            (Some(text), Some(html)) => MultiPart::alternative().singlepart(text).singlepart(html).formatted(),
+            (Some(part), None) | (None, Some(part)) => part.formatted(),
+            (None, None) => SinglePart::plain(String::new()).formatted(),
+        });
+    }
+
+    // Put the body before the attachments
+    let mut mixed = MultiPart::mixed().build();
+    match (text_part, html_part) {
+        (Some(text), Some(html)) => mixed = mixed.multipart(MultiPart::alternative().singlepart(text).singlepart(html)),
+        (Some(part), None) | (None, Some(part)) => mixed = mixed.singlepart(part),
+        (None, None) => {},
+    }
+
+    // Add the attachments
+    for attachment in &email.attachments {
+        let content_type = match &attachment.section {
+            Some(section) if attachment.needs_download() => format!(
+                "{}; {}={}; {}={}",
+                attachment.content_type, SECTION_PARAM, section, PART_SIZE_PARAM, attachment.size,
+            ),
+            _ => attachment.content_type.clone(),
+        };
+        let content_type = ContentType::parse(&content_type)
+            .or_else(|_| ContentType::parse("application/octet-stream"))?;
+        let disposition = if attachment.inline {
+            header::ContentDisposition::inline_with_name(&attachment.filename)
+        } else {
+            header::ContentDisposition::attachment(&attachment.filename)
+        };
+        let mut part = SinglePart::builder()
+            .header(content_type)
+            .header(disposition);
+        if let Some(content_id) = &attachment.content_id {
+            part = part.header(header::ContentId::from(format!("<{}>", content_id)));
+        }
+        mixed = mixed.singlepart(part.body(attachment.data.clone()));
+    }
+
+    Ok(mixed.formatted())
+}
+
+/// Restores the section and size of the attachments that weren't downloaded.
+///
+/// # Parameters
+/// - `raw`: The message file's contents
+/// - `email`: The email read from it, with its attachments in message order
+fn restore_attachment_sections(raw: &[u8], email: &mut Email) {
+    let Some(message) = MessageParser::default().parse(raw) else {
+        return;
+    };
+    
+    // The same parts are attachments as in the full parse
+    let parts = message.attachments()
+        .filter(|part| part.attachment_name().is_some() || part.content_id().is_some());
+    for (attachment, part) in email.attachments.iter_mut().zip(parts) {
+        let param = |name| part.content_type().and_then(|content_type| content_type.attribute(name));
+        if let Some(section) = param(SECTION_PARAM) {
+            attachment.section = Some(section.to_string());
+            attachment.size = param(PART_SIZE_PARAM).and_then(|size| size.parse().ok()).unwrap_or(attachment.size);
+        }
+    }
+}
+
+/// Adds a header to a message, encoding it if needed.
+///
+/// # Parameters
+/// - `headers`: The message headers
+/// - `name`: The header name
+/// - `value`: The header value
+///
+/// # Returns
+/// A Result indicating success or failure
+fn insert_header(headers: &mut Headers, name: &str, value: String) -> Result<()> {
+    let name = HeaderName::new_from_ascii(name.to_string())
+        .map_err(|_| anyhow!("Invalid header name {}", name))?;
+    headers.insert_raw(HeaderValue::new(name, value));
+
+    Ok(())
+}
+
+/// Gets the value of a header in a message.
+///
+/// # Parameters
+/// - `raw`: The message
+/// - `name`: The header name
+///
+/// # Returns
+/// The trimmed value, or None if the message doesn't have the header
+fn header_value(raw: &[u8], name: &str) -> Option<String> {
+    String::from_utf8_lossy(raw)
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .find_map(|line| {
+            let (header, value) = line.split_once(':')?;
+            header.trim().eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+        })
+}
+
+/// Removes a message file, if another tool hasn't already.
+///
+/// # Parameters
+/// - `path`: The message file
+///
+/// # Returns
+/// A Result indicating success or failure
+fn remove_message_file(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Gets the email ID from the name of a message file.
+///
+/// # Parameters
+/// - `path`: The message file
+///
+/// # Returns
+/// The unique part of the file name, unescaped
+fn file_id(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let unique = name.split(INFO_SEPARATOR).next().unwrap_or_default();
+    unescape_name(unique)
+}
+
+/// Gets the flags from the name of a message file.
+///
+/// # Parameters
+/// - `path`: The message file
+///
+/// # Returns
+/// The flags after "2,", empty for a message in `new`
+fn file_flags(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.split_once(INFO_SEPARATOR)
+        .and_then(|(_, info)| info.strip_prefix("2,"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Escapes a name for use in a file name.
+///
+/// Characters that aren't safe in file names, and a leading dot, are
+/// written as `%XX` for each of their bytes.
+///
+/// # Parameters
+/// - `name`: The name
+///
+/// # Returns
+/// The escaped name
+fn escape_name(name: &str) -> String {
+    let mut escaped = String::new();
+
+    for (index, c) in name.chars().enumerate() {
+        if c.is_alphanumeric() || " -_+=,@[]".contains(c) || (c == '.' && index > 0) {
+            escaped.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                escaped.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+
+    escaped
+}
+
+/// Reverses escape_name.
+///
+/// # Parameters
+/// - `name`: The escaped name
+///
+/// # Returns
+/// The original name; text that doesn't look escaped is kept as it is
+fn unescape_name(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut unescaped = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escape = bytes.get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escape {
+            Some(byte) => {
+                unescaped.push(byte);
+                index += 3;
+            },
+            None => {
+                unescaped.push(bytes[index]);
+                index += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&unescaped).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Maildirs in their own directory, removed when the test ends.
+    struct TestMaildir {
+        storage: MaildirStorage,
+        dir: PathBuf,
+    }
+
+    impl TestMaildir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("linksy-maildir-test-{}", uuid::Uuid::new_v4()));
+            Self {
+                storage: MaildirStorage::new(&dir).unwrap(),
+                dir,
+            }
+        }
+
+        /// The names of the message files in the work inbox's `cur`.
+        fn file_names(&self) -> Vec<String> {
+            let mut names: Vec<String> = fs::read_dir(self.dir.join("work").join("INBOX").join("cur"))
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        }
+    }
+
+    impl Drop for TestMaildir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// An email of the work inbox.
+    fn email(id: &str) -> Email {
+        let mut email = Email::new();
+        email.id = id.to_string();
+        email.account_id = "work".to_string();
+        email.folder = "INBOX".to_string();
+        email.from = "alice@example.com".to_string();
+        email.to = vec!["bob@example.com".to_string()];
+        email.subject = "Lunch".to_string();
+        email.body_text = Some("Noon at the usual place?".to_string());
+        email
+    }
+
+    #[test]
+    fn reads_back_a_stored_email() {
+        let test = TestMaildir::new();
+        let mut stored = email("42");
+        stored.is_read = true;
+        stored.is_flagged = true;
+        stored.size = Some(1234);
+        stored.keywords = vec!["$Label1".to_string(), "work".to_string()];
+        stored.local_tags = vec!["later".to_string()];
+        test.storage.store_email(&stored).unwrap();
+
+        let read = test.storage.get_email("work", "INBOX", "42").unwrap().unwrap();
+        assert_eq!(read.id, "42");
+        assert!(read.is_read && read.is_flagged);
+        assert_eq!(read.subject, "Lunch");
+        assert_eq!(read.body_text.as_deref().map(str::trim_end), Some("Noon at the usual place?"));
+        assert_eq!(read.size, stored.size);
+        assert_eq!(read.keywords, stored.keywords);
+        assert_eq!(read.local_tags, stored.local_tags);
+        assert!(!read.headers_only && !read.truncated);
+        assert!(read.headers.iter().all(|(name, _)| !name.to_ascii_lowercase().starts_with("x-linksy-")));
+        assert_eq!(test.file_names(), vec![format!("42{}2,FS", INFO_SEPARATOR)]);
+    }
+
+    #[test]
+    fn reads_back_a_headers_only_email() {
+        let test = TestMaildir::new();
+        let mut stored = email("7");
+        stored.headers_only = true;
+        test.storage.store_email(&stored).unwrap();
+
+        let read = test.storage.get_email("work", "INBOX", "7").unwrap().unwrap();
+        assert!(read.headers_only);
+        assert_eq!(read.body_text, None);
+        assert_eq!(read.subject, "Lunch");
+    }
+
+    #[test]
+    fn escapes_slashes_and_a_leading_dot() {
+        let test = TestMaildir::new();
+        for id in ["a/b", ".hidden"] {
+            test.storage.store_email(&email(id)).unwrap();
+        }
+
+        assert!(test.file_names().iter().all(|name| !name.starts_with('.') && !name.contains('/')));
+        assert!(test.storage.get_email("work", "INBOX", "a/b").unwrap().is_some());
+        assert!(test.storage.get_email("work", "INBOX", ".hidden").unwrap().is_some());
+        let mut ids: Vec<String> = test.storage.get_emails("work", "INBOX").unwrap()
+            .into_iter()
+            .map(|email| email.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![".hidden".to_string(), "a/b".to_string()]);
+        assert_eq!(unescape_name(&escape_name("[Gmail]/.Sent 100%")), "[Gmail]/.Sent 100%");
+    }
+
+    #[test]
+    fn keeps_flags_set_by_other_tools() {
+        let test = TestMaildir::new();
+        let mut stored = email("1");
+        stored.is_read = true;
+        test.storage.store_email(&stored).unwrap();
+
+        // Another tool marks it replied, renaming the indexed file
+        let cur = test.dir.join("work").join("INBOX").join("cur");
+        fs::rename(
+            cur.join(format!("1{}2,S", INFO_SEPARATOR)),
+            cur.join(format!("1{}2,RS", INFO_SEPARATOR)),
+        ).unwrap();
+        assert!(test.storage.get_email("work", "INBOX", "1").unwrap().unwrap().is_read);
+
+        stored.is_read = false;
+        stored.is_flagged = true;
+        test.storage.store_email(&stored).unwrap();
+        assert_eq!(test.file_names(), vec![format!("1{}2,FR", INFO_SEPARATOR)]);
+
+        test.storage.delete_email("work", "INBOX", "1").unwrap();
+        assert!(test.file_names().is_empty());
+        assert!(test.storage.get_email("work", "INBOX", "1").unwrap().is_none());
+    }
+
+    #[test]
+    fn stores_the_same_email_from_several_threads() {
+        let test = TestMaildir::new();
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| test.storage.clone().store_email(&email("5")).unwrap());
+            }
+        });
+
+        assert_eq!(test.file_names(), vec![format!("5{}2,", INFO_SEPARATOR)]);
+        let tmp = test.dir.join("work").join("INBOX").join("tmp");
+        assert_eq!(fs::read_dir(tmp).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn keeps_the_uid_validity_out_of_the_messages() {
+        let test = TestMaildir::new();
+        assert_eq!(test.storage.get_uid_validity("work", "INBOX").unwrap(), None);
+
+        test.storage.store_email(&email("1")).unwrap();
+        test.storage.store_uid_validity("work", "INBOX", 1700).unwrap();
+        assert_eq!(test.storage.get_uid_validity("work", "INBOX").unwrap(), Some(1700));
+        assert_eq!(test.storage.get_emails("work", "INBOX").unwrap().len(), 1);
+    }
+}
@@ -2,12 +2,180 @@
 //! 
 //! This module handles local storage and caching of emails and other data.
 
-use crate::models::{Email, Account};
+mod maildir;
+
+pub use maildir::MaildirStorage;
+
+use crate::config::{AppSettings, StorageBackend};
+use crate::models::{Address, Email, Account, RecentRecipient, ScheduledEmail};
 use anyhow::{Result, anyhow};
-use log::{warn, info};
+use log::{debug, warn, info};
+use serde::{de::DeserializeOwned, Serialize};
 use sled::Db;
+use std::collections::HashSet;
 use std::path::Path;
 use std::fs;
+use std::sync::Arc;
+
+/// Schema version of the records this version of Linksy writes.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Key of the schema version of the stored records.
+const SCHEMA_VERSION_KEY: &str = "meta:schema_version";
+
+/// Upgrades the stored records to the next schema version.
+type Migration = fn(&Db) -> Result<()>;
+
+/// Migrations by the version they upgrade from, so the first one upgrades
+/// version 0 to 1. Bumping SCHEMA_VERSION needs a migration added here.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [reserialize_records];
+
+/// Stores the cached emails of each account and folder.
+///
+/// Emails can be kept in the sled database or in Maildir directories;
+/// folder lists and scheduled emails always stay in the database.
+pub trait EmailStore: Send + Sync {
+    /// Stores an email, replacing any earlier copy.
+    ///
+    /// # Parameters
+    /// - `email`: The email to store
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn store_email(&self, email: &Email) -> Result<()>;
+    
+    /// Retrieves an email.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `folder`: The folder/mailbox
+    /// - `email_id`: The email ID
+    ///
+    /// # Returns
+    /// A Result containing the Email, None if it isn't stored, or an error
+    fn get_email(&self, account_id: &str, folder: &str, email_id: &str) -> Result<Option<Email>>;
+    
+    /// Retrieves all emails for an account and folder.
+    ///
+    /// Emails that can't be read are skipped and logged, so one corrupt
+    /// record doesn't hide the rest of the folder.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `folder`: The folder/mailbox
+    ///
+    /// # Returns
+    /// A Result containing the readable emails, newest first, or an error
+    fn get_emails(&self, account_id: &str, folder: &str) -> Result<Vec<Email>>;
+    
+    /// Deletes an email.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `folder`: The folder/mailbox
+    /// - `email_id`: The email ID
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn delete_email(&self, account_id: &str, folder: &str, email_id: &str) -> Result<()>;
+    
+    /// Deletes every email of an account and folder.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `folder`: The folder/mailbox
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn delete_emails(&self, account_id: &str, folder: &str) -> Result<()>;
+    
+    /// Retrieves the UIDVALIDITY of a folder the cached emails were fetched with.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `folder`: The folder/mailbox
+    ///
+    /// # Returns
+    /// A Result containing the UIDVALIDITY, None if it isn't stored, or an error
+    fn get_uid_validity(&self, account_id: &str, folder: &str) -> Result<Option<u32>>;
+    
+    /// Stores the UIDVALIDITY of a folder the cached emails were fetched with.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `folder`: The folder/mailbox
+    /// - `uid_validity`: The UIDVALIDITY the server sent
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn store_uid_validity(&self, account_id: &str, folder: &str, uid_validity: u32) -> Result<()>;
+    
+    /// Removes the oldest emails of a folder beyond a number to keep.
+    ///
+    /// Flagged emails and the emails the server lists are never removed,
+    /// though they count toward the number, so a folder with many flagged
+    /// emails can keep more. Removing a listed email would only have it
+    /// downloaded again on the next fetch.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `folder`: The folder/mailbox
+    /// - `keep`: How many emails to keep
+    /// - `listed`: The emails the last fetch listed
+    ///
+    /// # Returns
+    /// A Result containing the number of emails removed, or an error
+    fn trim_emails(&self, account_id: &str, folder: &str, keep: usize, listed: &[Email]) -> Result<usize> {
+        let emails = self.get_emails(account_id, folder)?;
+        let excess = emails.len().saturating_sub(keep);
+        if excess == 0 {
+            return Ok(0);
+        }
+        
+        // Evict the oldest by date, leaving the flagged and listed ones
+        let listed: HashSet<&str> = listed.iter().map(|email| email.id.as_str()).collect();
+        let mut evictable: Vec<&Email> = emails.iter()
+            .filter(|email| !email.is_flagged && !listed.contains(email.id.as_str()))
+            .collect();
+        evictable.sort_by_key(|email| email.date);
+        for email in evictable.iter().take(excess) {
+            self.delete_email(account_id, folder, &email.id)?;
+        }
+        
+        Ok(excess.min(evictable.len()))
+    }
+    
+    /// Updates an email.
+    ///
+    /// # Parameters
+    /// - `email`: The email to update
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn update_email(&self, email: &Email) -> Result<()> {
+        // Store the email (overwrites existing)
+        self.store_email(email)
+    }
+    
+    /// Writes any buffered changes.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn close(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// What rebuilding the storage indexes did.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RebuildReport {
+    /// Number of email records processed
+    pub processed: usize,
+    /// Number of records moved to the key of their account, folder and ID
+    pub rekeyed: usize,
+    /// Number of unreadable records removed
+    pub dropped: usize,
+}
 
 /// Represents the email storage.
 #[derive(Clone)]
@@ -64,114 +232,54 @@ impl EmailStorage {
         // Create the database
         let db = sled::open(path)?;
         
+        // Bring the records up to the current schema
+        Self::migrate(&db, path)?;
+        
         Ok(Self { db })
     }
     
-    /// Stores an email in the database.
+    /// Runs the migrations for a database written by an older version.
     ///
-    /// # Parameters
-    /// - `email`: The email to store
-    ///
-    /// # Returns
-    /// A Result indicating success or failure
-    pub fn store_email(&self, email: &Email) -> Result<()> {
-        // Create a key for the email
-        let key = format!("email:{}:{}:{}", email.account_id, email.folder, email.id);
-        
-        // Serialize the email
-        let value = serde_json::to_vec(email)?;
-        
-        // Store the email
-        self.db.insert(key.as_bytes(), value)?;
-        self.db.flush()?;
-        
-        Ok(())
-    }
-    
-    /// Retrieves an email from the database.
+    /// A database without a schema version was written before versions
+    /// were stored, unless it's empty.
     ///
     /// # Parameters
-    /// - `account_id`: The account ID
-    /// - `folder`: The folder/mailbox
-    /// - `email_id`: The email ID
+    /// - `db`: The database
+    /// - `path`: Path to the storage directory, for the error message
     ///
     /// # Returns
-    /// A Result containing the Email or an error
-    #[allow(dead_code)]
-    pub fn get_email(&self, account_id: &str, folder: &str, email_id: &str) -> Result<Option<Email>> {
-        // Create a key for the email
-        let key = format!("email:{}:{}:{}", account_id, folder, email_id);
+    /// A Result indicating success, or an error if a migration failed or the
+    /// database was written by a newer version of Linksy
+    fn migrate(db: &Db, path: &Path) -> Result<()> {
+        let stored = match db.get(SCHEMA_VERSION_KEY.as_bytes())? {
+            Some(value) => Some(serde_json::from_slice::<u32>(&value)?),
+            None => None,
+        };
+        let version = stored.unwrap_or(if db.is_empty() { SCHEMA_VERSION } else { 0 });
         
-        // Retrieve the email
-        if let Some(value) = self.db.get(key.as_bytes())? {
-            // Deserialize the email
-            let email: Email = serde_json::from_slice(&value)?;
-            Ok(Some(email))
-        } else {
-            Ok(None)
+        if version > SCHEMA_VERSION {
+            return Err(anyhow!(
+                "The storage at {:?} has schema version {}, but this version of Linksy only supports up to version {}. Upgrade Linksy, or remove the storage directory to start with an empty cache.",
+                path, version, SCHEMA_VERSION
+            ));
         }
-    }
-    
-    /// Retrieves all emails for an account and folder.
-    ///
-    /// # Parameters
-    /// - `account_id`: The account ID
-    /// - `folder`: The folder/mailbox
-    ///
-    /// # Returns
-    /// A Result containing a vector of emails or an error
-    pub fn get_emails(&self, account_id: &str, folder: &str) -> Result<Vec<Email>> {
-        // Create a prefix for the emails
-        let prefix = format!("email:{}:{}:", account_id, folder);
-        
-        // Retrieve all emails with the prefix
-        let mut emails = Vec::new();
         
-        for result in self.db.scan_prefix(prefix.as_bytes()) {
-            let (_, value) = result?;
-            let email: Email = serde_json::from_slice(&value)?;
-            emails.push(email);
+        // Upgrade one version at a time, recording each one
+        for from in version..SCHEMA_VERSION {
+            info!("Migrating storage from schema version {} to {}", from, from + 1);
+            MIGRATIONS[from as usize](db)
+                .map_err(|e| anyhow!("Failed to migrate storage from schema version {}: {}", from, e))?;
+            db.insert(SCHEMA_VERSION_KEY.as_bytes(), serde_json::to_vec(&(from + 1))?)?;
         }
         
-        // Sort emails by date (newest first)
-        emails.sort_by(|a, b| b.date.cmp(&a.date));
-        
-        Ok(emails)
-    }
-    
-    /// Deletes an email from the database.
-    ///
-    /// # Parameters
-    /// - `account_id`: The account ID
-    /// - `folder`: The folder/mailbox
-    /// - `email_id`: The email ID
-    ///
-    /// # Returns
-    /// A Result indicating success or failure
-    #[allow(dead_code)]
-    pub fn delete_email(&self, account_id: &str, folder: &str, email_id: &str) -> Result<()> {
-        // Create a key for the email
-        let key = format!("email:{}:{}:{}", account_id, folder, email_id);
-        
-        // Delete the email
-        self.db.remove(key.as_bytes())?;
-        self.db.flush()?;
+        if stored != Some(SCHEMA_VERSION) {
+            db.insert(SCHEMA_VERSION_KEY.as_bytes(), serde_json::to_vec(&SCHEMA_VERSION)?)?;
+            db.flush()?;
+        }
         
         Ok(())
     }
     
-    /// Updates an email in the database.
-    ///
-    /// # Parameters
-    /// - `email`: The email to update
-    ///
-    /// # Returns
-    /// A Result indicating success or failure
-    pub fn update_email(&self, email: &Email) -> Result<()> {
-        // Store the email (overwrites existing)
-        self.store_email(email)
-    }
-    
     /// Stores account information in the database.
     ///
     /// # Parameters
@@ -259,11 +367,230 @@ impl EmailStorage {
             self.db.remove(key)?;
         }
         
+        // Delete the cached folder list and the folders' UIDVALIDITY
+        self.db.remove(format!("folders:{}", account_id).as_bytes())?;
+        let validity_prefix = format!("uidvalidity:{}:", account_id);
+        for result in self.db.scan_prefix(validity_prefix.as_bytes()) {
+            let (key, _) = result?;
+            self.db.remove(key)?;
+        }
+        
+        // Delete all scheduled emails for the account
+        let scheduled_prefix = format!("scheduled:{}:", account_id);
+        for result in self.db.scan_prefix(scheduled_prefix.as_bytes()) {
+            let (key, _) = result?;
+            self.db.remove(key)?;
+        }
+        
+        self.db.flush()?;
+        
+        Ok(())
+    }
+    
+    /// Stores the last-known folder list of an account.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `folders`: The folder names
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn store_folders(&self, account_id: &str, folders: &[String]) -> Result<()> {
+        // Create a key for the folder list
+        let key = format!("folders:{}", account_id);
+        
+        // Serialize the folder list
+        let value = serde_json::to_vec(folders)?;
+        
+        // Store the folder list
+        self.db.insert(key.as_bytes(), value)?;
+        self.db.flush()?;
+        
+        Ok(())
+    }
+    
+    /// Retrieves the last-known folder list of an account.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    ///
+    /// # Returns
+    /// A Result containing the folder names, empty if none were stored, or an error
+    pub fn get_folders(&self, account_id: &str) -> Result<Vec<String>> {
+        // Create a key for the folder list
+        let key = format!("folders:{}", account_id);
+        
+        // Retrieve the folder list
+        match self.db.get(key.as_bytes())? {
+            Some(value) => Ok(serde_json::from_slice(&value)?),
+            None => Ok(Vec::new()),
+        }
+    }
+    
+    /// Stores a scheduled email in the database.
+    ///
+    /// # Parameters
+    /// - `scheduled`: The scheduled email to store
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn store_scheduled_email(&self, scheduled: &ScheduledEmail) -> Result<()> {
+        // Create a key for the scheduled email
+        let key = format!("scheduled:{}:{}", scheduled.email.account_id, scheduled.email.id);
+        
+        // Serialize the scheduled email
+        let value = serde_json::to_vec(scheduled)?;
+        
+        // Store the scheduled email
+        self.db.insert(key.as_bytes(), value)?;
+        self.db.flush()?;
+        
+        Ok(())
+    }
+    
+    /// Retrieves the scheduled emails for an account.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    ///
+    /// # Returns
+    /// A Result containing the scheduled emails, soonest first, or an error
+    pub fn get_scheduled_emails(&self, account_id: &str) -> Result<Vec<ScheduledEmail>> {
+        self.scan_scheduled_emails(&format!("scheduled:{}:", account_id))
+    }
+    
+    /// Retrieves the scheduled emails of all accounts.
+    ///
+    /// # Returns
+    /// A Result containing the scheduled emails, soonest first, or an error
+    pub fn get_all_scheduled_emails(&self) -> Result<Vec<ScheduledEmail>> {
+        self.scan_scheduled_emails("scheduled:")
+    }
+    
+    /// Retrieves the scheduled emails whose key starts with a prefix.
+    ///
+    /// Records that can't be read are skipped and logged, so one bad record
+    /// doesn't stop the others from being sent.
+    ///
+    /// # Parameters
+    /// - `prefix`: The key prefix
+    ///
+    /// # Returns
+    /// A Result containing the scheduled emails, soonest first, or an error
+    fn scan_scheduled_emails(&self, prefix: &str) -> Result<Vec<ScheduledEmail>> {
+        let mut scheduled = Vec::new();
+        
+        for result in self.db.scan_prefix(prefix.as_bytes()) {
+            let (key, value) = result?;
+            match serde_json::from_slice::<ScheduledEmail>(&value) {
+                Ok(entry) => scheduled.push(entry),
+                Err(e) => warn!("Skipping unreadable record {}: {}", String::from_utf8_lossy(&key), e),
+            }
+        }
+        
+        // Sort by send time (soonest first)
+        scheduled.sort_by_key(|entry| entry.send_at);
+        
+        Ok(scheduled)
+    }
+    
+    /// Deletes a scheduled email from the database.
+    ///
+    /// # Parameters
+    /// - `account_id`: The account ID
+    /// - `email_id`: The email ID
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn delete_scheduled_email(&self, account_id: &str, email_id: &str) -> Result<()> {
+        // Create a key for the scheduled email
+        let key = format!("scheduled:{}:{}", account_id, email_id);
+        
+        // Delete the scheduled email
+        self.db.remove(key.as_bytes())?;
         self.db.flush()?;
         
         Ok(())
     }
     
+    /// Stores the draft of a message, replacing the previous one.
+    ///
+    /// # Parameters
+    /// - `draft`: The unfinished message
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn store_draft(&self, draft: &Email) -> Result<()> {
+        self.db.insert(b"draft", serde_json::to_vec(draft)?)?;
+        self.db.flush()?;
+        
+        Ok(())
+    }
+    
+    /// Removes the draft from the database to continue writing it.
+    ///
+    /// # Returns
+    /// A Result containing the draft, or None if there is none
+    pub fn take_draft(&self) -> Result<Option<Email>> {
+        let Some(value) = self.db.remove(b"draft")? else {
+            return Ok(None);
+        };
+        self.db.flush()?;
+        
+        Ok(Some(serde_json::from_slice(&value)?))
+    }
+    
+    /// Records that mail was sent to some addresses.
+    ///
+    /// # Parameters
+    /// - `addresses`: The recipients, each with its display name if it has one
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn record_recipients(&self, addresses: &[String]) -> Result<()> {
+        for text in addresses {
+            let Some(address) = Address::parse(text) else {
+                continue;
+            };
+            
+            // One record per bare address, counting every message sent to it
+            let key = format!("recipient:{}", address.email.to_lowercase());
+            let count = match self.db.get(key.as_bytes())? {
+                Some(value) => serde_json::from_slice::<RecentRecipient>(&value).map_or(0, |recipient| recipient.count),
+                None => 0,
+            };
+            let recipient = RecentRecipient {
+                address: address.to_header(),
+                last_used: std::time::SystemTime::now(),
+                count: count + 1,
+            };
+            self.db.insert(key.as_bytes(), serde_json::to_vec(&recipient)?)?;
+        }
+        self.db.flush()?;
+        
+        Ok(())
+    }
+    
+    /// Retrieves the addresses mail was sent to.
+    ///
+    /// # Returns
+    /// A Result containing the recipients, most recently used first, or an error
+    pub fn get_recent_recipients(&self) -> Result<Vec<RecentRecipient>> {
+        let mut recipients = Vec::new();
+        
+        for result in self.db.scan_prefix(b"recipient:") {
+            let (key, value) = result?;
+            match serde_json::from_slice::<RecentRecipient>(&value) {
+                Ok(recipient) => recipients.push(recipient),
+                Err(e) => warn!("Skipping unreadable record {}: {}", String::from_utf8_lossy(&key), e),
+            }
+        }
+        
+        recipients.sort_by_key(|recipient| std::cmp::Reverse(recipient.last_used));
+        
+        Ok(recipients)
+    }
+    
     /// Closes the database.
     ///
     /// # Returns
@@ -274,6 +601,61 @@ impl EmailStorage {
         Ok(())
     }
     
+    /// Rebuilds the indexes of the email records from the records themselves.
+    ///
+    /// Emails are found by their keys, which hold the account, folder and ID
+    /// of each email, so a record under the wrong key is missing from its
+    /// folder. Such records are moved to the key their content belongs
+    /// under, and records that can't be read are removed so they're fetched
+    /// again. Searches go to the server, so there's no search index to rebuild.
+    ///
+    /// # Parameters
+    /// - `progress`: Called after each record with the number processed and the total
+    ///
+    /// # Returns
+    /// A Result containing what was rebuilt or an error
+    pub fn rebuild_indexes(&self, mut progress: impl FnMut(usize, usize)) -> Result<RebuildReport> {
+        let mut report = RebuildReport::default();
+        
+        // Collect the keys first, as records are moved while going through them
+        let keys = self.db.scan_prefix(b"email:")
+            .keys()
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let total = keys.len();
+        
+        for key in keys {
+            report.processed += 1;
+            
+            let Some(value) = self.db.get(&key)? else {
+                progress(report.processed, total);
+                continue;
+            };
+            match serde_json::from_slice::<Email>(&value) {
+                Ok(email) => {
+                    // Move the record if its key doesn't match its content
+                    let expected = format!("email:{}:{}:{}", email.account_id, email.folder, email.id);
+                    if key.as_ref() != expected.as_bytes() {
+                        self.db.insert(expected.as_bytes(), serde_json::to_vec(&email)?)?;
+                        self.db.remove(&key)?;
+                        report.rekeyed += 1;
+                    }
+                },
+                Err(e) => {
+                    warn!("Removing unreadable record {}: {}", String::from_utf8_lossy(&key), e);
+                    self.db.remove(&key)?;
+                    report.dropped += 1;
+                },
+            }
+            
+            progress(report.processed, total);
+        }
+        
+        self.db.flush()?;
+        info!("Rebuilt storage indexes: {:?}", report);
+        
+        Ok(report)
+    }
+    
     /// Utility function to manually clean up stale lock files.
     ///
     /// # Parameters
@@ -285,3 +667,282 @@ impl EmailStorage {
         Self::check_and_remove_stale_lock_files(path)
     }
 }
+
+impl EmailStore for EmailStorage {
+    fn store_email(&self, email: &Email) -> Result<()> {
+        // Create a key for the email
+        let key = format!("email:{}:{}:{}", email.account_id, email.folder, email.id);
+        
+        // Serialize the email
+        let value = serde_json::to_vec(email)?;
+        
+        // Store the email
+        self.db.insert(key.as_bytes(), value)?;
+        self.db.flush()?;
+        
+        Ok(())
+    }
+    
+    fn get_email(&self, account_id: &str, folder: &str, email_id: &str) -> Result<Option<Email>> {
+        // Create a key for the email
+        let key = format!("email:{}:{}:{}", account_id, folder, email_id);
+        
+        // Retrieve the email
+        if let Some(value) = self.db.get(key.as_bytes())? {
+            // Deserialize the email
+            let email: Email = serde_json::from_slice(&value)?;
+            Ok(Some(email))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    fn get_emails(&self, account_id: &str, folder: &str) -> Result<Vec<Email>> {
+        // Create a prefix for the emails
+        let prefix = format!("email:{}:{}:", account_id, folder);
+        
+        // Retrieve all emails with the prefix, skipping unreadable records
+        let mut emails = Vec::new();
+        let mut skipped = 0;
+        
+        for result in self.db.scan_prefix(prefix.as_bytes()) {
+            let (key, value) = result?;
+            match serde_json::from_slice::<Email>(&value) {
+                Ok(email) => emails.push(email),
+                Err(e) => {
+                    debug!("Skipping unreadable record {}: {}", String::from_utf8_lossy(&key), e);
+                    skipped += 1;
+                },
+            }
+        }
+        if skipped > 0 {
+            warn!("Skipped {} unreadable emails in {} of account {}", skipped, folder, account_id);
+        }
+        
+        // Sort emails by date (newest first)
+        emails.sort_by_key(|e| std::cmp::Reverse(e.date));
+        
+        Ok(emails)
+    }
+    
+    fn delete_email(&self, account_id: &str, folder: &str, email_id: &str) -> Result<()> {
+        // Create a key for the email
+        let key = format!("email:{}:{}:{}", account_id, folder, email_id);
+        
+        // Delete the email
+        self.db.remove(key.as_bytes())?;
+        self.db.flush()?;
+        
+        Ok(())
+    }
+    
+    fn delete_emails(&self, account_id: &str, folder: &str) -> Result<()> {
+        // Create a prefix for the emails
+        let prefix = format!("email:{}:{}:", account_id, folder);
+        
+        // Delete all emails with the prefix
+        for result in self.db.scan_prefix(prefix.as_bytes()) {
+            let (key, _) = result?;
+            self.db.remove(key)?;
+        }
+        self.db.flush()?;
+        
+        Ok(())
+    }
+    
+    fn get_uid_validity(&self, account_id: &str, folder: &str) -> Result<Option<u32>> {
+        let key = format!("uidvalidity:{}:{}", account_id, folder);
+        
+        match self.db.get(key.as_bytes())? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+    
+    fn store_uid_validity(&self, account_id: &str, folder: &str, uid_validity: u32) -> Result<()> {
+        let key = format!("uidvalidity:{}:{}", account_id, folder);
+        
+        self.db.insert(key.as_bytes(), serde_json::to_vec(&uid_validity)?)?;
+        self.db.flush()?;
+        
+        Ok(())
+    }
+    
+    fn close(&self) -> Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+/// Migrates version 0 to 1 by writing every record again, so that records
+/// from before the schema version was stored have all current fields.
+///
+/// Cached emails and accounts that can't be read are dropped, as they are
+/// fetched again. Scheduled emails are kept as they are.
+///
+/// # Parameters
+/// - `db`: The database
+///
+/// # Returns
+/// A Result indicating success or failure
+fn reserialize_records(db: &Db) -> Result<()> {
+    reserialize::<Email>(db, "email:", true)?;
+    reserialize::<Account>(db, "account:", true)?;
+    reserialize::<ScheduledEmail>(db, "scheduled:", false)?;
+    reserialize::<Email>(db, "draft", false)?;
+    
+    Ok(())
+}
+
+/// Writes the records whose key starts with a prefix again.
+///
+/// # Parameters
+/// - `db`: The database
+/// - `prefix`: The key prefix
+/// - `drop_unreadable`: Whether to remove records that can't be read, or keep them
+///
+/// # Returns
+/// A Result indicating success or failure
+fn reserialize<T: Serialize + DeserializeOwned>(db: &Db, prefix: &str, drop_unreadable: bool) -> Result<()> {
+    for result in db.scan_prefix(prefix.as_bytes()) {
+        let (key, value) = result?;
+        match serde_json::from_slice::<T>(&value) {
+            Ok(record) => {
+                db.insert(key, serde_json::to_vec(&record)?)?;
+            },
+            Err(e) if drop_unreadable => {
+                warn!("Dropping unreadable record {}: {}", String::from_utf8_lossy(&key), e);
+                db.remove(key)?;
+            },
+            Err(e) => warn!("Keeping unreadable record {}: {}", String::from_utf8_lossy(&key), e),
+        }
+    }
+    
+    Ok(())
+}
+
+/// Opens the email store chosen in the settings.
+///
+/// # Parameters
+/// - `settings`: The settings with the storage backend
+/// - `storage`: The database, which is also the sled backend
+/// - `base_dir`: The base directory, which holds the default Maildir
+///
+/// # Returns
+/// A Result containing the email store or an error
+pub fn open_email_store(settings: &AppSettings, storage: &EmailStorage, base_dir: &Path) -> Result<Arc<dyn EmailStore>> {
+    match settings.storage_backend {
+        StorageBackend::Sled => Ok(Arc::new(storage.clone())),
+        StorageBackend::Maildir => {
+            let path = settings.maildir_dir(base_dir);
+            info!("Storing emails in Maildir at {:?}", path);
+            Ok(Arc::new(MaildirStorage::new(&path)?))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    /// A database in its own directory, removed when the test ends.
+    struct TestStorage {
+        storage: EmailStorage,
+        dir: std::path::PathBuf,
+    }
+
+    impl TestStorage {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("linksy-test-{}", uuid::Uuid::new_v4()));
+            Self {
+                storage: EmailStorage::new(&dir).unwrap(),
+                dir,
+            }
+        }
+    }
+
+    impl Drop for TestStorage {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// An email of the work inbox, received a number of days after the first.
+    fn email(id: &str, day: u64, flagged: bool) -> Email {
+        let mut email = Email::new();
+        email.id = id.to_string();
+        email.account_id = "work".to_string();
+        email.folder = "INBOX".to_string();
+        email.date = SystemTime::UNIX_EPOCH + Duration::from_secs(day * 24 * 60 * 60);
+        email.is_flagged = flagged;
+        email
+    }
+
+    /// Stores emails and gets the IDs left after trimming, oldest first.
+    fn trim(emails: &[Email], keep: usize, listed: &[Email]) -> (usize, Vec<String>) {
+        let test = TestStorage::new();
+        for email in emails {
+            test.storage.store_email(email).unwrap();
+        }
+        let removed = test.storage.trim_emails("work", "INBOX", keep, listed).unwrap();
+
+        let mut left = test.storage.get_emails("work", "INBOX").unwrap();
+        left.sort_by_key(|email| email.date);
+        (removed, left.into_iter().map(|email| email.id).collect())
+    }
+
+    #[test]
+    fn trims_the_oldest_emails() {
+        let emails: Vec<Email> = (1..=5).map(|day| email(&day.to_string(), day, false)).collect();
+        assert_eq!(trim(&emails, 3, &[]), (2, vec!["3".to_string(), "4".to_string(), "5".to_string()]));
+    }
+
+    #[test]
+    fn keeps_folders_within_the_limit() {
+        let emails: Vec<Email> = (1..=3).map(|day| email(&day.to_string(), day, false)).collect();
+        assert_eq!(trim(&emails, 3, &[]).0, 0);
+        assert_eq!(trim(&emails, 10, &[]).0, 0);
+    }
+
+    #[test]
+    fn keeps_flagged_emails() {
+        let emails = vec![email("1", 1, true), email("2", 2, false), email("3", 3, false), email("4", 4, false)];
+        assert_eq!(trim(&emails, 2, &[]), (2, vec!["1".to_string(), "4".to_string()]));
+
+        // Flagged emails count toward the limit but are kept beyond it
+        let flagged: Vec<Email> = (1..=3).map(|day| email(&day.to_string(), day, true)).collect();
+        assert_eq!(trim(&flagged, 1, &[]), (0, vec!["1".to_string(), "2".to_string(), "3".to_string()]));
+    }
+
+    #[test]
+    fn keeps_the_emails_the_server_lists() {
+        // The newest UID was sent with an old date, and is still listed
+        let emails = vec![email("10", 1, false), email("2", 2, false), email("3", 3, false), email("4", 4, false)];
+        let listed = vec![emails[0].clone(), emails[3].clone()];
+        assert_eq!(trim(&emails, 2, &listed), (2, vec!["10".to_string(), "4".to_string()]));
+    }
+
+    #[test]
+    fn skips_unreadable_scheduled_emails() {
+        let test = TestStorage::new();
+        let scheduled = ScheduledEmail::new(email("1", 1, false), SystemTime::UNIX_EPOCH);
+        test.storage.store_scheduled_email(&scheduled).unwrap();
+        test.storage.db.insert(b"scheduled:work:2", b"not json".to_vec()).unwrap();
+
+        let left = test.storage.get_all_scheduled_emails().unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].email.id, "1");
+    }
+
+    #[test]
+    fn keeps_the_uid_validity_per_folder() {
+        let test = TestStorage::new();
+        test.storage.store_uid_validity("work", "INBOX", 7).unwrap();
+        assert_eq!(test.storage.get_uid_validity("work", "INBOX").unwrap(), Some(7));
+        assert_eq!(test.storage.get_uid_validity("work", "Archive").unwrap(), None);
+
+        test.storage.delete_account("work").unwrap();
+        assert_eq!(test.storage.get_uid_validity("work", "INBOX").unwrap(), None);
+    }
+}
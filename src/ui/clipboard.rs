@@ -0,0 +1,32 @@
+//! System clipboard access for the Linksy email client.
+//!
+//! The clipboard handle is kept for the life of the application because on
+//! X11 copied text is only available while its owner is still running.
+
+use anyhow::{anyhow, Result};
+use arboard::Clipboard;
+use std::sync::Mutex;
+
+/// The clipboard handle, created on first use.
+static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+
+/// Copies text to the system clipboard.
+///
+/// # Parameters
+/// - `text`: The text to copy
+///
+/// # Returns
+/// A Result indicating success, or an error if no clipboard is available
+pub fn copy_text(text: &str) -> Result<()> {
+    let mut clipboard = CLIPBOARD.lock().map_err(|_| anyhow!("Clipboard lock poisoned"))?;
+    
+    if clipboard.is_none() {
+        *clipboard = Some(Clipboard::new().map_err(|e| anyhow!("No clipboard available: {}", e))?);
+    }
+    
+    if let Some(clipboard) = clipboard.as_mut() {
+        clipboard.set_text(text).map_err(|e| anyhow!("Failed to copy: {}", e))?;
+    }
+    
+    Ok(())
+}
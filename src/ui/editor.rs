@@ -0,0 +1,120 @@
+//! External editor support for the Linksy email client.
+//!
+//! This module suspends the UI while the user edits text in their own
+//! editor, then brings the UI back up.
+
+use crate::state::SharedState;
+use crate::ui::{init_terminal, restore_terminal, write_private_temp_file};
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use std::io;
+use std::process::Command;
+use tui::{backend::CrosstermBackend, Terminal};
+
+/// Determines the command used to launch the external editor.
+///
+/// # Parameters
+/// - `configured`: The editor from the settings, if any
+///
+/// # Returns
+/// The configured editor, falling back to $VISUAL, $EDITOR and then vi
+pub fn editor_command(configured: Option<&str>) -> String {
+    configured
+        .filter(|editor| !editor.trim().is_empty())
+        .map(|editor| editor.to_string())
+        .or_else(|| std::env::var("VISUAL").ok().filter(|editor| !editor.trim().is_empty()))
+        .or_else(|| std::env::var("EDITOR").ok().filter(|editor| !editor.trim().is_empty()))
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Edits text in an external editor through a temporary file.
+///
+/// # Parameters
+/// - `editor`: The editor command, which may include arguments
+/// - `text`: The text to seed the file with
+///
+/// # Returns
+/// A Result containing the edited text, or None if the editor exited with an error
+fn edit_text(editor: &str, text: &str) -> Result<Option<String>> {
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or_else(|| anyhow!("No editor configured"))?;
+
+    let path = write_private_temp_file(&format!("linksy-compose-{}.txt", uuid::Uuid::new_v4()), text.as_bytes())?;
+
+    let status = Command::new(program).args(parts).arg(&path).status();
+    let result = match status {
+        Ok(status) if status.success() => {
+            // Editors usually add a final newline that wasn't in the body
+            let edited = std::fs::read_to_string(&path)?;
+            Ok(Some(edited.trim_end_matches('\n').to_string()))
+        },
+        Ok(status) => {
+            info!("Editor {} exited with {}", program, status);
+            Ok(None)
+        },
+        Err(e) => Err(anyhow!("Failed to launch {}: {}", program, e)),
+    };
+
+    if let Err(e) = std::fs::remove_file(&path) {
+        error!("Failed to remove temporary file {}: {}", path.display(), e);
+    }
+
+    result
+}
+
+/// Runs the external editor on the compose body if compose asked for it.
+///
+/// The terminal is restored before the editor starts and initialized again
+/// afterwards, even if the editor couldn't be launched.
+///
+/// # Parameters
+/// - `terminal`: The UI terminal
+/// - `state`: The application state
+///
+/// # Returns
+/// A Result containing the terminal to keep drawing on
+pub async fn run_pending_editor(
+    terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    state: &SharedState,
+) -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
+    // Look for a request first, since taking the state for writing counts as a change
+    if !state.read().await.external_editor_requested {
+        return Ok(terminal);
+    }
+    
+    // Take the request without holding the lock while the editor runs
+    let request = {
+        let mut state = state.write().await;
+        if !state.take_external_editor_request() {
+            return Ok(terminal);
+        }
+        let editor = editor_command(state.config_manager.get_config().settings.editor.as_deref());
+        state.get_compose_state().map(|compose| (editor, compose.body.clone()))
+    };
+    let Some((editor, body)) = request else {
+        return Ok(terminal);
+    };
+
+    restore_terminal(terminal)?;
+    let result = edit_text(&editor, &body);
+    let mut terminal = init_terminal()?;
+    terminal.clear()?;
+
+    let mut state = state.write().await;
+    match result {
+        Ok(Some(edited)) => {
+            if let Some(compose) = state.get_compose_state_mut() {
+                compose.set_body(edited);
+            }
+        },
+        Ok(None) => {
+            state.set_status_message("Editor exited with an error, body left unchanged".to_string());
+        },
+        Err(e) => {
+            error!("External editor failed: {}", e);
+            state.set_status_message(format!("External editor failed: {}", e));
+        },
+    }
+
+    Ok(terminal)
+}
@@ -2,8 +2,11 @@
 //! 
 //! This module contains the terminal-based UI implementation using the tui crate.
 
-pub mod app;
+pub mod clipboard;
+pub mod editor;
+pub mod notify;
 pub mod renderer;
+pub mod viewer;
 pub mod views;
 pub mod widgets;
 
@@ -11,11 +14,12 @@ use anyhow::Result;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle},
 };
-use std::{io, time::Duration};
+use std::{fs::OpenOptions, io, io::Write, path::PathBuf, time::Duration};
 use tui::{
     backend::CrosstermBackend,
+    layout::Rect,
     Terminal,
 };
 
@@ -54,6 +58,77 @@ pub fn restore_terminal(mut terminal: Terminal<CrosstermBackend<io::Stdout>>) ->
     Ok(())
 }
 
+/// Saves the terminal title so it can be restored on exit.
+///
+/// Terminals that can't save their title ignore this.
+///
+/// # Returns
+/// A Result indicating success or failure
+pub fn save_title() -> Result<()> {
+    // XTWINOPS: push the window title onto the terminal's title stack
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b[22;0t")?;
+    stdout.flush()?;
+    
+    Ok(())
+}
+
+/// Restores the terminal title saved by save_title.
+///
+/// The title is cleared first for terminals that can't restore it.
+///
+/// # Returns
+/// A Result indicating success or failure
+pub fn restore_title() -> Result<()> {
+    let mut stdout = io::stdout();
+    execute!(stdout, SetTitle(""))?;
+    
+    // XTWINOPS: pop the window title from the terminal's title stack
+    stdout.write_all(b"\x1b[23;0t")?;
+    stdout.flush()?;
+    
+    Ok(())
+}
+
+/// Sets the terminal title.
+///
+/// # Parameters
+/// - `title`: The new title
+///
+/// # Returns
+/// A Result indicating success or failure
+pub fn set_title(title: &str) -> Result<()> {
+    execute!(io::stdout(), SetTitle(title))?;
+    
+    Ok(())
+}
+
+/// Creates the terminal title for a number of unread emails.
+///
+/// # Parameters
+/// - `unread`: The number of unread emails across all accounts
+///
+/// # Returns
+/// e.g. "linksy (3 unread)", or "linksy" if there are no unread emails
+pub fn unread_title(unread: usize) -> String {
+    if unread > 0 {
+        format!("linksy ({} unread)", unread)
+    } else {
+        "linksy".to_string()
+    }
+}
+
+/// Gets the area the current view is drawn in.
+///
+/// This is the whole terminal but the status bar on the last line.
+///
+/// # Returns
+/// A Result containing the area or an error
+pub fn content_area() -> Result<Rect> {
+    let (width, height) = terminal::size()?;
+    Ok(Rect::new(0, 0, width, height.saturating_sub(1)))
+}
+
 /// Waits for a key press event.
 ///
 /// # Parameters
@@ -61,6 +136,7 @@ pub fn restore_terminal(mut terminal: Terminal<CrosstermBackend<io::Stdout>>) ->
 ///
 /// # Returns
 /// A Result containing the KeyEvent or None if timeout occurred
+#[allow(clippy::collapsible_if)]
 pub fn wait_for_key(timeout: Option<Duration>) -> Result<Option<KeyEvent>> {
     if let Some(duration) = timeout {
         if event::poll(duration)? {
@@ -103,3 +179,56 @@ pub fn is_key_with_modifier(key: &KeyEvent, code: KeyCode, modifiers: KeyModifie
 pub fn is_key(key: &KeyEvent, code: KeyCode) -> bool {
     key.code == code && key.modifiers == KeyModifiers::NONE
 }
+
+/// Writes a temporary file that only the user can read.
+///
+/// The files hold message text, and the temporary directory is shared, so
+/// the file is created new with owner-only permissions instead of being
+/// written over whatever is already at the path.
+///
+/// # Parameters
+/// - `name`: The file name in the temporary directory
+/// - `contents`: The contents to write
+///
+/// # Returns
+/// A Result containing the path of the file, or an error if it already exists or can't be written
+pub fn write_private_temp_file(name: &str, contents: &[u8]) -> Result<PathBuf> {
+    let path = std::env::temp_dir().join(name);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(&path)?;
+    if let Err(e) = file.write_all(contents) {
+        let _ = std::fs::remove_file(&path);
+        return Err(e.into());
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_private_temp_files() {
+        let name = format!("linksy-test-{}.txt", uuid::Uuid::new_v4());
+        let path = write_private_temp_file(&name, b"Dear Bob").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"Dear Bob");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // A file already at the path, e.g. a planted link, isn't written to
+        assert!(write_private_temp_file(&name, b"Other text").is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"Dear Bob");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
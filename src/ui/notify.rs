@@ -0,0 +1,172 @@
+//! New mail notifications for the Linksy email client.
+//!
+//! New mail can ring the terminal bell and, when built with the
+//! `desktop-notifications` feature, show a desktop notification through the
+//! system's notification service. Without a graphical session, e.g. over
+//! SSH, only the bell is used.
+//!
+//! A command from the `new_mail_command` setting can also be run for each
+//! account with new mail, e.g. to trigger a custom notifier or a sync.
+
+use crate::config::AppSettings;
+use log::{debug, info, warn};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Tells the user about new mail as the settings say.
+///
+/// # Parameters
+/// - `settings`: The settings with the notification options
+/// - `counts`: The number of new emails per account name
+pub fn notify_new_mail(settings: &AppSettings, counts: &[(String, usize)]) {
+    if counts.is_empty() {
+        return;
+    }
+
+    if settings.notify_bell {
+        ring_bell();
+    }
+
+    if settings.notify_desktop {
+        let body = counts.iter()
+            .map(|(account, count)| format!("{} new in {}", count, account))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if let Err(e) = show_desktop_notification("New mail", &body) {
+            debug!("No desktop notification shown: {}", e);
+        }
+    }
+}
+
+/// Runs the new mail command for an account, without waiting for it.
+///
+/// The command runs through the shell with the account ID and the number of
+/// new emails as its arguments and in the `LINKSY_ACCOUNT_ID`,
+/// `LINKSY_ACCOUNT_NAME` and `LINKSY_NEW_COUNT` environment variables. Its
+/// output and a failing exit status are written to the log.
+///
+/// # Parameters
+/// - `command`: The command from the settings
+/// - `account_id`: The ID of the account with new mail
+/// - `account_name`: The name of the account
+/// - `count`: The number of new emails
+pub fn run_new_mail_command(command: &str, account_id: &str, account_name: &str, count: usize) {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        // The arguments after the script name are $1 and $2
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command).arg("linksy").arg(account_id).arg(count.to_string());
+        shell
+    };
+
+    let child = shell
+        .env("LINKSY_ACCOUNT_ID", account_id)
+        .env("LINKSY_ACCOUNT_NAME", account_name)
+        .env("LINKSY_NEW_COUNT", count.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Failed to run the new mail command: {}", e);
+            return;
+        },
+    };
+
+    // Log the output once the command exits
+    std::thread::spawn(move || {
+        match child.wait_with_output() {
+            Ok(output) => {
+                for line in String::from_utf8_lossy(&output.stdout).lines() {
+                    info!("New mail command: {}", line);
+                }
+                for line in String::from_utf8_lossy(&output.stderr).lines() {
+                    warn!("New mail command: {}", line);
+                }
+                if !output.status.success() {
+                    warn!("New mail command exited with {}", output.status);
+                }
+            },
+            Err(e) => warn!("Failed to wait for the new mail command: {}", e),
+        }
+    });
+}
+
+/// Describes new mail for the status line.
+///
+/// # Parameters
+/// - `counts`: The number of new emails per account name
+///
+/// # Returns
+/// e.g. "New mail: 3 in Work, 1 in Personal"
+pub fn describe_new_mail(counts: &[(String, usize)]) -> String {
+    let counts = counts.iter()
+        .map(|(account, count)| format!("{} in {}", count, account))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("New mail: {}", counts)
+}
+
+/// Rings the terminal bell.
+fn ring_bell() {
+    let mut stdout = std::io::stdout();
+    if let Err(e) = stdout.write_all(b"\x07").and_then(|_| stdout.flush()) {
+        warn!("Failed to ring the terminal bell: {}", e);
+    }
+}
+
+/// Shows a desktop notification, without waiting for the notifier to finish.
+///
+/// # Parameters
+/// - `title`: The notification title
+/// - `body`: The notification text
+///
+/// # Returns
+/// A Result indicating success, or an error if there's no graphical session
+#[cfg(feature = "desktop-notifications")]
+fn show_desktop_notification(title: &str, body: &str) -> anyhow::Result<()> {
+    // A notification needs a graphical session to show up in
+    if cfg!(all(unix, not(target_os = "macos"))) {
+        let graphical = ["DISPLAY", "WAYLAND_DISPLAY"].iter().any(|var| std::env::var_os(var).is_some());
+        if !graphical {
+            return Err(anyhow::anyhow!("No graphical session"));
+        }
+    }
+
+    let mut notification = notify_rust::Notification::new();
+    notification.appname("Linksy").summary(title).body(body);
+
+    // Talking to the notification service can block, so it's done off the UI thread
+    std::thread::spawn(move || {
+        if let Err(e) = notification.show() {
+            debug!("No desktop notification shown: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// Shows a desktop notification; not available in this build.
+///
+/// The `notify_desktop` setting is on, so the first call logs a warning
+/// saying why no notifications show up.
+///
+/// # Parameters
+/// - `_title`: The notification title
+/// - `_body`: The notification text
+///
+/// # Returns
+/// An error saying that desktop notifications weren't built in
+#[cfg(not(feature = "desktop-notifications"))]
+fn show_desktop_notification(_title: &str, _body: &str) -> anyhow::Result<()> {
+    static WARNED: std::sync::Once = std::sync::Once::new();
+    WARNED.call_once(|| {
+        warn!("notify_desktop is on, but Linksy was built without the desktop-notifications feature");
+    });
+    Err(anyhow::anyhow!("Linksy was built without the desktop-notifications feature"))
+}
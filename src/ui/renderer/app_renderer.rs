@@ -1,14 +1,16 @@
 //! Main application renderer for the Linksy email client.
 
-use crate::state::{AppState, View};
+use crate::models::SCHEDULED_FOLDER;
+use crate::state::{RenderSnapshot, View};
 use crate::ui::views;
 use anyhow::Result;
 use tui::{
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Style},
-    widgets::{Block, Borders, Paragraph},
+    widgets::Paragraph,
     Frame,
 };
+use unicode_width::UnicodeWidthStr;
 
 /// Renders the application UI.
 pub struct AppRenderer;
@@ -26,11 +28,11 @@ impl AppRenderer {
     ///
     /// # Parameters
     /// - `f`: The frame to render on
-    /// - `state`: The application state
+    /// - `snapshot`: The state to render
     ///
     /// # Returns
     /// A Result indicating success or failure
-    pub fn render(&self, f: &mut Frame, state: &AppState) -> Result<()> {
+    pub fn render(&self, f: &mut Frame, snapshot: &RenderSnapshot) -> Result<()> {
         // Create the main layout
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -41,18 +43,42 @@ impl AppRenderer {
             .split(f.size());
             
         // Render the main content based on the current view
-        match state.get_current_view() {
-            View::Accounts => self.render_accounts_view(f, state, chunks[0])?,
-            View::Folders => self.render_folders_view(f, state, chunks[0])?,
-            View::Emails => self.render_emails_view(f, state, chunks[0])?,
-            View::EmailDetail => self.render_email_detail_view(f, state, chunks[0])?,
-            View::ComposeEmail => self.render_compose_email_view(f, state, chunks[0])?,
-            View::Settings => self.render_settings_view(f, state, chunks[0])?,
-            View::AccountConfig => self.render_account_config_view(f, state, chunks[0])?,
+        match snapshot.current_view {
+            View::Accounts => self.render_accounts_view(f, snapshot, chunks[0])?,
+            View::Folders => self.render_folders_view(f, snapshot, chunks[0])?,
+            View::Emails => self.render_emails_view(f, snapshot, chunks[0])?,
+            View::EmailDetail => self.render_email_detail_view(f, snapshot, chunks[0])?,
+            View::ComposeEmail => self.render_compose_email_view(f, snapshot, chunks[0])?,
+            View::Settings => self.render_settings_view(f, snapshot, chunks[0])?,
+            View::TemplateForm => self.render_template_form_view(f, snapshot, chunks[0])?,
+            View::AccountConfig => self.render_account_config_view(f, snapshot, chunks[0])?,
+            View::AccountStatus => self.render_account_status_view(f, snapshot, chunks[0])?,
+            View::Log => self.render_log_view(f, snapshot, chunks[0])?,
+        }
+        
+        // Render popups over the main content
+        if let Some(picker) = &snapshot.link_picker {
+            views::links::render_link_picker(f, chunks[0], picker);
+        }
+        if let (Some(picker), Some(email)) = (&snapshot.attachment_picker, &snapshot.viewed_email) {
+            views::attachments::render_attachment_picker(f, chunks[0], &email.attachments, picker);
+        }
+        let tagged_email = snapshot.selected_email.and_then(|index| snapshot.emails.get(index));
+        if let (Some(picker), Some(email)) = (&snapshot.tag_picker, tagged_email) {
+            views::tags::render_tag_picker(f, chunks[0], email, picker);
+        }
+        if let Some(prompt) = &snapshot.prompt {
+            views::prompt::render_prompt(f, chunks[0], prompt);
+        }
+        if let Some(picker) = &snapshot.template_picker {
+            views::templates::render_template_picker(f, chunks[0], &snapshot.templates, picker);
+        }
+        if let Some(confirm) = &snapshot.confirm {
+            views::prompt::render_confirm(f, chunks[0], confirm);
         }
         
         // Render the status bar
-        self.render_status_bar(f, state, chunks[1])?;
+        self.render_status_bar(f, snapshot, chunks[1])?;
         
         Ok(())
     }
@@ -61,18 +87,53 @@ impl AppRenderer {
     ///
     /// # Parameters
     /// - `f`: The frame to render on
-    /// - `state`: The application state
+    /// - `snapshot`: The state to render
     /// - `area`: The area to render in
     ///
     /// # Returns
     /// A Result indicating success or failure
-    fn render_accounts_view(&self, f: &mut Frame, state: &AppState, area: Rect) -> Result<()> {
-        // Use the accounts directly from the state without trying to lock them
-        // This avoids blocking operations in the UI rendering code
-        let account_summaries = state.account_summaries.clone();
-        
+    fn render_accounts_view(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
         // Render accounts
-        views::render_accounts(f, area, &account_summaries, state.get_selected_account());
+        views::render_accounts(f, area, &snapshot.account_summaries, snapshot.selected_account);
+        
+        // Explain how to get started on first run
+        if snapshot.no_accounts {
+            views::render_onboarding(f, area, &snapshot.config_path.to_string_lossy());
+        }
+        
+        Ok(())
+    }
+    
+    /// Renders the account status dashboard view.
+    ///
+    /// # Parameters
+    /// - `f`: The frame to render on
+    /// - `snapshot`: The state to render
+    /// - `area`: The area to render in
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn render_account_status_view(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
+        // Render from the summaries, which are refreshed every frame
+        views::render_account_status(f, area, &snapshot.account_summaries, snapshot.selected_account);
+        
+        Ok(())
+    }
+    
+    /// Renders the log view.
+    ///
+    /// # Parameters
+    /// - `f`: The frame to render on
+    /// - `snapshot`: The state to render
+    /// - `area`: The area to render in
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn render_log_view(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
+        // Render the messages taken with the snapshot
+        if let Some(log_view) = &snapshot.log_view {
+            views::log::render_log(f, area, &snapshot.log_entries, log_view, snapshot.settings.log_level);
+        }
         
         Ok(())
     }
@@ -81,34 +142,18 @@ impl AppRenderer {
     ///
     /// # Parameters
     /// - `f`: The frame to render on
-    /// - `state`: The application state
+    /// - `snapshot`: The state to render
     /// - `area`: The area to render in
     ///
     /// # Returns
     /// A Result indicating success or failure
-    fn render_folders_view(&self, f: &mut Frame, state: &AppState, area: Rect) -> Result<()> {
-        // Use a default folder list if we can't get the account's folders
-        let default_folders = vec!["INBOX".to_string()];
-        
-        // Get folders from the selected account if available
-        let folders = if let Some(index) = state.get_selected_account() {
-            if index < state.accounts.len() {
-                // Try to get a non-blocking lock on the account
-                if let Ok(account) = state.accounts[index].try_lock() {
-                    account.folders.clone()
-                } else {
-                    // If we can't get a lock, use the default folders
-                    default_folders
-                }
-            } else {
-                default_folders
-            }
-        } else {
-            default_folders
-        };
+    fn render_folders_view(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
+        // Keep the highlight on the list if it got shorter
+        let count = snapshot.folders.len() + snapshot.saved_searches.len();
+        let selected = snapshot.selected_folder_index.min(count.saturating_sub(1));
         
-        // Render folders
-        views::render_folders(f, area, &folders, Some(0));
+        // Render folders, followed by the saved searches
+        views::render_folders(f, area, &snapshot.folders, &snapshot.saved_searches, &snapshot.last_synced, Some(selected));
         
         Ok(())
     }
@@ -117,14 +162,25 @@ impl AppRenderer {
     ///
     /// # Parameters
     /// - `f`: The frame to render on
-    /// - `state`: The application state
+    /// - `snapshot`: The state to render
     /// - `area`: The area to render in
     ///
     /// # Returns
     /// A Result indicating success or failure
-    fn render_emails_view(&self, f: &mut Frame, state: &AppState, area: Rect) -> Result<()> {
-        // Render emails
-        views::render_emails(f, area, &state.emails, state.get_selected_email());
+    fn render_emails_view(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
+        // Render emails, or the messages waiting in the Scheduled folder
+        if let Some(search) = &snapshot.search {
+            let settings = &snapshot.settings;
+            views::search::render_search_results(f, area, &snapshot.emails, snapshot.selected_email, &snapshot.selected_emails, settings, search);
+        } else if snapshot.selected_folder == SCHEDULED_FOLDER {
+            views::render_scheduled_emails(f, area, &snapshot.emails, snapshot.selected_email);
+        } else if let Some(grouping) = &snapshot.grouping {
+            let settings = &snapshot.settings;
+            views::grouping::render_grouped_emails(f, area, &snapshot.emails, snapshot.selected_email, &snapshot.selected_emails, grouping, settings, &snapshot.folder_view);
+        } else {
+            let settings = &snapshot.settings;
+            views::render_emails(f, area, &snapshot.emails, snapshot.selected_email, &snapshot.selected_emails, settings, &snapshot.folder_view);
+        }
         
         Ok(())
     }
@@ -133,15 +189,24 @@ impl AppRenderer {
     ///
     /// # Parameters
     /// - `f`: The frame to render on
-    /// - `state`: The application state
+    /// - `snapshot`: The state to render
     /// - `area`: The area to render in
     ///
     /// # Returns
     /// A Result indicating success or failure
-    fn render_email_detail_view(&self, f: &mut Frame, state: &AppState, area: Rect) -> Result<()> {
+    fn render_email_detail_view(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
         // Render email detail
-        if let Some(email) = state.get_viewed_email() {
-            views::render_email_detail(f, area, email, state.get_email_scroll_offset());
+        if let Some(email) = &snapshot.viewed_email {
+            views::render_email_detail(
+                f,
+                area,
+                email,
+                snapshot.email_scroll_offset,
+                &snapshot.settings,
+                snapshot.show_quoted_text,
+                snapshot.show_remote_content,
+                snapshot.body_find.as_ref(),
+            );
         }
         
         Ok(())
@@ -151,17 +216,16 @@ impl AppRenderer {
     ///
     /// # Parameters
     /// - `f`: The frame to render on
-    /// - `state`: The application state
+    /// - `snapshot`: The state to render
     /// - `area`: The area to render in
     ///
     /// # Returns
     /// A Result indicating success or failure
-    fn render_compose_email_view(&self, f: &mut Frame, _state: &AppState, area: Rect) -> Result<()> {
-        // TODO: Implement compose email view
-        let compose = Paragraph::new("Compose Email (not implemented)")
-            .block(Block::default().borders(Borders::ALL).title("Compose Email"));
-            
-        f.render_widget(compose, area);
+    fn render_compose_email_view(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
+        // Render the compose form
+        if let Some(compose) = &snapshot.compose_state {
+            views::compose::render_compose(f, area, compose);
+        }
         
         Ok(())
     }
@@ -170,14 +234,32 @@ impl AppRenderer {
     ///
     /// # Parameters
     /// - `f`: The frame to render on
-    /// - `state`: The application state
+    /// - `snapshot`: The state to render
+    /// - `area`: The area to render in
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn render_settings_view(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
+        // Render the template list
+        views::templates::render_template_settings(f, area, &snapshot.templates, snapshot.selected_template);
+        
+        Ok(())
+    }
+    
+    /// Renders the template form view.
+    ///
+    /// # Parameters
+    /// - `f`: The frame to render on
+    /// - `snapshot`: The state to render
     /// - `area`: The area to render in
     ///
     /// # Returns
     /// A Result indicating success or failure
-    fn render_settings_view(&self, f: &mut Frame, _state: &AppState, area: Rect) -> Result<()> {
-        // Render settings
-        views::render_settings(f, area);
+    fn render_template_form_view(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
+        // Render the template form
+        if let Some(form) = &snapshot.template_form {
+            views::templates::render_template_form(f, area, form);
+        }
         
         Ok(())
     }
@@ -186,14 +268,14 @@ impl AppRenderer {
     ///
     /// # Parameters
     /// - `f`: The frame to render on
-    /// - `state`: The application state
+    /// - `snapshot`: The state to render
     /// - `area`: The area to render in
     ///
     /// # Returns
     /// A Result indicating success or failure
-    fn render_account_config_view(&self, f: &mut Frame, state: &AppState, area: Rect) -> Result<()> {
+    fn render_account_config_view(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
         // Render account configuration
-        if let Some(form_state) = state.get_account_form_state() {
+        if let Some(form_state) = &snapshot.account_form_state {
             views::account_config::render_account_config(f, area, form_state);
         }
         
@@ -204,30 +286,80 @@ impl AppRenderer {
     ///
     /// # Parameters
     /// - `f`: The frame to render on
-    /// - `state`: The application state
+    /// - `snapshot`: The state to render
     /// - `area`: The area to render in
     ///
     /// # Returns
     /// A Result indicating success or failure
-    fn render_status_bar(&self, f: &mut Frame, state: &AppState, area: Rect) -> Result<()> {
+    fn render_status_bar(&self, f: &mut Frame, snapshot: &RenderSnapshot, area: Rect) -> Result<()> {
+        // Describe how fresh the email list is
+        let sync_info = match snapshot.folder_last_synced() {
+            Some(time) => format!("Last synced {}", views::format_time_ago(time)),
+            None => "Not synced yet".to_string(),
+        };
+        
         // Create status message
-        let status_message = if let Some(message) = state.get_status_message() {
+        let status_message = if let Some(message) = &snapshot.status_message {
             message.clone()
         } else {
-            match state.get_current_view() {
-                View::Accounts => "Accounts - Press 'a' to add, 'e' to edit, 'd' to delete, Enter to select".to_string(),
-                View::Folders => "Folders - Press Enter to select, Esc to go back".to_string(),
-                View::Emails => "Emails - Press Enter to view, 'c' to compose, 'r' to reply, 'f' to forward, 'd' to delete, Esc to go back".to_string(),
-                View::EmailDetail => "Email - Press 'r' to reply, 'f' to forward, 'd' to delete, Esc to go back".to_string(),
-                View::ComposeEmail => "Compose - Press Ctrl+s to send, Esc to cancel".to_string(),
-                View::Settings => "Settings - Press Esc to go back".to_string(),
-                View::AccountConfig => "Account Configuration - Press Enter to edit field, Tab to navigate, Enter on Save to save".to_string(),
+            match snapshot.current_view {
+                View::Accounts => "Accounts - Press 'a' to add, 'c' to copy, 'e' to edit, 'd' to delete, 's' for status, 'L' for the log, 'x' to disable/enable, 'R' to reconnect, 'o' for settings, Shift+Up/Down to reorder, Enter to select".to_string(),
+                View::Folders if snapshot.send_only => "Send-only account - Press 'c' to compose, Enter for scheduled messages, 'R' to reconnect, Esc to go back".to_string(),
+                View::Folders if snapshot.saved_search_selected => "Saved search - Press Enter to open, 'e' to edit the query, 'n' to rename, 'd' to delete, Esc to go back".to_string(),
+                View::Folders => "Folders - Press Enter to select, type a name to jump to it, 'c' to compose, 'R' to reconnect, Esc to go back".to_string(),
+                View::Emails if !snapshot.selected_emails.is_empty() => format!("{} selected - Press Space to select or unselect, Ctrl+a to select all, '!' to invert, 'u' read/unread, '*' flag, Esc to clear the selection", snapshot.selected_emails.len()),
+                View::Emails if snapshot.search.as_ref().is_some_and(|search| search.name.is_some()) => "Saved search - Press Enter to view, F5 to rerun, 'r' to reply, 'f' to forward, Esc to go back".to_string(),
+                View::Emails if snapshot.search.is_some() => "Search results - Press Enter to view, '/' to search again, F5 to rerun, 's' to save the search, 'r' to reply, 'f' to forward, Esc to clear the search".to_string(),
+                View::Emails if snapshot.sent_folder_selected && snapshot.search.is_none() => format!("Sent folder, {} - Press Enter to view, 'e' to edit a copy as new, F5 to refresh, '/' to search, 'o' to sort, 'v' to filter, 'f' to forward, 'u' read/unread, '*' flag, 'T' tags, 'd' to delete, Esc to go back", sync_info),
+                View::Emails if snapshot.selected_folder == SCHEDULED_FOLDER => "Scheduled - Press Enter to edit, 'd' to cancel, Esc to go back".to_string(),
+                View::Emails => format!("{} - Press Enter to view, F5 to refresh, '/' to search, 'o' to sort, 'v' to filter, 'G' to group, Space to select, 'c' to compose, 'r' to reply, 'f' to forward, 'm' to write to the sender, 'n'/'N' next/previous unread, 'u' read/unread, '*' flag, 'T' tags, 'V' VIP sender, 'd' to delete, Esc to go back", sync_info),
+                View::EmailDetail if snapshot.viewed_email.as_ref().is_some_and(|email| email.truncated) => "Email (truncated) - Press 'g' to download the full message, 't' to show/hide quoted text, 'l' for links, 'r' to reply, 'f' to forward, Esc to go back".to_string(),
+                View::EmailDetail if snapshot.viewed_email.as_ref().is_some_and(|email| email.invite.as_ref().is_some_and(|event| event.can_reply())) => "Invitation - Press 'i' to accept, 'I' to decline, 'a' for attachments, 'r' to reply, 'f' to forward, 'u' read/unread, '*' flag, 'T' tags, Esc to go back".to_string(),
+                View::EmailDetail if snapshot.sent_folder_selected => "Sent email - Press 'e' to edit a copy as new, 't' to show/hide quoted text, 'l' for links, 'a' for attachments, '/' to find, 'f' to forward, 'u' read/unread, '*' flag, 'T' tags, 'd' to delete, Esc to go back".to_string(),
+                View::EmailDetail => "Email - Press 't' to show/hide quoted text, 'R'/'A' for remote images, 'l' for links, 'a' for attachments, '/' to find, 'o' to open in your mail viewer, 's' to save, 'y'/'Y'/Ctrl+y to copy sender/subject/body, 'r' to reply, 'f' to forward, 'm' to write to the sender, 'n'/'N' next/previous unread, 'u' read/unread, '*' flag, 'T' tags, 'V' VIP sender, 'd' to delete, Esc to go back".to_string(),
+                View::ComposeEmail => "Compose - Press Tab to switch fields, Ctrl+e to edit the body in $EDITOR, Ctrl+t for templates, Ctrl+p for priority, Ctrl+s to send, Ctrl+l to send later, Esc to cancel".to_string(),
+                View::Settings => "Settings - Press 'a' to add a template, 'e' or Enter to edit, 'd' to delete, Esc to go back".to_string(),
+                View::TemplateForm => "Template - Press Tab to switch fields, Ctrl+s to save, Esc to cancel".to_string(),
+                View::AccountConfig => "Account Configuration - Press Enter to edit field, Tab to navigate, Ctrl+h to show/hide a password, Enter on Save to save".to_string(),
+                View::AccountStatus => "Account Status - Press Up/Down to select, 'R' to reconnect, 'L' for the log, Esc to go back".to_string(),
+                View::Log => "Log - Press Up/Down or PgUp/PgDn to scroll, Home/End for the oldest/newest, 'v' to change the level, Esc to go back".to_string(),
             }
         };
         
+        // Make it clear that nothing is being fetched or sent
+        let status_message = if snapshot.offline {
+            format!("Offline | {}", status_message)
+        } else {
+            status_message
+        };
+        
+        // A running fetch takes priority, then a message waiting to be sent
+        let pending_send = snapshot.pending_send.as_ref().map(|pending| {
+            format!("Sending \"{}\" in {}s - Press Ctrl+z to undo", pending.subject, pending.seconds)
+        });
+        let (status_message, color) = match (&snapshot.loading_message, pending_send) {
+            (Some(message), _) => (message.clone(), Color::Yellow),
+            (None, Some(message)) => (message, Color::Cyan),
+            (None, None) => (status_message, Color::White),
+        };
+        
+        // Keep the connection health of all accounts at the right
+        let area = match views::connections_indicator(&snapshot.account_summaries) {
+            Some(indicator) => {
+                let width = indicator.content.width() as u16;
+                let chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Min(0), Constraint::Length(width + 2)].as_ref())
+                    .split(area);
+                f.render_widget(Paragraph::new(indicator).alignment(Alignment::Right), chunks[1]);
+                chunks[0]
+            },
+            None => area,
+        };
+        
         // Create status bar
         let status_bar = Paragraph::new(status_message)
-            .style(Style::default().fg(Color::White));
+            .style(Style::default().fg(color));
         
         f.render_widget(status_bar, area);
         
@@ -1,8 +1,12 @@
 //! View renderer for the Linksy email client.
 
-use crate::models::{AccountSummary, Email};
+use crate::config::{AppSettings, FolderView, SavedSearch};
+use crate::models::{AccountSummary, Email, EmailKey};
 use crate::ui::views;
+use crate::ui::views::find::FindState;
 use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
 use tui::{
     layout::{Constraint, Direction, Layout, Rect},
     widgets::{Block, Borders, Paragraph},
@@ -10,8 +14,10 @@ use tui::{
 };
 
 /// Renders specific views in the application.
+#[allow(dead_code)]
 pub struct ViewRenderer;
 
+#[allow(dead_code)]
 impl ViewRenderer {
     /// Creates a new ViewRenderer instance.
     ///
@@ -49,7 +55,9 @@ impl ViewRenderer {
     /// - `f`: The frame to render on
     /// - `area`: The area to render in
     /// - `folders`: The folders to display
-    /// - `selected`: The index of the selected folder
+    /// - `saved_searches`: The saved searches to display
+    /// - `last_synced`: The time each folder was last synced
+    /// - `selected`: The index of the selected folder or saved search
     ///
     /// # Returns
     /// A Result indicating success or failure
@@ -58,9 +66,11 @@ impl ViewRenderer {
         f: &mut Frame,
         area: Rect,
         folders: &[String],
+        saved_searches: &[SavedSearch],
+        last_synced: &HashMap<String, SystemTime>,
         selected: Option<usize>,
     ) -> Result<()> {
-        views::render_folders(f, area, folders, selected);
+        views::render_folders(f, area, folders, saved_searches, last_synced, selected);
         
         Ok(())
     }
@@ -72,17 +82,24 @@ impl ViewRenderer {
     /// - `area`: The area to render in
     /// - `emails`: The emails to display
     /// - `selected`: The index of the selected email
+    /// - `selection`: The emails selected for a batch change
+    /// - `settings`: The settings with the date and time formats
+    /// - `view`: The folder's sort order and filter
     ///
     /// # Returns
     /// A Result indicating success or failure
+    #[allow(clippy::too_many_arguments)]
     pub fn render_emails(
         &self,
         f: &mut Frame,
         area: Rect,
         emails: &[Email],
         selected: Option<usize>,
+        selection: &HashSet<EmailKey>,
+        settings: &AppSettings,
+        view: &FolderView,
     ) -> Result<()> {
-        views::render_emails(f, area, emails, selected);
+        views::render_emails(f, area, emails, selected, selection, settings, view);
         
         Ok(())
     }
@@ -94,17 +111,26 @@ impl ViewRenderer {
     /// - `area`: The area to render in
     /// - `email`: The email to display
     /// - `scroll_offset`: The vertical scroll offset for the email body
+    /// - `settings`: The settings with the date formats and whether to prefer the HTML part
+    /// - `show_quoted`: Whether to expand quoted text in the plain-text part
+    /// - `show_remote`: Whether to show remote images in the HTML part
+    /// - `find`: The text being found in the body, if any
     ///
     /// # Returns
     /// A Result indicating success or failure
+    #[allow(clippy::too_many_arguments)]
     pub fn render_email_detail(
         &self,
         f: &mut Frame,
         area: Rect,
         email: &Email,
         scroll_offset: u16,
+        settings: &AppSettings,
+        show_quoted: bool,
+        show_remote: bool,
+        find: Option<&FindState>,
     ) -> Result<()> {
-        views::render_email_detail(f, area, email, scroll_offset);
+        views::render_email_detail(f, area, email, scroll_offset, settings, show_quoted, show_remote, find);
         
         Ok(())
     }
@@ -234,6 +260,7 @@ impl ViewRenderer {
     ///
     /// # Returns
     /// A Result indicating success or failure
+    #[allow(clippy::too_many_arguments)]
     pub fn render_split_view(
         &self,
         f: &mut Frame,
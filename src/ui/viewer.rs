@@ -0,0 +1,43 @@
+//! External mail viewer support for the Linksy email client.
+//!
+//! A message the terminal can't show well, such as a complex HTML email,
+//! can be opened in the system's default viewer for .eml files. The
+//! message is written to a temporary file for the viewer to read.
+
+use crate::ui::write_private_temp_file;
+use anyhow::{anyhow, Result};
+use log::error;
+use std::path::PathBuf;
+
+/// Writes a message to a temporary .eml file and opens it in the default viewer.
+///
+/// The viewer is started without waiting for it, so the file is left in
+/// place for it to read.
+///
+/// # Parameters
+/// - `raw`: The message exactly as the server keeps it
+///
+/// # Returns
+/// A Result containing the path of the file, or an error
+pub fn open_message(raw: &[u8]) -> Result<PathBuf> {
+    let path = write_private_temp_file(&format!("linksy-message-{}.eml", uuid::Uuid::new_v4()), raw)?;
+
+    if let Err(e) = open::that_detached(&path) {
+        remove_files(std::slice::from_ref(&path));
+        return Err(anyhow!("Failed to open a mail viewer: {}", e));
+    }
+
+    Ok(path)
+}
+
+/// Removes the files written for the viewer.
+///
+/// # Parameters
+/// - `paths`: The files to remove
+pub fn remove_files(paths: &[PathBuf]) {
+    for path in paths.iter().filter(|path| path.exists()) {
+        if let Err(e) = std::fs::remove_file(path) {
+            error!("Failed to remove temporary file {}: {}", path.display(), e);
+        }
+    }
+}
@@ -4,7 +4,7 @@
 
 use crate::config::{EmailAccount, ServerConfig};
 use tui::{
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Span},
     widgets::{Block, Borders, Paragraph},
@@ -30,8 +30,15 @@ pub struct AccountFormState {
     pub imap_enabled: bool,
     /// Whether POP3 settings are enabled
     pub pop3_enabled: bool,
+    /// Whether the selected password field shows its plaintext
+    pub reveal_password: bool,
+    /// Index of the first section shown, when the form is taller than the terminal
+    pub scroll: usize,
 }
 
+/// Heights of the form's sections: the account, IMAP, POP3 and SMTP settings and the buttons.
+const SECTION_HEIGHTS: [u16; 5] = [7, 8, 8, 8, 3];
+
 impl AccountFormState {
     /// Creates a new AccountFormState for a new account.
     ///
@@ -55,6 +62,13 @@ impl AccountFormState {
             imap: None,
             pop3: None,
             smtp: smtp_config,
+            send_delay_secs: 0,
+            proxy: None,
+            bcc_self: false,
+            auto_cc: None,
+            reply_to: None,
+            auto_connect: true,
+            enabled: true,
         };
         
         Self {
@@ -66,6 +80,8 @@ impl AccountFormState {
             is_new_account: true,
             imap_enabled: false,
             pop3_enabled: false,
+            reveal_password: false,
+            scroll: 0,
         }
     }
     
@@ -89,9 +105,80 @@ impl AccountFormState {
             is_new_account: false,
             imap_enabled,
             pop3_enabled,
+            reveal_password: false,
+            scroll: 0,
+        }
+    }
+    
+    /// Creates a new AccountFormState for a new account with the settings of another.
+    ///
+    /// The servers, ports and options are kept, while the ID, name, address
+    /// and each server's username and password are left blank for the new
+    /// account.
+    ///
+    /// # Parameters
+    /// - `account`: The account to copy
+    ///
+    /// # Returns
+    /// A new AccountFormState instance
+    pub fn copy_of(account: &EmailAccount) -> Self {
+        let mut account = account.clone();
+        account.id.clear();
+        account.name.clear();
+        account.email.clear();
+        account.enabled = true;
+        for server in account.imap.iter_mut().chain(account.pop3.iter_mut()).chain(std::iter::once(&mut account.smtp)) {
+            server.username.clear();
+            server.password.clear();
+        }
+        
+        Self {
+            is_new_account: true,
+            ..Self::from_account(account)
         }
     }
     
+    /// Gets the section of the form the selected field is in.
+    ///
+    /// # Returns
+    /// The index of the section in SECTION_HEIGHTS
+    fn selected_section(&self) -> usize {
+        match self.selected_field {
+            0..=2 => 0,
+            3..=8 => 1,
+            9..=14 => 2,
+            15..=19 => 3,
+            _ => 4,
+        }
+    }
+    
+    /// Gets the first section to show so the selected field is in view.
+    ///
+    /// The form stays where it was scrolled to while the selected field is
+    /// shown, and otherwise scrolls by as few sections as it takes.
+    ///
+    /// # Parameters
+    /// - `height`: The rows available to the sections
+    ///
+    /// # Returns
+    /// The index of the first section to show
+    pub fn first_visible_section(&self, height: u16) -> usize {
+        let selected = self.selected_section();
+        let mut first = self.scroll.min(selected);
+        while first < selected && SECTION_HEIGHTS[first..=selected].iter().sum::<u16>() > height {
+            first += 1;
+        }
+        first
+    }
+    
+    /// Scrolls the form so the selected field is in view.
+    ///
+    /// # Parameters
+    /// - `height`: The rows available to the sections
+    pub fn scroll_to_selected(&mut self, height: u16) {
+        self.scroll = self.first_visible_section(height);
+    }
+    
     /// Gets the field name for the currently selected field.
     ///
     /// # Returns
@@ -158,9 +245,38 @@ impl AccountFormState {
     
     /// Sets the value of the selected field.
     ///
+    /// Host values are trimmed and stripped of a URL scheme, and port values
+    /// must be a number from 1 to 65535. A rejected value leaves the field
+    /// unchanged and records the reason in `validation_errors`.
+    ///
     /// # Parameters
     /// - `value`: The new value for the field
-    pub fn set_selected_field_value(&mut self, value: String) {
+    ///
+    /// # Returns
+    /// true if the value was applied, false if it was rejected
+    #[allow(clippy::collapsible_if, clippy::collapsible_match)]
+    pub fn set_selected_field_value(&mut self, value: String) -> bool {
+        let field_name = self.get_selected_field_name();
+        let checked = if field_name.ends_with("_host") {
+            normalize_host(&value)
+        } else if field_name.ends_with("_port") {
+            parse_port(&value).map(|port| port.to_string())
+        } else {
+            Ok(value)
+        };
+        
+        // Any earlier error for the field was about the old value
+        let value = match checked {
+            Ok(value) => {
+                self.validation_errors.remove(field_name);
+                value
+            },
+            Err(message) => {
+                self.validation_errors.insert(field_name.to_string(), message);
+                return false;
+            },
+        };
+        
         match self.selected_field {
             0 => self.account.id = value,
             1 => self.account.name = value,
@@ -339,44 +455,26 @@ impl AccountFormState {
                 use_ssl: true,
             });
         }
+        
+        true
     }
     
     /// Validates the form fields.
     ///
+    /// Besides checking each field, this checks that the enabled protocols
+    /// have their settings.
+    ///
     /// # Returns
     /// true if all fields are valid, false otherwise
     pub fn validate(&mut self) -> bool {
         self.validation_errors.clear();
         
-        // Validate account ID
-        if self.account.id.is_empty() {
-            self.validation_errors.insert("account_id".to_string(), "Account ID is required".to_string());
-        }
-        
-        // Validate account name
-        if self.account.name.is_empty() {
-            self.validation_errors.insert("account_name".to_string(), "Account name is required".to_string());
-        }
-        
-        // Validate email
-        if self.account.email.is_empty() {
-            self.validation_errors.insert("email".to_string(), "Email is required".to_string());
-        } else if !self.account.email.contains('@') {
-            self.validation_errors.insert("email".to_string(), "Invalid email format".to_string());
-        }
+        let mut fields = vec!["account_id", "account_name", "email"];
         
         // Validate IMAP settings if enabled
         if self.imap_enabled {
-            if let Some(imap) = &self.account.imap {
-                if imap.host.is_empty() {
-                    self.validation_errors.insert("imap_host".to_string(), "IMAP host is required".to_string());
-                }
-                if imap.username.is_empty() {
-                    self.validation_errors.insert("imap_username".to_string(), "IMAP username is required".to_string());
-                }
-                if imap.password.is_empty() {
-                    self.validation_errors.insert("imap_password".to_string(), "IMAP password is required".to_string());
-                }
+            if self.account.imap.is_some() {
+                fields.extend(["imap_host", "imap_username", "imap_password"]);
             } else {
                 self.validation_errors.insert("imap_enabled".to_string(), "IMAP settings are incomplete".to_string());
             }
@@ -384,35 +482,60 @@ impl AccountFormState {
         
         // Validate POP3 settings if enabled
         if self.pop3_enabled {
-            if let Some(pop3) = &self.account.pop3 {
-                if pop3.host.is_empty() {
-                    self.validation_errors.insert("pop3_host".to_string(), "POP3 host is required".to_string());
-                }
-                if pop3.username.is_empty() {
-                    self.validation_errors.insert("pop3_username".to_string(), "POP3 username is required".to_string());
-                }
-                if pop3.password.is_empty() {
-                    self.validation_errors.insert("pop3_password".to_string(), "POP3 password is required".to_string());
-                }
+            if self.account.pop3.is_some() {
+                fields.extend(["pop3_host", "pop3_username", "pop3_password"]);
             } else {
                 self.validation_errors.insert("pop3_enabled".to_string(), "POP3 settings are incomplete".to_string());
             }
         }
         
         // Validate SMTP settings (required)
-        if self.account.smtp.host.is_empty() {
-            self.validation_errors.insert("smtp_host".to_string(), "SMTP host is required".to_string());
-        }
-        if self.account.smtp.username.is_empty() {
-            self.validation_errors.insert("smtp_username".to_string(), "SMTP username is required".to_string());
-        }
-        if self.account.smtp.password.is_empty() {
-            self.validation_errors.insert("smtp_password".to_string(), "SMTP password is required".to_string());
+        fields.extend(["smtp_host", "smtp_username", "smtp_password"]);
+        
+        for field_name in fields {
+            if let Some(error) = self.field_error(field_name) {
+                self.validation_errors.insert(field_name.to_string(), error);
+            }
         }
         
         self.validation_errors.is_empty()
     }
     
+    /// Checks the value of a single field.
+    ///
+    /// # Parameters
+    /// - `field_name`: The name of the field
+    ///
+    /// # Returns
+    /// The reason the value is invalid, or None if it's valid or the field isn't checked
+    fn field_error(&self, field_name: &str) -> Option<String> {
+        let imap = self.account.imap.as_ref().filter(|_| self.imap_enabled);
+        let pop3 = self.account.pop3.as_ref().filter(|_| self.pop3_enabled);
+        let smtp = Some(&self.account.smtp);
+        let missing = |value: Option<&String>, message: &str| {
+            value.is_some_and(|value| value.is_empty()).then(|| message.to_string())
+        };
+        
+        match field_name {
+            "account_id" => missing(Some(&self.account.id), "Account ID is required"),
+            "account_name" => missing(Some(&self.account.name), "Account name is required"),
+            "email" if !self.account.email.is_empty() && !self.account.email.contains('@') => {
+                Some("Invalid email format".to_string())
+            },
+            "email" => missing(Some(&self.account.email), "Email is required"),
+            "imap_host" => missing(imap.map(|c| &c.host), "IMAP host is required"),
+            "imap_username" => missing(imap.map(|c| &c.username), "IMAP username is required"),
+            "imap_password" => missing(imap.map(|c| &c.password), "IMAP password is required"),
+            "pop3_host" => missing(pop3.map(|c| &c.host), "POP3 host is required"),
+            "pop3_username" => missing(pop3.map(|c| &c.username), "POP3 username is required"),
+            "pop3_password" => missing(pop3.map(|c| &c.password), "POP3 password is required"),
+            "smtp_host" => missing(smtp.map(|c| &c.host), "SMTP host is required"),
+            "smtp_username" => missing(smtp.map(|c| &c.username), "SMTP username is required"),
+            "smtp_password" => missing(smtp.map(|c| &c.password), "SMTP password is required"),
+            _ => None,
+        }
+    }
+    
     /// Gets the validation error for a field.
     ///
     /// # Parameters
@@ -442,10 +565,20 @@ impl AccountFormState {
     }
     
     /// Stops editing the selected field and applies the changes.
+    ///
+    /// A rejected value stays in the edit buffer so it can be corrected.
+    /// An applied value is checked right away, like on Save, so a missing
+    /// value or an address without '@' shows its error next to the field.
     pub fn stop_editing(&mut self) {
-        self.editing = false;
-        self.set_selected_field_value(self.edit_buffer.clone());
-        self.edit_buffer.clear();
+        if self.set_selected_field_value(self.edit_buffer.clone()) {
+            self.editing = false;
+            self.edit_buffer.clear();
+            
+            let field_name = self.get_selected_field_name();
+            if let Some(error) = self.field_error(field_name) {
+                self.validation_errors.insert(field_name.to_string(), error);
+            }
+        }
     }
     
     /// Cancels editing the selected field without applying changes.
@@ -454,8 +587,32 @@ impl AccountFormState {
         self.edit_buffer.clear();
     }
     
+    /// Shows or hides the plaintext of the selected password field.
+    pub fn toggle_reveal_password(&mut self) {
+        if self.get_selected_field_name().ends_with("_password") {
+            self.reveal_password = !self.reveal_password;
+        }
+    }
+    
+    /// Gets the text shown for a password, masked unless it is revealed.
+    ///
+    /// # Parameters
+    /// - `password`: The password or the edit buffer of a password field
+    /// - `field`: Index of the password field
+    ///
+    /// # Returns
+    /// The plaintext if the field is selected and revealed, asterisks otherwise
+    pub fn display_password(&self, password: &str, field: usize) -> String {
+        if self.reveal_password && self.selected_field == field {
+            password.to_string()
+        } else {
+            "*".repeat(password.chars().count().max(1))
+        }
+    }
+    
     /// Moves the selection to the previous field.
     pub fn select_previous_field(&mut self) {
+        self.reveal_password = false;
         if self.selected_field > 0 {
             self.selected_field -= 1;
         } else {
@@ -475,6 +632,7 @@ impl AccountFormState {
     
     /// Moves the selection to the next field.
     pub fn select_next_field(&mut self) {
+        self.reveal_password = false;
         if self.selected_field < 21 {
             self.selected_field += 1;
         } else {
@@ -556,6 +714,82 @@ impl AccountFormState {
     }
 }
 
+impl Default for AccountFormState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Normalizes a server host entered in the form.
+///
+/// # Parameters
+/// - `value`: The host as entered
+///
+/// # Returns
+/// The host without surrounding spaces, URL scheme or trailing path, or an error message
+fn normalize_host(value: &str) -> Result<String, String> {
+    let host = value.trim();
+    
+    // Accept "imaps://imap.example.com/" by keeping just the host
+    let host = host.split_once("://").map_or(host, |(_, rest)| rest);
+    let host = host.split('/').next().unwrap_or_default().trim();
+    
+    if host.chars().any(char::is_whitespace) {
+        return Err("Host can't contain spaces".to_string());
+    }
+    
+    // IPv6 addresses may be written in brackets, e.g. "[::1]"
+    if let Some(rest) = host.strip_prefix('[') {
+        let Some((address, after)) = rest.split_once(']') else {
+            return Err("Missing ']' after the IPv6 address".to_string());
+        };
+        if address.parse::<std::net::Ipv6Addr>().is_err() {
+            return Err("Invalid IPv6 address".to_string());
+        }
+        return match after {
+            "" => Ok(host.to_string()),
+            _ if after.starts_with(':') => Err("Put the port in the port field".to_string()),
+            _ => Err("Unexpected text after ']'".to_string()),
+        };
+    }
+    
+    // Without brackets, only an IPv6 address has several colons
+    if host.matches(':').count() > 1 {
+        if host.parse::<std::net::Ipv6Addr>().is_err() {
+            return Err("Invalid IPv6 address".to_string());
+        }
+        return Ok(host.to_string());
+    }
+    
+    // A port typed into the host belongs in the port field
+    if let Some((_, port)) = host.split_once(':')
+        && !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) {
+        return Err("Put the port in the port field".to_string());
+    }
+    
+    Ok(host.to_string())
+}
+
+/// Parses a server port entered in the form.
+///
+/// # Parameters
+/// - `value`: The port as entered
+///
+/// # Returns
+/// The port, or an error message if it isn't a number from 1 to 65535
+fn parse_port(value: &str) -> Result<u16, String> {
+    let value = value.trim();
+    
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err("Port must be a number".to_string());
+    }
+    
+    match value.parse::<u32>() {
+        Ok(port) if (1..=65535).contains(&port) => Ok(port as u16),
+        _ => Err("Port must be between 1 and 65535".to_string()),
+    }
+}
+
 /// Renders the account configuration view.
 ///
 /// # Parameters
@@ -567,33 +801,27 @@ pub fn render_account_config(
     area: Rect,
     form_state: &AccountFormState,
 ) {
-    // Create layout
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints([
-            Constraint::Length(7),   // Basic account info
-            Constraint::Length(8),   // IMAP settings
-            Constraint::Length(8),   // POP3 settings
-            Constraint::Length(8),   // SMTP settings
-            Constraint::Length(3),   // Buttons
-        ].as_ref())
-        .split(area);
+    // Stack the sections from the first one in view, cutting off the last
+    // one that doesn't fit, so the form works at any terminal height
+    let area = area.inner(&Margin { horizontal: 1, vertical: 1 });
+    let first = form_state.first_visible_section(area.height);
+    let mut y = area.y;
+    for (section, height) in SECTION_HEIGHTS.iter().enumerate().skip(first) {
+        let bottom = area.y + area.height;
+        if y >= bottom {
+            break;
+        }
+        let section_area = Rect::new(area.x, y, area.width, (*height).min(bottom - y));
+        y += height;
         
-    // Render basic account info
-    render_basic_info(f, chunks[0], form_state);
-    
-    // Render IMAP settings
-    render_imap_settings(f, chunks[1], form_state);
-    
-    // Render POP3 settings
-    render_pop3_settings(f, chunks[2], form_state);
-    
-    // Render SMTP settings
-    render_smtp_settings(f, chunks[3], form_state);
-    
-    // Render buttons
-    render_buttons(f, chunks[4], form_state);
+        match section {
+            0 => render_basic_info(f, section_area, form_state),
+            1 => render_imap_settings(f, section_area, form_state),
+            2 => render_pop3_settings(f, section_area, form_state),
+            3 => render_smtp_settings(f, section_area, form_state),
+            _ => render_buttons(f, section_area, form_state),
+        }
+    }
 }
 
 /// Renders the basic account information section.
@@ -722,10 +950,10 @@ fn render_imap_settings(
             form_state.get_validation_error("imap_username"));
             
         render_field(f, inner_area[4], "Password:", 
-            &"*".repeat(imap.password.len().max(1)), 
+            &form_state.display_password(&imap.password, 7), 
             form_state.selected_field == 7, 
             form_state.editing && form_state.selected_field == 7,
-            &form_state.edit_buffer,
+            &form_state.display_password(&form_state.edit_buffer, 7),
             form_state.get_validation_error("imap_password"));
             
         render_field(f, inner_area[5], "Use SSL/TLS:", 
@@ -809,10 +1037,10 @@ fn render_pop3_settings(
             form_state.get_validation_error("pop3_username"));
             
         render_field(f, inner_area[4], "Password:", 
-            &"*".repeat(pop3.password.len().max(1)), 
+            &form_state.display_password(&pop3.password, 13), 
             form_state.selected_field == 13, 
             form_state.editing && form_state.selected_field == 13,
-            &form_state.edit_buffer,
+            &form_state.display_password(&form_state.edit_buffer, 13),
             form_state.get_validation_error("pop3_password"));
             
         render_field(f, inner_area[5], "Use SSL/TLS:", 
@@ -874,10 +1102,10 @@ fn render_smtp_settings(
         form_state.get_validation_error("smtp_username"));
         
     render_field(f, inner_area[3], "Password:", 
-        &"*".repeat(form_state.account.smtp.password.len().max(1)), 
+        &form_state.display_password(&form_state.account.smtp.password, 18), 
         form_state.selected_field == 18, 
         form_state.editing && form_state.selected_field == 18,
-        &form_state.edit_buffer,
+        &form_state.display_password(&form_state.edit_buffer, 18),
         form_state.get_validation_error("smtp_password"));
         
     render_field(f, inner_area[4], "Use SSL/TLS:", 
@@ -950,6 +1178,7 @@ fn render_buttons(
 /// - `editing`: Whether the field is being edited
 /// - `edit_buffer`: The edit buffer for the field
 /// - `error`: The validation error for the field
+#[allow(clippy::too_many_arguments)]
 fn render_field(
     f: &mut Frame,
     area: Rect,
@@ -968,11 +1197,7 @@ fn render_field(
     
     // Determine the base style for the entire line
     let base_style = if selected {
-        if editing {
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-        } else {
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-        }
+        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
     } else {
         Style::default()
     };
@@ -0,0 +1,166 @@
+//! Attachment list and preview popups for the Linksy email client.
+//!
+//! This module renders the list of the viewed email's attachments and a
+//! scrollable preview of the selected one. Text attachments are previewed;
+//! other content types are named as not previewable.
+
+use crate::models::{format_size, Attachment};
+use crate::ui::views::{centered_rect, convert_html_to_text};
+use anyhow::{anyhow, Result};
+use tui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+/// Number of bytes of an attachment shown in a preview.
+const PREVIEW_LIMIT: usize = 1024 * 1024;
+
+/// Content types outside text/* that are previewed as text.
+const TEXT_TYPES: [&str; 3] = ["application/json", "application/xml", "message/rfc822"];
+
+/// Represents the preview of an attachment.
+#[derive(Debug, Clone)]
+pub struct AttachmentPreview {
+    /// Filename of the previewed attachment
+    pub filename: String,
+    /// The text shown
+    pub text: String,
+    /// Number of lines scrolled past
+    pub scroll: u16,
+}
+
+/// Represents the state of the attachment list popup.
+#[derive(Debug, Clone, Default)]
+pub struct AttachmentPickerState {
+    /// Index of the currently selected attachment
+    pub selected: usize,
+    /// Preview of the selected attachment, while it's open
+    pub preview: Option<AttachmentPreview>,
+}
+
+impl AttachmentPickerState {
+    /// Moves the selection to the previous attachment.
+    pub fn select_previous(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+        }
+    }
+
+    /// Moves the selection to the next attachment.
+    ///
+    /// # Parameters
+    /// - `count`: The number of attachments
+    pub fn select_next(&mut self, count: usize) {
+        if self.selected + 1 < count {
+            self.selected += 1;
+        }
+    }
+}
+
+/// Checks if an attachment can be previewed as text.
+///
+/// # Parameters
+/// - `attachment`: The attachment
+///
+/// # Returns
+/// true for text types, false otherwise
+pub fn can_preview(attachment: &Attachment) -> bool {
+    let content_type = attachment.content_type.to_ascii_lowercase();
+    content_type.starts_with("text/") || TEXT_TYPES.contains(&content_type.as_str())
+}
+
+/// Builds the preview of an attachment.
+///
+/// # Parameters
+/// - `attachment`: The attachment to preview
+///
+/// # Returns
+/// A Result containing the preview, or an error saying why the attachment
+/// can't be previewed
+pub fn preview_attachment(attachment: &Attachment) -> Result<AttachmentPreview> {
+    if !can_preview(attachment) {
+        return Err(anyhow!("Cannot preview this type ({})", attachment.content_type));
+    }
+
+    // Attachments past the cut-off of a truncated message are only listed
+    if attachment.data.is_empty() && attachment.size > 0 {
+        return Err(anyhow!("{} isn't downloaded yet - press 'g' to download the full message", attachment.filename));
+    }
+
+    // Only the start of a large attachment is shown
+    let end = attachment.data.len().min(PREVIEW_LIMIT);
+    let mut text = String::from_utf8_lossy(&attachment.data[..end]).to_string();
+    if attachment.content_type.eq_ignore_ascii_case("text/html") {
+        text = convert_html_to_text(&text);
+    }
+    if attachment.data.len() > PREVIEW_LIMIT {
+        text.push_str(&format!("\n\n[Preview cut off at {}]", format_size(PREVIEW_LIMIT)));
+    }
+
+    Ok(AttachmentPreview {
+        filename: attachment.filename.clone(),
+        text,
+        scroll: 0,
+    })
+}
+
+/// Renders the attachment list popup, or the preview if one is open.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to center the popup in
+/// - `attachments`: The attachments of the viewed email
+/// - `picker`: The attachment list state
+pub fn render_attachment_picker(f: &mut Frame, area: Rect, attachments: &[Attachment], picker: &AttachmentPickerState) {
+    if let Some(preview) = &picker.preview {
+        render_preview(f, area, preview);
+        return;
+    }
+
+    let popup_area = centered_rect(70, 50, area);
+
+    let items: Vec<ListItem> = attachments
+        .iter()
+        .enumerate()
+        .map(|(i, attachment)| ListItem::new(format!(
+            "[{}] {} ({}, {}{})",
+            i + 1,
+            attachment.filename,
+            attachment.content_type,
+            format_size(attachment.size),
+            if attachment.inline { ", inline" } else { "" },
+        )))
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Attachments - Enter to preview, Esc to close"))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    state.select(Some(picker.selected));
+
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Renders the preview of an attachment.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to center the popup in
+/// - `preview`: The attachment preview
+fn render_preview(f: &mut Frame, area: Rect, preview: &AttachmentPreview) {
+    let popup_area = centered_rect(90, 80, area);
+
+    let title = format!("{} - Up/Down to scroll, Esc to close", preview.filename);
+    let text = Paragraph::new(preview.text.as_str())
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .scroll((preview.scroll, 0))
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(text, popup_area);
+}
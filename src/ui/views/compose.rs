@@ -0,0 +1,775 @@
+//! Compose email view for the Linksy email client.
+//!
+//! This module contains the UI implementation for writing new emails.
+
+use crate::models::{Address, Email, Priority, RecentRecipient};
+use crate::ui::views::convert_html_to_text;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Number of recent recipients suggested at a time.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Represents the fields of the compose form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComposeField {
+    /// Recipients
+    To,
+    /// Carbon copy recipients
+    Cc,
+    /// Blind carbon copy recipients
+    Bcc,
+    /// Subject line
+    Subject,
+    /// Message body
+    Body,
+}
+
+impl ComposeField {
+    /// All fields in tab order.
+    const ALL: [ComposeField; 5] = [ComposeField::To, ComposeField::Cc, ComposeField::Bcc, ComposeField::Subject, ComposeField::Body];
+
+    /// Gets the label shown for the field.
+    ///
+    /// # Returns
+    /// The field label
+    pub fn label(&self) -> &'static str {
+        match self {
+            ComposeField::To => "To",
+            ComposeField::Cc => "Cc",
+            ComposeField::Bcc => "Bcc",
+            ComposeField::Subject => "Subject",
+            ComposeField::Body => "Body",
+        }
+    }
+}
+
+/// Represents the state of the compose form.
+#[derive(Clone)]
+pub struct ComposeState {
+    /// Comma-separated recipients
+    pub to: String,
+    /// Comma-separated carbon copy recipients
+    pub cc: String,
+    /// Comma-separated blind carbon copy recipients
+    pub bcc: String,
+    /// Subject line
+    pub subject: String,
+    /// Message body
+    pub body: String,
+    /// Byte offset of the cursor in the body
+    pub body_cursor: usize,
+    /// The field receiving typed input
+    pub focused_field: ComposeField,
+    /// Priority of the message
+    pub priority: Priority,
+    /// Recent recipients matching the address being typed
+    pub suggestions: Vec<String>,
+    /// Index of the highlighted suggestion, if one is
+    pub selected_suggestion: Option<usize>,
+}
+
+impl ComposeState {
+    /// Creates a new, empty ComposeState.
+    ///
+    /// # Returns
+    /// A new ComposeState instance
+    pub fn new() -> Self {
+        Self {
+            to: String::new(),
+            cc: String::new(),
+            bcc: String::new(),
+            subject: String::new(),
+            body: String::new(),
+            body_cursor: 0,
+            focused_field: ComposeField::To,
+            priority: Priority::Normal,
+            suggestions: Vec::new(),
+            selected_suggestion: None,
+        }
+    }
+
+    /// Creates a ComposeState filled in from an existing message.
+    ///
+    /// # Parameters
+    /// - `email`: The message to edit
+    ///
+    /// # Returns
+    /// A new ComposeState instance
+    pub fn from_email(email: &Email) -> Self {
+        // An HTML-only message is edited as its text
+        let body = email.body_text.clone()
+            .or_else(|| email.body_html.as_deref().map(convert_html_to_text))
+            .unwrap_or_default();
+        Self {
+            to: email.to.join(", "),
+            cc: email.cc.join(", "),
+            bcc: email.bcc.join(", "),
+            subject: email.subject.clone(),
+            body_cursor: body.len(),
+            body,
+            focused_field: ComposeField::Body,
+            priority: email.priority,
+            suggestions: Vec::new(),
+            selected_suggestion: None,
+        }
+    }
+
+    /// Builds a message from the fields of the form.
+    ///
+    /// # Returns
+    /// A message with the recipients, subject, body and priority, without
+    /// an account or sender
+    pub fn to_email(&self) -> Email {
+        let mut email = Email::new();
+        email.to = Self::parse_addresses(&self.to);
+        email.cc = Self::parse_addresses(&self.cc);
+        email.bcc = Self::parse_addresses(&self.bcc);
+        email.priority = self.priority;
+        email.subject = self.subject.clone();
+        email.body_text = Some(self.body.clone());
+        email
+    }
+
+    /// Checks if anything has been written in the form.
+    ///
+    /// # Returns
+    /// true if any field has more than whitespace, false otherwise
+    pub fn has_content(&self) -> bool {
+        [&self.to, &self.cc, &self.bcc, &self.subject, &self.body].iter()
+            .any(|field| !field.trim().is_empty())
+    }
+
+    /// Splits an address field into addresses for the message headers.
+    ///
+    /// Commas in quoted display names don't split addresses, and groups
+    /// are replaced by their members; see Address::parse_list.
+    ///
+    /// # Parameters
+    /// - `field`: The field text
+    ///
+    /// # Returns
+    /// The addresses in the field, each with its display name if it has one
+    pub fn parse_addresses(field: &str) -> Vec<String> {
+        Address::parse_list(field)
+            .iter()
+            .map(Address::to_header)
+            .collect()
+    }
+
+    /// Adds an account's automatic Cc and Bcc recipients.
+    ///
+    /// An address already in any of the recipient fields isn't added again.
+    ///
+    /// # Parameters
+    /// - `cc`: Address to add as Cc, if any
+    /// - `bcc`: Address to add as Bcc, if any
+    pub fn add_default_recipients(&mut self, cc: Option<&str>, bcc: Option<&str>) {
+        for (address, field) in [(cc, ComposeField::Cc), (bcc, ComposeField::Bcc)] {
+            let Some(address) = address.map(str::trim).filter(|address| !address.is_empty()) else {
+                continue;
+            };
+            
+            // Compare the bare addresses, so "Me <me@example.com>" counts as me@example.com
+            let Some(added) = Address::parse(address) else {
+                continue;
+            };
+            let present = [&self.to, &self.cc, &self.bcc].iter()
+                .flat_map(|text| Address::parse_list(text))
+                .any(|existing| existing.email.eq_ignore_ascii_case(&added.email));
+            if present {
+                continue;
+            }
+            
+            let text = if field == ComposeField::Cc { &mut self.cc } else { &mut self.bcc };
+            if !text.trim().is_empty() {
+                text.push_str(", ");
+            }
+            text.push_str(address);
+        }
+    }
+    
+    /// Suggests recent recipients for the address being typed.
+    ///
+    /// The address being typed is the text after the last comma or
+    /// semicolon of a recipient field. Recipients whose name or address
+    /// contains it are suggested, most recently used first, leaving out
+    /// addresses already in the field.
+    ///
+    /// # Parameters
+    /// - `recipients`: The recent recipients, most recently used first
+    pub fn update_suggestions(&mut self, recipients: &[RecentRecipient]) {
+        self.selected_suggestion = None;
+        self.suggestions.clear();
+        
+        let Some(text) = self.recipient_text() else {
+            return;
+        };
+        let entry = current_entry(text);
+        let typed = entry.trim().to_lowercase();
+        if typed.is_empty() {
+            return;
+        }
+        
+        // Compare the bare addresses entered before the one being typed
+        let present: Vec<String> = Address::parse_list(&text[..text.len() - entry.len()]).into_iter()
+            .map(|address| address.email.to_lowercase())
+            .collect();
+        self.suggestions = recipients.iter()
+            .filter(|recipient| recipient.address.to_lowercase().contains(&typed))
+            .filter(|recipient| {
+                Address::parse(&recipient.address)
+                    .is_none_or(|address| !present.contains(&address.email.to_lowercase()))
+            })
+            .take(MAX_SUGGESTIONS)
+            .map(|recipient| recipient.address.clone())
+            .collect();
+    }
+    
+    /// Hides the suggestions.
+    pub fn clear_suggestions(&mut self) {
+        self.suggestions.clear();
+        self.selected_suggestion = None;
+    }
+    
+    /// Highlights the next suggestion.
+    pub fn select_next_suggestion(&mut self) {
+        self.selected_suggestion = match self.selected_suggestion {
+            None if !self.suggestions.is_empty() => Some(0),
+            Some(index) if index + 1 < self.suggestions.len() => Some(index + 1),
+            selected => selected,
+        };
+    }
+    
+    /// Highlights the previous suggestion, or none above the first.
+    pub fn select_previous_suggestion(&mut self) {
+        self.selected_suggestion = self.selected_suggestion.and_then(|index| index.checked_sub(1));
+    }
+    
+    /// Replaces the address being typed with the highlighted suggestion.
+    ///
+    /// # Returns
+    /// true if a suggestion was highlighted, false otherwise
+    pub fn accept_suggestion(&mut self) -> bool {
+        let Some(suggestion) = self.selected_suggestion.and_then(|index| self.suggestions.get(index)).cloned() else {
+            return false;
+        };
+        
+        let text = self.focused_text_mut();
+        let typed = current_entry(text).len();
+        text.truncate(text.len() - typed);
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(&suggestion);
+        text.push_str(", ");
+        self.clear_suggestions();
+        
+        true
+    }
+    
+    /// Gets the text of the focused field if it holds recipients.
+    ///
+    /// # Returns
+    /// The To, Cc or Bcc text, or None if another field is focused
+    fn recipient_text(&self) -> Option<&String> {
+        match self.focused_field {
+            ComposeField::To => Some(&self.to),
+            ComposeField::Cc => Some(&self.cc),
+            ComposeField::Bcc => Some(&self.bcc),
+            ComposeField::Subject | ComposeField::Body => None,
+        }
+    }
+    
+    /// Checks if nothing has been entered yet.
+    ///
+    /// # Returns
+    /// true if all fields are empty, false otherwise
+    pub fn is_empty(&self) -> bool {
+        self.to.trim().is_empty() && self.cc.trim().is_empty() && self.subject.trim().is_empty() && self.body.trim().is_empty()
+    }
+
+    /// Moves focus to the next field.
+    pub fn focus_next_field(&mut self) {
+        let index = ComposeField::ALL.iter().position(|f| *f == self.focused_field).unwrap_or(0);
+        self.focused_field = ComposeField::ALL[(index + 1) % ComposeField::ALL.len()];
+    }
+
+    /// Moves focus to the previous field.
+    pub fn focus_previous_field(&mut self) {
+        let index = ComposeField::ALL.iter().position(|f| *f == self.focused_field).unwrap_or(0);
+        self.focused_field = ComposeField::ALL[(index + ComposeField::ALL.len() - 1) % ComposeField::ALL.len()];
+    }
+
+    /// Gets a mutable reference to the text of the focused field.
+    ///
+    /// # Returns
+    /// The text of the focused field
+    fn focused_text_mut(&mut self) -> &mut String {
+        match self.focused_field {
+            ComposeField::To => &mut self.to,
+            ComposeField::Cc => &mut self.cc,
+            ComposeField::Bcc => &mut self.bcc,
+            ComposeField::Subject => &mut self.subject,
+            ComposeField::Body => &mut self.body,
+        }
+    }
+
+    /// Types a character into the focused field, at the cursor in the body.
+    ///
+    /// # Parameters
+    /// - `c`: The character to add
+    pub fn insert_char(&mut self, c: char) {
+        if self.focused_field == ComposeField::Body {
+            self.body.insert(self.body_cursor, c);
+            self.body_cursor += c.len_utf8();
+        } else {
+            self.focused_text_mut().push(c);
+        }
+    }
+
+    /// Handles Enter: a new line in the body, or the next field otherwise.
+    pub fn insert_newline(&mut self) {
+        if self.focused_field == ComposeField::Body {
+            self.insert_char('\n');
+        } else {
+            self.focus_next_field();
+        }
+    }
+
+    /// Replaces the body, leaving the cursor at its end.
+    ///
+    /// # Parameters
+    /// - `body`: The new body
+    pub fn set_body(&mut self, body: String) {
+        self.body_cursor = body.len();
+        self.body = body;
+    }
+
+    /// Gets the line and column of the body cursor.
+    ///
+    /// # Returns
+    /// The zero-based line and column, counted in terminal cells so wide
+    /// characters such as CJK or emoji take two
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let before = &self.body[..self.body_cursor];
+        let line = before.matches('\n').count();
+        let column = before.rsplit('\n').next().unwrap_or_default().width();
+        (line, column)
+    }
+
+    /// Moves the body cursor one character left, along with the accents combined with it.
+    pub fn move_cursor_left(&mut self) {
+        let mut before = self.body[..self.body_cursor].chars();
+        while let Some(c) = before.next_back() {
+            self.body_cursor -= c.len_utf8();
+            if c.width() != Some(0) {
+                break;
+            }
+        }
+    }
+
+    /// Moves the body cursor one character right, along with the accents combined with it.
+    pub fn move_cursor_right(&mut self) {
+        let mut after = self.body[self.body_cursor..].chars();
+        if let Some(c) = after.next() {
+            self.body_cursor += c.len_utf8();
+        }
+        for c in after.take_while(|c| c.width() == Some(0)) {
+            self.body_cursor += c.len_utf8();
+        }
+    }
+
+    /// Moves the body cursor to the previous line, keeping the column where possible.
+    pub fn move_cursor_up(&mut self) {
+        let (line, column) = self.cursor_position();
+        if line > 0 {
+            self.move_cursor_to(line - 1, column);
+        }
+    }
+
+    /// Moves the body cursor to the next line, keeping the column where possible.
+    pub fn move_cursor_down(&mut self) {
+        let (line, column) = self.cursor_position();
+        if line + 1 < self.body.split('\n').count() {
+            self.move_cursor_to(line + 1, column);
+        }
+    }
+
+    /// Moves the body cursor to the start of its line.
+    pub fn move_cursor_home(&mut self) {
+        let (line, _) = self.cursor_position();
+        self.move_cursor_to(line, 0);
+    }
+
+    /// Moves the body cursor to the end of its line.
+    pub fn move_cursor_end(&mut self) {
+        let (line, _) = self.cursor_position();
+        self.move_cursor_to(line, usize::MAX);
+    }
+
+    /// Moves the body cursor to a line and column, clamping the column to the line length.
+    ///
+    /// # Parameters
+    /// - `line`: The zero-based line
+    /// - `column`: The zero-based column, in terminal cells
+    fn move_cursor_to(&mut self, line: usize, column: usize) {
+        let mut offset = 0;
+        for (index, text) in self.body.split('\n').enumerate() {
+            if index == line {
+                // Stop before the first character that would end past the column
+                let mut width = 0;
+                let within = text.char_indices()
+                    .find(|(_, c)| {
+                        width += c.width().unwrap_or(0);
+                        width > column
+                    })
+                    .map(|(i, _)| i)
+                    .unwrap_or(text.len());
+                self.body_cursor = offset + within;
+                return;
+            }
+            offset += text.len() + 1;
+        }
+    }
+
+    /// Inserts a template's text into the message.
+    ///
+    /// The subject is only set if it's still empty, and the body is appended
+    /// after any text already written.
+    ///
+    /// # Parameters
+    /// - `subject`: The template subject, with placeholders filled in
+    /// - `body`: The template body, with placeholders filled in
+    pub fn apply_template(&mut self, subject: &str, body: &str) {
+        if self.subject.trim().is_empty() {
+            self.subject = subject.to_string();
+        }
+
+        if self.body.trim().is_empty() {
+            self.body = body.to_string();
+        } else {
+            if !self.body.ends_with('\n') {
+                self.body.push('\n');
+            }
+            self.body.push_str(body);
+        }
+
+        self.body_cursor = self.body.len();
+        self.focused_field = ComposeField::Body;
+    }
+
+    /// Counts the words in the body.
+    ///
+    /// # Returns
+    /// The number of whitespace-separated words
+    pub fn word_count(&self) -> usize {
+        self.body.split_whitespace().count()
+    }
+
+    /// Counts the characters in the body.
+    ///
+    /// # Returns
+    /// The number of characters, not bytes
+    pub fn char_count(&self) -> usize {
+        self.body.chars().count()
+    }
+
+    /// Removes the character before the cursor in the body, or the last character of another field.
+    pub fn backspace(&mut self) {
+        if self.focused_field == ComposeField::Body {
+            if let Some(c) = self.body[..self.body_cursor].chars().next_back() {
+                self.body_cursor -= c.len_utf8();
+                self.body.remove(self.body_cursor);
+            }
+        } else {
+            self.focused_text_mut().pop();
+        }
+    }
+}
+
+impl Default for ComposeState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Gets the entry being typed at the end of a recipient field.
+///
+/// # Parameters
+/// - `text`: The field text
+///
+/// # Returns
+/// The text after the last comma or semicolon, or all of it
+fn current_entry(text: &str) -> &str {
+    match text.rfind([',', ';']) {
+        Some(index) => &text[index + 1..],
+        None => text,
+    }
+}
+
+/// Renders the compose form.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to render in
+/// - `compose`: The compose form state
+pub fn render_compose(f: &mut Frame, area: Rect, compose: &ComposeState) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // To
+            Constraint::Length(3),  // Cc
+            Constraint::Length(3),  // Bcc
+            Constraint::Length(3),  // Subject
+            Constraint::Min(3),     // Body
+            Constraint::Length(1),  // Stats
+        ].as_ref())
+        .split(area);
+
+    // Draw the single-line header fields
+    let header_fields = [
+        (ComposeField::To, &compose.to),
+        (ComposeField::Cc, &compose.cc),
+        (ComposeField::Bcc, &compose.bcc),
+        (ComposeField::Subject, &compose.subject),
+    ];
+
+    for (chunk, (field, text)) in chunks.iter().zip(header_fields) {
+        let paragraph = Paragraph::new(text.as_str())
+            .block(field_block(field, compose.focused_field));
+        f.render_widget(paragraph, *chunk);
+
+        if field == compose.focused_field {
+            let x = chunk.x + 1 + text.width() as u16;
+            f.set_cursor(x.min(chunk.x + chunk.width.saturating_sub(2)), chunk.y + 1);
+        }
+    }
+
+    // Draw the body, keeping the cursor line in view while typing
+    let body_area = chunks[4];
+    let visible_lines = body_area.height.saturating_sub(2).max(1);
+    let (line, column) = compose.cursor_position();
+    let scroll = (line as u16 + 1).saturating_sub(visible_lines);
+
+    let body = Paragraph::new(compose.body.as_str())
+        .block(field_block(ComposeField::Body, compose.focused_field))
+        .scroll((scroll, 0));
+    f.render_widget(body, body_area);
+
+    if compose.focused_field == ComposeField::Body {
+        f.set_cursor(
+            (body_area.x + 1 + column as u16).min(body_area.x + body_area.width.saturating_sub(2)),
+            body_area.y + 1 + line as u16 - scroll,
+        );
+    }
+
+    // Draw the stats line, warning about a missing subject before it's sent
+    let mut stats = vec![Span::styled(
+        format!("{} words, {} characters", compose.word_count(), compose.char_count()),
+        Style::default().fg(Color::DarkGray),
+    )];
+    if compose.priority != Priority::Normal {
+        stats.push(Span::styled(format!("  {} priority", compose.priority.label()), Style::default().fg(Color::Red)));
+    }
+    if compose.subject.trim().is_empty() {
+        stats.push(Span::styled("  No subject", Style::default().fg(Color::Yellow)));
+    }
+    f.render_widget(Paragraph::new(Line::from(stats)), chunks[5]);
+
+    // Draw the suggestions below the recipient field being typed in
+    let field_index = ComposeField::ALL.iter().position(|field| *field == compose.focused_field).unwrap_or(0);
+    if !compose.suggestions.is_empty() && field_index < 3 {
+        render_suggestions(f, area, chunks[field_index], compose);
+    }
+}
+
+/// Renders the recent recipients suggested for the address being typed.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area of the compose form
+/// - `field`: The area of the recipient field
+/// - `compose`: The compose form state
+fn render_suggestions(f: &mut Frame, area: Rect, field: Rect, compose: &ComposeState) {
+    // Overlap the bottom border of the field, if there's room below it at all
+    let y = (field.y + field.height).saturating_sub(1);
+    let height = (compose.suggestions.len() as u16 + 2).min((area.y + area.height).saturating_sub(y));
+    if height == 0 || field.width == 0 {
+        return;
+    }
+    let popup_area = Rect::new(field.x + 1, y, field.width.saturating_sub(2).min(60), height);
+
+    let items: Vec<ListItem> = compose.suggestions.iter()
+        .map(|suggestion| ListItem::new(suggestion.as_str()))
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Recent - Up/Down, Enter to use"))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    state.select(compose.selected_suggestion);
+
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Creates the bordered block for a compose field.
+///
+/// # Parameters
+/// - `field`: The field the block is for
+/// - `focused`: The currently focused field
+///
+/// # Returns
+/// A block highlighted if the field is focused
+fn field_block(field: ComposeField, focused: ComposeField) -> Block<'static> {
+    let style = if field == focused {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(field.label())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A compose form with the body cursor at the end of a text.
+    fn body(text: &str) -> ComposeState {
+        let mut compose = ComposeState::new();
+        compose.focused_field = ComposeField::Body;
+        for c in text.chars() {
+            compose.insert_char(c);
+        }
+        compose
+    }
+
+    #[test]
+    fn counts_columns_in_terminal_cells() {
+        assert_eq!(body("abc").cursor_position(), (0, 3));
+        assert_eq!(body("日本語").cursor_position(), (0, 6));
+        assert_eq!(body("hi 😀").cursor_position(), (0, 5));
+        assert_eq!(body("first\nÅäö").cursor_position(), (1, 3));
+    }
+
+    #[test]
+    fn counts_combining_accents_as_part_of_their_character() {
+        // "é" written as "e" followed by a combining acute accent
+        assert_eq!(body("cafe\u{301}").cursor_position(), (0, 4));
+        assert_eq!(body("cafe\u{301}").char_count(), 5);
+    }
+
+    #[test]
+    fn keeps_the_screen_column_between_lines() {
+        let mut compose = body("日本語です\nabcdefgh");
+        compose.move_cursor_left();
+        compose.move_cursor_left();
+        assert_eq!(compose.cursor_position(), (1, 6));
+
+        // Three wide characters fill the six columns above
+        compose.move_cursor_up();
+        assert_eq!(compose.cursor_position(), (0, 6));
+        assert_eq!(&compose.body[compose.body_cursor..], "です\nabcdefgh");
+
+        compose.move_cursor_down();
+        assert_eq!(compose.cursor_position(), (1, 6));
+    }
+
+    #[test]
+    fn stops_before_a_wide_character_that_would_pass_the_column() {
+        let mut compose = body("日本語\nabc");
+        compose.move_cursor_up();
+        assert_eq!(compose.cursor_position(), (0, 2));
+        assert_eq!(&compose.body[compose.body_cursor..], "本語\nabc");
+
+        compose.move_cursor_end();
+        assert_eq!(compose.cursor_position(), (0, 6));
+        compose.move_cursor_down();
+        assert_eq!(compose.cursor_position(), (1, 3));
+    }
+
+    #[test]
+    fn moves_over_a_character_with_its_accents() {
+        let mut compose = body("ae\u{301}\u{323}b");
+        compose.move_cursor_left();
+        compose.move_cursor_left();
+        assert_eq!(&compose.body[compose.body_cursor..], "e\u{301}\u{323}b");
+
+        compose.move_cursor_right();
+        assert_eq!(&compose.body[compose.body_cursor..], "b");
+
+        compose.move_cursor_home();
+        compose.move_cursor_right();
+        compose.move_cursor_right();
+        assert_eq!(compose.cursor_position(), (0, 2));
+    }
+
+    #[test]
+    fn edits_multibyte_characters_whole() {
+        let mut compose = body("日😀");
+        compose.move_cursor_left();
+        compose.insert_char('本');
+        assert_eq!(compose.body, "日本😀");
+
+        compose.backspace();
+        compose.backspace();
+        assert_eq!(compose.body, "😀");
+        assert_eq!(compose.cursor_position(), (0, 0));
+
+        compose.move_cursor_end();
+        compose.backspace();
+        assert!(compose.body.is_empty());
+        compose.backspace();
+        assert_eq!(compose.body_cursor, 0);
+    }
+
+    #[test]
+    fn renders_suggestions_on_tiny_terminals() {
+        use tui::{backend::TestBackend, Terminal};
+
+        let mut compose = ComposeState::new();
+        compose.to = "al".to_string();
+        compose.suggestions = vec!["alice@example.com".to_string(), "alan@example.com".to_string()];
+
+        for (width, height) in [(0, 0), (1, 1), (10, 2), (20, 4), (80, 5)] {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal.draw(|f| render_compose(f, f.size(), &compose)).unwrap();
+        }
+    }
+
+    #[test]
+    fn removes_multibyte_characters_from_header_fields() {
+        let mut compose = ComposeState::new();
+        compose.focused_field = ComposeField::Subject;
+        for c in "Grüße 🎉".chars() {
+            compose.insert_char(c);
+        }
+        compose.backspace();
+        compose.backspace();
+        assert_eq!(compose.subject, "Grüße");
+    }
+
+    #[test]
+    fn edits_an_html_only_message_as_text() {
+        let email = Email {
+            body_html: Some("<p>Hello <b>there</b></p>".to_string()),
+            ..Email::default()
+        };
+        let compose = ComposeState::from_email(&email);
+        assert!(compose.body.contains("Hello"));
+        assert!(!compose.body.contains('<'));
+        assert_eq!(compose.body_cursor, compose.body.len());
+    }
+}
@@ -0,0 +1,170 @@
+//! Find in the viewed email for the Linksy email client.
+//!
+//! Unlike a search, which asks the server for matching emails, finding
+//! looks through the body of the email that is open. Matches are
+//! highlighted, and the view scrolls from one to the next.
+
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Paragraph, Wrap},
+};
+
+/// Represents the text being found in the viewed email.
+#[derive(Debug, Clone)]
+pub struct FindState {
+    /// The text to find, matched ignoring case
+    pub query: String,
+    /// Index of the match that was jumped to
+    pub current: usize,
+}
+
+impl FindState {
+    /// Creates a new FindState at the first match.
+    ///
+    /// # Parameters
+    /// - `query`: The text to find
+    ///
+    /// # Returns
+    /// A new FindState instance
+    pub fn new(query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            current: 0,
+        }
+    }
+
+    /// Moves to the next or previous match, wrapping around at either end.
+    ///
+    /// # Parameters
+    /// - `forward`: true for the next match, false for the previous one
+    /// - `count`: The number of matches
+    pub fn step(&mut self, forward: bool, count: usize) {
+        if count == 0 {
+            return;
+        }
+        self.current = match forward {
+            true => (self.current + 1) % count,
+            false => (self.current + count - 1) % count,
+        };
+    }
+}
+
+/// Finds where a text occurs in the body, ignoring case.
+///
+/// # Parameters
+/// - `body`: The body as displayed
+/// - `query`: The text to find
+///
+/// # Returns
+/// The byte ranges of the matches that don't overlap, in order
+pub fn find_matches(body: &str, query: &str) -> Vec<(usize, usize)> {
+    let Some(first) = query.chars().next() else {
+        return Vec::new();
+    };
+    let starts_like = |c: char| c.to_lowercase().eq(first.to_lowercase());
+
+    let mut matches = Vec::new();
+    let mut searched_to = 0;
+    for (start, c) in body.char_indices() {
+        if start < searched_to || !starts_like(c) {
+            continue;
+        }
+        if let Some(end) = match_end(&body[start..], query) {
+            matches.push((start, start + end));
+            searched_to = start + end;
+        }
+    }
+
+    matches
+}
+
+/// Checks if a text starts with the query, ignoring case.
+///
+/// # Parameters
+/// - `text`: The text to check
+/// - `query`: The text to find
+///
+/// # Returns
+/// The byte length of the match in the text, or None if it doesn't start with the query
+fn match_end(text: &str, query: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    for wanted in query.chars() {
+        let (_, c) = chars.next()?;
+        if !c.to_lowercase().eq(wanted.to_lowercase()) {
+            return None;
+        }
+    }
+
+    Some(chars.next().map_or(text.len(), |(end, _)| end))
+}
+
+/// Builds the body text with the matches highlighted.
+///
+/// # Parameters
+/// - `body`: The body as displayed
+/// - `matches`: The byte ranges of the matches
+/// - `current`: Index of the match that was jumped to, highlighted differently
+///
+/// # Returns
+/// The lines of the body, with a span for each match
+pub fn highlight_matches(body: &str, matches: &[(usize, usize)], current: usize) -> Text<'static> {
+    let mut lines = Vec::new();
+    let mut remaining = matches.iter().enumerate().peekable();
+    let mut line_start = 0;
+
+    // Lines are split like the unhighlighted body, without their line breaks
+    for segment in body.split_inclusive('\n') {
+        let line = segment.strip_suffix('\n').unwrap_or(segment);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let line_end = line_start + line.len();
+        let mut spans = Vec::new();
+        let mut position = line_start;
+
+        // The query is typed on one line, so no match spans a line break
+        while let Some(&(index, &(start, end))) = remaining.peek() {
+            if start >= line_end {
+                break;
+            }
+            let end = end.min(line_end);
+            spans.push(Span::raw(body[position..start].to_string()));
+            spans.push(Span::styled(body[start..end].to_string(), match_style(index == current)));
+            position = end;
+            remaining.next();
+        }
+        spans.push(Span::raw(body[position..line_end].to_string()));
+
+        lines.push(Line::from(spans));
+        line_start += segment.len();
+    }
+
+    Text::from(lines)
+}
+
+/// Gets the style of a highlighted match.
+///
+/// # Parameters
+/// - `current`: Whether it's the match that was jumped to
+///
+/// # Returns
+/// The style of the match
+fn match_style(current: bool) -> Style {
+    match current {
+        true => Style::default().fg(Color::Black).bg(Color::LightRed).add_modifier(Modifier::BOLD),
+        false => Style::default().fg(Color::Black).bg(Color::Yellow),
+    }
+}
+
+/// Gets the line of the wrapped body a match ends on.
+///
+/// # Parameters
+/// - `body`: The body as displayed
+/// - `end`: The byte offset the match ends at
+/// - `width`: The width the body is wrapped at
+///
+/// # Returns
+/// The number of wrapped lines above the match's last line
+pub fn match_line(body: &str, end: usize, width: u16) -> usize {
+    let before = Paragraph::new(&body[..end]).wrap(Wrap { trim: false });
+    before.line_count(width).saturating_sub(1)
+}
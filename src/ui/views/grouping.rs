@@ -0,0 +1,246 @@
+//! Sender grouping of the email list for the Linksy email client.
+//!
+//! For triage, the listed emails can be grouped by sender or by the
+//! sender's domain. Each group is a line with its counts that expands to
+//! its emails, and read and flag changes on a group apply to all of them.
+
+use crate::config::{AppSettings, FolderView};
+use crate::models::{Email, EmailKey};
+use crate::ui::views::tags::{spans_width, tag_spans};
+use crate::ui::views::{email_list_style, format_list_date, list_item_width, pad_to_width, selection_count, selection_marker, truncate_to_width};
+use std::collections::HashSet;
+use tui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// What the emails are grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    /// The sender's address
+    Sender,
+    /// The domain of the sender's address
+    Domain,
+}
+
+impl GroupBy {
+    /// Gets the name of the grouping.
+    ///
+    /// # Returns
+    /// The name shown in the list title
+    pub fn label(&self) -> &'static str {
+        match self {
+            GroupBy::Sender => "sender",
+            GroupBy::Domain => "sender domain",
+        }
+    }
+
+    /// Gets the group an email belongs to.
+    ///
+    /// # Parameters
+    /// - `email`: The email
+    ///
+    /// # Returns
+    /// The lowercase address or domain of the sender
+    pub fn key(&self, email: &Email) -> String {
+        let address = email.from.to_lowercase();
+        match self {
+            GroupBy::Sender => address,
+            GroupBy::Domain => match address.rsplit_once('@') {
+                Some((_, domain)) => domain.to_string(),
+                None => address,
+            },
+        }
+    }
+}
+
+/// Represents the state of the grouped email list.
+#[derive(Debug, Clone)]
+pub struct GroupingState {
+    /// What the emails are grouped by
+    pub by: GroupBy,
+    /// Keys of the groups whose emails are listed
+    pub expanded: HashSet<String>,
+    /// Key of the group whose line is selected, or None if an email is
+    pub selected_group: Option<String>,
+}
+
+impl GroupingState {
+    /// Creates a new GroupingState with every group collapsed.
+    ///
+    /// # Parameters
+    /// - `by`: What the emails are grouped by
+    ///
+    /// # Returns
+    /// A new GroupingState instance
+    pub fn new(by: GroupBy) -> Self {
+        Self {
+            by,
+            expanded: HashSet::new(),
+            selected_group: None,
+        }
+    }
+
+    /// Lists the lines of the grouped list.
+    ///
+    /// Groups are in the order their first email is listed in.
+    ///
+    /// # Parameters
+    /// - `emails`: The listed emails
+    ///
+    /// # Returns
+    /// The group lines, each followed by its emails if it's expanded
+    pub fn rows(&self, emails: &[Email]) -> Vec<GroupRow> {
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+        for (index, email) in emails.iter().enumerate() {
+            let key = self.by.key(email);
+            match groups.iter_mut().find(|(group, _)| *group == key) {
+                Some((_, members)) => members.push(index),
+                None => groups.push((key, vec![index])),
+            }
+        }
+
+        let mut rows = Vec::new();
+        for (key, members) in groups {
+            let expanded = self.expanded.contains(&key);
+            rows.push(GroupRow::Group { key, members: members.clone() });
+            if expanded {
+                rows.extend(members.into_iter().map(GroupRow::Email));
+            }
+        }
+
+        rows
+    }
+
+    /// Finds the selected line.
+    ///
+    /// # Parameters
+    /// - `rows`: The lines of the grouped list
+    /// - `selected_email`: The selected email index
+    ///
+    /// # Returns
+    /// The index of the selected group's line, of the selected email's line,
+    /// or of its group's line if the group is collapsed
+    pub fn selected_row(&self, rows: &[GroupRow], selected_email: Option<usize>) -> Option<usize> {
+        if let Some(selected) = &self.selected_group {
+            return rows.iter().position(|row| matches!(row, GroupRow::Group { key, .. } if key == selected));
+        }
+
+        let selected_email = selected_email?;
+        rows.iter()
+            .position(|row| *row == GroupRow::Email(selected_email))
+            .or_else(|| rows.iter().position(|row| row.members().contains(&selected_email)))
+    }
+}
+
+/// A line of the grouped email list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupRow {
+    /// A group, with the indexes of its emails
+    Group {
+        /// The group's key
+        key: String,
+        /// Indexes of the group's emails in the list
+        members: Vec<usize>,
+    },
+    /// An email of an expanded group, by its index in the list
+    Email(usize),
+}
+
+impl GroupRow {
+    /// Gets the emails of a group line.
+    ///
+    /// # Returns
+    /// The indexes of the group's emails, or nothing for an email line
+    pub fn members(&self) -> &[usize] {
+        match self {
+            GroupRow::Group { members, .. } => members,
+            GroupRow::Email(_) => &[],
+        }
+    }
+}
+
+/// Renders the email list grouped by sender.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to render in
+/// - `emails`: The emails to display
+/// - `selected`: The index of the selected email
+/// - `selection`: The emails selected for a batch change
+/// - `grouping`: The grouping state
+/// - `settings`: The settings with the date format and VIP senders
+/// - `view`: The sort order and filter of the folder, for the title
+#[allow(clippy::too_many_arguments)]
+pub fn render_grouped_emails(
+    f: &mut Frame,
+    area: Rect,
+    emails: &[Email],
+    selected: Option<usize>,
+    selection: &HashSet<EmailKey>,
+    grouping: &GroupingState,
+    settings: &AppSettings,
+    view: &FolderView,
+) {
+    let rows = grouping.rows(emails);
+    let width = list_item_width(area);
+
+    // Pad the dates to the widest so the summaries start in one column
+    let dates: Vec<String> = emails.iter()
+        .map(|email| format_list_date(email.date, settings))
+        .collect();
+    let date_width = dates.iter().map(|date| date.width()).max().unwrap_or(0);
+
+    let items: Vec<ListItem> = rows.iter()
+        .map(|row| match row {
+            GroupRow::Group { key, members } => {
+                let first = &emails[members[0]];
+                let name = match grouping.by {
+                    GroupBy::Sender => first.get_sender_display(),
+                    GroupBy::Domain => key.clone(),
+                };
+                let unread = members.iter().filter(|&&index| !emails[index].is_read).count();
+                let marker = if grouping.expanded.contains(key) { "▾" } else { "▸" };
+                let counts = match unread {
+                    0 => format!("{}", members.len()),
+                    unread => format!("{}, {} unread", members.len(), unread),
+                };
+                let style = if unread > 0 {
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().add_modifier(Modifier::BOLD)
+                };
+                let counts = format!(" ({})", counts);
+                let name = truncate_to_width(&name, width.saturating_sub(2 + counts.width()));
+                ListItem::new(format!("{} {}{}", marker, name, counts)).style(style)
+            },
+            GroupRow::Email(index) => {
+                let email = &emails[*index];
+                let tags = tag_spans(email);
+                let mut spans = vec![Span::raw("    ")];
+                spans.extend(selection_marker(email, selection));
+                spans.push(Span::styled(format!("{}  ", pad_to_width(&dates[*index], date_width)), Style::default().fg(Color::DarkGray)));
+                let summary_width = width.saturating_sub(spans_width(&spans) + spans_width(&tags));
+                spans.push(Span::raw(truncate_to_width(&email.get_summary(), summary_width)));
+                spans.extend(tags);
+                ListItem::new(Line::from(spans))
+                .style(email_list_style(email, settings))
+            },
+        })
+        .collect();
+
+    let title = format!("Emails ({}, grouped by {}){}", view.describe(), grouping.by.label(), selection_count(selection));
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().bg(Color::DarkGray))
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    state.select(grouping.selected_row(&rows, selected));
+
+    f.render_stateful_widget(list, area, &mut state);
+}
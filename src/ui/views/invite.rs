@@ -0,0 +1,59 @@
+//! Calendar invitation card for the Linksy email client.
+//!
+//! The event of an invitation is shown in a card between the header and
+//! the body of the detail view, with the keys to accept or decline it.
+
+use crate::models::CalendarEvent;
+use tui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// Builds the lines of the invitation card.
+///
+/// # Parameters
+/// - `event`: The event of the invitation
+///
+/// # Returns
+/// A line per detail of the event, and the keys if it can be answered
+pub fn invite_lines(event: &CalendarEvent) -> Vec<Line<'static>> {
+    let mut lines: Vec<Line> = event.details().into_iter()
+        .map(|(label, value)| Line::from(vec![
+            Span::styled(format!("{}: ", label), Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(value),
+        ]))
+        .collect();
+
+    if event.can_reply() {
+        lines.push(Line::from(Span::styled(
+            "Press 'i' to accept or 'I' to decline",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    lines
+}
+
+/// Renders the invitation card.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to render in, as tall as the lines and the borders
+/// - `event`: The event of the invitation
+pub fn render_invite(f: &mut Frame, area: Rect, event: &CalendarEvent) {
+    let color = match event.method.as_deref() {
+        Some("CANCEL") => Color::Red,
+        _ => Color::Cyan,
+    };
+
+    let card = Paragraph::new(invite_lines(event))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(color))
+            .title(event.heading()));
+
+    f.render_widget(card, area);
+}
@@ -0,0 +1,175 @@
+//! Link extraction and the link picker popup for the Linksy email client.
+//!
+//! This module finds URLs in email bodies and renders the numbered list the
+//! user picks a link to open from.
+
+use crate::models::Email;
+use crate::ui::views::centered_rect;
+use tui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+/// URL schemes that are picked up from email bodies.
+const URL_SCHEMES: [&str; 3] = ["https://", "http://", "mailto:"];
+
+/// Represents the state of the link picker popup.
+#[derive(Clone)]
+pub struct LinkPickerState {
+    /// Links found in the viewed email
+    pub links: Vec<String>,
+    /// Index of the currently selected link
+    pub selected: usize,
+}
+
+impl LinkPickerState {
+    /// Creates a new LinkPickerState.
+    ///
+    /// # Parameters
+    /// - `links`: The links to pick from
+    ///
+    /// # Returns
+    /// A new LinkPickerState instance
+    pub fn new(links: Vec<String>) -> Self {
+        Self {
+            links,
+            selected: 0,
+        }
+    }
+
+    /// Moves the selection to the previous link.
+    pub fn select_previous(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+        }
+    }
+
+    /// Moves the selection to the next link.
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.links.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Gets the selected link.
+    ///
+    /// # Returns
+    /// An Option containing the selected link
+    pub fn get_selected_link(&self) -> Option<&String> {
+        self.links.get(self.selected)
+    }
+}
+
+/// Extracts the links from an email, in the order they appear.
+///
+/// Plain-text URLs come first, followed by `href` targets from the HTML part
+/// that weren't already found. Duplicates are removed.
+///
+/// # Parameters
+/// - `email`: The email to extract links from
+///
+/// # Returns
+/// The links found in the email
+pub fn extract_links(email: &Email) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+
+    let text_links = email.body_text.as_deref().map(extract_text_links).unwrap_or_default();
+    let html_links = email.body_html.as_deref().map(extract_html_links).unwrap_or_default();
+
+    for link in text_links.into_iter().chain(html_links) {
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    }
+
+    links
+}
+
+/// Extracts URLs written out in plain text.
+///
+/// # Parameters
+/// - `text`: The text to search
+///
+/// # Returns
+/// The URLs found in the text
+fn extract_text_links(text: &str) -> Vec<String> {
+    let mut links = Vec::new();
+
+    for word in text.split_whitespace() {
+        // URLs are often wrapped in <...> or (...), so search inside the word
+        let start = URL_SCHEMES.iter().filter_map(|scheme| word.find(scheme)).min();
+        if let Some(start) = start {
+            let url = word[start..].trim_end_matches(|c: char| ".,;:!?)]>'\"".contains(c));
+            if !URL_SCHEMES.contains(&url) {
+                links.push(url.to_string());
+            }
+        }
+    }
+
+    links
+}
+
+/// Extracts the targets of `href` attributes from HTML.
+///
+/// # Parameters
+/// - `html`: The HTML to search
+///
+/// # Returns
+/// The link targets found in the HTML
+fn extract_html_links(html: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let lower = html.to_ascii_lowercase();
+    let mut position = 0;
+
+    while let Some(offset) = lower[position..].find("href=") {
+        let value_start = position + offset + "href=".len();
+        position = value_start;
+
+        // Attribute values may be quoted with either quote character
+        let quote = match html[value_start..].chars().next() {
+            Some(c) if c == '"' || c == '\'' => c,
+            _ => continue,
+        };
+        let value_start = value_start + 1;
+        let Some(length) = html[value_start..].find(quote) else {
+            break;
+        };
+
+        let url = html[value_start..value_start + length].trim().replace("&amp;", "&");
+        if URL_SCHEMES.iter().any(|scheme| url.to_ascii_lowercase().starts_with(scheme)) {
+            links.push(url);
+        }
+        position = value_start + length;
+    }
+
+    links
+}
+
+/// Renders the link picker popup over the current view.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to center the popup in
+/// - `picker`: The link picker state
+pub fn render_link_picker(f: &mut Frame, area: Rect, picker: &LinkPickerState) {
+    let popup_area = centered_rect(80, 60, area);
+
+    let items: Vec<ListItem> = picker.links
+        .iter()
+        .enumerate()
+        .map(|(i, link)| ListItem::new(format!("[{}] {}", i + 1, link)))
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Links - Enter to open, Esc to close"))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    state.select(Some(picker.selected));
+
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
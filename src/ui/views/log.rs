@@ -0,0 +1,94 @@
+//! Log view for the Linksy email client.
+//!
+//! This module contains the state and rendering of the list of recent log
+//! messages, which shows what happened, e.g. while connecting, without
+//! leaving the TUI.
+
+use crate::config::LogLevel;
+use crate::logging::LogEntry;
+use crate::state::View;
+use crate::ui::views::format_with;
+use log::Level;
+use tui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// Represents the state of the log view.
+#[derive(Debug, Clone)]
+pub struct LogViewState {
+    /// The view the log was opened from, returned to when it's closed
+    pub previous_view: View,
+    /// Number of newer messages scrolled past; 0 keeps the newest in view
+    pub scroll: usize,
+}
+
+impl LogViewState {
+    /// Creates a new LogViewState showing the newest messages.
+    ///
+    /// # Parameters
+    /// - `previous_view`: The view the log is opened from
+    ///
+    /// # Returns
+    /// A new LogViewState instance
+    pub fn new(previous_view: View) -> Self {
+        Self {
+            previous_view,
+            scroll: 0,
+        }
+    }
+}
+
+/// Renders the log messages, newest at the bottom.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to render in
+/// - `entries`: The messages of the listed levels, oldest first
+/// - `log_view`: The log view state
+/// - `level`: The least severe level listed
+pub fn render_log(f: &mut Frame, area: Rect, entries: &[LogEntry], log_view: &LogViewState, level: LogLevel) {
+    // Fill the view up to the message scrolled to
+    let height = area.height.saturating_sub(2) as usize;
+    let end = entries.len().saturating_sub(log_view.scroll);
+    let start = end.saturating_sub(height);
+
+    let lines: Vec<Line> = if entries.is_empty() {
+        vec![Line::from(Span::styled("No log messages", Style::default().fg(Color::DarkGray)))]
+    } else {
+        entries[start..end].iter()
+            .map(|entry| Line::from(vec![
+                Span::styled(format!("{} ", format_with(entry.time, "%H:%M:%S")), Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{:<5} ", entry.level), level_style(entry.level)),
+                Span::styled(format!("{}: ", entry.target), Style::default().fg(Color::DarkGray)),
+                Span::raw(entry.message.clone()),
+            ]))
+            .collect()
+    };
+
+    let title = format!("Log ({} and above) - {} messages", level.label(), entries.len());
+    let log = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title));
+
+    f.render_widget(log, area);
+}
+
+/// Gets the style of a log level.
+///
+/// # Parameters
+/// - `level`: The log level
+///
+/// # Returns
+/// Red for errors, yellow for warnings, and dimmer colors for details
+fn level_style(level: Level) -> Style {
+    match level {
+        Level::Error => Style::default().fg(Color::Red),
+        Level::Warn => Style::default().fg(Color::Yellow),
+        Level::Info => Style::default().fg(Color::Green),
+        Level::Debug => Style::default().fg(Color::Gray),
+        Level::Trace => Style::default().fg(Color::DarkGray),
+    }
+}
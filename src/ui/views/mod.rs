@@ -3,14 +3,35 @@
 //! This module contains the different views used in the application UI.
 
 pub mod account_config;
+pub mod attachments;
+pub mod compose;
+pub mod find;
+pub mod grouping;
+pub mod invite;
+pub mod links;
+pub mod log;
+pub mod prompt;
+pub mod quoting;
+pub mod remote_content;
+pub mod reply;
+pub mod search;
+pub mod tags;
+pub mod templates;
 
-use crate::models::{AccountSummary, Email};
+use crate::config::{AppSettings, FolderView, SavedSearch};
+use crate::models::{AccountSummary, ConnectionStatus, Email, EmailKey, Priority};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::time::SystemTime;
 use tui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
     widgets::{Block, Borders, List, ListItem, Paragraph, ListState},
     Frame,
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Renders the accounts view.
 ///
@@ -26,8 +47,22 @@ pub fn render_accounts(
     accounts: &[AccountSummary],
     selected: Option<usize>,
 ) {
+    let width = list_item_width(area);
     let account_items: Vec<ListItem> = accounts.iter()
-        .map(|account| ListItem::new(account.get_display_name()))
+        .map(|account| match account.enabled {
+            // Show that a connection is in progress rather than looking frozen
+            true if account.is_connecting() => {
+                let connecting = format!(" {} Connecting…", spinner_frame());
+                ListItem::new(Line::from(vec![
+                    Span::raw(truncate_to_width(&account.get_display_name(), width.saturating_sub(connecting.width()))),
+                    Span::styled(connecting, Style::default().fg(Color::Yellow)),
+                ]))
+            },
+            true => ListItem::new(truncate_to_width(&account.get_display_name(), width)),
+            // Dim disabled accounts
+            false => ListItem::new(format!("{} (disabled)", truncate_to_width(&account.get_display_name(), width.saturating_sub(11))))
+                .style(Style::default().fg(Color::DarkGray)),
+        })
         .collect();
         
     let accounts_list = List::new(account_items)
@@ -43,24 +78,374 @@ pub fn render_accounts(
     f.render_stateful_widget(accounts_list, area, &mut state);
 }
 
+/// Gets the frame of the spinner shown while something is in progress.
+///
+/// The frame follows the clock, so it turns on every redraw without any
+/// state to keep.
+///
+/// # Returns
+/// The character to show for the current frame
+pub fn spinner_frame() -> char {
+    const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    FRAMES[(millis / 100) as usize % FRAMES.len()]
+}
+
+/// Renders the account connection status dashboard.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to render in
+/// - `accounts`: The accounts to display
+/// - `selected`: The index of the selected account
+pub fn render_account_status(
+    f: &mut Frame,
+    area: Rect,
+    accounts: &[AccountSummary],
+    selected: Option<usize>,
+) {
+    let width = list_item_width(area);
+    let status_items: Vec<ListItem> = accounts.iter()
+        .map(|account| {
+            let last_synced = account.last_synced
+                .map(format_time_ago)
+                .unwrap_or_else(|| "never".to_string());
+            
+            let status = if account.enabled {
+                account.get_status_summary()
+            } else {
+                "Disabled - not connected or checked".to_string()
+            };
+            
+            let mut lines = vec![
+                Line::from(Span::styled(
+                    truncate_to_width(&account.get_display_name(), width),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )),
+                Line::from(truncate_to_width(&format!("  {}", status), width)),
+                Line::from(truncate_to_width(&format!(
+                    "  {} ({} total) | Last synced: {}",
+                    account.get_unread_summary(),
+                    account.total_count,
+                    last_synced,
+                ), width)),
+            ];
+            
+            if let Some(error) = &account.last_error {
+                lines.push(Line::from(Span::styled(
+                    truncate_to_width(&format!("  Last error: {}", error), width),
+                    Style::default().fg(Color::Red),
+                )));
+            }
+            
+            // Dim disabled accounts
+            let style = if account.enabled { Style::default() } else { Style::default().fg(Color::DarkGray) };
+            ListItem::new(lines).style(style)
+        })
+        .collect();
+        
+    let status_list = List::new(status_items)
+        .block(Block::default().borders(Borders::ALL).title("Account Status"))
+        .highlight_style(Style::default().fg(Color::Yellow))
+        .highlight_symbol("> ");
+        
+    let mut state = ListState::default();
+    if let Some(i) = selected {
+        state.select(Some(i));
+    }
+    
+    f.render_stateful_widget(status_list, area, &mut state);
+}
+
+/// Summarizes the connections of all accounts for the status bar.
+///
+/// Disabled accounts aren't counted.
+///
+/// # Parameters
+/// - `accounts`: The accounts
+///
+/// # Returns
+/// e.g. "Accounts: 2✓ 1✗" in red if an account failed, yellow while one is
+/// connecting and green otherwise, or None if no account is in use
+pub fn connections_indicator(accounts: &[AccountSummary]) -> Option<Span<'static>> {
+    let statuses: Vec<ConnectionStatus> = accounts.iter()
+        .filter(|account| account.enabled)
+        .map(AccountSummary::connection_status)
+        .collect();
+    if statuses.is_empty() {
+        return None;
+    }
+    let count = |status| statuses.iter().filter(|known| **known == status).count();
+    
+    // Connected accounts are always counted, the others only if there are any
+    let mut text = format!("Accounts: {}✓", count(ConnectionStatus::Connected));
+    for (status, symbol) in [(ConnectionStatus::Failed, "✗"), (ConnectionStatus::Connecting, "⟳"), (ConnectionStatus::Disconnected, "-")] {
+        if count(status) > 0 {
+            let _ = write!(text, " {}{}", count(status), symbol);
+        }
+    }
+    
+    let color = if count(ConnectionStatus::Failed) > 0 {
+        Color::Red
+    } else if count(ConnectionStatus::Connecting) > 0 {
+        Color::Yellow
+    } else {
+        Color::Green
+    };
+    Some(Span::styled(text, Style::default().fg(color)))
+}
+
+/// Renders the first-run overlay shown while no accounts are configured.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to center the overlay in
+/// - `config_path`: Where the configuration is stored
+pub fn render_onboarding(f: &mut Frame, area: Rect, config_path: &str) {
+    let popup_area = centered_rect(60, 50, area);
+    
+    let text = vec![
+        Line::from(Span::styled("Welcome to Linksy!", Style::default().add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from("No email accounts are configured yet."),
+        Line::from(""),
+        Line::from(vec![
+            Span::raw("Press "),
+            Span::styled("a", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" or "),
+            Span::styled("Enter", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" to add your first account."),
+        ]),
+        Line::from(vec![
+            Span::raw("Press "),
+            Span::styled("Ctrl+q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" to quit."),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("Accounts can also be added to {}", config_path),
+            Style::default().fg(Color::DarkGray),
+        )),
+    ];
+    
+    let onboarding = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)).title("Getting started"))
+        .alignment(tui::layout::Alignment::Center)
+        .wrap(tui::widgets::Wrap { trim: true });
+    
+    f.render_widget(tui::widgets::Clear, popup_area);
+    f.render_widget(onboarding, popup_area);
+}
+
+/// Formats a point in time relative to now (e.g. "2m ago").
+///
+/// # Parameters
+/// - `time`: The time to format
+///
+/// # Returns
+/// A short human-readable description of how long ago the time was
+pub fn format_time_ago(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+        
+    if secs < 10 {
+        "just now".to_string()
+    } else if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 60 * 60 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 60 * 60 * 24 {
+        format!("{}h ago", secs / (60 * 60))
+    } else {
+        format!("{}d ago", secs / (60 * 60 * 24))
+    }
+}
+
+/// Formats a time with a strftime-style format, in UTC.
+///
+/// # Parameters
+/// - `time`: The time to format
+/// - `format`: The format string
+///
+/// # Returns
+/// The formatted time, or an empty string if the format is invalid
+pub fn format_with(time: SystemTime, format: &str) -> String {
+    let mut formatted = String::new();
+    if write!(formatted, "{}", DateTime::<Utc>::from(time).format(format)).is_err() {
+        formatted.clear();
+    }
+    formatted
+}
+
+/// Gets the number of cells a list item has, inside the borders and the
+/// highlight symbol.
+///
+/// # Parameters
+/// - `area`: The area of the list
+///
+/// # Returns
+/// The width available to an item's text
+pub fn list_item_width(area: Rect) -> usize {
+    area.width.saturating_sub(4) as usize
+}
+
+/// Shortens text to fit a number of terminal cells, ending it with "…" if
+/// it's cut.
+///
+/// Wide characters such as CJK or emoji take two cells and zero-width ones
+/// none. Control characters, e.g. a tab in a subject, are shown as spaces
+/// so a list item never wraps or shifts the columns after it.
+///
+/// # Parameters
+/// - `text`: The text to fit
+/// - `width`: The number of cells available
+///
+/// # Returns
+/// The text, shortened if it's wider than the cells
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    let text: String = text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    if text.width() <= width {
+        return text;
+    }
+    
+    // Keep what fits next to the ellipsis, without splitting a wide character
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        used += char_width;
+        truncated.push(c);
+    }
+    if width > 0 {
+        truncated.push('…');
+    }
+    
+    truncated
+}
+
+/// Pads text with spaces to a number of terminal cells.
+///
+/// # Parameters
+/// - `text`: The text to pad
+/// - `width`: The number of cells to fill
+///
+/// # Returns
+/// The text followed by enough spaces to fill the cells
+pub fn pad_to_width(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
+
+/// Formats a date for the email list: the time for today, the date otherwise.
+///
+/// # Parameters
+/// - `time`: The time to format
+/// - `settings`: The settings with the date and time formats
+///
+/// # Returns
+/// The formatted date or time
+pub fn format_list_date(time: SystemTime, settings: &AppSettings) -> String {
+    let today = DateTime::<Utc>::from(SystemTime::now()).date_naive();
+    if DateTime::<Utc>::from(time).date_naive() == today {
+        format_with(time, &settings.time_format)
+    } else {
+        format_with(time, &settings.date_format)
+    }
+}
+
+/// Formats a full date and time, e.g. for the email detail header.
+///
+/// # Parameters
+/// - `time`: The time to format
+/// - `settings`: The settings with the date and time formats
+///
+/// # Returns
+/// The formatted date followed by the time
+pub fn format_date_time(time: SystemTime, settings: &AppSettings) -> String {
+    format!("{} {}", format_with(time, &settings.date_format), format_with(time, &settings.time_format))
+}
+
+/// Formats a point in the future relative to now (e.g. "in 2h 5m").
+///
+/// # Parameters
+/// - `time`: The time to format
+///
+/// # Returns
+/// A short human-readable description of how long until the time
+pub fn format_time_until(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::now())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+        
+    if secs == 0 {
+        "now".to_string()
+    } else if secs < 60 {
+        format!("in {}s", secs)
+    } else if secs < 60 * 60 {
+        format!("in {}m", secs.div_ceil(60))
+    } else if secs < 60 * 60 * 24 {
+        format!("in {}h {}m", secs / (60 * 60), secs % (60 * 60) / 60)
+    } else {
+        format!("in {}d {}h", secs / (60 * 60 * 24), secs % (60 * 60 * 24) / (60 * 60))
+    }
+}
+
 /// Renders the folders view.
 ///
+/// Saved searches are listed after the folders, with their query.
+///
 /// # Parameters
 /// - `f`: The frame to render on
 /// - `area`: The area to render in
 /// - `folders`: The folders to display
-/// - `selected`: The index of the selected folder
+/// - `saved_searches`: The saved searches to display
+/// - `last_synced`: The time each folder was last synced
+/// - `selected`: The index of the selected folder or saved search
 #[allow(dead_code)]
 pub fn render_folders(
     f: &mut Frame,
     area: Rect,
     folders: &[String],
+    saved_searches: &[SavedSearch],
+    last_synced: &HashMap<String, SystemTime>,
     selected: Option<usize>,
 ) {
-    let folder_items: Vec<ListItem> = folders.iter()
-        .map(|folder| ListItem::new(folder.clone()))
+    let width = list_item_width(area);
+    let mut folder_items: Vec<ListItem> = folders.iter()
+        .map(|folder| {
+            let line = match last_synced.get(folder) {
+                Some(time) => {
+                    let synced = format!("  (synced {})", format_time_ago(*time));
+                    Line::from(vec![
+                        Span::raw(truncate_to_width(folder, width.saturating_sub(synced.width()))),
+                        Span::styled(synced, Style::default().fg(Color::DarkGray)),
+                    ])
+                },
+                None => Line::from(truncate_to_width(folder, width)),
+            };
+            ListItem::new(line)
+        })
         .collect();
         
+    folder_items.extend(saved_searches.iter().map(|search| {
+        let name = truncate_to_width(&format!("Search: {}", search.name), width);
+        let query = truncate_to_width(&format!("  ({})", search.query), width.saturating_sub(name.width()));
+        ListItem::new(Line::from(vec![
+            Span::styled(name, Style::default().fg(Color::Cyan)),
+            Span::styled(query, Style::default().fg(Color::DarkGray)),
+        ]))
+    }));
+        
     let folders_list = List::new(folder_items)
         .block(Block::default().borders(Borders::ALL).title("Folders"))
         .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
@@ -81,27 +466,46 @@ pub fn render_folders(
 /// - `area`: The area to render in
 /// - `emails`: The emails to display
 /// - `selected`: The index of the selected email
-#[allow(dead_code)]
+/// - `selection`: The emails selected for a batch change
+/// - `settings`: The settings with the date and time formats
+/// - `view`: The folder's sort order and filter, shown in the title
 pub fn render_emails(
     f: &mut Frame,
     area: Rect,
     emails: &[Email],
     selected: Option<usize>,
+    selection: &HashSet<EmailKey>,
+    settings: &AppSettings,
+    view: &FolderView,
 ) {
+    // Pad the dates to the widest so the summaries start in one column
+    let dates: Vec<String> = emails.iter()
+        .map(|email| format_list_date(email.date, settings))
+        .collect();
+    let date_width = dates.iter().map(|date| date.width()).max().unwrap_or(0);
+    let width = list_item_width(area);
+    
     let email_items: Vec<ListItem> = emails.iter()
-        .map(|email| {
-            let style = if email.is_read {
-                Style::default()
-            } else {
-                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-            };
+        .zip(&dates)
+        .map(|(email, date)| {
+            let style = email_list_style(email, settings);
+            
+            // Cut the summary rather than the tags and size after it
+            let tags = tags::tag_spans(email);
+            let size = email.get_size_string().map(|size| format!("  {}", size)).unwrap_or_default();
+            let mut spans: Vec<Span> = selection_marker(email, selection).into_iter().collect();
+            spans.push(Span::styled(format!("{}  ", pad_to_width(date, date_width)), Style::default().fg(Color::DarkGray)));
+            let summary_width = width.saturating_sub(tags::spans_width(&spans) + tags::spans_width(&tags) + size.width());
+            spans.push(Span::raw(truncate_to_width(&email.get_summary(), summary_width)));
+            spans.extend(tags);
+            spans.push(Span::styled(size, Style::default().fg(Color::DarkGray)));
             
-            ListItem::new(email.get_summary()).style(style)
+            ListItem::new(Line::from(spans)).style(style)
         })
         .collect();
         
     let emails_list = List::new(email_items)
-        .block(Block::default().borders(Borders::ALL).title("Emails"))
+        .block(Block::default().borders(Borders::ALL).title(format!("Emails ({}){}", view.describe(), selection_count(selection))))
         .highlight_style(Style::default().bg(Color::DarkGray))
         .highlight_symbol("> ");
         
@@ -113,6 +517,259 @@ pub fn render_emails(
     f.render_stateful_widget(emails_list, area, &mut state);
 }
 
+/// Builds the mark in front of an email that shows if it's selected.
+///
+/// # Parameters
+/// - `email`: The email
+/// - `selection`: The emails selected for a batch change
+///
+/// # Returns
+/// A check mark or as much space, or None while nothing is selected
+pub fn selection_marker(email: &Email, selection: &HashSet<EmailKey>) -> Option<Span<'static>> {
+    if selection.is_empty() {
+        return None;
+    }
+    
+    Some(match selection.contains(&email.key()) {
+        true => Span::styled("✓ ", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        false => Span::raw("  "),
+    })
+}
+
+/// Describes how many emails are selected, for the title of a list.
+///
+/// # Parameters
+/// - `selection`: The emails selected for a batch change
+///
+/// # Returns
+/// The count to add to the title, or an empty string while nothing is selected
+pub fn selection_count(selection: &HashSet<EmailKey>) -> String {
+    match selection.len() {
+        0 => String::new(),
+        count => format!(" - {} selected", count),
+    }
+}
+
+/// Gets the style of an email in a list.
+///
+/// Unread emails are bold, and emails from VIP senders are magenta rather
+/// than the yellow of other unread emails.
+///
+/// # Parameters
+/// - `email`: The email
+/// - `settings`: The settings with the VIP senders
+///
+/// # Returns
+/// The style of the email's line
+pub fn email_list_style(email: &Email, settings: &AppSettings) -> Style {
+    let style = if settings.is_vip(&email.from) {
+        Style::default().fg(Color::Magenta)
+    } else if email.is_read {
+        Style::default()
+    } else {
+        Style::default().fg(Color::Yellow)
+    };
+    
+    if email.is_read {
+        style
+    } else {
+        style.add_modifier(Modifier::BOLD)
+    }
+}
+
+/// Renders the Scheduled folder's list of messages waiting to be sent.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to render in
+/// - `emails`: The scheduled messages, dated at their send time
+/// - `selected`: The index of the selected message
+pub fn render_scheduled_emails(
+    f: &mut Frame,
+    area: Rect,
+    emails: &[Email],
+    selected: Option<usize>,
+) {
+    let width = list_item_width(area);
+    let email_items: Vec<ListItem> = if emails.is_empty() {
+        vec![ListItem::new(Span::styled("No scheduled messages", Style::default().fg(Color::DarkGray)))]
+    } else {
+        emails.iter()
+            .map(|email| {
+                let sends = format!("  (sends {})", format_time_until(email.date));
+                let summary = format!("To: {} - {}", email.to.join(", "), email.subject);
+                ListItem::new(Line::from(vec![
+                    Span::raw(truncate_to_width(&summary, width.saturating_sub(sends.width()))),
+                    Span::styled(sends, Style::default().fg(Color::Cyan)),
+                ]))
+            })
+            .collect()
+    };
+        
+    let emails_list = List::new(email_items)
+        .block(Block::default().borders(Borders::ALL).title("Scheduled"))
+        .highlight_style(Style::default().bg(Color::DarkGray))
+        .highlight_symbol("> ");
+        
+    let mut state = ListState::default();
+    if !emails.is_empty() {
+        state.select(selected);
+    }
+    
+    f.render_stateful_widget(emails_list, area, &mut state);
+}
+
+/// Represents how much of an email body fits in the detail view.
+///
+/// Lines are counted as they're wrapped to the width of the view, so a
+/// body that is one very long line, like minified HTML, still scrolls to
+/// its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyViewport {
+    /// Number of lines shown at once
+    pub height: u16,
+    /// Number of columns the body is wrapped at
+    pub width: u16,
+    /// Number of lines of the body once it's wrapped
+    pub lines: usize,
+}
+
+impl BodyViewport {
+    /// Gets the furthest the body can be scrolled.
+    ///
+    /// # Returns
+    /// The offset at which the last line is at the bottom of the view
+    pub fn max_scroll(&self) -> u16 {
+        u16::try_from(self.lines.saturating_sub(usize::from(self.height))).unwrap_or(u16::MAX)
+    }
+
+    /// Gets how far a page scrolls.
+    ///
+    /// # Parameters
+    /// - `overlap`: The lines of the previous page kept in view
+    ///
+    /// # Returns
+    /// One screen minus the overlap, at least one line
+    pub fn page(&self, overlap: u16) -> u16 {
+        self.height.saturating_sub(overlap).max(1)
+    }
+
+    /// Scrolls by a number of lines, without going past either end.
+    ///
+    /// # Parameters
+    /// - `offset`: The current scroll offset
+    /// - `lines`: The lines to scroll by, negative to scroll up
+    ///
+    /// # Returns
+    /// The new scroll offset
+    pub fn scroll(&self, offset: u16, lines: i32) -> u16 {
+        let offset = (i32::from(offset) + lines).clamp(0, i32::from(self.max_scroll()));
+        u16::try_from(offset).unwrap_or(0)
+    }
+}
+
+/// Splits the email detail view into its header, invitation and body.
+///
+/// # Parameters
+/// - `area`: The area of the view
+/// - `email`: The email to display
+///
+/// # Returns
+/// The areas of the header, of the invitation card, empty if there's none,
+/// and of the body
+fn email_detail_chunks(area: Rect, email: &Email) -> std::rc::Rc<[Rect]> {
+    // The header grows by a line for the attachments and one for the tags
+    let header_height = 6 + u16::from(!email.attachments.is_empty()) + u16::from(!tags::tag_spans(email).is_empty());
+    let invite_height = email.invite.as_ref().map_or(0, |event| invite::invite_lines(event).len() as u16 + 2);
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(header_height),  // Header
+            Constraint::Length(invite_height),  // Calendar invitation
+            Constraint::Min(0),                 // Body
+        ].as_ref())
+        .split(area)
+}
+
+/// Gets the text shown in the body of the email detail view.
+///
+/// # Parameters
+/// - `email`: The email to display
+/// - `settings`: The settings with whether to prefer the HTML part
+/// - `show_quoted`: Whether to expand quoted text in the plain-text part
+/// - `show_remote`: Whether to show remote images in the HTML part
+///
+/// # Returns
+/// The body, with notes about its download and blocked images
+pub fn email_detail_body(email: &Email, settings: &AppSettings, show_quoted: bool, show_remote: bool) -> String {
+    // The body may still be downloading
+    let body_text = if email.headers_only {
+        "Message not downloaded yet".to_string()
+    } else if email.raw_source.is_some() {
+        format!("[This message couldn't be parsed - showing its raw source]\n\n{}", display_body_text(email, settings.prefer_html, show_quoted, show_remote))
+    } else if email.truncated {
+        format!("{}\n\n[Message truncated - press 'g' to download the full message]", display_body_text(email, settings.prefer_html, show_quoted, show_remote))
+    } else {
+        display_body_text(email, settings.prefer_html, show_quoted, show_remote)
+    };
+    
+    // Say when remote images were left out of the displayed HTML
+    let blocked = match displayed_html(email, settings.prefer_html) {
+        Some(html) if !show_remote => remote_content::count_remote_images(html),
+        _ => 0,
+    };
+    if blocked > 0 {
+        let images = if blocked == 1 { "image" } else { "images" };
+        return format!(
+            "[{} remote {} blocked - press 'R' to load, 'A' to always load from this sender]\n\n{}",
+            blocked, images, body_text,
+        );
+    }
+    
+    body_text
+}
+
+/// Gets the area the body text is drawn in.
+///
+/// # Parameters
+/// - `body_area`: The area of the body, with its borders
+/// - `settings`: The settings with the maximum body width
+///
+/// # Returns
+/// The area inside the borders, centered if the terminal is wider than the text may be
+fn email_body_text_area(body_area: Rect, settings: &AppSettings) -> Rect {
+    limit_width(Block::default().borders(Borders::ALL).inner(body_area), settings.max_body_width)
+}
+
+/// Measures how much of an email body fits in the detail view.
+///
+/// # Parameters
+/// - `area`: The area of the view
+/// - `email`: The email to display
+/// - `settings`: The settings with the body width and whether to prefer the HTML part
+/// - `show_quoted`: Whether to expand quoted text in the plain-text part
+/// - `show_remote`: Whether to show remote images in the HTML part
+///
+/// # Returns
+/// The height of the body and its number of wrapped lines
+pub fn email_body_viewport(
+    area: Rect,
+    email: &Email,
+    settings: &AppSettings,
+    show_quoted: bool,
+    show_remote: bool,
+) -> BodyViewport {
+    let text_area = email_body_text_area(email_detail_chunks(area, email)[2], settings);
+    let body = Paragraph::new(email_detail_body(email, settings, show_quoted, show_remote))
+        .wrap(tui::widgets::Wrap { trim: false });
+    
+    BodyViewport {
+        height: text_area.height,
+        width: text_area.width,
+        lines: body.line_count(text_area.width),
+    }
+}
+
 /// Renders the email detail view.
 ///
 /// # Parameters
@@ -120,74 +777,126 @@ pub fn render_emails(
 /// - `area`: The area to render in
 /// - `email`: The email to display
 /// - `scroll_offset`: The vertical scroll offset for the email body
+/// - `settings`: The settings with the date formats and whether to prefer the HTML part
+/// - `show_quoted`: Whether to expand quoted text in the plain-text part
+/// - `show_remote`: Whether to show remote images in the HTML part
+/// - `find`: The text being found in the body, if any, whose matches are highlighted
+#[allow(clippy::too_many_arguments)]
 pub fn render_email_detail(
     f: &mut Frame,
     area: Rect,
     email: &Email,
     scroll_offset: u16,
+    settings: &AppSettings,
+    show_quoted: bool,
+    show_remote: bool,
+    find: Option<&find::FindState>,
 ) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),  // Header
-            Constraint::Min(0),     // Body
-        ].as_ref())
-        .split(area);
+    let tags = tags::tag_spans(email);
+    let chunks = email_detail_chunks(area, email);
+    let body_area = chunks[2];
         
     // Draw header
-    let from = format!("From: {}", if let Some(name) = &email.from_name {
-        format!("{} <{}>", name, email.from)
+    // Say where a reply goes if it isn't the sender
+    let from = if email.has_other_reply_to() {
+        format!("From: {}  Reply-To: {}", email.get_sender_display(), email.reply_to.join(", "))
     } else {
-        email.from.clone()
-    });
+        format!("From: {}", email.get_sender_display())
+    };
     
     let to = format!("To: {}", email.to.join(", "));
-    let subject = format!("Subject: {}", email.subject);
+    let date = match email.get_size_string() {
+        Some(size) => format!("Date: {}  Size: {}", format_date_time(email.date, settings), size),
+        None => format!("Date: {}", format_date_time(email.date, settings)),
+    };
+    let subject = match email.priority {
+        Priority::Normal => format!("Subject: {}", email.get_subject_display()),
+        priority => format!("Subject: {}  [{} priority]", email.get_subject_display(), priority.label()),
+    };
     
-    let header_text = vec![
-        from,
-        to,
-        subject,
-    ].join("\n");
+    let mut header_lines = vec![
+        Line::from(from),
+        Line::from(to),
+        Line::from(date),
+        Line::from(subject),
+    ];
+    if !email.attachments.is_empty() {
+        // Inline images are shown in the body, so they are only counted
+        let names: Vec<&str> = email.attachments.iter()
+            .filter(|attachment| !attachment.inline)
+            .map(|attachment| attachment.filename.as_str())
+            .collect();
+        let inline = email.attachments.len() - names.len();
+        let line = match (names.is_empty(), inline) {
+            (_, 0) => format!("Attachments: {}", names.join(", ")),
+            (true, inline) => format!("Attachments: {} inline", inline),
+            (false, inline) => format!("Attachments: {} (+{} inline)", names.join(", "), inline),
+        };
+        header_lines.push(Line::from(line));
+    }
+    if !tags.is_empty() {
+        let mut spans = vec![Span::raw("Tags:")];
+        spans.extend(tags);
+        header_lines.push(Line::from(spans));
+    }
     
-    let header = Paragraph::new(header_text)
+    let header = Paragraph::new(header_lines)
         .block(Block::default().borders(Borders::ALL).title("Email"));
     
     f.render_widget(header, chunks[0]);
     
-    // Process and draw body
-    let body_text = if let Some(text) = &email.body_text {
-        format_plain_text(text)
-    } else if let Some(html) = &email.body_html {
-        convert_html_to_text(html)
-    } else {
-        "No content".to_string()
+    // Draw the invitation's event between the header and the body
+    if let Some(event) = &email.invite {
+        invite::render_invite(f, chunks[1], event);
+    }
+    
+    // Create a scrollable paragraph for the body, keeping in bounds a
+    // scroll offset from before the terminal was resized
+    let body_text = email_detail_body(email, settings, show_quoted, show_remote);
+    let text_area = email_body_text_area(body_area, settings);
+    
+    // Highlight what is being found, saying in the title which match is the current one
+    let (title, text) = match find {
+        Some(find) => {
+            let matches = find::find_matches(&body_text, &find.query);
+            let title = match matches.len() {
+                0 => format!("Body - no matches for \"{}\"", find.query),
+                count => format!("Body - match {} of {} for \"{}\" (n/N for next/previous, Esc to stop)", find.current.min(count - 1) + 1, count, find.query),
+            };
+            (title, find::highlight_matches(&body_text, &matches, find.current))
+        },
+        None => ("Body".to_string(), Text::from(body_text)),
     };
     
-    // Create a scrollable paragraph for the body
-    let body = Paragraph::new(body_text.clone())
-        .block(Block::default().borders(Borders::ALL).title("Body"))
-        .scroll((scroll_offset, 0))
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let body = Paragraph::new(text)
         .wrap(tui::widgets::Wrap { trim: false });
+    let viewport = BodyViewport {
+        height: text_area.height,
+        width: text_area.width,
+        lines: body.line_count(text_area.width),
+    };
+    let scroll_offset = scroll_offset.min(viewport.max_scroll());
         
-    f.render_widget(body, chunks[1]);
+    f.render_widget(block, body_area);
+    f.render_widget(body.scroll((scroll_offset, 0)), text_area);
     
-    // Draw scroll indicator if needed
-    let body_height = chunks[1].height as usize - 2; // Account for borders
-    let lines: Vec<&str> = body_text.lines().collect();
-    
-    if lines.len() > body_height {
+    // Draw scroll indicator if needed, on the bottom border if it fits there
+    if viewport.lines > usize::from(viewport.height) {
         let scroll_indicator = format!("(Scroll: {}/{})", 
             scroll_offset.saturating_add(1), 
-            lines.len().saturating_sub(body_height).saturating_add(1)
+            viewport.max_scroll().saturating_add(1)
         );
+        if body_area.height == 0 || usize::from(body_area.width) < scroll_indicator.len() + 2 {
+            return;
+        }
         
         let scroll_text = Paragraph::new(scroll_indicator.clone())
             .style(Style::default().fg(Color::Gray));
             
         let scroll_area = Rect::new(
-            chunks[1].x + chunks[1].width - scroll_indicator.len() as u16 - 2,
-            chunks[1].y + chunks[1].height - 1,
+            body_area.x + body_area.width - scroll_indicator.len() as u16 - 2,
+            body_area.y + body_area.height - 1,
             scroll_indicator.len() as u16,
             1
         );
@@ -196,6 +905,67 @@ pub fn render_email_detail(
     }
 }
 
+/// Narrows an area to a maximum width, keeping it centered.
+///
+/// # Parameters
+/// - `area`: The area
+/// - `max_width`: The maximum width in columns, or 0 for no limit
+///
+/// # Returns
+/// The area, or its centered middle if it is wider than the limit
+fn limit_width(area: Rect, max_width: u16) -> Rect {
+    if max_width == 0 || area.width <= max_width {
+        return area;
+    }
+    
+    Rect::new(area.x + (area.width - max_width) / 2, area.y, max_width, area.height)
+}
+
+/// Gets the body text shown in the email detail view.
+///
+/// Falls back to the other part if the preferred one is missing, and to the
+/// raw source for a message that couldn't be parsed.
+///
+/// # Parameters
+/// - `email`: The email to get the body of
+/// - `prefer_html`: Whether to use the HTML part when both parts exist
+/// - `show_quoted`: Whether to expand quoted text in the plain-text part
+/// - `show_remote`: Whether to include remote images of the HTML part
+///
+/// # Returns
+/// The body as displayed
+pub fn display_body_text(email: &Email, prefer_html: bool, show_quoted: bool, show_remote: bool) -> String {
+    if let Some(raw_source) = &email.raw_source {
+        return raw_source.clone();
+    }
+    
+    let plain = email.body_text.as_deref()
+        .map(format_plain_text)
+        .map(|text| if show_quoted { text } else { quoting::collapse_quoted_text(&text) });
+    let html = email.body_html.as_deref()
+        .map(|html| remote_content::replace_inline_images(html, &email.attachments))
+        .map(|html| convert_html_to_text(&remote_content::replace_remote_images(&html, show_remote)));
+    
+    if prefer_html { html.or(plain) } else { plain.or(html) }
+        .unwrap_or_else(|| "No content".to_string())
+}
+
+/// Gets the HTML part of an email if it's the part that is displayed.
+///
+/// # Parameters
+/// - `email`: The email
+/// - `prefer_html`: Whether the HTML part is used when both parts exist
+///
+/// # Returns
+/// The HTML part, or None if the plain-text part is displayed
+fn displayed_html(email: &Email, prefer_html: bool) -> Option<&str> {
+    if prefer_html || email.body_text.is_none() {
+        email.body_html.as_deref()
+    } else {
+        None
+    }
+}
+
 /// Formats plain text for better display.
 ///
 /// # Parameters
@@ -216,7 +986,7 @@ fn format_plain_text(text: &str) -> String {
         if trimmed.is_empty() {
             blank_line_count += 1;
             if blank_line_count <= 2 {
-                result.push_str("\n");
+                result.push('\n');
             }
         } else {
             blank_line_count = 0;
@@ -235,7 +1005,7 @@ fn format_plain_text(text: &str) -> String {
 ///
 /// # Returns
 /// Plain text representation of the HTML content
-fn convert_html_to_text(html: &str) -> String {
+pub fn convert_html_to_text(html: &str) -> String {
     // This is a basic implementation - a more robust solution would use a proper HTML parser
     
     // Replace common HTML entities
@@ -300,7 +1070,7 @@ fn convert_html_to_text(html: &str) -> String {
         if trimmed.is_empty() {
             blank_line_count += 1;
             if blank_line_count <= 2 {
-                final_result.push_str("\n");
+                final_result.push('\n');
             }
         } else {
             blank_line_count = 0;
@@ -342,7 +1112,7 @@ pub fn render_compose_email(
     let to_line = format!("To: {}", to);
     let subject_line = format!("Subject: {}", subject);
     
-    let header_text = vec![
+    let header_text = [
         to_line,
         subject_line,
     ].join("\n");
@@ -377,3 +1147,32 @@ pub fn render_settings(
         
     f.render_widget(settings, area);
 }
+
+/// Computes a rectangle centered in the given area.
+///
+/// # Parameters
+/// - `percent_x`: Width of the rectangle as a percentage of the area
+/// - `percent_y`: Height of the rectangle as a percentage of the area
+/// - `area`: The area to center in
+///
+/// # Returns
+/// The centered rectangle
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ].as_ref())
+        .split(area);
+        
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ].as_ref())
+        .split(vertical[1])[1]
+}
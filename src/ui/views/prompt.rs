@@ -0,0 +1,153 @@
+//! Text input prompt for the Linksy email client.
+//!
+//! This module contains a single-line input popup used to ask the user for a
+//! value, such as a file path, before running an action, and a yes/no
+//! confirmation popup.
+
+use crate::ui::views::centered_rect;
+use tui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// The action to run with the prompt's input once it's confirmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptAction {
+    /// Save the body of the viewed email to the entered path
+    SaveEmailBody,
+    /// Send the composed email after the entered delay
+    ScheduleSend,
+    /// Search for emails matching the entered query
+    Search,
+    /// Save the listed search under the entered name
+    SaveSearch,
+    /// Replace the query of the saved search at the given index
+    EditSavedSearch(usize),
+    /// Rename the saved search at the given index
+    RenameSavedSearch(usize),
+    /// Add the entered tag to the tag list and the selected email
+    NewTag,
+    /// Find the entered text in the body of the viewed email
+    FindInBody,
+}
+
+/// Represents the state of an open input prompt.
+#[derive(Clone)]
+pub struct PromptState {
+    /// Title shown on the prompt border
+    pub title: String,
+    /// The text entered so far
+    pub input: String,
+    /// The action to run on confirmation
+    pub action: PromptAction,
+}
+
+impl PromptState {
+    /// Creates a new PromptState.
+    ///
+    /// # Parameters
+    /// - `title`: Title shown on the prompt border
+    /// - `input`: Initial input, e.g. a suggested value
+    /// - `action`: The action to run on confirmation
+    ///
+    /// # Returns
+    /// A new PromptState instance
+    pub fn new(title: &str, input: String, action: PromptAction) -> Self {
+        Self {
+            title: title.to_string(),
+            input,
+            action,
+        }
+    }
+}
+
+/// The action to run once a confirmation is accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfirmAction {
+    /// Send the composed email even though its subject is empty
+    SendWithoutSubject,
+    /// Delete the template at the given index
+    DeleteTemplate(usize),
+    /// Save the account form even though it matches an existing account
+    SaveDuplicateAccount,
+    /// Delete the saved search at the given index
+    DeleteSavedSearch(usize),
+    /// Quit and empty Trash on every exit from now on
+    EmptyTrashOnExit,
+    /// Quit the application
+    Quit,
+    /// Save the message being written as a draft and quit, or quit without it on no
+    SaveDraftAndQuit,
+    /// Quit while messages wait in the outbox, keeping them to send on the next start
+    QuitWithUnsent,
+    /// Accept (true) or decline (false) the invitation in the viewed email
+    AnswerInvite(bool),
+}
+
+/// Represents the state of an open yes/no confirmation.
+#[derive(Clone)]
+pub struct ConfirmState {
+    /// The question to ask
+    pub message: String,
+    /// The action to run if the user answers yes
+    pub action: ConfirmAction,
+}
+
+impl ConfirmState {
+    /// Creates a new ConfirmState.
+    ///
+    /// # Parameters
+    /// - `message`: The question to ask
+    /// - `action`: The action to run if the user answers yes
+    ///
+    /// # Returns
+    /// A new ConfirmState instance
+    pub fn new(message: &str, action: ConfirmAction) -> Self {
+        Self {
+            message: message.to_string(),
+            action,
+        }
+    }
+}
+
+/// Renders the input prompt over the current view.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to center the prompt in
+/// - `prompt`: The prompt state
+pub fn render_prompt(f: &mut Frame, area: Rect, prompt: &PromptState) {
+    let popup_area = centered_rect(70, 20, area);
+    let popup_area = Rect::new(popup_area.x, popup_area.y, popup_area.width, 3.min(area.height));
+
+    let title = format!("{} - Enter to confirm, Esc to cancel", prompt.title);
+    let input = Paragraph::new(prompt.input.as_str())
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)).title(title));
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(input, popup_area);
+
+    // Keep the cursor at the end of the input
+    let x = popup_area.x + 1 + prompt.input.width() as u16;
+    f.set_cursor(x.min(popup_area.x + popup_area.width.saturating_sub(2)), popup_area.y + 1);
+}
+
+/// Renders a yes/no confirmation over the current view.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to center the confirmation in
+/// - `confirm`: The confirmation state
+pub fn render_confirm(f: &mut Frame, area: Rect, confirm: &ConfirmState) {
+    let popup_area = centered_rect(50, 20, area);
+    let popup_area = Rect::new(popup_area.x, popup_area.y, popup_area.width, 3.min(area.height));
+
+    let question = Paragraph::new(format!("{} (y/n)", confirm.message))
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)).title("Confirm"));
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(question, popup_area);
+}
@@ -0,0 +1,152 @@
+//! Quoted text detection for email bodies.
+//!
+//! Splits a plain-text body into the sender's new content and quoted reply
+//! history so the detail view can collapse the latter.
+
+/// A region of an email body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BodySegment {
+    /// Lines written by the sender
+    New(Vec<String>),
+    /// Quoted history, including its attribution line
+    Quoted(Vec<String>),
+}
+
+/// Checks if a line is quoted with a ">" prefix.
+///
+/// # Parameters
+/// - `line`: The line to check
+///
+/// # Returns
+/// true if the line is quoted, false otherwise
+fn is_quoted_line(line: &str) -> bool {
+    line.trim_start().starts_with('>')
+}
+
+/// Checks if the attribution line of a reply starts at the given line.
+///
+/// Attributions look like "On Mon, 1 Jan 2024, Jane <jane@example.com> wrote:"
+/// and are often wrapped over two lines by the sending client.
+///
+/// # Parameters
+/// - `lines`: All lines of the body
+/// - `index`: The line to check
+///
+/// # Returns
+/// The number of lines the attribution spans, or None if there is none
+fn attribution_length(lines: &[&str], index: usize) -> Option<usize> {
+    let first = lines[index].trim();
+    if !first.starts_with("On ") {
+        return None;
+    }
+
+    if first.ends_with("wrote:") {
+        return Some(1);
+    }
+
+    match lines.get(index + 1) {
+        Some(next) if next.trim().ends_with("wrote:") => Some(2),
+        _ => None,
+    }
+}
+
+/// Finds the next line at or after `index` that isn't blank.
+///
+/// # Parameters
+/// - `lines`: All lines of the body
+/// - `index`: The line to start from
+///
+/// # Returns
+/// An Option containing the line, or None if only blank lines remain
+fn next_non_blank<'a>(lines: &[&'a str], index: usize) -> Option<&'a str> {
+    lines[index..].iter().copied().find(|line| !line.trim().is_empty())
+}
+
+/// Splits a plain-text body into new and quoted regions.
+///
+/// A quoted region is a run of ">" lines, optionally preceded by an
+/// attribution line. An attribution that isn't followed by ">" lines
+/// (common with top-posting clients) quotes everything after it. Blank lines
+/// between quoted lines stay in the quoted region.
+///
+/// # Parameters
+/// - `text`: The plain-text body
+///
+/// # Returns
+/// The body's segments in order
+pub fn segment_quoted_text(text: &str) -> Vec<BodySegment> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut segments = Vec::new();
+    let mut current_new = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        // Find where the next quoted region starts, if it starts here
+        let attribution = attribution_length(&lines, index);
+        if attribution.is_none() && !is_quoted_line(lines[index]) {
+            current_new.push(lines[index].to_string());
+            index += 1;
+            continue;
+        }
+
+        let start = index;
+        if let Some(length) = attribution {
+            index += length;
+
+            // Without ">" prefixes, the rest of the body is the quoted message
+            if !next_non_blank(&lines, index).is_some_and(is_quoted_line) {
+                index = lines.len();
+            }
+        }
+
+        // Extend over quoted lines and blank lines between them
+        while index < lines.len() {
+            let line = lines[index];
+            let continues = is_quoted_line(line)
+                || (line.trim().is_empty() && next_non_blank(&lines, index).is_some_and(is_quoted_line));
+            if !continues {
+                break;
+            }
+            index += 1;
+        }
+
+        if !current_new.is_empty() {
+            segments.push(BodySegment::New(std::mem::take(&mut current_new)));
+        }
+        segments.push(BodySegment::Quoted(lines[start..index].iter().map(|line| line.to_string()).collect()));
+    }
+
+    if !current_new.is_empty() {
+        segments.push(BodySegment::New(current_new));
+    }
+
+    segments
+}
+
+/// Replaces each quoted region of a body with a one-line marker.
+///
+/// # Parameters
+/// - `text`: The plain-text body
+///
+/// # Returns
+/// The body with quoted regions collapsed
+pub fn collapse_quoted_text(text: &str) -> String {
+    let mut result = String::new();
+
+    for segment in segment_quoted_text(text) {
+        match segment {
+            BodySegment::New(lines) => {
+                for line in lines {
+                    result.push_str(&line);
+                    result.push('\n');
+                }
+            },
+            BodySegment::Quoted(lines) => {
+                let noun = if lines.len() == 1 { "line" } else { "lines" };
+                result.push_str(&format!("[… {} {} of quoted text …]\n", lines.len(), noun));
+            },
+        }
+    }
+
+    result
+}
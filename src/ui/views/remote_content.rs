@@ -0,0 +1,203 @@
+//! Remote content blocking for HTML email bodies.
+//!
+//! Finds images loaded from remote servers in an HTML body so the detail
+//! view can leave them out until the user asks for them. Tracking pixels,
+//! images of one pixel or hidden with CSS, are always left out. Inline
+//! images sent with the message, referred to as "cid:...", are named in
+//! their place.
+
+use crate::models::Attachment;
+
+/// Prefixes of image sources loaded from a remote server.
+const REMOTE_PREFIXES: [&str; 3] = ["https://", "http://", "//"];
+
+/// Counts the remote images in an HTML body, including tracking pixels.
+///
+/// # Parameters
+/// - `html`: The HTML body
+///
+/// # Returns
+/// The number of images loaded from a remote server
+pub fn count_remote_images(html: &str) -> usize {
+    image_tags(html)
+        .into_iter()
+        .filter(|&(start, end)| attribute(&html[start..end], "src").is_some_and(|src| is_remote(&src)))
+        .count()
+}
+
+/// Replaces the images in an HTML body with text placeholders.
+///
+/// Remote images become "[image: alt] (url)" when they are shown and are
+/// removed otherwise. Tracking pixels are always removed, and other images
+/// are left for the HTML conversion to drop.
+///
+/// # Parameters
+/// - `html`: The HTML body
+/// - `show_remote`: Whether to show remote images
+///
+/// # Returns
+/// The HTML with remote images replaced
+pub fn replace_remote_images(html: &str, show_remote: bool) -> String {
+    let mut result = String::new();
+    let mut position = 0;
+
+    for (start, end) in image_tags(html) {
+        let tag = &html[start..end];
+        let Some(src) = attribute(tag, "src").filter(|src| is_remote(src)) else {
+            continue;
+        };
+
+        result.push_str(&html[position..start]);
+        if show_remote && !is_tracking_pixel(tag) {
+            match attribute(tag, "alt").filter(|alt| !alt.trim().is_empty()) {
+                Some(alt) => result.push_str(&format!("[image: {}] ({})", alt.trim(), src)),
+                None => result.push_str(&format!("[image] ({})", src)),
+            }
+        }
+        position = end;
+    }
+
+    result.push_str(&html[position..]);
+    result
+}
+
+/// Replaces the inline images of an HTML body with text placeholders.
+///
+/// Images referring to a part of the message by Content-ID become
+/// "[image: alt] (filename)", or "[image: alt]" if the part is missing.
+///
+/// # Parameters
+/// - `html`: The HTML body
+/// - `attachments`: The parts of the message
+///
+/// # Returns
+/// The HTML with inline images replaced
+pub fn replace_inline_images(html: &str, attachments: &[Attachment]) -> String {
+    let mut result = String::new();
+    let mut position = 0;
+
+    for (start, end) in image_tags(html) {
+        let tag = &html[start..end];
+        let Some(src) = attribute(tag, "src").filter(|src| src.trim().to_ascii_lowercase().starts_with("cid:")) else {
+            continue;
+        };
+
+        result.push_str(&html[position..start]);
+        let alt = attribute(tag, "alt").filter(|alt| !alt.trim().is_empty());
+        let part = attachments.iter().find(|attachment| attachment.matches_cid(&src));
+        match (alt, part) {
+            (Some(alt), Some(part)) => result.push_str(&format!("[image: {}] ({})", alt.trim(), part.filename)),
+            (None, Some(part)) => result.push_str(&format!("[image] ({})", part.filename)),
+            (Some(alt), None) => result.push_str(&format!("[image: {}]", alt.trim())),
+            (None, None) => result.push_str("[image]"),
+        }
+        position = end;
+    }
+
+    result.push_str(&html[position..]);
+    result
+}
+
+/// Finds the `<img>` tags in an HTML body.
+///
+/// # Parameters
+/// - `html`: The HTML body
+///
+/// # Returns
+/// The byte ranges of the tags, including the angle brackets
+fn image_tags(html: &str) -> Vec<(usize, usize)> {
+    let lower = html.to_ascii_lowercase();
+    let mut tags = Vec::new();
+    let mut position = 0;
+
+    while let Some(offset) = lower[position..].find("<img") {
+        let start = position + offset;
+        let Some(length) = lower[start..].find('>') else {
+            break;
+        };
+        let end = start + length + 1;
+
+        // Skip tags that only start with "img", e.g. <imgsrc>
+        if lower[start + "<img".len()..].starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>') {
+            tags.push((start, end));
+        }
+        position = end;
+    }
+
+    tags
+}
+
+/// Gets the value of an attribute of a tag.
+///
+/// # Parameters
+/// - `tag`: The tag, including the angle brackets
+/// - `name`: The attribute name, in lowercase
+///
+/// # Returns
+/// The value without quotes, or None if the tag doesn't have the attribute
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets the same in both strings
+    let lower = tag.to_ascii_lowercase();
+    let mut position = 0;
+
+    while let Some(offset) = lower[position..].find(name) {
+        let start = position + offset;
+        position = start + name.len();
+
+        // Only match whole attribute names followed by a value
+        if !lower[..start].ends_with(|c: char| c.is_whitespace()) {
+            continue;
+        }
+        let Some(value) = lower[position..].trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let value = tag[tag.len() - value.len()..].trim_start();
+
+        return Some(match value.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => {
+                let value = &value[1..];
+                value[..value.find(quote).unwrap_or(value.len())].to_string()
+            },
+            _ => value
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+
+    None
+}
+
+/// Checks if an image source is loaded from a remote server.
+///
+/// # Parameters
+/// - `src`: The image source
+///
+/// # Returns
+/// true if the source is a remote URL, false otherwise
+fn is_remote(src: &str) -> bool {
+    let src = src.trim().to_ascii_lowercase();
+    REMOTE_PREFIXES.iter().any(|prefix| src.starts_with(prefix))
+}
+
+/// Checks if an image tag looks like a tracking pixel.
+///
+/// # Parameters
+/// - `tag`: The image tag
+///
+/// # Returns
+/// true if the image is at most one pixel wide or high, or hidden, false otherwise
+fn is_tracking_pixel(tag: &str) -> bool {
+    let tiny = |name: &str| {
+        attribute(tag, name)
+            .and_then(|value| value.trim().trim_end_matches("px").parse::<u32>().ok())
+            .is_some_and(|pixels| pixels <= 1)
+    };
+    let hidden = attribute(tag, "style").is_some_and(|style| {
+        let style = style.to_ascii_lowercase().replace(' ', "");
+        style.contains("display:none") || style.contains("visibility:hidden")
+    });
+
+    tiny("width") || tiny("height") || hidden
+}
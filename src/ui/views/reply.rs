@@ -0,0 +1,265 @@
+//! Reply and forward building for the Linksy email client.
+//!
+//! This module turns a received email into a compose form for replying to or
+//! forwarding it, following the quoting settings.
+
+use crate::config::{AppSettings, QuoteStyle, ReplyPosition};
+use crate::models::Email;
+use crate::ui::views::compose::{ComposeField, ComposeState};
+use crate::ui::views::display_body_text;
+use std::time::SystemTime;
+
+/// Builds the compose form for replying to an email.
+///
+/// # Parameters
+/// - `email`: The email being replied to
+/// - `settings`: The settings with the reply position, quote style and attribution
+///
+/// # Returns
+/// A ComposeState addressed to the sender, or to the Reply-To addresses if
+/// the email has any, with the message quoted
+pub fn build_reply(email: &Email, settings: &AppSettings) -> ComposeState {
+    let attribution = fill_attribution(&settings.reply_attribution, email);
+    let quoted = quote_text(&original_text(email), settings.quote_style);
+    let quote = format!("{}\n{}", attribution, quoted);
+
+    let mut compose = ComposeState::new();
+    compose.to = reply_address(email);
+    compose.subject = prefixed_subject("Re:", &email.subject);
+
+    // Leave the cursor where the reply is written
+    match settings.reply_position {
+        ReplyPosition::Bottom => compose.set_body(format!("{}\n\n", quote)),
+        ReplyPosition::Top => {
+            compose.set_body(format!("\n\n{}", quote));
+            compose.body_cursor = 0;
+        },
+    }
+    compose.focused_field = ComposeField::Body;
+
+    compose
+}
+
+/// Builds the compose form for forwarding an email.
+///
+/// # Parameters
+/// - `email`: The email being forwarded
+/// - `settings`: The settings with the forward attribution
+///
+/// # Returns
+/// A ComposeState with no recipients and the original message below the cursor
+pub fn build_forward(email: &Email, settings: &AppSettings) -> ComposeState {
+    let attribution = fill_attribution(&settings.forward_attribution, email);
+    let original = original_text(email);
+
+    let mut compose = ComposeState::new();
+    compose.subject = prefixed_subject("Fwd:", &email.subject);
+    compose.set_body(format!("\n\n{}\n\n{}", attribution, original));
+    compose.body_cursor = 0;
+    compose.focused_field = ComposeField::To;
+
+    compose
+}
+
+/// Builds the compose form for a new message to the sender of an email.
+///
+/// Unlike a reply, the message starts a new thread, so neither the subject
+/// nor the text of the email is carried over.
+///
+/// # Parameters
+/// - `email`: The email whose sender is written to
+///
+/// # Returns
+/// A ComposeState addressed to the sender, with the cursor in the subject
+pub fn build_message_to_sender(email: &Email) -> ComposeState {
+    let mut compose = ComposeState::new();
+    compose.to = sender_address(email);
+    compose.focused_field = ComposeField::Subject;
+
+    compose
+}
+
+/// Fills in the placeholders of an attribution line.
+///
+/// Supported placeholders are `{date}`, `{name}`, `{email}`, `{from}`,
+/// `{subject}` and `{to}`. Unknown placeholders are left as they are.
+///
+/// # Parameters
+/// - `template`: The attribution template
+/// - `email`: The original email
+///
+/// # Returns
+/// The attribution with placeholders replaced
+pub fn fill_attribution(template: &str, email: &Email) -> String {
+    let name = email.from_name.clone().unwrap_or_else(|| email.from.clone());
+
+    template
+        .replace("{date}", &format_date(email.date))
+        .replace("{name}", &name)
+        .replace("{email}", &email.from)
+        .replace("{from}", &sender_address(email))
+        .replace("{subject}", &email.subject)
+        .replace("{to}", &email.to.join(", "))
+}
+
+/// Marks each line of a text as quoted.
+///
+/// # Parameters
+/// - `text`: The text to quote
+/// - `style`: How quoted lines are marked
+///
+/// # Returns
+/// The quoted text
+pub fn quote_text(text: &str, style: QuoteStyle) -> String {
+    text.trim_end()
+        .lines()
+        .map(|line| match style {
+            // Already quoted lines get another level without a space in between
+            QuoteStyle::Prefix if line.starts_with('>') => format!(">{}", line),
+            QuoteStyle::Prefix if line.is_empty() => ">".to_string(),
+            QuoteStyle::Prefix => format!("> {}", line),
+            QuoteStyle::Indent => format!("    {}", line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Gets the text of an email to quote or forward.
+///
+/// # Parameters
+/// - `email`: The email
+///
+/// # Returns
+/// The plain-text part, or the text of the HTML part if there is none, with quoted text kept
+fn original_text(email: &Email) -> String {
+    display_body_text(email, false, true, false)
+}
+
+/// Adds a prefix such as "Re:" to a subject unless it already has one.
+///
+/// # Parameters
+/// - `prefix`: The prefix to add
+/// - `subject`: The original subject
+///
+/// # Returns
+/// The prefixed subject
+fn prefixed_subject(prefix: &str, subject: &str) -> String {
+    let has_prefix = subject
+        .get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix));
+
+    if has_prefix {
+        subject.to_string()
+    } else {
+        format!("{} {}", prefix, subject)
+    }
+}
+
+/// Gets the address a reply to an email goes to.
+///
+/// Mailing lists and no-reply senders point replies elsewhere with the
+/// Reply-To header, so it's preferred over the sender.
+///
+/// # Parameters
+/// - `email`: The email
+///
+/// # Returns
+/// The Reply-To addresses, or the sender with their name if the email has
+/// no other Reply-To address
+fn reply_address(email: &Email) -> String {
+    if email.has_other_reply_to() {
+        email.reply_to.join(", ")
+    } else {
+        sender_address(email)
+    }
+}
+
+/// Formats the sender of an email as an address with its display name.
+///
+/// # Parameters
+/// - `email`: The email
+///
+/// # Returns
+/// "Name <address>", or just the address if there is no name
+fn sender_address(email: &Email) -> String {
+    match &email.from_name {
+        Some(name) if !name.is_empty() => format!("{} <{}>", name, email.from),
+        _ => email.from.clone(),
+    }
+}
+
+/// Formats an email date for an attribution line.
+///
+/// # Parameters
+/// - `date`: The date
+///
+/// # Returns
+/// The date in RFC 822 format
+fn format_date(date: SystemTime) -> String {
+    let secs = date
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    mail_parser::DateTime::from_timestamp(secs).to_rfc822()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a message from a mailing list that asks for replies to the list.
+    fn list_message(reply_to: Option<&str>) -> Email {
+        let reply_to = reply_to.map(|address| format!("Reply-To: {}\r\n", address)).unwrap_or_default();
+        let raw = format!(
+            "From: Alice <alice@example.com>\r\nTo: dev@lists.example.com\r\n{}Subject: Release plan\r\n\r\nShip it on Friday.\r\n",
+            reply_to,
+        );
+        Email::parse_from_raw(raw.as_bytes(), "work", "INBOX").unwrap()
+    }
+
+    #[test]
+    fn replies_to_a_distinct_reply_to_address() {
+        let email = list_message(Some("Dev List <dev@lists.example.com>"));
+        assert!(email.has_other_reply_to());
+
+        let reply = build_reply(&email, &AppSettings::default());
+        assert_eq!(reply.to, "dev@lists.example.com");
+        assert_eq!(reply.subject, "Re: Release plan");
+    }
+
+    #[test]
+    fn replies_to_every_reply_to_address() {
+        let email = list_message(Some("dev@lists.example.com, Bob <bob@example.com>"));
+
+        let reply = build_reply(&email, &AppSettings::default());
+        assert_eq!(reply.to, "dev@lists.example.com, bob@example.com");
+    }
+
+    #[test]
+    fn replies_to_the_sender_without_reply_to() {
+        let email = list_message(None);
+        assert!(!email.has_other_reply_to());
+
+        let reply = build_reply(&email, &AppSettings::default());
+        assert_eq!(reply.to, "Alice <alice@example.com>");
+    }
+
+    #[test]
+    fn keeps_the_sender_name_when_reply_to_is_the_sender() {
+        let email = list_message(Some("ALICE@example.com"));
+        assert!(!email.has_other_reply_to());
+
+        let reply = build_reply(&email, &AppSettings::default());
+        assert_eq!(reply.to, "Alice <alice@example.com>");
+    }
+
+    #[test]
+    fn writes_new_messages_to_the_sender_despite_reply_to() {
+        let email = list_message(Some("dev@lists.example.com"));
+
+        let compose = build_message_to_sender(&email);
+        assert_eq!(compose.to, "Alice <alice@example.com>");
+        assert!(compose.subject.is_empty());
+    }
+}
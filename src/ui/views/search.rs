@@ -0,0 +1,164 @@
+//! Search results view for the Linksy email client.
+//!
+//! This module contains the state of a server search and the list of its
+//! results, which can come from the current folder or from every folder of
+//! the account.
+
+use crate::config::AppSettings;
+use crate::models::{Email, EmailKey};
+use crate::ui::views::tags::{spans_width, tag_spans};
+use crate::ui::views::{email_list_style, format_list_date, list_item_width, pad_to_width, selection_count, selection_marker, truncate_to_width};
+use std::collections::HashSet;
+use tui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Represents the state of the search whose results are listed.
+#[derive(Debug, Clone)]
+pub struct SearchState {
+    /// The text that was searched for
+    pub query: String,
+    /// Whether every folder of the account was searched
+    pub all_folders: bool,
+    /// The folder the search was started from, restored when it's cleared
+    pub folder: String,
+    /// The name of the saved search being listed, if any
+    pub name: Option<String>,
+}
+
+impl SearchState {
+    /// Creates a new SearchState.
+    ///
+    /// # Parameters
+    /// - `query`: The text that was searched for
+    /// - `all_folders`: Whether every folder of the account was searched
+    /// - `folder`: The folder the search was started from
+    /// - `name`: The name of the saved search, if one was opened
+    ///
+    /// # Returns
+    /// A new SearchState instance
+    pub fn new(query: String, all_folders: bool, folder: String, name: Option<String>) -> Self {
+        Self {
+            query,
+            all_folders,
+            folder,
+            name,
+        }
+    }
+
+    /// Describes where the search looked.
+    ///
+    /// # Returns
+    /// "all folders", or the name of the searched folder
+    pub fn scope_label(&self) -> String {
+        scope_label(self.all_folders, &self.folder)
+    }
+}
+
+/// Creates the title of the search prompt.
+///
+/// # Parameters
+/// - `all_folders`: Whether every folder will be searched
+/// - `folder`: The current folder
+///
+/// # Returns
+/// The prompt title, including how to change the scope
+pub fn search_prompt_title(all_folders: bool, folder: &str) -> String {
+    format!("Search {} (Tab to change)", scope_label(all_folders, folder))
+}
+
+/// Describes a search scope.
+///
+/// # Parameters
+/// - `all_folders`: Whether every folder is searched
+/// - `folder`: The current folder
+///
+/// # Returns
+/// "all folders", or the folder name
+fn scope_label(all_folders: bool, folder: &str) -> String {
+    if all_folders {
+        "all folders".to_string()
+    } else {
+        folder.to_string()
+    }
+}
+
+/// Renders the results of a search.
+///
+/// Results from a search of all folders are tagged with their folder.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to render in
+/// - `emails`: The matching emails
+/// - `selected`: The index of the selected email
+/// - `selection`: The emails selected for a batch change
+/// - `settings`: The settings with the date and time formats
+/// - `search`: The search the results are for
+pub fn render_search_results(
+    f: &mut Frame,
+    area: Rect,
+    emails: &[Email],
+    selected: Option<usize>,
+    selection: &HashSet<EmailKey>,
+    settings: &AppSettings,
+    search: &SearchState,
+) {
+    // Pad the dates to the widest so the summaries start in one column
+    let dates: Vec<String> = emails.iter()
+        .map(|email| format_list_date(email.date, settings))
+        .collect();
+    let date_width = dates.iter().map(|date| date.width()).max().unwrap_or(0);
+    let width = list_item_width(area);
+
+    let items: Vec<ListItem> = if emails.is_empty() {
+        vec![ListItem::new(Span::styled("No matching emails", Style::default().fg(Color::DarkGray)))]
+    } else {
+        emails.iter()
+            .zip(&dates)
+            .map(|(email, date)| {
+                let style = email_list_style(email, settings);
+
+                let mut spans: Vec<Span> = selection_marker(email, selection).into_iter().collect();
+                spans.push(Span::styled(
+                    format!("{}  ", pad_to_width(date, date_width)),
+                    Style::default().fg(Color::DarkGray),
+                ));
+                if search.all_folders {
+                    spans.push(Span::styled(format!("[{}] ", email.folder), Style::default().fg(Color::Cyan)));
+                }
+
+                // Cut the summary rather than the tags and size after it
+                let tags = tag_spans(email);
+                let size = email.get_size_string().map(|size| format!("  {}", size)).unwrap_or_default();
+                let summary_width = width.saturating_sub(spans_width(&spans) + spans_width(&tags) + size.width());
+                spans.push(Span::raw(truncate_to_width(&email.get_summary(), summary_width)));
+                spans.extend(tags);
+                spans.push(Span::styled(size, Style::default().fg(Color::DarkGray)));
+
+                ListItem::new(Line::from(spans)).style(style)
+            })
+            .collect()
+    };
+
+    let title = match &search.name {
+        Some(name) => format!("{} (\"{}\" in {}) - {} found{}", name, search.query, search.scope_label(), emails.len(), selection_count(selection)),
+        None => format!("Search \"{}\" in {} - {} found{}", search.query, search.scope_label(), emails.len(), selection_count(selection)),
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().bg(Color::DarkGray))
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    if !emails.is_empty() {
+        state.select(selected);
+    }
+
+    f.render_stateful_widget(list, area, &mut state);
+}
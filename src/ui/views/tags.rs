@@ -0,0 +1,159 @@
+//! Tags for the Linksy email client.
+//!
+//! Emails can be tagged with IMAP keywords such as "$Todo" or "$Important".
+//! This module contains the picker that adds and removes the tags of an
+//! email and the colored tags shown in the email list and detail view.
+
+use crate::models::Email;
+use crate::ui::views::centered_rect;
+use tui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Colors the tags are shown in, picked by the tag's name.
+const TAG_COLORS: [Color; 6] = [Color::Cyan, Color::Green, Color::Magenta, Color::Blue, Color::LightRed, Color::LightGreen];
+
+/// Represents the state of the tag picker popup.
+#[derive(Debug, Clone)]
+pub struct TagPickerState {
+    /// The tags offered: the configured ones and those of the email
+    pub tags: Vec<String>,
+    /// Index of the currently selected tag
+    pub selected: usize,
+}
+
+impl TagPickerState {
+    /// Creates a new TagPickerState for an email.
+    ///
+    /// # Parameters
+    /// - `configured`: The tags from the settings
+    /// - `email`: The email whose tags are changed
+    ///
+    /// # Returns
+    /// A new TagPickerState instance
+    pub fn new(configured: &[String], email: &Email) -> Self {
+        let mut tags = configured.to_vec();
+        for tag in email.tags() {
+            if !tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.to_string());
+            }
+        }
+
+        Self {
+            tags,
+            selected: 0,
+        }
+    }
+
+    /// Moves the selection to the previous tag.
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Moves the selection to the next tag.
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.tags.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Gets the selected tag.
+    ///
+    /// # Returns
+    /// The tag, or None if there are none
+    pub fn selected_tag(&self) -> Option<&String> {
+        self.tags.get(self.selected)
+    }
+}
+
+/// Gets the name a tag is shown with.
+///
+/// # Parameters
+/// - `tag`: The tag
+///
+/// # Returns
+/// The tag without the "$" that registered keywords start with
+pub fn tag_label(tag: &str) -> &str {
+    tag.strip_prefix('$').filter(|label| !label.is_empty()).unwrap_or(tag)
+}
+
+/// Gets the color a tag is shown in.
+///
+/// # Parameters
+/// - `tag`: The tag
+///
+/// # Returns
+/// The same color for a tag every time, whatever its case
+pub fn tag_color(tag: &str) -> Color {
+    let hash = tag.bytes().fold(0usize, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte.to_ascii_lowercase() as usize));
+    TAG_COLORS[hash % TAG_COLORS.len()]
+}
+
+/// Builds the colored tags of an email.
+///
+/// # Parameters
+/// - `email`: The email
+///
+/// # Returns
+/// A span per tag, each starting with a space
+pub fn tag_spans(email: &Email) -> Vec<Span<'static>> {
+    email.tags().into_iter()
+        .map(|tag| Span::styled(format!(" [{}]", tag_label(tag)), Style::default().fg(tag_color(tag))))
+        .collect()
+}
+
+/// Gets the width of a line's spans.
+///
+/// # Parameters
+/// - `spans`: The spans
+///
+/// # Returns
+/// The number of terminal cells the spans take
+pub fn spans_width(spans: &[Span]) -> usize {
+    spans.iter().map(|span| span.content.width()).sum()
+}
+
+/// Renders the tag picker popup.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to center the popup in
+/// - `email`: The email whose tags are changed
+/// - `picker`: The tag picker state
+pub fn render_tag_picker(f: &mut Frame, area: Rect, email: &Email, picker: &TagPickerState) {
+    let popup_area = centered_rect(50, 40, area);
+
+    let items: Vec<ListItem> = if picker.tags.is_empty() {
+        vec![ListItem::new(Span::styled("No tags yet - press 'n' to add one", Style::default().fg(Color::DarkGray)))]
+    } else {
+        picker.tags.iter()
+            .map(|tag| {
+                let mark = if email.has_tag(tag) { "[x] " } else { "[ ] " };
+                let local = if email.is_local_tag(tag) { "  (local)" } else { "" };
+                ListItem::new(Line::from(vec![
+                    Span::raw(mark),
+                    Span::styled(tag_label(tag).to_string(), Style::default().fg(tag_color(tag))),
+                    Span::styled(local, Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Tags - Enter to add/remove, n for a new tag, Esc to close"))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    if !picker.tags.is_empty() {
+        state.select(Some(picker.selected));
+    }
+
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
@@ -0,0 +1,283 @@
+//! Template views for the Linksy email client.
+//!
+//! This module contains the template picker used while composing, the
+//! template list in the settings view and the form for editing a template.
+
+use crate::config::EmailTemplate;
+use crate::models::Address;
+use crate::ui::views::centered_rect;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+/// Fills in the placeholders of a template text.
+///
+/// # Parameters
+/// - `text`: The template subject or body
+/// - `to`: The compose form's To field; the first recipient fills `{name}` and `{email}`
+/// - `my_name`: Name of the sending account, for `{my_name}`
+///
+/// # Returns
+/// The text with placeholders replaced
+pub fn fill_placeholders(text: &str, to: &str, my_name: &str) -> String {
+    let (name, email) = match Address::parse_list(to).into_iter().next() {
+        Some(recipient) => (recipient.name, recipient.email),
+        None => (None, String::new()),
+    };
+
+    // Without a display name, the local part of the address is the best guess
+    let name = name.unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_string());
+
+    text.replace("{name}", &name)
+        .replace("{email}", &email)
+        .replace("{my_name}", my_name)
+}
+
+/// Represents the state of the template picker popup.
+#[derive(Clone, Default)]
+pub struct TemplatePickerState {
+    /// Index of the currently selected template
+    pub selected: usize,
+}
+
+impl TemplatePickerState {
+    /// Creates a new TemplatePickerState.
+    ///
+    /// # Returns
+    /// A new TemplatePickerState instance
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the selection to the previous template.
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Moves the selection to the next template.
+    ///
+    /// # Parameters
+    /// - `count`: The number of templates
+    pub fn select_next(&mut self, count: usize) {
+        if self.selected + 1 < count {
+            self.selected += 1;
+        }
+    }
+}
+
+/// Represents the fields of the template form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateField {
+    /// Template name
+    Name,
+    /// Subject line
+    Subject,
+    /// Message body
+    Body,
+}
+
+/// Represents the state of the template form.
+#[derive(Clone)]
+pub struct TemplateFormState {
+    /// The template being edited
+    pub template: EmailTemplate,
+    /// Index of the template in the configuration, or None for a new template
+    pub index: Option<usize>,
+    /// The field receiving typed input
+    pub focused_field: TemplateField,
+}
+
+impl TemplateFormState {
+    /// Creates a TemplateFormState for a new template.
+    ///
+    /// # Returns
+    /// A new TemplateFormState instance
+    pub fn new() -> Self {
+        Self {
+            template: EmailTemplate::default(),
+            index: None,
+            focused_field: TemplateField::Name,
+        }
+    }
+
+    /// Creates a TemplateFormState for editing an existing template.
+    ///
+    /// # Parameters
+    /// - `template`: The template to edit
+    /// - `index`: Index of the template in the configuration
+    ///
+    /// # Returns
+    /// A new TemplateFormState instance
+    pub fn from_template(template: EmailTemplate, index: usize) -> Self {
+        Self {
+            template,
+            index: Some(index),
+            focused_field: TemplateField::Name,
+        }
+    }
+
+    /// Moves focus to the next field.
+    pub fn focus_next_field(&mut self) {
+        self.focused_field = match self.focused_field {
+            TemplateField::Name => TemplateField::Subject,
+            TemplateField::Subject => TemplateField::Body,
+            TemplateField::Body => TemplateField::Name,
+        };
+    }
+
+    /// Moves focus to the previous field.
+    pub fn focus_previous_field(&mut self) {
+        self.focused_field = match self.focused_field {
+            TemplateField::Name => TemplateField::Body,
+            TemplateField::Subject => TemplateField::Name,
+            TemplateField::Body => TemplateField::Subject,
+        };
+    }
+
+    /// Gets a mutable reference to the text of the focused field.
+    ///
+    /// # Returns
+    /// The text of the focused field
+    fn focused_text_mut(&mut self) -> &mut String {
+        match self.focused_field {
+            TemplateField::Name => &mut self.template.name,
+            TemplateField::Subject => &mut self.template.subject,
+            TemplateField::Body => &mut self.template.body,
+        }
+    }
+
+    /// Types a character into the focused field.
+    ///
+    /// # Parameters
+    /// - `c`: The character to add
+    pub fn insert_char(&mut self, c: char) {
+        self.focused_text_mut().push(c);
+    }
+
+    /// Handles Enter: a new line in the body, or the next field otherwise.
+    pub fn insert_newline(&mut self) {
+        if self.focused_field == TemplateField::Body {
+            self.template.body.push('\n');
+        } else {
+            self.focus_next_field();
+        }
+    }
+
+    /// Removes the last character of the focused field.
+    pub fn backspace(&mut self) {
+        self.focused_text_mut().pop();
+    }
+}
+
+impl Default for TemplateFormState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Renders the template picker popup over the compose view.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to center the popup in
+/// - `templates`: The configured templates
+/// - `picker`: The picker state
+pub fn render_template_picker(f: &mut Frame, area: Rect, templates: &[EmailTemplate], picker: &TemplatePickerState) {
+    let popup_area = centered_rect(60, 50, area);
+
+    let items: Vec<ListItem> = templates
+        .iter()
+        .map(|template| ListItem::new(template.name.clone()))
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Templates - Enter to insert, Esc to close"))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    state.select(Some(picker.selected));
+
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Renders the settings view with the list of templates.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to render in
+/// - `templates`: The configured templates
+/// - `selected`: The index of the selected template
+pub fn render_template_settings(f: &mut Frame, area: Rect, templates: &[EmailTemplate], selected: Option<usize>) {
+    let items: Vec<ListItem> = if templates.is_empty() {
+        vec![ListItem::new(Span::styled("No templates yet - press 'a' to add one", Style::default().fg(Color::DarkGray)))]
+    } else {
+        templates
+            .iter()
+            .map(|template| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(template.name.clone(), Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("  {}", template.subject), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Settings - Templates"))
+        .highlight_style(Style::default().fg(Color::Yellow))
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    if !templates.is_empty() {
+        state.select(selected);
+    }
+
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// Renders the template form.
+///
+/// # Parameters
+/// - `f`: The frame to render on
+/// - `area`: The area to render in
+/// - `form`: The template form state
+pub fn render_template_form(f: &mut Frame, area: Rect, form: &TemplateFormState) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // Name
+            Constraint::Length(3),  // Subject
+            Constraint::Min(3),     // Body
+            Constraint::Length(1),  // Help
+        ].as_ref())
+        .split(area);
+
+    let fields = [
+        (TemplateField::Name, "Name", &form.template.name),
+        (TemplateField::Subject, "Subject", &form.template.subject),
+        (TemplateField::Body, "Body", &form.template.body),
+    ];
+
+    for (chunk, (field, label, text)) in chunks.iter().zip(fields) {
+        let style = if field == form.focused_field {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+
+        let paragraph = Paragraph::new(text.as_str())
+            .block(Block::default().borders(Borders::ALL).border_style(style).title(label))
+            .wrap(tui::widgets::Wrap { trim: false });
+        f.render_widget(paragraph, *chunk);
+    }
+
+    let help = Paragraph::new("Placeholders: {name} and {email} of the first recipient, {my_name} of the sending account")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help, chunks[3]);
+}
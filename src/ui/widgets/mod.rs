@@ -100,7 +100,7 @@ impl<'a> Widget for EmailView<'a> {
             .split(area);
             
         // Render header
-        let header_text = vec![
+        let header_text = [
             format!("From: {}", self.from),
             format!("To: {}", self.to),
             format!("Subject: {}", self.subject),
@@ -197,7 +197,7 @@ impl<'a> Widget for AccountInfoWidget<'a> {
         }
         
         // Render account info
-        let text = vec![
+        let text = [
             format!("Name: {}", self.name),
             format!("Email: {}", self.email),
             format!("Status: {}", self.status),